@@ -0,0 +1,42 @@
+import { renderer, costumes, target_skins, target_names } from "../shared";
+
+export function create_clone(
+  parent_index: number,
+  clone_index: number,
+  costume_num: number,
+) {
+  const target_index = clone_index % target_names().length;
+  const costume = costumes()[target_index][costume_num];
+  const parent_drawable = renderer().getDrawable(
+    target_skins()[parent_index][1],
+  );
+  const [skin, drawable_id] = renderer().createSkin(
+    costume.dataFormat,
+    "sprite",
+    costume.data,
+    renderer().getSkin(target_skins()[parent_index][0]).rotationCenter,
+  );
+  const drawable = renderer().getDrawable(drawable_id);
+  drawable.updateVisible(parent_drawable._visible);
+  drawable.updatePosition(parent_drawable._position);
+  drawable.updateDirection(parent_drawable._direction);
+  drawable.updateScale(parent_drawable._scale);
+  for (const effect of [
+    "color",
+    "fisheye",
+    "whirl",
+    "pixelate",
+    "mosaic",
+    "brightness",
+    "ghost",
+  ]) {
+    drawable.updateEffect(effect, parent_drawable._uniforms[`u_${effect}`]);
+  }
+  // clones are created directly behind their parent
+  renderer().setDrawableOrder(
+    drawable_id,
+    renderer().getDrawableOrder(target_skins()[parent_index][1]),
+    "sprite",
+  );
+  target_skins()[clone_index] = [skin, drawable_id];
+}
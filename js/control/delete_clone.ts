@@ -0,0 +1,9 @@
+import { renderer, target_skins, update_bubble } from "../shared";
+
+export function delete_clone(clone_index: number) {
+  update_bubble(clone_index, "say", "");
+  const [skin, drawable_id] = target_skins()[clone_index];
+  renderer().destroyDrawable(drawable_id, "sprite");
+  renderer().destroySkin(skin);
+  delete target_skins()[clone_index];
+}
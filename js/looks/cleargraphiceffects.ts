@@ -0,0 +1,18 @@
+import { renderer, target_skins } from "../shared";
+
+const EFFECTS = [
+  "color",
+  "fisheye",
+  "whirl",
+  "pixelate",
+  "mosaic",
+  "brightness",
+  "ghost",
+];
+
+export function cleargraphiceffects(target_index: number) {
+  const drawable_id = target_skins()[target_index][1];
+  for (const effect of EFFECTS) {
+    renderer().updateDrawableEffect(drawable_id, effect, 0);
+  }
+}
@@ -0,0 +1,10 @@
+import { renderer, target_skins } from "../shared";
+
+export function goforwardbackwardlayers(layers: number, target_index: number) {
+  renderer().setDrawableOrder(
+    target_skins()[target_index][1],
+    layers,
+    "sprite",
+    true,
+  );
+}
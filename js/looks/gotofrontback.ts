@@ -0,0 +1,11 @@
+import { renderer, target_skins } from "../shared";
+
+export function gotofrontback(front: boolean, target_index: number) {
+  // see https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/sprites/rendered-target.js#L924
+  renderer().setDrawableOrder(
+    target_skins()[target_index][1],
+    front ? Infinity : -Infinity,
+    "sprite",
+    false,
+  );
+}
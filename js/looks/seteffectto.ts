@@ -0,0 +1,9 @@
+import { renderer, target_skins } from "../shared";
+
+export function seteffectto(effect: string, value: number, target_index: number) {
+  renderer().updateDrawableEffect(
+    target_skins()[target_index][1],
+    effect,
+    value,
+  );
+}
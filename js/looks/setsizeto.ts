@@ -1,7 +1,8 @@
 import { renderer, costumes, target_skins } from "../shared";
 
 export function setsizeto(size: number, target_index: number) {
-  renderer()
-    .getDrawable(target_skins()[target_index][1])
-    .updateScale([size, size]);
+  const drawable = renderer().getDrawable(target_skins()[target_index][1]);
+  // keep left-right sprites flipped if they're facing left
+  const flip = Math.sign(drawable.scale[0]) || 1;
+  drawable.updateScale([flip * size, size]);
 }
@@ -1,7 +1,9 @@
-import { renderer, costumes, target_skins } from "../shared";
+import { renderer, costumes, target_skins, target_names } from "../shared";
 
 export function switchcostumeto(costume_num: number, target_index: number) {
-  const costume = costumes()[target_index][costume_num];
+  // target_index may be the index of a clone
+  const costume =
+    costumes()[target_index % target_names().length][costume_num];
   if (typeof costume === "undefined") return;
   renderer().getSkin(target_skins()[target_index][0]).setSVG(costume.data);
 }
@@ -1,7 +1,25 @@
 import { renderer, target_skins } from "../shared";
 
-export function pointindirection(target_index: number, direction: number) {
-  renderer()
-    .getDrawable(target_skins()[target_index][1])
-    .updateDirection(direction);
+/** see `RotationStyle::id` */
+const ROTATION_STYLE_ALL_AROUND = 0;
+const ROTATION_STYLE_LEFT_RIGHT = 1;
+
+// adapted from https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/sprites/rendered-target.js#L349
+// (licensed under BSD-3.0 - see https://raw.githubusercontent.com/scratchfoundation/scratch-vm/8dbcc1f/LICENSE)
+export function pointindirection(
+  target_index: number,
+  direction: number,
+  rotation_style: number,
+) {
+  const drawable = renderer().getDrawable(target_skins()[target_index][1]);
+  if (rotation_style === ROTATION_STYLE_ALL_AROUND) {
+    drawable.updateDirection(direction);
+    return;
+  }
+  drawable.updateDirection(90);
+  // left-right sprites are flipped horizontally when facing left
+  const flip =
+    rotation_style === ROTATION_STYLE_LEFT_RIGHT && direction < 0 ? -1 : 1;
+  const [scale_x, scale_y] = drawable.scale;
+  drawable.updateScale([flip * Math.abs(scale_x), scale_y]);
 }
@@ -0,0 +1,6 @@
+import { renderer, target_skins, pen_skin } from "../shared";
+
+export function stamp(target_index: number) {
+  // target_index may be the index of a clone
+  renderer().penStamp(pen_skin(), target_skins()[target_index][1]);
+}
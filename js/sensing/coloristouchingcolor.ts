@@ -0,0 +1,19 @@
+import { renderer, target_skins } from "../shared";
+
+const rgb = (color: number) => [
+  (color >> 16) & 0xff,
+  (color >> 8) & 0xff,
+  color & 0xff,
+];
+
+// see https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_sensing.js#L114
+export function coloristouchingcolor(
+  instance: number,
+  color: number,
+  color2: number,
+): boolean {
+  const drawable = target_skins()[instance]?.[1];
+  if (typeof drawable === "undefined") return false;
+  // the sprite's pixels of colour `color` are checked to see if they touch colour `color2`
+  return renderer().isTouchingColor(drawable, rgb(color2), rgb(color));
+}
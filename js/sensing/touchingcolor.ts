@@ -0,0 +1,11 @@
+import { renderer, target_skins } from "../shared";
+
+export function touchingcolor(instance: number, color: number): boolean {
+  const drawable = target_skins()[instance]?.[1];
+  if (typeof drawable === "undefined") return false;
+  return renderer().isTouchingColor(drawable, [
+    (color >> 16) & 0xff,
+    (color >> 8) & 0xff,
+    color & 0xff,
+  ]);
+}
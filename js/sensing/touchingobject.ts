@@ -0,0 +1,44 @@
+import { renderer, target_names, target_skins } from "../shared";
+
+// adapted from https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/sprites/rendered-target.js#L872
+// (licensed under BSD-3.0 - see https://raw.githubusercontent.com/scratchfoundation/scratch-vm/8dbcc1f/LICENSE)
+export function touchingobject(
+  instance: number,
+  object: string,
+  mouse_x: number,
+  mouse_y: number,
+): boolean {
+  const drawable = target_skins()[instance]?.[1];
+  if (typeof drawable === "undefined") return false;
+  if (object === "_mouse_") {
+    const canvas = renderer().canvas;
+    return renderer().drawableTouching(
+      drawable,
+      ((mouse_x + 240) / 480) * canvas.clientWidth,
+      ((180 - mouse_y) / 360) * canvas.clientHeight,
+    );
+  }
+  if (object === "_edge_") {
+    const bounds = renderer().getBounds(drawable);
+    return (
+      bounds.left < -240 ||
+      bounds.right > 240 ||
+      bounds.top > 180 ||
+      bounds.bottom < -180
+    );
+  }
+  const names = target_names();
+  const target_index = names.indexOf(object);
+  if (target_index === -1) return false;
+  // the target's original sprite and all of its clones
+  const candidates = target_skins()
+    .map((skin, i) => [skin, i] as const)
+    .filter(
+      ([skin, i]) =>
+        typeof skin !== "undefined" &&
+        i !== instance &&
+        i % names.length === target_index,
+    )
+    .map(([skin]) => skin[1]);
+  return renderer().isTouchingDrawables(drawable, candidates);
+}
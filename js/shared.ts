@@ -10,12 +10,39 @@ let _stageIndex: number;
 let _update_var_val: (id: string, val: any) => void = () => {};
 let _update_var_visible: (id: string, visible: boolean) => void = () => {};
 let _get_key_pressed: (key: string) => boolean = (_) => false;
+let _play_sound: PlaySound = () => {};
+let _stop_all_sounds: () => void = () => {};
+let _update_sound_state: (instance: number, state: SoundState) => void =
+  () => {};
 
 type Costume = {
   data: string;
   dataFormat: string;
 };
 
+export type SoundState = {
+  /** 0-100 */
+  volume: number;
+  /** -360-360 */
+  pitch: number;
+  /** -100-100 */
+  pan: number;
+};
+
+/**
+ * Plays sound `sound_index` (0-indexed, may be out of range, in which case it should be wrapped
+ * around) of target `target_index`. If `struct` is not null, the sound was played by a
+ * `play sound until done` block, and `struct` should be passed to the `mark_waiting_flag`
+ * export once the sound has finished.
+ */
+type PlaySound = (
+  target_index: number,
+  instance: number,
+  sound_index: number,
+  state: SoundState,
+  struct: object | null,
+) => void;
+
 export function unsetup() {
   _target_names = null;
   _target_bubbles = null;
@@ -28,6 +55,9 @@ export function unsetup() {
   _update_var_val = () => {};
   _update_var_visible = () => {};
   _get_key_pressed = (_) => false;
+  _play_sound = () => {};
+  _stop_all_sounds = () => {};
+  _update_sound_state = () => {};
   _setup = false;
 }
 
@@ -43,11 +73,17 @@ export function setup(
     update_var_val,
     update_var_visible,
     get_key_pressed,
+    play_sound,
+    stop_all_sounds,
+    update_sound_state,
   }: {
     queue_question: (question: string, struct: object) => void;
     update_var_val: (id: string, val: any) => void;
     update_var_visible: (id: string, visible: boolean) => void;
     get_key_pressed: (key: string) => boolean;
+    play_sound: PlaySound;
+    stop_all_sounds: () => void;
+    update_sound_state: (instance: number, state: SoundState) => void;
   },
 ) {
   _target_names = target_names;
@@ -61,6 +97,9 @@ export function setup(
   _update_var_val = update_var_val;
   _update_var_visible = update_var_visible;
   _get_key_pressed = get_key_pressed;
+  _play_sound = play_sound;
+  _stop_all_sounds = stop_all_sounds;
+  _update_sound_state = update_sound_state;
   _setup = true;
 }
 
@@ -104,6 +143,16 @@ export function stageIndex(): number {
   return _stageIndex;
 }
 
+/**
+ * The current layer of the given target (or clone); targets on higher layers are drawn in front
+ * of those on lower layers. The stage is always on layer 0.
+ */
+export function layer_order(target_index: number): number {
+  check_setup();
+  if (target_index === _stageIndex) return 0;
+  return _renderer.getDrawableOrder(_target_skins[target_index][1]);
+}
+
 export function update_bubble(
   target_index: number,
   verb: "say" | "think",
@@ -152,3 +201,24 @@ export function get_key_pressed(key: string): boolean {
   check_setup();
   return _get_key_pressed(key);
 }
+
+export function play_sound(
+  target_index: number,
+  instance: number,
+  sound_index: number,
+  state: SoundState,
+  struct: object | null,
+) {
+  check_setup();
+  _play_sound(target_index, instance, sound_index, state, struct);
+}
+
+export function stop_all_sounds() {
+  check_setup();
+  _stop_all_sounds();
+}
+
+export function update_sound_state(instance: number, state: SoundState) {
+  check_setup();
+  _update_sound_state(instance, state);
+}
@@ -0,0 +1,18 @@
+import { play_sound, target_names } from "../shared";
+
+export function play(
+  sound_index: number,
+  volume: number,
+  pitch: number,
+  pan: number,
+  instance: number,
+) {
+  // instance may be the index of a clone
+  play_sound(
+    instance % target_names().length,
+    instance,
+    sound_index,
+    { volume, pitch, pan },
+    null,
+  );
+}
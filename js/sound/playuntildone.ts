@@ -0,0 +1,19 @@
+import { play_sound, target_names } from "../shared";
+
+export function playuntildone(
+  sound_index: number,
+  volume: number,
+  pitch: number,
+  pan: number,
+  instance: number,
+  struct: object,
+) {
+  // instance may be the index of a clone
+  play_sound(
+    instance % target_names().length,
+    instance,
+    sound_index,
+    { volume, pitch, pan },
+    struct,
+  );
+}
@@ -0,0 +1,5 @@
+import { stop_all_sounds } from "../shared";
+
+export function stopallsounds() {
+  stop_all_sounds();
+}
@@ -0,0 +1,10 @@
+import { update_sound_state } from "../shared";
+
+export function update_state(
+  volume: number,
+  pitch: number,
+  pan: number,
+  instance: number,
+) {
+  update_sound_state(instance, { volume, pitch, pan });
+}
@@ -11,6 +11,11 @@ const opcodes = [
   "event_whenbroadcastreceived",
   "event_whenthisspriteclicked",
   "event_whenstageclicked",
+  "event_whenkeypressed",
+  "event_whenbackdropswitchesto",
+  "event_whengreaterthan",
+  "event_whentouchingobject",
+  "control_start_as_clone",
   "procedures_definition",
 ].sort();
 await writeFile(
@@ -1,4 +1,8 @@
-import { sb3_to_wasm, WasmFlags } from "../../js/compiler/hyperquark.js";
+import {
+  check_sb3,
+  sb3_to_wasm,
+  WasmFlags,
+} from "../../js/compiler/hyperquark.js";
 
 console.log("web worker initialised");
 
@@ -7,12 +11,33 @@ postMessage("ready");
 addEventListener("message", ({ data }) => {
   switch (data.stage) {
     case "compile": {
-      let wasmProject = sb3_to_wasm(data.proj, WasmFlags.from_js(data.flags));
+      let wasmProject;
+      try {
+        wasmProject = sb3_to_wasm(data.proj, WasmFlags.from_js(data.flags));
+      } catch (error) {
+        // report every error in the project rather than just the first one, so
+        // that they can all be fixed at once
+        let errors = [error];
+        try {
+          const allErrors = check_sb3(
+            data.proj,
+            WasmFlags.from_js(data.flags),
+          );
+          if (allErrors.length > 0) errors = allErrors;
+        } catch (checkError) {
+          console.error(checkError);
+        }
+        // compiler errors are plain objects, so they're passed back as a message;
+        // rethrowing them would reduce them to "[object Object]"
+        postMessage({ errors });
+        break;
+      }
       postMessage(
         {
           wasm_bytes: wasmProject.wasm_bytes,
           strings: wasmProject.strings,
           target_names: wasmProject.target_names,
+          layer_orders: wasmProject.layer_orders,
         },
         [wasmProject.wasm_bytes.buffer],
       );
@@ -0,0 +1,37 @@
+const newIssueUrl = "https://github.com/hyperquark/hyperquark/issues/new";
+const newIssueLink = `<a href="${newIssueUrl}">${newIssueUrl}</a>`;
+
+const explanations = {
+  Unimplemented:
+    "this is a bug or missing feature that is known and will be fixed or implemented in a future update",
+  InternalError: `this is probably a bug with HyperQuark itself. Please report this bug, with this error message, at ${newIssueLink}`,
+  MalformedProject: `this is probably a problem with the project itself, but if it works in vanilla scratch then this is a bug; please report it, by creating an issue at ${newIssueLink}, including this error message`,
+};
+
+const escapeHtml = (text) =>
+  String(text)
+    .replaceAll("&", "&amp;")
+    .replaceAll("<", "&lt;")
+    .replaceAll(">", "&gt;");
+
+/**
+ * Formats an error object returned by the compiler as HTML.
+ */
+export function formatDiagnostic({
+  err_type,
+  msg,
+  file,
+  line,
+  column,
+  location,
+}) {
+  let html = `${err_type == "Unimplemented" ? "todo" : "error"}: ${escapeHtml(msg)}`;
+  if (location) {
+    html += `<br>in block ${escapeHtml(location.block_id)} of target '${escapeHtml(location.target)}'`;
+    if (location.proccode) {
+      html += `, in the definition of custom block '${escapeHtml(location.proccode)}'`;
+    }
+  }
+  html += `<br>at ${file}:${line}:${column}<br>${explanations[err_type]}`;
+  return html;
+}
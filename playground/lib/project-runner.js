@@ -3,7 +3,9 @@ import { imports as baseImports } from "./imports.js";
 import {
   renderer as get_renderer,
   stageIndex,
+  target_names,
   target_skins,
+  update_bubble,
 } from "../../js/shared.ts";
 import { WasmStringType } from "../../js/no-compiler/hyperquark.js";
 import { setup } from "./setup.js";
@@ -31,8 +33,11 @@ export class ProjectRunner extends EventTarget {
   #timeout;
   #framerate_wait;
   #requests_refresh;
+  #frame_deadline;
+  #warp_deadline;
   turbo;
   #sensing_timer;
+  #virtual_clock;
   #threads_count;
   flag_clicked;
   #threads;
@@ -40,6 +45,9 @@ export class ProjectRunner extends EventTarget {
   #mouseY;
   #mouseDown;
   #triggerSpriteClicked;
+  #deleteAllClones;
+  #keyPressedTriggers;
+  #edgeActivatedHats;
   monitors;
   #keysPressed = {};
 
@@ -58,8 +66,12 @@ export class ProjectRunner extends EventTarget {
     this.#timeout = timeout;
     this.#framerate_wait = framerate_wait;
     this.#requests_refresh = exports.requests_refresh ?? { value: 0 };
+    this.#frame_deadline = exports.frame_deadline ?? { value: 0 };
+    this.#warp_deadline = exports.warp_deadline ?? { value: 0 };
     this.turbo = turbo;
     this.#sensing_timer = exports.sensing_timer;
+    // with a virtual clock, the timer is advanced by the tick function instead
+    this.#virtual_clock = typeof exports.virtual_clock !== "undefined";
     this.#threads_count = exports.threads_count;
     this.flag_clicked = exports.flag_clicked;
     this.#threads = exports.threads;
@@ -67,6 +79,13 @@ export class ProjectRunner extends EventTarget {
     this.#mouseY = exports.mouseY ?? { value: 0 };
     this.#mouseDown = exports.mouseDown ?? { value: false };
     this.#triggerSpriteClicked = exports.trigger_sprite_clicked;
+    this.#deleteAllClones = exports.delete_all_clones;
+    this.#keyPressedTriggers = Object.fromEntries(
+      Object.entries(exports)
+        .filter(([name]) => name.startsWith("key_pressed_"))
+        .map(([name, trigger]) => [name.slice("key_pressed_".length), trigger]),
+    );
+    this.#edgeActivatedHats = typeof exports.edge_activated_hats !== "undefined";
     this.monitors = Object.fromEntries(
       project_json.monitors?.map?.((monitor) => {
         return [
@@ -90,6 +109,7 @@ export class ProjectRunner extends EventTarget {
     turbo,
     wasm_bytes,
     target_names,
+    layer_orders,
     strings,
     project_json,
     assets,
@@ -105,26 +125,44 @@ export class ProjectRunner extends EventTarget {
         ),
       );
 
-    await setup(makeRenderer, project_json, assets, target_names, {
-      queue_question: (question, struct) => {
-        this.dispatchEvent(
-          new CustomEvent("queueQuestion", { detail: { question, struct } }),
-        );
-      },
-      update_var_val: (id, value) => {
-        this.dispatchEvent(
-          new CustomEvent("updateVariableVal", { detail: { id, value } }),
-        );
-      },
-      update_var_visible: (id, visible) => {
-        this.dispatchEvent(
-          new CustomEvent("updateVariableVisibility", {
-            detail: { id, visible },
-          }),
-        );
+    await setup(
+      makeRenderer,
+      project_json,
+      assets,
+      target_names,
+      layer_orders,
+      {
+        queue_question: (question, struct) => {
+          this.dispatchEvent(
+            new CustomEvent("queueQuestion", { detail: { question, struct } }),
+          );
+        },
+        update_var_val: (id, value) => {
+          this.dispatchEvent(
+            new CustomEvent("updateVariableVal", { detail: { id, value } }),
+          );
+        },
+        update_var_visible: (id, visible) => {
+          this.dispatchEvent(
+            new CustomEvent("updateVariableVisibility", {
+              detail: { id, visible },
+            }),
+          );
+        },
+        get_key_pressed: this.#getKeyPressed.bind(this),
+        play_sound: this.#playSound.bind(this),
+        stop_all_sounds: () => {
+          this.dispatchEvent(new CustomEvent("stopAllSounds"));
+        },
+        update_sound_state: (instance, state) => {
+          this.dispatchEvent(
+            new CustomEvent("updateSoundState", {
+              detail: { instance, state },
+            }),
+          );
+        },
       },
-      get_key_pressed: this.#getKeyPressed.bind(this),
-    });
+    );
 
     const renderer = get_renderer();
 
@@ -211,13 +249,22 @@ export class ProjectRunner extends EventTarget {
         return this.dispatchEcent(new CustomEvent("timeout"));
       }
       let thisTickStartTime = Date.now();
-      if (typeof this.#sensing_timer !== "undefined") {
+      if (
+        typeof this.#sensing_timer !== "undefined" &&
+        !this.#virtual_clock
+      ) {
         this.#sensing_timer.value +=
           (thisTickStartTime - previousTickStartTime) / 1000;
       }
       previousTickStartTime = thisTickStartTime;
+      // loops outside of warp mode keep running until this time unless a redraw is requested
+      this.#frame_deadline.value =
+        thisTickStartTime + this.#framerate_wait * 0.8;
+      // when the warp timer is enabled, warped loops yield once the frame has run for 500ms
+      this.#warp_deadline.value = thisTickStartTime + 500;
       do {
-        if (this.#threads_count.value === 0) {
+        // edge-activated hats are checked in each tick, so we need to keep ticking
+        if (this.#threads_count.value === 0 && !this.#edgeActivatedHats) {
           break $outertickloop;
         }
         this.#tick();
@@ -247,6 +294,7 @@ export class ProjectRunner extends EventTarget {
     if (typeof this.#sensing_timer !== "undefined") {
       this.#sensing_timer.value = 0.0;
     }
+    this.#removeClones();
     this.flag_clicked();
     this.run();
   }
@@ -266,9 +314,24 @@ export class ProjectRunner extends EventTarget {
         this.#threads.set(i, null);
       }
     }
+    this.#removeClones();
     this.dispatchEvent(new CustomEvent("stopped"));
   }
 
+  #removeClones() {
+    this.#deleteAllClones?.();
+    // anything after the original targets is a clone
+    const targetSkins = target_skins();
+    const targetCount = target_names().length;
+    for (let i = targetCount; i < targetSkins.length; i++) {
+      if (typeof targetSkins[i] === "undefined") continue;
+      update_bubble(i, "say", "");
+      this.#renderer.destroyDrawable(targetSkins[i][1], "sprite");
+      this.#renderer.destroySkin(targetSkins[i][0]);
+    }
+    targetSkins.length = targetCount;
+  }
+
   mark_question_resolved(struct) {
     this.#mark_question_resolved_func(struct);
   }
@@ -334,6 +397,11 @@ export class ProjectRunner extends EventTarget {
         if (key.length > 1) return;
         keyName = key.toUpperCase();
     }
+    if (pressed && !this.#keysPressed[keyName]) {
+      this.#keyPressedTriggers[keyName.toLowerCase()]?.();
+      this.#keyPressedTriggers["any"]?.();
+      if (!this.#running) this.run();
+    }
     this.#keysPressed[keyName] = pressed;
   }
 
@@ -341,13 +409,27 @@ export class ProjectRunner extends EventTarget {
     return this.#keysPressed[key] ?? false;
   }
 
+  #playSound(target_index, instance, sound_index, state, struct) {
+    const done = () => {
+      if (struct !== null) this.#mark_question_resolved_func(struct);
+    };
+    // listeners which actually play the sound should call preventDefault(), and then call
+    // `done` once the sound has finished; otherwise the sound finishes immediately.
+    const event = new CustomEvent("playSound", {
+      detail: { target_index, instance, sound_index, state, done },
+      cancelable: true,
+    });
+    if (this.dispatchEvent(event)) done();
+  }
+
   #pickMouseOverTarget(x, y) {
     // adapted from https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/io/mouse.js#L40
     // (licensed under BSD-3.0 - see https://raw.githubusercontent.com/scratchfoundation/scratch-vm/8dbcc1f/LICENSE)
     const drawableID = this.#renderer.pick(x, y);
     const targetSkins = target_skins();
     for (let i = 0; i < targetSkins.length; i++) {
-      const thisDrawableID = targetSkins[i][1];
+      // clones which have been deleted leave holes in the array
+      const thisDrawableID = targetSkins[i]?.[1];
       if (thisDrawableID === drawableID) {
         return i;
       }
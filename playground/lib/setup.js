@@ -17,7 +17,16 @@ export async function setup(
   project_json,
   assets,
   target_names,
-  { queue_question, update_var_val, update_var_visible, get_key_pressed },
+  layer_orders,
+  {
+    queue_question,
+    update_var_val,
+    update_var_visible,
+    get_key_pressed,
+    play_sound,
+    stop_all_sounds,
+    update_sound_state,
+  },
 ) {
   if (is_setup()) return;
 
@@ -38,7 +47,15 @@ export async function setup(
   renderer.setLayerGroupOrdering(["background", "video", "pen", "sprite"]);
   const pen_skin = renderer.createSkin("pen", "pen")[0];
 
-  const target_skins = project_json.targets.map((target, index) => {
+  const target_skins = [];
+  // drawables are created on top of their layer group, so create them from back to front
+  const layerOrder = (index) =>
+    layer_orders?.[index] ?? project_json.targets[index].layerOrder ?? 0;
+  const targetIndices = project_json.targets
+    .map((_, index) => index)
+    .sort((a, b) => layerOrder(a) - layerOrder(b));
+  for (const index of targetIndices) {
+    const target = project_json.targets[index];
     const realCostume = target.costumes[target.currentCostume];
     const costume = costumes[index][target.currentCostume];
     if (costume.dataFormat.toLowerCase() !== "svg") {
@@ -56,16 +73,20 @@ export async function setup(
     if (!target.isStage) {
       drawable.updateVisible(!!target.visible);
       drawable.updatePosition([target.x, target.y]);
-      drawable.updateDirection(target.direction);
-      drawable.updateScale([target.size, target.size]);
+      // see js/motion/pointindirection.ts
+      const rotates = (target.rotationStyle ?? "all around") === "all around";
+      const flip =
+        target.rotationStyle === "left-right" && target.direction < 0 ? -1 : 1;
+      drawable.updateDirection(rotates ? target.direction : 90);
+      drawable.updateScale([flip * target.size, target.size]);
     } else {
       drawable.updateVisible(true);
       drawable.updatePosition([0, 0]);
       drawable.updateDirection(90);
       drawable.updateScale([100, 100]);
     }
-    return [skin, drawableId];
-  });
+    target_skins[index] = [skin, drawableId];
+  }
 
   const stageIndex = project_json.targets.findIndex((target) => target.isStage);
 
@@ -81,6 +102,9 @@ export async function setup(
       update_var_val,
       update_var_visible,
       get_key_pressed,
+      play_sound,
+      stop_all_sounds,
+      update_sound_state,
     },
   );
 }
@@ -1,32 +1,59 @@
 use alloc::boxed::Box;
 use core::cell::{BorrowError, BorrowMutError};
 
+use serde::Serialize;
 use wasm_bindgen::JsValue;
 
 pub type HQResult<T> = Result<T, HQError>;
 
-#[derive(Clone, Debug)]
+/// An error emitted by the compiler.
+///
+/// `file`, `line` and `column` refer to the compiler's own source code; `location` refers to the
+/// block in the Scratch project which was being compiled when the error occurred, if known.
+///
+/// This is passed to JS as a plain object with the same fields as this struct.
+#[derive(Clone, Debug, Serialize)]
 pub struct HQError {
     pub err_type: HQErrorType,
     pub msg: Box<str>,
     pub file: Box<str>,
     pub line: u32,
     pub column: u32,
+    pub location: Option<Box<BlockLocation>>,
 }
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub enum HQErrorType {
     MalformedProject,
     InternalError,
     Unimplemented,
 }
 
+/// The block in a Scratch project that an error originated from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BlockLocation {
+    pub block_id: Box<str>,
+    /// the name of the target that the block belongs to
+    pub target: Box<str>,
+    /// the proccode of the custom block whose definition the block is in, if any
+    pub proccode: Option<Box<str>>,
+}
+
+impl HQError {
+    /// Attaches a block location to this error, unless it already has one. Because errors
+    /// propagate outwards, this means that the innermost block which was being compiled is the
+    /// one that gets reported.
+    #[must_use]
+    pub fn with_location(mut self, location: BlockLocation) -> Self {
+        if self.location.is_none() {
+            self.location = Some(Box::new(location));
+        }
+        self
+    }
+}
+
 impl From<HQError> for JsValue {
     fn from(val: HQError) -> Self {
-        Self::from_str(match val.err_type {
-            HQErrorType::Unimplemented => format!("todo: {}<br>at {}:{}:{}<br>this is a bug or missing feature that is known and will be fixed or implemented in a future update", val.msg, val.file, val.line, val.column),
-            HQErrorType::InternalError => format!("error: {}<br>at {}:{}:{}<br>this is probably a bug with HyperQuark itself. Please report this bug, with this error message, at <a href=\"https://github.com/hyperquark/hyperquark/issues/new\">https://github.com/hyperquark/hyperquark/issues/new</a>", val.msg, val.file, val.line, val.column),
-            HQErrorType::MalformedProject => format!("error: {}<br>at {}:{}:{}<br>this is probably a problem with the project itself, but if it works in vanilla scratch then this is a bug; please report it, by creating an issue at <a href=\"https://github.com/hyperquark/hyperquark/issues/new\">https://github.com/hyperquark/hyperquark/issues/new</a>, including this error message", val.msg, val.file, val.line, val.column),
-        }.as_str())
+        serde_wasm_bindgen::to_value(&val).unwrap_or_else(|_| Self::from_str(&val.msg))
     }
 }
 
@@ -57,6 +84,7 @@ impl From<BorrowError> for HQError {
             file: file!().into(),
             line: line!(),
             column: column!(),
+            location: None,
         }
     }
 }
@@ -74,6 +102,7 @@ impl From<BorrowMutError> for HQError {
             file: file!().into(),
             line: line!(),
             column: column!(),
+            location: None,
         }
     }
 }
@@ -88,7 +117,8 @@ macro_rules! hq_todo {
             msg: "todo".into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         });
     }};
     ($($args:tt)+) => {#[cfg_attr(feature = "panic", expect(unreachable_code, reason = "panic infrastructure only for debugging"))]{
@@ -98,7 +128,8 @@ macro_rules! hq_todo {
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         });
     }};
 }
@@ -113,7 +144,8 @@ macro_rules! hq_bug {
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         });
     }};
 }
@@ -129,7 +161,8 @@ macro_rules! hq_assert {
                 msg: format!("Assertion failed: {}", stringify!($expr)).into(),
                 file: file!().into(),
                 line: line!(),
-                column: column!()
+                column: column!(),
+                location: None,
             });
         };
         assert!($expr);
@@ -142,7 +175,8 @@ macro_rules! hq_assert {
                 msg: format!("Assertion failed: {}\nMessage: {}", stringify!($expr), format_args!($($args)*)).into(),
                 file: file!().into(),
                 line: line!(),
-                column: column!()
+                column: column!(),
+                location: None,
             });
         };
         assert!($expr);
@@ -160,7 +194,8 @@ macro_rules! hq_assert_eq {
                 msg: format!("Assertion failed: {} == {}\nLeft: {}\nRight: {}", stringify!($l), stringify!($r), $l, $r).into(),
                 file: file!().into(),
                 line: line!(),
-                column: column!()
+                column: column!(),
+                location: None,
             });
         };
         assert_eq!($l, $r);
@@ -173,7 +208,8 @@ macro_rules! hq_assert_eq {
                 msg: format!("Assertion failed: {} == {}\nLeft: {}\nRight: {}\nMessage: {}", stringify!($l), stringify!($r), $l, $r, format_args!($($args)*)).into(),
                 file: file!().into(),
                 line: line!(),
-                column: column!()
+                column: column!(),
+                location: None,
             });
         };
         assert_eq!($l, $r);
@@ -190,7 +226,8 @@ macro_rules! hq_bad_proj {
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         });
     }};
 }
@@ -201,13 +238,13 @@ macro_rules! hq_bad_proj {
 macro_rules! make_hq_todo {
     ($($args:tt)+) => {#[cfg_attr(feature = "panic", expect(unreachable_code, reason = "panic infrastructure only for debugging"))]{
         maybe_panic!($($args)+);
-        use $crate::alloc::Box<str>::ToBox<str>;
         return $crate::HQError {
             err_type: $crate::HQErrorType::Unimplemented,
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         }
     }};
 }
@@ -223,7 +260,8 @@ macro_rules! make_hq_bug {
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         }
     }};
 }
@@ -239,7 +277,8 @@ macro_rules! make_hq_bad_proj {
             msg: format!("{}", format_args!($($args)*)).into(),
             file: file!().into(),
             line: line!(),
-            column: column!()
+            column: column!(),
+            location: None,
         }
     }};
 }
@@ -25,6 +25,7 @@ mod operator;
 mod pen;
 mod procedures;
 mod sensing;
+mod sound;
 
 #[macro_use]
 mod tests;
@@ -100,18 +101,34 @@ impl IrOpcode {
         )]
         match self {
             Self::hq_yield(HqYieldFields {
-                mode: YieldMode::Schedule(next_step),
+                mode:
+                    YieldMode::Schedule(next_step)
+                    | YieldMode::MaybeSchedule(next_step)
+                    | YieldMode::WarpTimer(next_step),
             })
             | Self::event_broadcast_and_wait(EventBroadcastAndWaitFields { next_step, .. })
             | Self::procedures_call_nonwarp(ProceduresCallNonwarpFields { next_step, .. })
             | Self::control_wait(ControlWaitFields { next_step, .. })
-            | Self::sensing_askandwait(SensingAskandwaitFields { next_step, .. }) => {
+            | Self::sensing_askandwait(SensingAskandwaitFields { next_step, .. })
+            | Self::sound_playuntildone(SoundPlayuntildoneFields { next_step, .. }) => {
                 Some(*next_step)
             }
             _ => None,
         }
     }
 
+    /// Does this opcode read state which can be changed by other blocks within the same frame
+    /// (e.g. the positions of sprites on the stage), rather than being a pure function of its
+    /// inputs? Such opcodes must not be reordered relative to other blocks.
+    pub const fn depends_on_frame_state(&self) -> bool {
+        matches!(
+            self,
+            Self::sensing_touchingobject
+                | Self::sensing_touchingcolor
+                | Self::sensing_coloristouchingcolor
+        )
+    }
+
     pub fn inline_steps(&self, ignore_conditions: bool) -> Option<Box<[Rc<RefCell<Step>>]>> {
         #[expect(
             clippy::wildcard_enum_match_arm,
@@ -195,6 +212,10 @@ impl IrOpcode {
 mod wrap_instructions;
 
 pub use hq::r#yield::YieldMode;
+pub use looks::GraphicEffect;
+pub use motion::RotationStyle;
+pub use sensing::TargetAttribute;
+pub use sound::SoundEffect;
 pub use wrap_instructions::wrap_instructions;
 
 /// Canonical NaN + bit 33, + string pointer in bits 1-32
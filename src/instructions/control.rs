@@ -1,5 +1,8 @@
+pub mod create_clone_of;
+pub mod delete_this_clone;
 pub mod get_thread_timeout;
 pub mod if_else;
 pub mod r#loop;
 pub mod stop_all;
+pub mod stop_other_scripts_in_sprite;
 pub mod wait;
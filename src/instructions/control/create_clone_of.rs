@@ -0,0 +1,274 @@
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::{BlockType, MemArg};
+
+use super::super::prelude::*;
+use crate::ir::Target;
+use crate::wasm::mem_layout;
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub target: Rc<Target>,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target": {}
+    }}"#,
+            self.target.index()
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields { target }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    if target.is_stage() {
+        hq_bug!("tried to create a clone of the stage")
+    }
+    let registries = func.registries();
+    registries.clones().register(target)?;
+
+    let sprite_index = registries.sprites().register_default(Rc::clone(target))?;
+    let sprite_offset = mem_layout::sprite_offset(sprite_index);
+    let is_myself = func.target_index() == target.index();
+
+    let create_clone_func = registries.external_functions().register(
+        ("control", "create_clone".into()),
+        (vec![ValType::I32, ValType::I32, ValType::I32], vec![]),
+    )?;
+    let clones_count = registries.globals().clones_count()?;
+    let current_instance = registries.globals().current_instance()?;
+    let mem_offset = registries.globals().instance_mem_offset()?;
+    let active_slot = registries.clones().active_slot_global(target)?;
+
+    let target_count: i32 = func
+        .target_count()?
+        .try_into()
+        .map_err(|_| make_hq_bug!("target count out of bounds"))?;
+    let target_index: i32 = target
+        .index()
+        .try_into()
+        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let block_size: i32 = sprite_layout::BLOCK_SIZE
+        .try_into()
+        .map_err(|_| make_hq_bug!("sprite block size out of bounds"))?;
+    let sprite_offset_i32: i32 = sprite_offset
+        .try_into()
+        .map_err(|_| make_hq_bug!("sprite offset out of bounds"))?;
+    let sprite_index_i32: i32 = sprite_index
+        .try_into()
+        .map_err(|_| make_hq_bug!("sprite index out of bounds"))?;
+    // clone slots are 1-indexed, so slot `n` lives at `slot_base + n * block_size`
+    let slot_base = mem_layout::clone_pool_offset(func.target_count()?) - sprite_layout::BLOCK_SIZE;
+    let slot_base_i32: i32 = slot_base
+        .try_into()
+        .map_err(|_| make_hq_bug!("clone pool offset out of bounds"))?;
+
+    let slot_local = func.local(ValType::I32)?;
+    let src_slot_local = func.local(ValType::I32)?;
+    let instance_local = func.local(ValType::I32)?;
+    func.free_local(slot_local)?;
+    func.free_local(src_slot_local)?;
+    func.free_local(instance_local)?;
+
+    // a sprite can clone itself, in which case the clone is a copy of the current instance
+    // (which may itself be a clone); otherwise the clone is a copy of the original sprite.
+    let (src_mem_offset, parent_instance, src_slot) = if is_myself {
+        (
+            wasm![#LazyGlobalGet(mem_offset)],
+            wasm![#LazyGlobalGet(current_instance)],
+            wasm![
+                #LazyGlobalGet(current_instance),
+                I32Const(target_count),
+                I32DivU,
+            ],
+        )
+    } else {
+        (
+            wasm![I32Const(0)],
+            wasm![I32Const(target_index)],
+            wasm![I32Const(0)],
+        )
+    };
+
+    Ok(wasm![
+        #LazyGlobalGet(clones_count),
+        I32Const(
+            mem_layout::MAX_CLONES
+                .try_into()
+                .map_err(|_| make_hq_bug!("MAX_CLONES out of bounds"))?
+        ),
+        I32LtU,
+        If(BlockType::Empty),
+        // find a free slot for the clone; there will always be one, as the number of slots in
+        // the clone pool is the same as the maximum number of clones
+        I32Const(1),
+        LocalSet(slot_local),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I32Load8U(MemArg {
+            offset: (slot_base + sprite_layout::CLONE_ACTIVE).into(),
+            align: 0,
+            memory_index: 0,
+        }),
+        I32Eqz,
+        BrIf(1),
+        LocalGet(slot_local),
+        I32Const(1),
+        I32Add,
+        LocalSet(slot_local),
+        Br(0),
+        End,
+        End,
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I32Const(slot_base_i32),
+        I32Add,
+    ]
+    .into_iter()
+    .chain(src_mem_offset)
+    .chain(wasm![
+        I32Const(sprite_offset_i32),
+        I32Add,
+        I32Const(block_size),
+        MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        },
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I32Const(1),
+        I32Store8(MemArg {
+            offset: (slot_base + sprite_layout::CLONE_ACTIVE).into(),
+            align: 0,
+            memory_index: 0,
+        }),
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I32Const(sprite_index_i32),
+        I32Store16(MemArg {
+            offset: (slot_base + sprite_layout::CLONE_OWNER).into(),
+            align: 1,
+            memory_index: 0,
+        }),
+        // the clone hasn't been checked by any touching hats yet
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I64Const(0),
+        I64Store(MemArg {
+            offset: (slot_base + sprite_layout::TOUCHING_HATS).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(clones_count),
+        I32Const(1),
+        I32Add,
+        #LazyGlobalSet(clones_count),
+        LocalGet(slot_local),
+        I32Const(target_count),
+        I32Mul,
+        I32Const(target_index),
+        I32Add,
+        LocalSet(instance_local),
+    ])
+    .chain(parent_instance)
+    .chain(wasm![
+        LocalGet(instance_local),
+        LocalGet(slot_local),
+        I32Const(block_size),
+        I32Mul,
+        I32Load(MemArg {
+            offset: (slot_base + sprite_layout::COSTUME).into(),
+            align: 2,
+            memory_index: 0,
+        }),
+        Call(create_clone_func),
+    ])
+    .chain(registries.clones().save_instructions(
+        target,
+        registries.variables(),
+        registries.lists(),
+        func.flags().list_type,
+    )?)
+    .chain(src_slot)
+    .chain(wasm![LocalSet(src_slot_local)])
+    .chain(registries.clones().copy_instructions(
+        target,
+        src_slot_local,
+        slot_local,
+        registries.variables(),
+        registries.lists(),
+        func.flags().list_type,
+        registries.static_functions(),
+        registries.tables(),
+    )?)
+    // the new clone may have been given the slot of a deleted clone whose variables are still
+    // loaded, so we reload the active slot to be safe
+    .chain(wasm![#LazyGlobalGet(active_slot), LocalSet(src_slot_local)])
+    .chain(registries.clones().load_instructions(
+        target,
+        src_slot_local,
+        registries.variables(),
+        registries.lists(),
+        func.flags().list_type,
+    )?)
+    .chain(wasm![
+        #LazyCloneStartSpawn((target.index(), instance_local)),
+        End,
+    ])
+    .collect())
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::{assert_valid_json, make_target};
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields();
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields() -> Fields {
+        Fields {
+            target: make_target(),
+        }
+    }
+}
+
+crate::instructions_test!(
+    mod tests for control_create_clone_of {
+        fields = super::test::make_fields();
+    }
+);
@@ -0,0 +1,162 @@
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::{BlockType, MemArg};
+
+use super::super::prelude::*;
+use crate::ir::Target;
+use crate::wasm::registries::functions::static_functions::DeleteInstanceThreads;
+use crate::wasm::{StepTarget, ThreadsTable, mem_layout};
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub target: Rc<Target>,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target": {}
+    }}"#,
+            self.target.index()
+        )
+    }
+}
+
+/// Deletes the current sprite instance if it is a clone, stopping all of its other threads and
+/// freeing its lists' buffers (if lists are stored in linear memory).
+///
+/// Outputs `true` if the instance was deleted, in which case the current thread should be
+/// stopped (by yielding) straight away; the current thread is marked as having no frames to
+/// return to, so that yielding will always end it.
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields { target }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("control_delete_this_clone called in stage")
+    };
+    let delete_clone_func = func.registries().external_functions().register(
+        ("control", "delete_clone".into()),
+        (vec![ValType::I32], vec![]),
+    )?;
+    let delete_threads_func = func
+        .registries()
+        .static_functions()
+        .register::<DeleteInstanceThreads, _>()?;
+    let threads_table = func.registries().tables().register::<ThreadsTable, _>()?;
+    let thread_struct_ty = func.registries().types().thread_struct_type()?;
+    let clones_count = func.registries().globals().clones_count()?;
+    let current_instance = func.registries().globals().current_instance()?;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let thread_index_local: u32 = (func.params().len() - 2)
+        .try_into()
+        .map_err(|_| make_hq_bug!("local index out of bounds"))?;
+    // a sprite which is never cloned can't be running as a clone, so it has nothing to free
+    let free_lists = if target.is_clonable()? {
+        func.registries().clones().free_instructions(
+            target,
+            func.registries().lists(),
+            func.flags().list_type,
+            func.registries().static_functions(),
+        )?
+    } else {
+        vec![]
+    };
+
+    Ok(wasm![
+        #LazyGlobalGet(current_instance),
+        I32Const(
+            func.target_count()?
+                .try_into()
+                .map_err(|_| make_hq_bug!("target count out of bounds"))?
+        ),
+        I32GeU,
+        If(BlockType::Result(ValType::I32)),
+        #LazyGlobalGet(mem_offset),
+        I32Const(0),
+        I32Store8(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::CLONE_ACTIVE)
+                .into(),
+            align: 0,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(clones_count),
+        I32Const(1),
+        I32Sub,
+        #LazyGlobalSet(clones_count),
+        #LazyGlobalGet(current_instance),
+        Call(delete_clone_func),
+        #LazyGlobalGet(current_instance),
+        LocalGet(thread_index_local),
+        #StaticFunctionCall(delete_threads_func),
+    ]
+    .into_iter()
+    .chain(free_lists)
+    .chain(wasm![
+        LocalGet(thread_index_local),
+        TableGet(threads_table),
+        RefAsNonNull,
+        I32Const(1),
+        StructSet {
+            struct_type_index: thread_struct_ty,
+            field_index: 0,
+        },
+        I32Const(1),
+        Else,
+        I32Const(0),
+        End,
+    ])
+    .collect())
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(Singleton(IrType::Boolean))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::{assert_valid_json, make_target};
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields(false);
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields(clonable: bool) -> Fields {
+        let target = make_target();
+        if clonable {
+            target.mark_clonable().unwrap();
+        }
+        Fields { target }
+    }
+}
+
+crate::instructions_test!(
+    mod tests for control_delete_this_clone {
+        fields = super::test::make_fields(false);
+    }
+);
+
+crate::instructions_test!(
+    mod tests_clonable for control_delete_this_clone {
+        fields = super::test::make_fields(true);
+    }
+);
@@ -0,0 +1,41 @@
+use super::super::prelude::*;
+use crate::instructions_test;
+use crate::wasm::registries::functions::static_functions::DeleteInstanceThreads;
+
+/// Stops all threads belonging to the current sprite instance (or the stage), apart from the
+/// current thread. Other clones of the same sprite are unaffected.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_control.js#L153>
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let delete_threads_func = func
+        .registries()
+        .static_functions()
+        .register::<DeleteInstanceThreads, _>()?;
+    let current_instance = func.registries().globals().current_instance()?;
+
+    Ok(wasm![
+        #LazyGlobalGet(current_instance),
+        LocalGet((func.params().len() - 2).try_into().map_err(|_| make_hq_bug!("local index out of bounds"))?),
+        #StaticFunctionCall(delete_threads_func),
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+instructions_test!(
+    mod test for control_stop_other_scripts_in_sprite {}
+);
@@ -1,10 +1,10 @@
-use wasm_encoder::{AbstractHeapType, ConstExpr, FieldType, HeapType, StorageType};
+use wasm_encoder::{AbstractHeapType, FieldType, HeapType, StorageType};
 
 use super::super::prelude::*;
 use crate::instructions_test;
 use crate::ir::StepIndex;
+use crate::wasm::StepFunc;
 use crate::wasm::registries::functions::static_functions::SpawnThreadInStack;
-use crate::wasm::{GlobalExportable, GlobalMutable, StepFunc};
 
 #[derive(Clone, Debug)]
 pub struct Fields {
@@ -52,15 +52,7 @@ pub fn wasm(
 
     func.free_local(struct_local)?;
 
-    let timer_global_index = func.registries().globals().register(
-        "sensing_timer".into(),
-        (
-            ValType::F64,
-            ConstExpr::f64_const(0.0.into()),
-            GlobalMutable(true),
-            GlobalExportable(true),
-        ),
-    )?;
+    let timer_global_index = func.registries().globals().sensing_timer()?;
 
     Ok(
         if t1.contains(IrType::FloatNeg) {
@@ -134,6 +126,7 @@ mod test {
             flags,
             StepTarget::Sprite(0),
             0,
+            1,
             Rc::new(vec![]),
         );
         wasm_proj.steps().borrow_mut().push(step_func);
@@ -1,3 +1,13 @@
+use mem_layout::list_header;
+use registries::ListRegistry;
+use registries::functions::static_functions::{CopyListBuffer, FreeListBuffer};
+use wasm_encoder::{BlockType as WasmBlockType, MemArg};
+
+use super::prelude::*;
+use crate::ir::RcList;
+use crate::wasm::flags::ListType;
+use crate::wasm::{StringsTable, WasmProject, mem_layout, registries};
+
 pub mod addtolist;
 pub mod deletealloflist;
 pub mod deleteoflist;
@@ -10,3 +20,322 @@ pub mod setvariableto;
 pub mod teevariable;
 pub mod variable;
 pub mod visvariable;
+
+// The functions below hide the differences between the list representations selected by the
+// `list_type` flag. Indices are 0-indexed i32s, and items are of the list's wasm type.
+
+fn header_mem_arg(field: u32) -> MemArg {
+    MemArg {
+        offset: field.into(),
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+fn item_mem_arg(list: &RcList) -> MemArg {
+    MemArg {
+        offset: list_header::BLOCK_SIZE.into(),
+        align: if ListRegistry::item_size(list) == 8 { 3 } else { 2 },
+        memory_index: 0,
+    }
+}
+
+/// Replaces the index at the top of the stack with the address of that item in the list's
+/// buffer, minus the size of the header. Only for lists stored in linear memory.
+fn item_address(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    let pointer_global = func.registries().lists().register_linear_memory(list)?;
+    let item_size = ListRegistry::item_size(list)
+        .try_into()
+        .map_err(|_| make_hq_bug!("list item size out of bounds"))?;
+    Ok(wasm![
+        I32Const(item_size),
+        I32Mul,
+        #LazyGlobalGet(pointer_global),
+        I32Add,
+    ])
+}
+
+/// Replaces the index at the top of the stack with the index of that item's slot in the strings
+/// table. Only for lists of strings stored in linear memory.
+fn string_slot(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    let pointer_global = func.registries().lists().register_linear_memory(list)?;
+    Ok(wasm![
+        #LazyGlobalGet(pointer_global),
+        I32Load(header_mem_arg(list_header::STRING_SLOTS)),
+        I32Add,
+    ])
+}
+
+/// Pushes the length of the list.
+fn list_length(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    if !*list.length_mutable().borrow() {
+        let length = list
+            .initial_value()
+            .len()
+            .try_into()
+            .map_err(|_| make_hq_bug!("list initial value length out of bounds"))?;
+        return Ok(wasm![I32Const(length)]);
+    }
+    Ok(match func.flags().list_type {
+        ListType::GCArray => {
+            let (_, Some(length_global)) = func.registries().lists().register(list)? else {
+                hq_bug!("list with mutable length should have a length global")
+            };
+            wasm![#LazyGlobalGet(length_global)]
+        }
+        ListType::LinearMemory => {
+            let pointer_global = func.registries().lists().register_linear_memory(list)?;
+            wasm![
+                #LazyGlobalGet(pointer_global),
+                I32Load(header_mem_arg(list_header::LENGTH)),
+            ]
+        }
+    })
+}
+
+/// Sets the length of the list to the i32 at the top of the stack.
+fn set_list_length(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    Ok(match func.flags().list_type {
+        ListType::GCArray => {
+            let (_, Some(length_global)) = func.registries().lists().register(list)? else {
+                hq_bug!("tried to set the length of a list with immutable length")
+            };
+            wasm![#LazyGlobalSet(length_global)]
+        }
+        ListType::LinearMemory => {
+            let pointer_global = func.registries().lists().register_linear_memory(list)?;
+            let length_local = func.local(ValType::I32)?;
+            func.free_local(length_local)?;
+            wasm![
+                LocalSet(length_local),
+                #LazyGlobalGet(pointer_global),
+                LocalGet(length_local),
+                I32Store(header_mem_arg(list_header::LENGTH)),
+            ]
+        }
+    })
+}
+
+/// Replaces the index at the top of the stack with the item at that index.
+fn list_item(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    let item_type = WasmProject::ir_type_to_wasm(*list.possible_types());
+    Ok(match func.flags().list_type {
+        ListType::GCArray => {
+            let (list_global, _) = func.registries().lists().register::<u32>(list)?;
+            let array_type = func.registries().lists().array_type(list)?;
+            let index_local = func.local(ValType::I32)?;
+            func.free_local(index_local)?;
+            wasm![
+                LocalSet(index_local),
+                #LazyGlobalGet(list_global),
+                LocalGet(index_local),
+                ArrayGet(array_type),
+            ]
+        }
+        ListType::LinearMemory if ListRegistry::has_string_slots(list) => {
+            let strings_table = func.registries().tables().register::<StringsTable, _>()?;
+            string_slot(func, list)?
+                .into_iter()
+                .chain(wasm![TableGet(strings_table)])
+                .collect()
+        }
+        ListType::LinearMemory => {
+            let mem_arg = item_mem_arg(list);
+            item_address(func, list)?
+                .into_iter()
+                .chain(match item_type {
+                    ValType::F64 => wasm![F64Load(mem_arg)],
+                    ValType::I64 => wasm![I64Load(mem_arg)],
+                    _ => wasm![I32Load(mem_arg)],
+                })
+                .collect()
+        }
+    })
+}
+
+/// Sets the item of the list at the index below the top of the stack to the item at the top of
+/// the stack.
+fn set_list_item(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    let item_type = WasmProject::ir_type_to_wasm(*list.possible_types());
+    let item_local = func.local(item_type)?;
+    let instructions = match func.flags().list_type {
+        ListType::GCArray => {
+            let (list_global, _) = func.registries().lists().register::<u32>(list)?;
+            let array_type = func.registries().lists().array_type(list)?;
+            let index_local = func.local(ValType::I32)?;
+            func.free_local(index_local)?;
+            func.persist_string(*list.possible_types())?
+                .into_iter()
+                .chain(wasm![
+                    LocalSet(item_local),
+                    LocalSet(index_local),
+                    #LazyGlobalGet(list_global),
+                    LocalGet(index_local),
+                    LocalGet(item_local),
+                    ArraySet(array_type),
+                ])
+                .collect()
+        }
+        ListType::LinearMemory if ListRegistry::has_string_slots(list) => {
+            let strings_table = func.registries().tables().register::<StringsTable, _>()?;
+            wasm![LocalSet(item_local)]
+                .into_iter()
+                .chain(string_slot(func, list)?)
+                .chain(wasm![LocalGet(item_local), TableSet(strings_table)])
+                .collect()
+        }
+        ListType::LinearMemory => {
+            let mem_arg = item_mem_arg(list);
+            wasm![LocalSet(item_local)]
+                .into_iter()
+                .chain(item_address(func, list)?)
+                .chain(wasm![LocalGet(item_local)])
+                .chain(match item_type {
+                    ValType::F64 => wasm![F64Store(mem_arg)],
+                    ValType::I64 => wasm![I64Store(mem_arg)],
+                    _ => wasm![I32Store(mem_arg)],
+                })
+                .collect()
+        }
+    };
+    func.free_local(item_local)?;
+    Ok(instructions)
+}
+
+/// Copies items within the list. Takes the destination index, the source index and the number of
+/// items to copy, in that order; the ranges may overlap.
+fn copy_list_items(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    let src_local = func.local(ValType::I32)?;
+    let count_local = func.local(ValType::I32)?;
+    let instructions = match func.flags().list_type {
+        ListType::GCArray => {
+            let (list_global, _) = func.registries().lists().register::<u32>(list)?;
+            let array_type = func.registries().lists().array_type(list)?;
+            let dst_local = func.local(ValType::I32)?;
+            func.free_local(dst_local)?;
+            wasm![
+                LocalSet(count_local),
+                LocalSet(src_local),
+                LocalSet(dst_local),
+                #LazyGlobalGet(list_global),
+                LocalGet(dst_local),
+                #LazyGlobalGet(list_global),
+                LocalGet(src_local),
+                LocalGet(count_local),
+                ArrayCopy {
+                    array_type_index_dst: array_type,
+                    array_type_index_src: array_type,
+                },
+            ]
+        }
+        ListType::LinearMemory if ListRegistry::has_string_slots(list) => {
+            let strings_table = func.registries().tables().register::<StringsTable, _>()?;
+            wasm![LocalSet(count_local), LocalSet(src_local)]
+                .into_iter()
+                .chain(string_slot(func, list)?)
+                .chain(wasm![LocalGet(src_local)])
+                .chain(string_slot(func, list)?)
+                .chain(wasm![
+                    LocalGet(count_local),
+                    TableCopy {
+                        src_table: strings_table,
+                        dst_table: strings_table,
+                    },
+                ])
+                .collect()
+        }
+        ListType::LinearMemory => {
+            let header_size = list_header::BLOCK_SIZE
+                .try_into()
+                .map_err(|_| make_hq_bug!("list header size out of bounds"))?;
+            let item_size = ListRegistry::item_size(list)
+                .try_into()
+                .map_err(|_| make_hq_bug!("list item size out of bounds"))?;
+            wasm![LocalSet(count_local), LocalSet(src_local)]
+                .into_iter()
+                .chain(item_address(func, list)?)
+                .chain(wasm![I32Const(header_size), I32Add, LocalGet(src_local)])
+                .chain(item_address(func, list)?)
+                .chain(wasm![
+                    I32Const(header_size),
+                    I32Add,
+                    LocalGet(count_local),
+                    I32Const(item_size),
+                    I32Mul,
+                    MemoryCopy {
+                        src_mem: 0,
+                        dst_mem: 0,
+                    },
+                ])
+                .collect()
+        }
+    };
+    func.free_local(src_local)?;
+    func.free_local(count_local)?;
+    Ok(instructions)
+}
+
+/// Makes sure that there is room in the list for one more item, which may move its buffer (freeing
+/// the old one). The list must have fewer than [`mem_layout::MAX_LIST_LENGTH`] items.
+fn reserve_list_item(func: &StepFunc, list: &RcList) -> HQResult<Vec<InternalInstruction>> {
+    if func.flags().list_type == ListType::GCArray {
+        // arrays of lists with mutable length are always allocated at their maximum length
+        return Ok(vec![]);
+    }
+    let pointer_global = func.registries().lists().register_linear_memory(list)?;
+    let copy_func = func
+        .registries()
+        .static_functions()
+        .register::<CopyListBuffer, _>()?;
+    let free_func = func
+        .registries()
+        .static_functions()
+        .register::<FreeListBuffer, _>()?;
+    let item_size = ListRegistry::item_size(list)
+        .try_into()
+        .map_err(|_| make_hq_bug!("list item size out of bounds"))?;
+    let max_length = mem_layout::MAX_LIST_LENGTH
+        .try_into()
+        .map_err(|_| make_hq_bug!("maximum list length out of bounds"))?;
+    let capacity_local = func.local(ValType::I32)?;
+    let old_pointer_local = func.local(ValType::I32)?;
+    func.free_local(capacity_local)?;
+    func.free_local(old_pointer_local)?;
+    Ok(wasm![
+        #LazyGlobalGet(pointer_global),
+        I32Load(header_mem_arg(list_header::LENGTH)),
+        #LazyGlobalGet(pointer_global),
+        I32Load(header_mem_arg(list_header::CAPACITY)),
+        LocalTee(capacity_local),
+        I32Eq,
+        If(WasmBlockType::Empty),
+        #LazyGlobalGet(pointer_global),
+        LocalTee(old_pointer_local),
+        I32Const(item_size),
+        // double the capacity, up to the maximum length of a list
+        LocalGet(capacity_local),
+        I32Const(1),
+        I32Shl,
+        LocalTee(capacity_local),
+        I32Const(max_length),
+        LocalGet(capacity_local),
+        I32Const(max_length),
+        I32LtU,
+        Select,
+        #StaticFunctionCall(copy_func),
+        #LazyGlobalSet(pointer_global),
+    ]
+    .into_iter()
+    .chain(ListRegistry::copy_string_items(
+        list,
+        func.registries().tables(),
+        &wasm![LocalGet(old_pointer_local)],
+        &wasm![#LazyGlobalGet(pointer_global)],
+    )?)
+    .chain(wasm![
+        LocalGet(old_pointer_local),
+        #StaticFunctionCall(free_func),
+        End,
+    ])
+    .collect())
+}
@@ -1,6 +1,7 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use super::{list_length, reserve_list_item, set_list_item, set_list_length};
 use crate::ir::RcList;
 use crate::wasm::WasmProject;
 
@@ -28,35 +29,30 @@ pub fn wasm(
     Fields { list }: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
     let t = inputs[0];
-    let (list_global, Some(length_global)) = func.registries().lists().register(list)? else {
+    if !*list.length_mutable().borrow() {
         hq_bug!("tried to addtolist of a list with immutable length")
-    };
+    }
     let local = func.local(WasmProject::ir_type_to_wasm(*list.possible_types()))?;
-    func.free_local(local)?;
-    let array_type = func.registries().lists().array_type(list)?;
-    Ok(if list.possible_types().is_base_type() {
+    let instructions = if list.possible_types().is_base_type() {
         vec![]
     } else {
         wasm![@boxed(t)]
     }
     .into_iter()
-    .chain(wasm![
-        LocalSet(local),
-        #LazyGlobalGet(length_global),
-        I32Const(200_000),
-        I32LtS,
-        If(WasmBlockType::Empty),
-        #LazyGlobalGet(list_global),
-        #LazyGlobalGet(length_global),
-        LocalGet(local),
-        ArraySet(array_type),
-        #LazyGlobalGet(length_global),
-        I32Const(1),
-        I32Add,
-        #LazyGlobalSet(length_global),
-        End,
-    ])
-    .collect())
+    .chain(wasm![LocalSet(local)])
+    .chain(list_length(func, list)?)
+    .chain(wasm![I32Const(200_000), I32LtS, If(WasmBlockType::Empty)])
+    .chain(reserve_list_item(func, list)?)
+    .chain(list_length(func, list)?)
+    .chain(wasm![LocalGet(local)])
+    .chain(set_list_item(func, list)?)
+    .chain(list_length(func, list)?)
+    .chain(wasm![I32Const(1), I32Add])
+    .chain(set_list_length(func, list)?)
+    .chain(wasm![End])
+    .collect();
+    func.free_local(local)?;
+    Ok(instructions)
 }
 
 pub fn acceptable_inputs(Fields { list }: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -121,3 +117,17 @@ crate::instructions_test!(
         fields = super::test::make_fields(true, IrType::String, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_int_linear_memory for data_addtolist(t) {
+        fields = super::test::make_fields(true, IrType::Int, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
+
+crate::instructions_test!(
+    mod test_string_linear_memory for data_addtolist(t) {
+        fields = super::test::make_fields(true, IrType::String, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
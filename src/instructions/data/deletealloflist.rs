@@ -1,4 +1,5 @@
 use super::super::prelude::*;
+use super::set_list_length;
 use crate::ir::RcList;
 
 /// we need these fields to be mutable for optimisations to be feasible
@@ -24,10 +25,13 @@ pub fn wasm(
     _inputs: Rc<[IrType]>,
     Fields { list }: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    let (_, Some(length_global)) = func.registries().lists().register(list)? else {
+    if !*list.length_mutable().borrow() {
         hq_bug!("tried to deletealloflist of a list with immutable length")
-    };
-    Ok(wasm![I32Const(0), #LazyGlobalSet(length_global)])
+    }
+    Ok(wasm![I32Const(0)]
+        .into_iter()
+        .chain(set_list_length(func, list)?)
+        .collect())
 }
 
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
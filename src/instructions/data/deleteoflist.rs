@@ -1,6 +1,7 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use super::{copy_list_items, list_length, set_list_length};
 use crate::ir::RcList;
 
 /// we need these fields to be mutable for optimisations to be feasible
@@ -26,14 +27,12 @@ pub fn wasm(
     _inputs: Rc<[IrType]>,
     fields: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    let (list_global, Some(length_global)) = func.registries().lists().register(&fields.list)?
-    else {
-        hq_bug!("tried to insertatlist of a list with immutable length")
-    };
-    let array_type = func.registries().lists().array_type(&fields.list)?;
+    let list = &fields.list;
+    if !*list.length_mutable().borrow() {
+        hq_bug!("tried to deleteoflist of a list with immutable length")
+    }
     let index_local = func.local(ValType::I32)?;
-    func.free_local(index_local)?;
-    Ok(wasm![
+    let instructions = wasm![
         LocalSet(index_local),
         Block(WasmBlockType::Empty),
         LocalGet(index_local),
@@ -41,28 +40,27 @@ pub fn wasm(
         I32LeS,
         BrIf(0),
         LocalGet(index_local),
-        #LazyGlobalGet(length_global),
+    ]
+    .into_iter()
+    .chain(list_length(func, list)?)
+    .chain(wasm![
         I32GtS,
         BrIf(0),
-        #LazyGlobalGet(list_global),
         LocalGet(index_local),
         I32Const(1),
         I32Sub,
-        #LazyGlobalGet(list_global),
-        LocalGet(index_local),
-        #LazyGlobalGet(length_global),
         LocalGet(index_local),
-        I32Sub,
-        ArrayCopy {
-            array_type_index_dst: array_type,
-            array_type_index_src: array_type,
-        },
-        #LazyGlobalGet(length_global),
-        I32Const(1),
-        I32Sub,
-        #LazyGlobalSet(length_global),
-        End,
     ])
+    .chain(list_length(func, list)?)
+    .chain(wasm![LocalGet(index_local), I32Sub])
+    .chain(copy_list_items(func, list)?)
+    .chain(list_length(func, list)?)
+    .chain(wasm![I32Const(1), I32Sub])
+    .chain(set_list_length(func, list)?)
+    .chain(wasm![End])
+    .collect();
+    func.free_local(index_local)?;
+    Ok(instructions)
 }
 
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -127,3 +125,17 @@ crate::instructions_test!(
         fields = super::test::make_fields(IrType::Any, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_int_linear_memory for data_deleteoflist(t) {
+        fields = super::test::make_fields(IrType::Int, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
+
+crate::instructions_test!(
+    mod test_string_linear_memory for data_deleteoflist(t) {
+        fields = super::test::make_fields(IrType::String, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
@@ -1,6 +1,7 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use super::{copy_list_items, list_length, reserve_list_item, set_list_item, set_list_length};
 use crate::ir::RcList;
 use crate::wasm::WasmProject;
 
@@ -30,68 +31,57 @@ pub fn wasm(
     hq_assert!(inputs.len() == 2);
     let t1 = inputs[0];
     let t2 = inputs[1];
-    let (list_global, Some(length_global)) = func.registries().lists().register(&fields.list)?
-    else {
+    let list = &fields.list;
+    if !*list.length_mutable().borrow() {
         hq_bug!("tried to insertatlist of a list with immutable length")
-    };
-    let array_type = func.registries().lists().array_type(&fields.list)?;
+    }
     let index_local = func.local(WasmProject::ir_type_to_wasm(t1))?;
     let val_local = func.local(WasmProject::ir_type_to_wasm(t2))?;
+    let instructions = wasm![LocalSet(val_local), LocalSet(index_local)]
+        .into_iter()
+        .chain(list_length(func, list)?)
+        .chain(wasm![
+            I32Const(200_000),
+            I32LtS,
+            If(WasmBlockType::Empty),
+            LocalGet(index_local),
+            I32Const(0),
+            I32LeS,
+            BrIf(0),
+            LocalGet(index_local),
+        ])
+        .chain(list_length(func, list)?)
+        .chain(wasm![I32Const(1), I32Add, I32GtS, BrIf(0)])
+        .chain(reserve_list_item(func, list)?)
+        .chain(wasm![
+            LocalGet(index_local),
+            LocalGet(index_local),
+            I32Const(1),
+            I32Sub,
+        ])
+        .chain(list_length(func, list)?)
+        .chain(wasm![LocalGet(index_local), I32Sub, I32Const(1), I32Add])
+        .chain(copy_list_items(func, list)?)
+        .chain(wasm![
+            LocalGet(index_local),
+            I32Const(1),
+            I32Sub,
+            LocalGet(val_local),
+        ])
+        .chain(if list.possible_types().is_base_type() {
+            vec![]
+        } else {
+            wasm![@boxed(t2)]
+        })
+        .chain(set_list_item(func, list)?)
+        .chain(list_length(func, list)?)
+        .chain(wasm![I32Const(1), I32Add])
+        .chain(set_list_length(func, list)?)
+        .chain(wasm![End])
+        .collect();
     func.free_local(index_local)?;
     func.free_local(val_local)?;
-    Ok(wasm![
-        LocalSet(val_local),
-        LocalSet(index_local),
-        #LazyGlobalGet(length_global),
-        I32Const(200_000),
-        I32LtS,
-        If(WasmBlockType::Empty),
-        LocalGet(index_local),
-        I32Const(0),
-        I32LeS,
-        BrIf(0),
-        LocalGet(index_local),
-        #LazyGlobalGet(length_global),
-        I32Const(1),
-        I32Add,
-        I32GtS,
-        BrIf(0),
-        #LazyGlobalGet(list_global),
-        LocalGet(index_local),
-        #LazyGlobalGet(list_global),
-        LocalGet(index_local),
-        I32Const(1),
-        I32Sub,
-        #LazyGlobalGet(length_global),
-        LocalGet(index_local),
-        I32Sub,
-        I32Const(1),
-        I32Add,
-        ArrayCopy {
-            array_type_index_dst: array_type,
-            array_type_index_src: array_type,
-        },
-        #LazyGlobalGet(list_global),
-        LocalGet(index_local),
-        I32Const(1),
-        I32Sub,
-        LocalGet(val_local),
-    ]
-    .into_iter()
-    .chain(if fields.list.possible_types().is_base_type() {
-        vec![]
-    } else {
-        wasm![@boxed(t2)]
-    })
-    .chain(wasm![
-        ArraySet(array_type),
-        #LazyGlobalGet(length_global),
-        I32Const(1),
-        I32Add,
-        #LazyGlobalSet(length_global),
-        End,
-    ])
-    .collect())
+    Ok(instructions)
 }
 
 pub fn acceptable_inputs(Fields { list }: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -157,3 +147,17 @@ crate::instructions_test!(
         fields = super::test::make_fields(IrType::Any, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_float_linear_memory for data_insertatlist(t1, t2) {
+        fields = super::test::make_fields(IrType::Float, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
+
+crate::instructions_test!(
+    mod test_any_linear_memory for data_insertatlist(t1, t2) {
+        fields = super::test::make_fields(IrType::Any, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
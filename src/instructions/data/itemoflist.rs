@@ -1,6 +1,7 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use super::{list_item, list_length};
 use crate::ir::RcList;
 use crate::wasm::WasmProject;
 
@@ -27,17 +28,14 @@ pub fn wasm(
     _inputs: Rc<[IrType]>,
     fields: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    let (list_global, maybe_length_global) = func.registries().lists().register(&fields.list)?;
-    let array_type = func.registries().lists().array_type(&fields.list)?;
     let empty_string = func.registries().strings().register_default("".into())?;
     let string_type = IrType::String;
     let elem_type = *fields.list.possible_types();
     let should_box = !IrType::String.contains(elem_type);
     let output_type = WasmProject::ir_type_to_wasm(elem_type.or(string_type));
     let i32_local = func.local(ValType::I32)?;
-    func.free_local(i32_local)?;
 
-    Ok(wasm![
+    let instructions = wasm![
         LocalTee(i32_local),
         I32Const(0),
         I32LeS,
@@ -52,17 +50,7 @@ pub fn wasm(
         vec![]
     })
     .chain(wasm![Else, LocalGet(i32_local),])
-    .chain(if let Some(length_global) = maybe_length_global {
-        wasm![#LazyGlobalGet(length_global)]
-    } else {
-        let array_length = fields
-            .list
-            .initial_value()
-            .len()
-            .try_into()
-            .map_err(|_| make_hq_bug!("list initial value length out of bounds"))?;
-        wasm![I32Const(array_length)]
-    })
+    .chain(list_length(func, &fields.list)?)
     .chain(wasm![
         I32GtS,
         If(WasmBlockType::Result(output_type)),
@@ -74,14 +62,8 @@ pub fn wasm(
     } else {
         vec![]
     })
-    .chain(wasm![
-        Else,
-        #LazyGlobalGet(list_global),
-        LocalGet(i32_local),
-        I32Const(1),
-        I32Sub,
-        ArrayGet(array_type),
-    ])
+    .chain(wasm![Else, LocalGet(i32_local), I32Const(1), I32Sub])
+    .chain(list_item(func, &fields.list)?)
     .chain(if should_box {
         wasm![
                 @boxed(elem_type) ]
@@ -89,7 +71,9 @@ pub fn wasm(
         vec![]
     })
     .chain(wasm![End, End,])
-    .collect())
+    .collect();
+    func.free_local(i32_local)?;
+    Ok(instructions)
 }
 
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -233,3 +217,20 @@ mod any_static for data_itemoflist(t) {
     };
 }
 );
+
+crate::instructions_test!(
+mod any_mut_linear_memory for data_itemoflist(t) {
+    fields = super::Fields {
+        list: {
+            let list = crate::ir::RcList::new(
+                vec![crate::sb3::VarVal::String("hi".into())],
+                &flags()
+            ).unwrap();
+            *list.length_mutable().borrow_mut() = true;
+            list.add_type(IrType::Any);
+            list
+        },
+    };
+    flags = { let mut flags = WasmFlags::new(unit_test_wasm_features()); flags.list_type = crate::wasm::flags::ListType::LinearMemory; flags };
+}
+);
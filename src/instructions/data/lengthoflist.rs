@@ -1,4 +1,5 @@
 use super::super::prelude::*;
+use super::list_length;
 use crate::ir::RcList;
 
 /// we need these fields to be mutable for optimisations to be feasible
@@ -24,18 +25,7 @@ pub fn wasm(
     _inputs: Rc<[IrType]>,
     Fields { list }: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    Ok(
-        if let (_, Some(length_global)) = func.registries().lists().register(list)? {
-            wasm![#LazyGlobalGet(length_global)]
-        } else {
-            let array_length = list
-                .initial_value()
-                .len()
-                .try_into()
-                .map_err(|_| make_hq_bug!("list initial value length out of bounds"))?;
-            wasm![I32Const(array_length)]
-        },
-    )
+    list_length(func, list)
 }
 
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -93,3 +83,10 @@ crate::instructions_test!(
         fields = super::test::make_fields(false, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_mut_linear_memory for data_lengthoflist {
+        fields = super::test::make_fields(true, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
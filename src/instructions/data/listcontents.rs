@@ -1,6 +1,7 @@
 use wasm_encoder::{BlockType as WasmBlockType, HeapType};
 
 use super::super::prelude::*;
+use super::{list_item, list_length};
 use crate::ir::RcList;
 use crate::wasm::StringsTable;
 
@@ -27,8 +28,6 @@ pub fn wasm(
     _inputs: Rc<[IrType]>,
     Fields { list }: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    let (list_global, maybe_length_global) = func.registries().lists().register(list)?;
-    let array_type = func.registries().lists().array_type(list)?;
     let empty_string = func.registries().strings().register_default("".into())?;
     let elem_type = *list.possible_types();
     let is_single_chars_local = func.local(ValType::I32)?;
@@ -52,15 +51,17 @@ pub fn wasm(
         LocalSet(i_local),
     ]
     .into_iter()
-    .chain(if let Some(length_global) = maybe_length_global {
-        wasm![
-            #LazyGlobalGet(length_global),
-            I32Eqz,
-            If(WasmBlockType::Empty),
-            GlobalGet(empty_string),
-            Br(1),
-            End,
-        ]
+    .chain(if *list.length_mutable().borrow() {
+        list_length(func, list)?
+            .into_iter()
+            .chain(wasm![
+                I32Eqz,
+                If(WasmBlockType::Empty),
+                GlobalGet(empty_string),
+                Br(1),
+                End,
+            ])
+            .collect()
     } else if list.initial_value().is_empty() {
         wasm![GlobalGet(empty_string), Br(0)]
     } else {
@@ -77,11 +78,10 @@ pub fn wasm(
                 LocalSet(is_single_chars_local),
                 Loop(WasmBlockType::Empty),
                 Block(WasmBlockType::Empty),
-                #LazyGlobalGet(list_global),
                 LocalGet(i_local),
-                ArrayGet(array_type),
             ]
             .into_iter()
+            .chain(list_item(func, list)?)
             .chain(match list.possible_types().base_type() {
                 Some(IrType::String) => {
                     wasm![Call(string_length), I32Const(1), I32Eq,]
@@ -123,13 +123,7 @@ pub fn wasm(
                 I32Add,
                 LocalTee(i_local),
             ])
-            .chain(if let Some(length_global) = maybe_length_global {
-                wasm![#LazyGlobalGet(length_global)]
-            } else {
-                wasm![I32Const(list.initial_value().len().try_into().map_err(
-                    |_| make_hq_bug!("list initial value length out of bounds")
-                )?)]
-            })
+            .chain(list_length(func, list)?)
             .chain(wasm![I32LtS, BrIf(1), End, End,])
             .collect()
         } else {
@@ -154,10 +148,9 @@ pub fn wasm(
         End,
         End,
         LocalGet(output_local),
-        #LazyGlobalGet(list_global),
         LocalGet(i_local),
-        ArrayGet(array_type),
     ])
+    .chain(list_item(func, list)?)
     .chain(match list.possible_types().base_type() {
         Some(IrType::String) => vec![],
         Some(IrType::Float) => {
@@ -246,13 +239,7 @@ pub fn wasm(
         I32Add,
         LocalTee(i_local),
     ])
-    .chain(if let Some(length_global) = maybe_length_global {
-        wasm![#LazyGlobalGet(length_global)]
-    } else {
-        wasm![I32Const(list.initial_value().len().try_into().map_err(
-            |_| make_hq_bug!("list initial value length out of bounds")
-        )?)]
-    })
+    .chain(list_length(func, list)?)
     .chain(wasm![I32LtS, BrIf(0), End, LocalGet(output_local),])
     .chain(wasm![End])
     .collect();
@@ -286,7 +273,7 @@ pub const fn const_fold(
 pub mod test_utils {
     use super::*;
     use crate::wasm::WasmFlags;
-    use crate::wasm::flags::{Switch, unit_test_wasm_features};
+    use crate::wasm::flags::{ListType, Switch, unit_test_wasm_features};
 
     pub fn make_list(mutable: bool, ty: IrType, flags: WasmFlags) -> RcList {
         let list = crate::ir::RcList::new(vec![], &flags).unwrap();
@@ -300,6 +287,12 @@ pub mod test_utils {
         flags.integers = Switch::On;
         flags
     }
+
+    pub fn flags_with_linear_memory() -> WasmFlags {
+        let mut flags = WasmFlags::new(unit_test_wasm_features());
+        flags.list_type = ListType::LinearMemory;
+        flags
+    }
 }
 
 #[cfg(test)]
@@ -386,3 +379,17 @@ crate::instructions_test!(
         fields = super::test::make_fields(false, IrType::Any, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_any_mut_linear_memory for data_listcontents {
+        fields = super::test::make_fields(true, IrType::Any, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
+
+crate::instructions_test!(
+    mod test_string_static_linear_memory for data_listcontents {
+        fields = super::test::make_fields(false, IrType::String, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
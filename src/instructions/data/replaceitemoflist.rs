@@ -1,6 +1,7 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use super::{list_length, set_list_item};
 use crate::ir::RcList;
 use crate::wasm::WasmProject;
 
@@ -30,13 +31,10 @@ pub fn wasm(
     hq_assert!(inputs.len() == 2);
     let t1 = inputs[0];
     let t2 = inputs[1];
-    let (list_global, maybe_length_global) = func.registries().lists().register(&fields.list)?;
-    let array_type = func.registries().lists().array_type(&fields.list)?;
+    let list = &fields.list;
     let index_local = func.local(WasmProject::ir_type_to_wasm(t1))?;
     let val_local = func.local(WasmProject::ir_type_to_wasm(t2))?;
-    func.free_local(index_local)?;
-    func.free_local(val_local)?;
-    Ok(wasm![
+    let instructions = wasm![
         LocalSet(val_local),
         LocalSet(index_local),
         Block(WasmBlockType::Empty),
@@ -47,33 +45,26 @@ pub fn wasm(
         LocalGet(index_local),
     ]
     .into_iter()
-    .chain(if let Some(length_global) = maybe_length_global {
-        wasm![#LazyGlobalGet(length_global)]
-    } else {
-        let array_length = fields
-            .list
-            .initial_value()
-            .len()
-            .try_into()
-            .map_err(|_| make_hq_bug!("list initial value length out of bounds"))?;
-        wasm![I32Const(array_length)]
-    })
+    .chain(list_length(func, list)?)
     .chain(wasm![
         I32GtS,
         BrIf(0),
-        #LazyGlobalGet(list_global),
         LocalGet(index_local),
         I32Const(1),
         I32Sub,
         LocalGet(val_local),
     ])
-    .chain(if fields.list.possible_types().is_base_type() {
+    .chain(if list.possible_types().is_base_type() {
         vec![]
     } else {
         wasm![@boxed(t2)]
     })
-    .chain(wasm![ArraySet(array_type), End,])
-    .collect())
+    .chain(set_list_item(func, list)?)
+    .chain(wasm![End])
+    .collect();
+    func.free_local(index_local)?;
+    func.free_local(val_local)?;
+    Ok(instructions)
 }
 
 pub fn acceptable_inputs(Fields { list }: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -163,3 +154,17 @@ crate::instructions_test!(
         fields = super::test::make_fields(false, IrType::Any, flags());
     }
 );
+
+crate::instructions_test!(
+    mod test_any_mut_linear_memory for data_replaceitemoflist(t1, t2) {
+        fields = super::test::make_fields(true, IrType::Any, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
+
+crate::instructions_test!(
+    mod test_string_static_linear_memory for data_replaceitemoflist(t1, t2) {
+        fields = super::test::make_fields(false, IrType::String, flags());
+        flags = super::test::flags_with_linear_memory();
+    }
+);
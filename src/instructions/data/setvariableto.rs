@@ -85,7 +85,10 @@ pub fn wasm(
             .variables()
             .register(&*var.try_borrow()?)?;
         if var.try_borrow()?.possible_types().is_base_type() {
-            wasm![#LazyGlobalSet(global_index)]
+            func.persist_string(t1)?
+                .into_iter()
+                .chain(wasm![#LazyGlobalSet(global_index)])
+                .collect()
         } else {
             wasm![
                 @boxed(t1),
@@ -83,7 +83,10 @@ pub fn wasm(
             .variables()
             .register(&*var.try_borrow()?)?;
         if var.borrow().possible_types().is_base_type() {
-            wasm![#LazyGlobalSet(global_index), #LazyGlobalGet(global_index)]
+            func.persist_string(t1)?
+                .into_iter()
+                .chain(wasm![#LazyGlobalSet(global_index), #LazyGlobalGet(global_index)])
+                .collect()
         } else {
             wasm![
                 @boxed(t1),
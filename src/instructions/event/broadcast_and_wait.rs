@@ -43,11 +43,22 @@ pub fn wasm(
         nullable: false,
         heap_type: HeapType::Concrete(i32_array_type),
     }))?;
+    let start_local = func.local(ValType::I32)?;
+    let index_local = func.local(ValType::I32)?;
     func.free_local(arr_local)?;
+    func.free_local(start_local)?;
+    func.free_local(index_local)?;
 
     Ok(wasm![
         LocalGet((func.params().len() - 2).try_into().map_err(|_| make_hq_bug!("local index out of bounds"))?),
-        #LazyBroadcastSpawnAndWait((broadcast.clone(), *poll_step, *next_step, arr_local))
+        #LazyBroadcastSpawnAndWait((
+            broadcast.clone(),
+            *poll_step,
+            *next_step,
+            arr_local,
+            start_local,
+            index_local
+        ))
     ])
 }
 
@@ -98,6 +109,7 @@ mod test {
             flags,
             StepTarget::Sprite(0),
             0,
+            1,
             Rc::new(vec![]),
         );
         wasm_proj.steps().borrow_mut().push(step_func);
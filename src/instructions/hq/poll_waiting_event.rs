@@ -1,7 +1,7 @@
 //! This is a bit of a strange instruction in that it relies on only ever being used inside a step
-//! that was spawned from a `sensing_askandwait` block (for now; there is potential to be expanded
-//! to other usecases which is why this is in the hq category rather than sensing). Any other usage
-//! will result in invalid wasm.
+//! that was spawned from a `sensing_askandwait` or `sound_playuntildone` block (for now; there is
+//! potential to be expanded to other usecases which is why this is in the hq category). Any other
+//! usage will result in invalid wasm.
 //!
 //! Returns 1 if still waiting on any threads, 0 otherwise.
 
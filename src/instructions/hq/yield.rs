@@ -3,12 +3,33 @@ use wasm_encoder::{BlockType, ConstExpr, HeapType};
 use super::super::prelude::*;
 use crate::instructions_test;
 use crate::ir::{Step, StepIndex};
+use crate::wasm::flags::ClockSource;
 use crate::wasm::{GlobalExportable, GlobalMutable, StepFunc, ThreadsTable};
 
+/// How many iterations of non-warped loops can run in one frame with a virtual clock, if nothing
+/// else makes them yield; this stands in for the frame deadline.
+pub const VIRTUAL_FRAME_ITERATIONS: i32 = 10_000;
+
 #[derive(Debug, Clone)]
 pub enum YieldMode {
     Inline(Rc<RefCell<Step>>),
     Schedule(StepIndex),
+    /// Used at the end of each iteration of a non-warped loop. Schedules the step like
+    /// [`YieldMode::Schedule`], but only actually yields if a redraw has been requested, if
+    /// another thread is running, or if the time budget for the current frame has run out;
+    /// otherwise the step is run straight away, as the scheduler would only run this thread
+    /// again before the next frame anyway.
+    ///
+    /// With a virtual clock the time budget is replaced by a budget of
+    /// [`VIRTUAL_FRAME_ITERATIONS`] iterations, so that runs don't depend on the host's clock.
+    MaybeSchedule(StepIndex),
+    /// Used at the end of each iteration of a loop in a warped procedure when the warp timer is
+    /// enabled. Schedules the step like [`YieldMode::Schedule`], but only yields if the frame has
+    /// been running for too long; the clock is only checked every `warp_timer` iterations.
+    ///
+    /// With a virtual clock this yields every `warp_timer` iterations instead, without checking
+    /// the host's clock.
+    WarpTimer(StepIndex),
     None,
     Return,
 }
@@ -22,6 +43,8 @@ impl fmt::Display for YieldMode {
             match self {
                 Self::Inline(_) => "inline",
                 Self::Schedule(_) => "schedule",
+                Self::MaybeSchedule(_) => "maybe_schedule",
+                Self::WarpTimer(_) => "warp_timer",
                 Self::None => "none",
                 Self::Return => "return",
             }
@@ -30,7 +53,7 @@ impl fmt::Display for YieldMode {
             Self::Inline(step) => {
                 write!(f, r#", "step": {}"#, RefCell::borrow(step))?;
             }
-            Self::Schedule(step) => {
+            Self::Schedule(step) | Self::MaybeSchedule(step) | Self::WarpTimer(step) => {
                 write!(f, r#", "step_index": {}"#, step.0)?;
             }
             Self::None | Self::Return => (),
@@ -145,37 +168,213 @@ pub fn wasm(
             );
             func.compile_inner_step(Rc::clone(step))?
         }
-        YieldMode::Schedule(step_index) => {
-            let threads_table = func.registries().tables().register::<ThreadsTable, _>()?;
-            let thread_struct_ty = func.registries().types().thread_struct_type()?;
-            let local = func.local(ValType::Ref(RefType {
-                nullable: false,
-                heap_type: HeapType::Concrete(thread_struct_ty),
-            }))?;
-            func.free_local(local)?;
-            let stack_array_ty = func.registries().types().stack_array_type()?;
-            let stack_struct_ty = func.registries().types().stack_struct_type()?;
-
-            wasm![
-                LocalGet(0),
-                TableGet(threads_table),
-                RefAsNonNull,
-                LocalTee(local),
-                StructGet { struct_type_index: thread_struct_ty, field_index: 1 },
-                LocalGet(local),
-                StructGet { struct_type_index: thread_struct_ty, field_index: 0 },
-                I32Const(1),
-                I32Sub,
-                ArrayGet(stack_array_ty),
-                RefAsNonNull,
-                #LazyStepRef(*step_index),
-                StructSet { struct_type_index: stack_struct_ty, field_index: 0 },
-                Return
-            ]
+        YieldMode::Schedule(step_index) => schedule(func, *step_index, None)?
+            .into_iter()
+            .chain(wasm![Return])
+            .collect(),
+        YieldMode::MaybeSchedule(step_index) => {
+            let requests_refresh = func.registries().globals().register(
+                "requests_refresh".into(),
+                (
+                    ValType::I32,
+                    ConstExpr::i32_const(0),
+                    GlobalMutable(true),
+                    GlobalExportable(true),
+                ),
+            )?;
+            let out_of_time = if func.flags().clock == ClockSource::Virtual {
+                let countdown = func.registries().globals().register(
+                    "frame_iterations_countdown".into(),
+                    (
+                        ValType::I32,
+                        ConstExpr::i32_const(0),
+                        GlobalMutable(true),
+                        GlobalExportable(false),
+                    ),
+                )?;
+                wasm![
+                    #LazyGlobalGet(countdown),
+                    I32Eqz,
+                    If(BlockType::Result(ValType::I32)),
+                    I32Const(VIRTUAL_FRAME_ITERATIONS - 1),
+                    #LazyGlobalSet(countdown),
+                    I32Const(1),
+                    Else,
+                    #LazyGlobalGet(countdown),
+                    I32Const(1),
+                    I32Sub,
+                    #LazyGlobalSet(countdown),
+                    I32Const(0),
+                    End,
+                ]
+            } else {
+                // set by the host at the start of each frame; this defaults to 0 so that hosts
+                // which don't set it get a yield on every iteration
+                let frame_deadline = func.registries().globals().register(
+                    "frame_deadline".into(),
+                    (
+                        ValType::F64,
+                        ConstExpr::f64_const(0.0.into()),
+                        GlobalMutable(true),
+                        GlobalExportable(true),
+                    ),
+                )?;
+                wasm![Call(now(func)?), #LazyGlobalGet(frame_deadline), F64Ge]
+            };
+            yield_if(
+                func,
+                *step_index,
+                wasm![
+                    // the clock is only checked if nothing else requires a yield, as calling
+                    // out to the host is relatively slow
+                    #LazyGlobalGet(requests_refresh),
+                    #LazyGlobalGet(threads_count),
+                    I32Const(1),
+                    I32GtU,
+                    I32Or,
+                    If(BlockType::Result(ValType::I32)),
+                    I32Const(1),
+                    Else,
+                ]
+                .into_iter()
+                .chain(out_of_time)
+                .chain(wasm![End])
+                .collect(),
+            )?
+        }
+        YieldMode::WarpTimer(step_index) => {
+            let interval = i32::try_from(func.flags().warp_timer)
+                .map_err(|_| make_hq_bug!("warp_timer interval out of range"))?;
+            let countdown = func.registries().globals().register(
+                "warp_timer_countdown".into(),
+                (
+                    ValType::I32,
+                    ConstExpr::i32_const(0),
+                    GlobalMutable(true),
+                    GlobalExportable(false),
+                ),
+            )?;
+            let out_of_time = if func.flags().clock == ClockSource::Virtual {
+                // the iteration budget is all that limits the loop, so that runs don't depend on
+                // the host's clock
+                wasm![I32Const(1)]
+            } else {
+                // set by the host at the start of each frame, like `frame_deadline`
+                let warp_deadline = func.registries().globals().register(
+                    "warp_deadline".into(),
+                    (
+                        ValType::F64,
+                        ConstExpr::f64_const(0.0.into()),
+                        GlobalMutable(true),
+                        GlobalExportable(true),
+                    ),
+                )?;
+                wasm![Call(now(func)?), #LazyGlobalGet(warp_deadline), F64Ge]
+            };
+            yield_if(
+                func,
+                *step_index,
+                wasm![
+                    #LazyGlobalGet(countdown),
+                    I32Eqz,
+                    If(BlockType::Result(ValType::I32)),
+                    I32Const(interval.saturating_sub(1)),
+                    #LazyGlobalSet(countdown),
+                ]
+                .into_iter()
+                .chain(out_of_time)
+                .chain(wasm![
+                    Else,
+                    #LazyGlobalGet(countdown),
+                    I32Const(1),
+                    I32Sub,
+                    #LazyGlobalSet(countdown),
+                    I32Const(0),
+                    End,
+                ])
+                .collect(),
+            )?
         }
     })
 }
 
+/// The host's current time in milliseconds.
+fn now(func: &StepFunc) -> HQResult<u32> {
+    func.registries()
+        .external_functions()
+        .register(("control", "now".into()), (vec![], vec![ValType::F64]))
+}
+
+/// Sets the step that the current thread will run next, and then yields if `should_yield` leaves
+/// a non-zero i32 on the stack; otherwise, that step is run straight away.
+fn yield_if(
+    func: &StepFunc,
+    step_index: StepIndex,
+    should_yield: Vec<InternalInstruction>,
+) -> HQResult<Vec<InternalInstruction>> {
+    let stack_struct_ty = func.registries().types().stack_struct_type()?;
+    let step_func_ty = func.registries().types().step_func_type()?;
+    let frame_local = func.local(ValType::Ref(RefType {
+        nullable: false,
+        heap_type: HeapType::Concrete(stack_struct_ty),
+    }))?;
+    let instructions = schedule(func, step_index, Some(frame_local))?
+        .into_iter()
+        .chain(should_yield)
+        .chain(wasm![
+            If(BlockType::Empty),
+            Return,
+            End,
+            LocalGet(0),
+            LocalGet(frame_local),
+            StructGet { struct_type_index: stack_struct_ty, field_index: 1 },
+            #LazyStepRef(step_index),
+            ReturnCallRef(step_func_ty),
+        ])
+        .collect();
+    func.free_local(frame_local)?;
+    Ok(instructions)
+}
+
+/// Sets the step that the current thread will run next, without returning. If `frame_local` is
+/// given, the thread's current stack frame is stored in it.
+fn schedule(
+    func: &StepFunc,
+    step_index: StepIndex,
+    frame_local: Option<u32>,
+) -> HQResult<Vec<InternalInstruction>> {
+    let threads_table = func.registries().tables().register::<ThreadsTable, _>()?;
+    let thread_struct_ty = func.registries().types().thread_struct_type()?;
+    let local = func.local(ValType::Ref(RefType {
+        nullable: false,
+        heap_type: HeapType::Concrete(thread_struct_ty),
+    }))?;
+    func.free_local(local)?;
+    let stack_array_ty = func.registries().types().stack_array_type()?;
+    let stack_struct_ty = func.registries().types().stack_struct_type()?;
+
+    Ok(wasm![
+        LocalGet(0),
+        TableGet(threads_table),
+        RefAsNonNull,
+        LocalTee(local),
+        StructGet { struct_type_index: thread_struct_ty, field_index: 1 },
+        LocalGet(local),
+        StructGet { struct_type_index: thread_struct_ty, field_index: 0 },
+        I32Const(1),
+        I32Sub,
+        ArrayGet(stack_array_ty),
+        RefAsNonNull,
+    ]
+    .into_iter()
+    .chain(frame_local.map_or_else(Vec::new, |frame_local| wasm![LocalTee(frame_local)]))
+    .chain(wasm![
+        #LazyStepRef(step_index),
+        StructSet { struct_type_index: stack_struct_ty, field_index: 0 },
+    ])
+    .collect())
+}
+
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
     Ok(Rc::from([]))
 }
@@ -207,6 +406,8 @@ mod test {
             YieldMode::None,
             YieldMode::Return,
             make_schedule(),
+            make_maybe_schedule(),
+            make_warp_timer(),
             YieldMode::Inline(Rc::new(RefCell::new(Step::new_empty(
                 Weak::new(),
                 false,
@@ -221,6 +422,14 @@ mod test {
         YieldMode::Schedule(StepIndex(0))
     }
 
+    pub fn make_maybe_schedule() -> YieldMode {
+        YieldMode::MaybeSchedule(StepIndex(0))
+    }
+
+    pub fn make_warp_timer() -> YieldMode {
+        YieldMode::WarpTimer(StepIndex(0))
+    }
+
     pub fn make_inline() -> YieldMode {
         let target = make_target();
         YieldMode::Inline(Rc::new(RefCell::new(Step::new_empty(
@@ -251,6 +460,54 @@ instructions_test! (
     }
 );
 
+instructions_test! (
+    mod test_maybe_schedule for hq_yield {
+        fields = super::Fields {
+            mode: super::test::make_maybe_schedule()
+        };
+    }
+);
+
+instructions_test! (
+    mod test_maybe_schedule_virtual for hq_yield {
+        fields = super::Fields {
+            mode: super::test::make_maybe_schedule()
+        };
+        flags = {
+            let mut flags = WasmFlags::new(unit_test_wasm_features());
+            flags.clock = crate::wasm::flags::ClockSource::Virtual;
+            flags
+        };
+    }
+);
+
+instructions_test! (
+    mod test_warp_timer for hq_yield {
+        fields = super::Fields {
+            mode: super::test::make_warp_timer()
+        };
+        flags = {
+            let mut flags = WasmFlags::new(unit_test_wasm_features());
+            flags.warp_timer = 64;
+            flags
+        };
+    }
+);
+
+instructions_test! (
+    mod test_warp_timer_virtual for hq_yield {
+        fields = super::Fields {
+            mode: super::test::make_warp_timer()
+        };
+        flags = {
+            let mut flags = WasmFlags::new(unit_test_wasm_features());
+            flags.warp_timer = 64;
+            flags.clock = crate::wasm::flags::ClockSource::Virtual;
+            flags
+        };
+    }
+);
+
 instructions_test!(
     mod test_inline for hq_yield {
         fields = super::Fields {
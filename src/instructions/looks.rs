@@ -1,10 +1,119 @@
+use crate::prelude::*;
+use crate::wasm::{StepTarget, mem_layout};
+
 pub mod backdropnumber;
+pub mod bubbleid;
+pub mod cleargraphiceffects;
 pub mod costumename;
 pub mod costumenumber;
+pub mod effect;
+pub mod goforwardbackwardlayers;
+pub mod gotofrontback;
 pub mod say;
+pub mod seteffectto;
 pub mod setsizeto;
 pub mod setvisible;
 pub mod size;
 pub mod switchbackdropto;
+pub mod switchbackdroptoandwait;
 pub mod switchcostumeto;
 pub mod think;
+
+/// A graphic effect which can be applied to a target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicEffect {
+    Color,
+    Fisheye,
+    Whirl,
+    Pixelate,
+    Mosaic,
+    Brightness,
+    Ghost,
+}
+
+impl GraphicEffect {
+    pub const ALL: [Self; 7] = [
+        Self::Color,
+        Self::Fisheye,
+        Self::Whirl,
+        Self::Pixelate,
+        Self::Mosaic,
+        Self::Brightness,
+        Self::Ghost,
+    ];
+
+    /// Parses the value of a `looks_seteffectto` or `looks_changeeffectby` block's `EFFECT`
+    /// field.
+    pub fn from_field(effect: &str) -> HQResult<Self> {
+        Ok(match effect.to_lowercase().as_str() {
+            "color" => Self::Color,
+            "fisheye" => Self::Fisheye,
+            "whirl" => Self::Whirl,
+            "pixelate" => Self::Pixelate,
+            "mosaic" => Self::Mosaic,
+            "brightness" => Self::Brightness,
+            "ghost" => Self::Ghost,
+            _ => hq_bad_proj!("unknown graphic effect {effect}"),
+        })
+    }
+
+    /// The (minimum, maximum) values of this effect, if it is clamped; see
+    /// <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_looks.js#L506>
+    #[must_use]
+    pub const fn range(self) -> Option<(f64, f64)> {
+        match self {
+            Self::Ghost => Some((0.0, 100.0)),
+            Self::Brightness => Some((-100.0, 100.0)),
+            Self::Color | Self::Fisheye | Self::Whirl | Self::Pixelate | Self::Mosaic => None,
+        }
+    }
+
+    /// The name of this effect, as used by the renderer
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Color => "color",
+            Self::Fisheye => "fisheye",
+            Self::Whirl => "whirl",
+            Self::Pixelate => "pixelate",
+            Self::Mosaic => "mosaic",
+            Self::Brightness => "brightness",
+            Self::Ghost => "ghost",
+        }
+    }
+
+    /// The memory offset of this effect for the given target, relative to the memory offset of
+    /// the current instance.
+    #[must_use]
+    pub const fn offset(self, target: StepTarget) -> u32 {
+        match target {
+            StepTarget::Sprite(index) => {
+                mem_layout::sprite_offset(index)
+                    + match self {
+                        Self::Color => mem_layout::sprite::COLOR_EFFECT,
+                        Self::Fisheye => mem_layout::sprite::FISHEYE_EFFECT,
+                        Self::Whirl => mem_layout::sprite::WHIRL_EFFECT,
+                        Self::Pixelate => mem_layout::sprite::PIXELATE_EFFECT,
+                        Self::Mosaic => mem_layout::sprite::MOSAIC_EFFECT,
+                        Self::Brightness => mem_layout::sprite::BRIGHTNESS_EFFECT,
+                        Self::Ghost => mem_layout::sprite::GHOST_EFFECT,
+                    }
+            }
+            StepTarget::Stage => match self {
+                Self::Color => mem_layout::stage::COLOR_EFFECT,
+                Self::Fisheye => mem_layout::stage::FISHEYE_EFFECT,
+                Self::Whirl => mem_layout::stage::WHIRL_EFFECT,
+                Self::Pixelate => mem_layout::stage::PIXELATE_EFFECT,
+                Self::Mosaic => mem_layout::stage::MOSAIC_EFFECT,
+                Self::Brightness => mem_layout::stage::BRIGHTNESS_EFFECT,
+                Self::Ghost => mem_layout::stage::GHOST_EFFECT,
+            },
+        }
+    }
+}
+
+impl fmt::Display for GraphicEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
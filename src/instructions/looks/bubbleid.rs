@@ -0,0 +1,66 @@
+//! Pushes a value which changes whenever the target's speech bubble is updated, so that timed
+//! bubbles can tell whether they have been replaced before they are cleared.
+//! See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_looks.js>
+
+use super::super::prelude::*;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields {
+    pub target_idx: u32,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target_idx": {}
+    }}"#,
+            self.target_idx
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields { target_idx }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let bubble_id = func.registries().globals().bubble_id(target_idx)?;
+    Ok(wasm![#LazyGlobalGet(bubble_id)])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(IrType::Int))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields { target_idx: 0 }));
+    }
+}
+
+crate::instructions_test! (
+    mod tests for looks_bubbleid {
+        fields = super::Fields { target_idx: 0 };
+    }
+);
@@ -0,0 +1,52 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::GraphicEffect;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let current_instance = func.registries().globals().current_instance()?;
+    let func_index = func.registries().external_functions().register(
+        ("looks", "cleargraphiceffects".into()),
+        (vec![ValType::I32], vec![]),
+    )?;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(GraphicEffect::ALL
+        .iter()
+        .flat_map(|effect| {
+            wasm![
+                #LazyGlobalGet(mem_offset),
+                F64Const(0.0.into()),
+                F64Store(MemArg {
+                    offset: effect.offset(func.target()).into(),
+                    align: 3,
+                    memory_index: 0,
+                }),
+            ]
+        })
+        .chain(wasm![
+            #LazyGlobalGet(current_instance),
+            Call(func_index),
+        ])
+        .collect())
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for looks_cleargraphiceffects {}
+);
@@ -5,13 +5,12 @@ use crate::wasm::registries::tables::TableOptions;
 use crate::wasm::{StepTarget, mem_layout};
 
 pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     let wasm_target_index = match func.target() {
         StepTarget::Sprite(index) => index,
         StepTarget::Stage => 0,
     };
-    let offset = mem_layout::stage::BLOCK_SIZE
-        + wasm_target_index * mem_layout::sprite::BLOCK_SIZE
-        + mem_layout::sprite::COSTUME;
+    let offset = mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::COSTUME;
 
     let costume_names = func
         .costume_names()
@@ -37,7 +36,7 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     }
 
     Ok(wasm![
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         I32Load(MemArg {
             offset: offset.into(),
             align: 2,
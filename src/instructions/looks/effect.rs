@@ -0,0 +1,78 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+//! Reports the current value of a graphic effect. This doesn't correspond to a scratch block,
+//! but is used to implement `looks_changeeffectby`.
+
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::GraphicEffect;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields(pub GraphicEffect);
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "effect": "{}"
+    }}"#,
+            self.0
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields(effect): &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: effect.offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(IrType::FloatNotNan))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields(GraphicEffect::Ghost)));
+    }
+}
+
+crate::instructions_test! (
+    mod tests for looks_effect {
+        fields = super::Fields(super::GraphicEffect::Color);
+    }
+);
@@ -0,0 +1,94 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+use super::super::prelude::*;
+use crate::wasm::StepTarget;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields {
+    /// whether the sprite should move forwards, rather than backwards
+    pub forward: bool,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "forward": {}
+    }}"#,
+            self.forward
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields { forward }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    if matches!(func.target(), StepTarget::Stage) {
+        hq_bad_proj!("looks_goforwardbackwardlayers called in stage")
+    }
+    let current_instance = func.registries().globals().current_instance()?;
+    let func_index = func.registries().external_functions().register(
+        ("looks", "goforwardbackwardlayers".into()),
+        (vec![ValType::I32, ValType::I32], vec![]),
+    )?;
+    // the import takes the (signed) number of layers to move forward by
+    let negate = if forward {
+        vec![]
+    } else {
+        wasm![I32Const(-1), I32Mul]
+    };
+    Ok(negate
+        .into_iter()
+        .chain(wasm![
+            #LazyGlobalGet(current_instance),
+            Call(func_index),
+        ])
+        .collect())
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Int]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields { forward: true }));
+    }
+}
+
+crate::instructions_test! (
+    mod tests_forward for looks_goforwardbackwardlayers(t) {
+        fields = super::Fields { forward: true };
+    }
+);
+
+crate::instructions_test! (
+    mod tests_backward for looks_goforwardbackwardlayers(t) {
+        fields = super::Fields { forward: false };
+    }
+);
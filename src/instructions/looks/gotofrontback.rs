@@ -0,0 +1,80 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+use super::super::prelude::*;
+use crate::wasm::StepTarget;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields {
+    /// whether the sprite should go to the front layer, rather than the back
+    pub front: bool,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "front": {}
+    }}"#,
+            self.front
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields { front }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    if matches!(func.target(), StepTarget::Stage) {
+        hq_bad_proj!("looks_gotofrontback called in stage")
+    }
+    let current_instance = func.registries().globals().current_instance()?;
+    let func_index = func.registries().external_functions().register(
+        ("looks", "gotofrontback".into()),
+        (vec![ValType::I32, ValType::I32], vec![]),
+    )?;
+    Ok(wasm![
+        I32Const(front.into()),
+        #LazyGlobalGet(current_instance),
+        Call(func_index),
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields { front: true }));
+    }
+}
+
+crate::instructions_test! (
+    mod tests for looks_gotofrontback {
+        fields = super::Fields { front: false };
+    }
+);
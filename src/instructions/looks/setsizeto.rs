@@ -5,10 +5,7 @@ use crate::wasm::{StepTarget, mem_layout};
 
 pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
     let t1 = inputs[0];
-    let ir_target_index: i32 = func
-        .target_index()
-        .try_into()
-        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let current_instance = func.registries().globals().current_instance()?;
     let func_index = func.registries().external_functions().register(
         ("looks", "setsizeto".into()),
         (vec![ValType::F64, ValType::I32], vec![]),
@@ -16,22 +13,21 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setsizeto called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     let local_index = func.local(ValType::F64)?;
     let w = wasm![
         @nanreduce(t1),
         LocalSet(local_index),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(local_index),
         F64Store(MemArg {
-            offset: (mem_layout::stage::BLOCK_SIZE
-                + wasm_target_index * mem_layout::sprite::BLOCK_SIZE
-                + mem_layout::sprite::SIZE)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::SIZE)
                 .into(),
             align: 3,
             memory_index: 0,
         }),
         LocalGet(local_index),
-        I32Const(ir_target_index),
+        #LazyGlobalGet(current_instance),
         Call(func_index),
     ];
     func.free_local(local_index)?;
@@ -4,10 +4,7 @@ use super::super::prelude::*;
 use crate::wasm::{StepTarget, mem_layout};
 
 pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
-    let ir_target_index: i32 = func
-        .target_index()
-        .try_into()
-        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let current_instance = func.registries().globals().current_instance()?;
     let func_index = func.registries().external_functions().register(
         ("looks", "setvisible".into()),
         (vec![ValType::I32, ValType::I32], vec![]),
@@ -15,22 +12,21 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setvisible called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     let local_index = func.local(ValType::I32)?;
     func.free_local(local_index)?;
     Ok(wasm![
         LocalSet(local_index),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(local_index),
         I32Store8(MemArg {
-            offset: (mem_layout::stage::BLOCK_SIZE
-                + wasm_target_index * mem_layout::sprite::BLOCK_SIZE
-                + mem_layout::sprite::VISIBLE)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::VISIBLE)
                 .into(),
             align: 0,
             memory_index: 0,
         }),
         LocalGet(local_index),
-        I32Const(ir_target_index),
+        #LazyGlobalGet(current_instance),
         Call(func_index),
     ])
 }
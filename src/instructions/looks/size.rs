@@ -7,12 +7,11 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_size called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     Ok(wasm![
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (mem_layout::stage::BLOCK_SIZE
-                + wasm_target_index * mem_layout::sprite::BLOCK_SIZE
-                + mem_layout::sprite::SIZE)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::SIZE)
                 .into(),
             align: 3,
             memory_index: 0,
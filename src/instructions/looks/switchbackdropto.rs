@@ -38,6 +38,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
             }),
             LocalGet(local_index),
             Call(func_index),
+            #LazyBackdropSwitchSpawn(local_index),
             // TODO: if out of range, try parsing as string
             End,
         ]
@@ -0,0 +1,159 @@
+use wasm_encoder::{BlockType, HeapType, MemArg, StorageType};
+
+use super::super::prelude::*;
+use crate::instructions_test;
+use crate::ir::StepIndex;
+use crate::wasm::mem_layout;
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub backdrop_count: u32,
+    pub poll_step: StepIndex,
+    pub next_step: StepIndex,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "backdrop_count": {},
+        "poll_step": {},
+        "next_step": {}
+    }}"#,
+            self.backdrop_count, self.poll_step.0, self.next_step.0,
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields {
+        backdrop_count,
+        poll_step,
+        next_step,
+    }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let func_index = func.registries().external_functions().register(
+        ("looks", "switchbackdropto".into()),
+        (vec![ValType::I32], vec![]),
+    )?;
+    let i32_array_type = func
+        .registries()
+        .types()
+        .array(StorageType::Val(ValType::I32), true)?;
+    let backdrop_local = func.local(ValType::I32)?;
+    let arr_local = func.local(ValType::Ref(RefType {
+        nullable: false,
+        heap_type: HeapType::Concrete(i32_array_type),
+    }))?;
+    let start_local = func.local(ValType::I32)?;
+    let index_local = func.local(ValType::I32)?;
+    func.free_local(backdrop_local)?;
+    func.free_local(arr_local)?;
+    func.free_local(start_local)?;
+    func.free_local(index_local)?;
+
+    Ok(wasm![
+        I32Const(1),
+        I32Sub,
+        LocalTee(backdrop_local),
+        I32Const(0),
+        I32GeS,
+        LocalGet(backdrop_local),
+        I32Const(
+            (*backdrop_count)
+                .try_into()
+                .map_err(|_| make_hq_bug!("backdrop count out of bounds"))?
+        ),
+        I32LtS,
+        I32And,
+        If(BlockType::Empty),
+        I32Const(0),
+        LocalGet(backdrop_local),
+        I32Store(MemArg {
+            offset: mem_layout::stage::COSTUME.into(),
+            align: 2,
+            memory_index: 0,
+        }),
+        LocalGet(backdrop_local),
+        Call(func_index),
+        Else,
+        // the backdrop doesn't change, so no hats are started, and we wait for no threads
+        I32Const(-1),
+        LocalSet(backdrop_local),
+        End,
+        LocalGet((func.params().len() - 2).try_into().map_err(|_| make_hq_bug!("local index out of bounds"))?),
+        #LazyBackdropSwitchSpawnAndWait((
+            backdrop_local,
+            *poll_step,
+            *next_step,
+            arr_local,
+            start_local,
+            index_local
+        ))
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    // backdrop names are resolved to numbers when generating IR (see `generate_backdrop_number`)
+    Ok(Rc::from([IrType::Int]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+    use crate::wasm::registries::TypeRegistry;
+    use crate::wasm::{StepTarget, WasmFlags, WasmProject};
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields();
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields() -> Fields {
+        Fields {
+            backdrop_count: 2,
+            poll_step: StepIndex(0),
+            next_step: StepIndex(0),
+        }
+    }
+
+    pub fn setup_project(wasm_proj: &WasmProject, flags: WasmFlags) {
+        let step_func = StepFunc::new_with_types(
+            Box::from([ValType::I32, TypeRegistry::STRUCT_REF]),
+            Box::from([]),
+            wasm_proj.registries(),
+            flags,
+            StepTarget::Stage,
+            0,
+            1,
+            Rc::new(vec![]),
+        );
+        wasm_proj.steps().borrow_mut().push(step_func);
+    }
+}
+
+instructions_test!(
+    mod test2 for looks_switchbackdroptoandwait(t) {
+        fields = super::test::make_fields();
+        setup = super::test::setup_project;
+    }
+);
@@ -4,20 +4,14 @@ use super::super::prelude::*;
 use crate::wasm::{StepTarget, mem_layout};
 
 pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
-    let ir_target_index: i32 = func
-        .target_index()
-        .try_into()
-        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let current_instance = func.registries().globals().current_instance()?;
     let func_index = func.registries().external_functions().register(
         ("looks", "switchcostumeto".into()),
         (vec![ValType::I32, ValType::I32], vec![]),
     )?;
     let offset = match func.target() {
-        StepTarget::Sprite(index) => {
-            mem_layout::stage::BLOCK_SIZE
-                + index * mem_layout::sprite::BLOCK_SIZE
-                + mem_layout::sprite::COSTUME
-        }
+        StepTarget::Sprite(index) => mem_layout::sprite_offset(index) + mem_layout::sprite::COSTUME,
         StepTarget::Stage => mem_layout::stage::COSTUME,
     };
 
@@ -40,7 +34,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
             I32LtS,
             I32And,
             If(BlockType::Empty),
-            I32Const(0),
+            #LazyGlobalGet(mem_offset),
             LocalGet(local_index),
             I32Store(MemArg {
                 offset: offset.into(),
@@ -48,7 +42,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 memory_index: 0,
             }),
             LocalGet(local_index),
-            I32Const(ir_target_index),
+            #LazyGlobalGet(current_instance),
             Call(func_index),
             // TODO: if out of range, try parsing as string
             End,
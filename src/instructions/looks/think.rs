@@ -28,7 +28,8 @@ pub fn wasm(
     let itarget_idx: i32 = target_idx
         .try_into()
         .map_err(|_| make_hq_bug!("target index out of bounds"))?;
-    Ok(if IrType::Int.contains(inputs[0]) {
+    let bubble_id = func.registries().globals().bubble_id(target_idx)?;
+    let update_bubble = if IrType::Int.contains(inputs[0]) {
         let func_index = func.registries().external_functions().register(
             ("looks", format!("{prefix}_int").into_boxed_str()),
             (vec![ValType::I32, ValType::I32], vec![]),
@@ -72,7 +73,17 @@ pub fn wasm(
         ]
     } else {
         hq_bug!("bad input")
-    })
+    };
+    // timed bubbles only clear themselves if this hasn't changed in the meantime
+    Ok(update_bubble
+        .into_iter()
+        .chain(wasm![
+            #LazyGlobalGet(bubble_id),
+            I32Const(1),
+            I32Add,
+            #LazyGlobalSet(bubble_id),
+        ])
+        .collect())
 }
 
 pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
@@ -1,7 +1,64 @@
+use crate::prelude::*;
+
 pub mod direction;
 pub mod gotoxy;
+pub mod ifonedgebounce;
 pub mod pointindirection;
+pub mod setrotationstyle;
 pub mod setx;
 pub mod sety;
 pub mod xposition;
+pub mod xpositionof;
 pub mod yposition;
+pub mod ypositionof;
+
+/// How a sprite's direction affects the way it is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotationStyle {
+    #[default]
+    AllAround,
+    LeftRight,
+    DontRotate,
+}
+
+impl RotationStyle {
+    /// Parses the value of a `motion_setrotationstyle` block's `STYLE` field, or a sprite's
+    /// `rotationStyle`. Scratch ignores unknown rotation styles, so this returns `None` for them
+    /// rather than an error.
+    #[must_use]
+    pub fn from_field(style: &str) -> Option<Self> {
+        match style {
+            "all around" => Some(Self::AllAround),
+            "left-right" => Some(Self::LeftRight),
+            "don't rotate" => Some(Self::DontRotate),
+            _ => None,
+        }
+    }
+
+    /// The name of this rotation style, as used in project.json
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AllAround => "all around",
+            Self::LeftRight => "left-right",
+            Self::DontRotate => "don't rotate",
+        }
+    }
+
+    /// The value which represents this rotation style in a sprite's `ROTATION_STYLE` byte, and
+    /// which is passed to the renderer imports
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::AllAround => 0,
+            Self::LeftRight => 1,
+            Self::DontRotate => 2,
+        }
+    }
+}
+
+impl fmt::Display for RotationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
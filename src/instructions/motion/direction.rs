@@ -1,4 +1,4 @@
-use mem_layout::{sprite as sprite_layout, stage as stage_layout};
+use mem_layout::sprite as sprite_layout;
 use wasm_encoder::MemArg;
 
 use super::super::prelude::*;
@@ -8,12 +8,11 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("motion_direction called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     Ok(wasm![
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::ROTATION)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::ROTATION)
                 .into(),
             align: 3,
             memory_index: 0
@@ -0,0 +1,466 @@
+//! `if on edge, bounce`; see
+//! <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_motion.js#L186>
+//!
+//! Scratch asks the renderer for the exact bounds of the sprite, which we can't do from wasm;
+//! instead, the sprite's bounding box is worked out from the bounds of its current costume, its
+//! size, its direction and its rotation style.
+
+use core::f64::consts::PI;
+
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::{BlockType, Instruction, MemArg};
+
+use super::super::prelude::*;
+use super::RotationStyle;
+use crate::instructions::IrOpcode;
+use crate::wasm::{StepTarget, mem_layout};
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    /// the (left, right, bottom, top) bounds of each of the sprite's costumes, as given by
+    /// `IrCostume::bounds`
+    pub costume_bounds: Box<[(f64, f64, f64, f64)]>,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let costume_bounds = self
+            .costume_bounds
+            .iter()
+            .map(|(left, right, bottom, top)| format!("[{left}, {right}, {bottom}, {top}]"))
+            .join(", ");
+        write!(
+            f,
+            r#"{{
+        "costume_bounds": [{costume_bounds}]
+    }}"#
+        )
+    }
+}
+
+/// The locals needed to work out the bounding box of a sprite
+#[derive(Clone, Copy)]
+struct BoundsLocals {
+    x: u32,
+    y: u32,
+    /// the size of the sprite, as a multiple of the size of its costume
+    scale: u32,
+    /// the rotation style of the sprite (i32)
+    style: u32,
+    /// the bounds of the sprite's current costume
+    left: u32,
+    right: u32,
+    bottom: u32,
+    top: u32,
+    /// the angle of the sprite, anticlockwise from the positive x-axis in radians
+    theta: u32,
+    /// the linear map from costume space to stage space, i.e.
+    /// `(x, y) -> (ax * x + bx * y, ay * x + by * y)`
+    ax: u32,
+    bx: u32,
+    ay: u32,
+    by: u32,
+    /// the edges of the sprite's bounding box
+    min_x: u32,
+    max_x: u32,
+    min_y: u32,
+    max_y: u32,
+}
+
+/// Sets the `min_x`, `max_x`, `min_y` and `max_y` locals to the edges of the sprite's bounding box
+/// when it points in the direction stored in `direction`.
+fn bounding_box(
+    locals: BoundsLocals,
+    direction: u32,
+    sin: u32,
+    cos: u32,
+) -> Vec<InternalInstruction> {
+    let mut instructions = wasm![
+        LocalGet(locals.style),
+        I32Const(RotationStyle::AllAround.id().into()),
+        I32Eq,
+        If(BlockType::Empty),
+        F64Const(90.0.into()),
+        LocalGet(direction),
+        F64Sub,
+        F64Const((PI / 180.0).into()),
+        F64Mul,
+        LocalTee(locals.theta),
+        Call(cos),
+        LocalTee(locals.ax),
+        LocalSet(locals.by),
+        LocalGet(locals.theta),
+        Call(sin),
+        LocalTee(locals.ay),
+        F64Neg,
+        LocalSet(locals.bx),
+        Else,
+        // sprites with the left-right rotation style are flipped horizontally when facing left
+        F64Const((-1.0).into()),
+        F64Const(1.0.into()),
+        LocalGet(locals.style),
+        I32Const(RotationStyle::LeftRight.id().into()),
+        I32Eq,
+        LocalGet(direction),
+        F64Const(0.0.into()),
+        F64Lt,
+        I32And,
+        Select,
+        LocalSet(locals.ax),
+        F64Const(0.0.into()),
+        LocalTee(locals.ay),
+        LocalSet(locals.bx),
+        F64Const(1.0.into()),
+        LocalSet(locals.by),
+        End,
+    ];
+    for (edge, a, b, centre, is_max) in [
+        (locals.min_x, locals.ax, locals.bx, locals.x, false),
+        (locals.max_x, locals.ax, locals.bx, locals.x, true),
+        (locals.min_y, locals.ay, locals.by, locals.y, false),
+        (locals.max_y, locals.ay, locals.by, locals.y, true),
+    ] {
+        let extremum = InternalInstruction::Immediate(if is_max {
+            Instruction::F64Max
+        } else {
+            Instruction::F64Min
+        });
+        instructions.extend(wasm![
+            LocalGet(a),
+            LocalGet(locals.left),
+            F64Mul,
+            LocalGet(a),
+            LocalGet(locals.right),
+            F64Mul,
+        ]);
+        instructions.push(extremum.clone());
+        instructions.extend(wasm![
+            LocalGet(b),
+            LocalGet(locals.bottom),
+            F64Mul,
+            LocalGet(b),
+            LocalGet(locals.top),
+            F64Mul,
+        ]);
+        instructions.push(extremum);
+        instructions.extend(wasm![
+            F64Add,
+            LocalGet(locals.scale),
+            F64Mul,
+            LocalGet(centre),
+            F64Add,
+            LocalSet(edge),
+        ]);
+    }
+    instructions
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields { costume_bounds }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("motion_ifonedgebounce called in stage")
+    };
+    let sprite_offset = mem_layout::sprite_offset(wasm_target_index);
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let sin = func.registries().external_functions().register(
+        ("operator", "sin".into()),
+        (vec![ValType::F64], vec![ValType::F64]),
+    )?;
+    let cos = func.registries().external_functions().register(
+        ("operator", "cos".into()),
+        (vec![ValType::F64], vec![ValType::F64]),
+    )?;
+    let atan2 = func.registries().external_functions().register(
+        ("operator", "atan2".into()),
+        (vec![ValType::F64, ValType::F64], vec![ValType::F64]),
+    )?;
+
+    let locals = BoundsLocals {
+        x: func.local(ValType::F64)?,
+        y: func.local(ValType::F64)?,
+        scale: func.local(ValType::F64)?,
+        style: func.local(ValType::I32)?,
+        left: func.local(ValType::F64)?,
+        right: func.local(ValType::F64)?,
+        bottom: func.local(ValType::F64)?,
+        top: func.local(ValType::F64)?,
+        theta: func.local(ValType::F64)?,
+        ax: func.local(ValType::F64)?,
+        bx: func.local(ValType::F64)?,
+        ay: func.local(ValType::F64)?,
+        by: func.local(ValType::F64)?,
+        min_x: func.local(ValType::F64)?,
+        max_x: func.local(ValType::F64)?,
+        min_y: func.local(ValType::F64)?,
+        max_y: func.local(ValType::F64)?,
+    };
+    let costume = func.local(ValType::I32)?;
+    let direction = func.local(ValType::F64)?;
+    let dx = func.local(ValType::F64)?;
+    let dy = func.local(ValType::F64)?;
+
+    let load_f64 = |field: u32| {
+        wasm![
+            #LazyGlobalGet(mem_offset),
+            F64Load(MemArg {
+                offset: (sprite_offset + field).into(),
+                align: 3,
+                memory_index: 0,
+            }),
+        ]
+    };
+
+    let mut instructions = load_f64(sprite_layout::X);
+    instructions.extend(wasm![LocalSet(locals.x)]);
+    instructions.extend(load_f64(sprite_layout::Y));
+    instructions.extend(wasm![LocalSet(locals.y)]);
+    instructions.extend(load_f64(sprite_layout::SIZE));
+    instructions.extend(wasm![F64Const(100.0.into()), F64Div, LocalSet(locals.scale)]);
+    instructions.extend(load_f64(sprite_layout::ROTATION));
+    instructions.extend(wasm![
+        LocalSet(direction),
+        #LazyGlobalGet(mem_offset),
+        I32Load8U(MemArg {
+            offset: (sprite_offset + sprite_layout::ROTATION_STYLE).into(),
+            align: 0,
+            memory_index: 0,
+        }),
+        LocalSet(locals.style),
+        #LazyGlobalGet(mem_offset),
+        I32Load(MemArg {
+            offset: (sprite_offset + sprite_layout::COSTUME).into(),
+            align: 2,
+            memory_index: 0,
+        }),
+        LocalSet(costume),
+        F64Const(0.0.into()),
+        LocalTee(locals.left),
+        LocalTee(locals.right),
+        LocalTee(locals.bottom),
+        LocalSet(locals.top),
+    ]);
+    for (index, &(left, right, bottom, top)) in costume_bounds.iter().enumerate() {
+        instructions.extend(wasm![
+            LocalGet(costume),
+            I32Const(
+                i32::try_from(index).map_err(|_| make_hq_bug!("costume index out of bounds"))?
+            ),
+            I32Eq,
+            If(BlockType::Empty),
+            F64Const(left.into()),
+            LocalSet(locals.left),
+            F64Const(right.into()),
+            LocalSet(locals.right),
+            F64Const(bottom.into()),
+            LocalSet(locals.bottom),
+            F64Const(top.into()),
+            LocalSet(locals.top),
+            End,
+        ]);
+    }
+
+    instructions.extend(bounding_box(locals, direction, sin, cos));
+    instructions.extend(wasm![
+        // only bounce if the sprite is touching an edge
+        LocalGet(locals.min_x),
+        F64Const((-240.0).into()),
+        F64Le,
+        LocalGet(locals.max_y),
+        F64Const(180.0.into()),
+        F64Ge,
+        I32Or,
+        LocalGet(locals.max_x),
+        F64Const(240.0.into()),
+        F64Ge,
+        I32Or,
+        LocalGet(locals.min_y),
+        F64Const((-180.0).into()),
+        F64Le,
+        I32Or,
+        If(BlockType::Empty),
+        F64Const(90.0.into()),
+        LocalGet(direction),
+        F64Sub,
+        F64Const((PI / 180.0).into()),
+        F64Mul,
+        LocalTee(locals.theta),
+        Call(cos),
+        LocalSet(dx),
+        LocalGet(locals.theta),
+        Call(sin),
+        F64Neg,
+        LocalSet(dy),
+        // the nearest edge is the first one that the sprite is touching, in the order left, top,
+        // right, bottom
+        LocalGet(locals.min_x),
+        F64Const((-240.0).into()),
+        F64Le,
+        If(BlockType::Empty),
+        LocalGet(dx),
+        F64Abs,
+        F64Const(0.2.into()),
+        F64Max,
+        LocalSet(dx),
+        Else,
+        LocalGet(locals.max_y),
+        F64Const(180.0.into()),
+        F64Ge,
+        If(BlockType::Empty),
+        LocalGet(dy),
+        F64Abs,
+        F64Const(0.2.into()),
+        F64Max,
+        LocalSet(dy),
+        Else,
+        LocalGet(locals.max_x),
+        F64Const(240.0.into()),
+        F64Ge,
+        If(BlockType::Empty),
+        LocalGet(dx),
+        F64Abs,
+        F64Const(0.2.into()),
+        F64Max,
+        F64Neg,
+        LocalSet(dx),
+        Else,
+        LocalGet(dy),
+        F64Abs,
+        F64Const(0.2.into()),
+        F64Max,
+        F64Neg,
+        LocalSet(dy),
+        End,
+        End,
+        End,
+        LocalGet(dy),
+        LocalGet(dx),
+        Call(atan2),
+        F64Const((180.0 / PI).into()),
+        F64Mul,
+        F64Const(90.0.into()),
+        F64Add,
+        LocalSet(direction),
+        // wrap the direction into the range (-180, 180]
+        LocalGet(direction),
+        F64Const(360.0.into()),
+        F64Sub,
+        LocalGet(direction),
+        LocalGet(direction),
+        F64Const(180.0.into()),
+        F64Gt,
+        Select,
+        LocalSet(direction),
+    ]);
+    // the bounding box changes as the sprite turns, so it needs to be recalculated before moving
+    // the sprite back onto the stage
+    instructions.extend(bounding_box(locals, direction, sin, cos));
+    instructions.extend(wasm![
+        LocalGet(locals.x),
+        F64Const((-240.0).into()),
+        LocalGet(locals.min_x),
+        F64Sub,
+        F64Const(0.0.into()),
+        F64Max,
+        F64Add,
+        F64Const(240.0.into()),
+        LocalGet(locals.max_x),
+        F64Sub,
+        F64Const(0.0.into()),
+        F64Min,
+        F64Add,
+        LocalGet(locals.y),
+        F64Const(180.0.into()),
+        LocalGet(locals.max_y),
+        F64Sub,
+        F64Const(0.0.into()),
+        F64Min,
+        F64Add,
+        F64Const((-180.0).into()),
+        LocalGet(locals.min_y),
+        F64Sub,
+        F64Const(0.0.into()),
+        F64Max,
+        F64Add,
+        LocalGet(direction),
+    ]);
+    instructions.extend(
+        IrOpcode::motion_pointindirection.wasm(func, Rc::from([IrType::FloatReal]))?,
+    );
+    instructions.extend(
+        IrOpcode::motion_gotoxy.wasm(func, Rc::from([IrType::FloatReal, IrType::FloatReal]))?,
+    );
+    instructions.extend(wasm![End]);
+
+    for local in [
+        locals.x,
+        locals.y,
+        locals.scale,
+        locals.left,
+        locals.right,
+        locals.bottom,
+        locals.top,
+        locals.theta,
+        locals.ax,
+        locals.bx,
+        locals.ay,
+        locals.by,
+        locals.min_x,
+        locals.max_x,
+        locals.min_y,
+        locals.max_y,
+        direction,
+        dx,
+        dy,
+    ] {
+        func.free_local(local)?;
+    }
+    func.free_local(locals.style)?;
+    func.free_local(costume)?;
+
+    Ok(instructions)
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!(
+            "{}",
+            Fields {
+                costume_bounds: Box::new([(-10.0, 10.0, -5.5, 5.5), (0.0, 0.0, 0.0, 0.0)])
+            }
+        ));
+    }
+}
+
+crate::instructions_test! (
+    mod tests for motion_ifonedgebounce {
+        fields = super::Fields {
+            costume_bounds: Box::new([(-48.0, 48.0, -50.0, 50.0), (-20.0, 30.0, -10.0, 40.0)]),
+        };
+    }
+);
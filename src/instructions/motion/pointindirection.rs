@@ -1,4 +1,4 @@
-use mem_layout::{sprite as sprite_layout, stage as stage_layout};
+use mem_layout::sprite as sprite_layout;
 use wasm_encoder::MemArg;
 
 use super::super::prelude::*;
@@ -8,31 +8,34 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("motion_pointindirection called in stage")
     };
-    let ir_target_index: i32 = func
-        .target_index()
-        .try_into()
-        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let current_instance = func.registries().globals().current_instance()?;
     let local_idx = func.local(ValType::F64)?;
     let t1 = inputs[0];
     let imported_func = func.registries().external_functions().register(
         ("motion", "pointindirection".into()),
-        (vec![ValType::I32, ValType::F64], vec![]),
+        (vec![ValType::I32, ValType::F64, ValType::I32], vec![]),
     )?;
     let w = wasm![
         @nanreduce(t1),
         LocalSet(local_idx),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(local_idx),
         F64Store(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::ROTATION)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::ROTATION)
                 .into(),
             align: 3,
             memory_index: 0
         }),
-        I32Const(ir_target_index),
+        #LazyGlobalGet(current_instance),
         LocalGet(local_idx),
+        #LazyGlobalGet(mem_offset),
+        I32Load8U(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::ROTATION_STYLE)
+                .into(),
+            align: 0,
+            memory_index: 0
+        }),
         Call(imported_func),
     ];
     func.free_local(local_idx)?;
@@ -0,0 +1,98 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::RotationStyle;
+use crate::wasm::{StepTarget, mem_layout};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields(pub RotationStyle);
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "style": "{}"
+    }}"#,
+            self.0
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields(style): &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("motion_setrotationstyle called in stage")
+    };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let current_instance = func.registries().globals().current_instance()?;
+    // the direction doesn't change, but the way that it's drawn might
+    let imported_func = func.registries().external_functions().register(
+        ("motion", "pointindirection".into()),
+        (vec![ValType::I32, ValType::F64, ValType::I32], vec![]),
+    )?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        I32Const(style.id().into()),
+        I32Store8(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::ROTATION_STYLE)
+                .into(),
+            align: 0,
+            memory_index: 0
+        }),
+        #LazyGlobalGet(current_instance),
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::ROTATION)
+                .into(),
+            align: 3,
+            memory_index: 0
+        }),
+        I32Const(style.id().into()),
+        Call(imported_func),
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields(RotationStyle::DontRotate)));
+    }
+}
+
+crate::instructions_test! (
+    mod tests_left_right for motion_setrotationstyle {
+        fields = super::Fields(super::RotationStyle::LeftRight);
+    }
+);
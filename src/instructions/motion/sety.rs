@@ -1,14 +1,11 @@
-use mem_layout::{sprite as sprite_layout, stage as stage_layout};
+use mem_layout::sprite as sprite_layout;
 use wasm_encoder::MemArg;
 
 use super::super::prelude::*;
 use crate::wasm::{StepTarget, mem_layout};
 
 pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
-    let ir_target_index: i32 = func
-        .target_index()
-        .try_into()
-        .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+    let current_instance = func.registries().globals().current_instance()?;
     let move_func_index = func.registries().external_functions().register(
         ("motion", "gotoxy".into()),
         (vec![ValType::F64, ValType::F64, ValType::I32], vec![]),
@@ -33,122 +30,101 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("motion_sety called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     let x_local = func.local(ValType::F64)?;
     let y_local = func.local(ValType::F64)?;
     func.free_local(x_local)?;
     func.free_local(y_local)?;
     Ok(wasm![
         LocalSet(y_local),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::X)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::X)
                 .into(),
             align: 3,
             memory_index: 0
         }),
         LocalSet(x_local),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         I32Load8U(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_DOWN)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_DOWN)
                 .into(),
             align: 0,
             memory_index: 0
         }),
         If(wasm_encoder::BlockType::Empty),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_SIZE)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_SIZE)
                 .into(),
             align: 3,
             memory_index: 0
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::X)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::X)
                 .into(),
             align: 3,
             memory_index: 0
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::Y)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::Y)
                 .into(),
             align: 3,
             memory_index: 0
         }),
         LocalGet(x_local),
         LocalGet(y_local),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_COLOR_R)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_COLOR_R)
                 .into(),
             align: 2,
             memory_index: 0
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_COLOR_G)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_COLOR_G)
                 .into(),
             align: 2,
             memory_index: 0
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_COLOR_B)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_COLOR_B)
                 .into(),
             align: 2,
             memory_index: 0
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::PEN_COLOR_A)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::PEN_COLOR_A)
                 .into(),
             align: 2,
             memory_index: 0
         }),
         Call(pen_func_index),
         End,
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(x_local),
         F64Store(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::X)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::X)
                 .into(),
             align: 3,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(y_local),
         F64Store(MemArg {
-            offset: (stage_layout::BLOCK_SIZE
-                + wasm_target_index * sprite_layout::BLOCK_SIZE
-                + sprite_layout::Y)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::Y)
                 .into(),
             align: 3,
             memory_index: 0,
         }),
         LocalGet(x_local),
         LocalGet(y_local),
-        I32Const(ir_target_index),
+        #LazyGlobalGet(current_instance),
         Call(move_func_index),
     ])
 }
@@ -0,0 +1,89 @@
+//! Reads the y-position of the original (i.e. non-clone) instance of another sprite.
+
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use crate::ir::Target;
+use crate::wasm::mem_layout;
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub target: Rc<Target>,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target": {}
+    }}"#,
+            self.target.index()
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields { target }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    if target.is_stage() {
+        hq_bug!("tried to get the y-position of the stage")
+    }
+    let sprite_index = func
+        .registries()
+        .sprites()
+        .register_default(Rc::clone(target))?;
+    Ok(wasm![
+        I32Const(0),
+        F64Load(MemArg {
+            offset: (mem_layout::sprite_offset(sprite_index) + sprite_layout::Y).into(),
+            align: 3,
+            memory_index: 0
+        }),
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(IrType::FloatReal))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::{assert_valid_json, make_target};
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields();
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields() -> Fields {
+        Fields {
+            target: make_target(),
+        }
+    }
+}
+
+crate::instructions_test!(
+    mod tests for motion_ypositionof {
+        fields = super::test::make_fields();
+    }
+);
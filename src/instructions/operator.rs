@@ -4,6 +4,7 @@ pub mod add;
 pub mod and;
 pub mod asin;
 pub mod atan;
+pub mod atan2;
 pub mod ceiling;
 pub mod contains;
 pub mod cos;
@@ -0,0 +1,48 @@
+//! The angle (in degrees) between the positive x-axis and the point (x, y), taking y and then x
+//! as inputs. This isn't a scratch block, but is needed for pointing towards things.
+
+use super::super::prelude::*;
+
+pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    hq_assert_eq!(inputs.len(), 2);
+    let t1 = inputs[0];
+    let t2 = inputs[1];
+    let imported_func = func.registries().external_functions().register(
+        ("operator", "atan2".into()),
+        (vec![ValType::F64, ValType::F64], vec![ValType::F64]),
+    )?;
+    let x_local = func.local(ValType::F64)?;
+    func.free_local(x_local)?;
+    Ok(wasm![
+        @nanreduce(t2),
+        LocalSet(x_local),
+        @nanreduce(t1),
+        LocalGet(x_local),
+        Call(imported_func),
+        F64Const(core::f64::consts::PI.into()),
+        F64Div,
+        F64Const(180.0.into()),
+        F64Mul,
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Float, IrType::Float]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(Singleton(IrType::FloatReal))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for operator_atan2(t1,t2) {}
+);
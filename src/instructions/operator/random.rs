@@ -1,6 +1,26 @@
 use wasm_encoder::BlockType as WasmBlockType;
 
 use super::super::prelude::*;
+use crate::wasm::flags::RandomSource;
+use crate::wasm::registries::functions::static_functions::RandomFloat;
+
+/// Pushes a random f64 in the range [0, 1), from either the host or the in-module generator
+/// depending on the `rng` flag.
+fn random_float(func: &StepFunc) -> HQResult<Vec<InternalInstruction>> {
+    Ok(if func.flags().rng == RandomSource::Seeded {
+        let random_func = func
+            .registries()
+            .static_functions()
+            .register::<RandomFloat, _>()?;
+        wasm![#StaticFunctionCall(random_func)]
+    } else {
+        let imported_function = func
+            .registries()
+            .external_functions()
+            .register(("operator", "random".into()), (vec![], vec![ValType::F64]))?;
+        wasm![Call(imported_function)]
+    })
+}
 
 pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
     hq_assert_eq!(inputs.len(), 2);
@@ -10,10 +30,6 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
     let to_local = func.local(ValType::F64)?;
     let low_local = func.local(ValType::F64)?;
     let high_local = func.local(ValType::F64)?;
-    let imported_function = func
-        .registries()
-        .external_functions()
-        .register(("operator", "random".into()), (vec![], vec![ValType::F64]))?;
     let w = if IrType::QuasiInt.contains(t1) {
         if IrType::QuasiInt.contains(t2) {
             wasm![
@@ -81,17 +97,19 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
             .or(IrType::FloatZero)
             .contains(t1.or(t2))
         {
-            wasm![
-                Call(imported_function),
-                LocalGet(high_local),
-                F64Const(1.0.into()),
-                F64Add,
-                LocalGet(low_local),
-                F64Sub,
-                F64Mul,
-                F64Floor,
-                F64Add,
-            ]
+            random_float(func)?
+                .into_iter()
+                .chain(wasm![
+                    LocalGet(high_local),
+                    F64Const(1.0.into()),
+                    F64Add,
+                    LocalGet(low_local),
+                    F64Sub,
+                    F64Mul,
+                    F64Floor,
+                    F64Add,
+                ])
+                .collect()
         } else if IrType::QuasiInt
             .or(IrType::FloatInt)
             .or(IrType::FloatZero)
@@ -109,7 +127,10 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 F64Eq,
                 I32And,
                 If(WasmBlockType::Result(ValType::F64)),
-                Call(imported_function),
+            ]
+            .into_iter()
+            .chain(random_float(func)?)
+            .chain(wasm![
                 LocalGet(high_local),
                 F64Const(1.0.into()),
                 F64Add,
@@ -118,7 +139,9 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 F64Mul,
                 F64Floor,
                 Else,
-                Call(imported_function),
+            ])
+            .chain(random_float(func)?)
+            .chain(wasm![
                 LocalGet(high_local),
                 LocalGet(low_local),
                 F64Sub,
@@ -126,16 +149,19 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 End,
                 LocalGet(low_local),
                 F64Add,
-            ]
+            ])
+            .collect()
         } else {
-            wasm![
-                Call(imported_function),
-                LocalGet(high_local),
-                LocalGet(low_local),
-                F64Sub,
-                F64Mul,
-                F64Add,
-            ]
+            random_float(func)?
+                .into_iter()
+                .chain(wasm![
+                    LocalGet(high_local),
+                    LocalGet(low_local),
+                    F64Sub,
+                    F64Mul,
+                    F64Add,
+                ])
+                .collect()
         },
     )
     .chain(wasm![End,])
@@ -196,3 +222,13 @@ pub const fn const_fold(
 crate::instructions_test! (
     mod tests for operator_random(t1,t2) {}
 );
+
+crate::instructions_test! (
+    mod tests_seeded for operator_random(t1,t2) {
+        flags = {
+            let mut flags = WasmFlags::new(unit_test_wasm_features());
+            flags.rng = crate::wasm::flags::RandomSource::Seeded;
+            flags
+        };
+    }
+);
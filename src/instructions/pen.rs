@@ -1,7 +1,93 @@
+use mem_layout::sprite as sprite_layout;
+use registries::functions::static_functions::{UpdatePenColorFromHSV, UpdatePenColorFromShade};
+use wasm_encoder::MemArg;
+
+use super::prelude::*;
+use crate::wasm::{mem_layout, registries};
+
 pub mod changecolorparamby;
+pub mod changepenhueby;
+pub mod changepenshadeby;
+pub mod changepensizeby;
 pub mod clear;
 pub mod pendown;
 pub mod penup;
 pub mod setpencolorparamto;
 pub mod setpencolortocolor;
+pub mod setpenhuetonumber;
+pub mod setpenshadetonumber;
 pub mod setpensizeto;
+pub mod stamp;
+
+/// Stores the f64 at the top of the stack as the pen colour (hue) of the given sprite, wrapped
+/// in the same way as Scratch's `MathUtil.wrapClamp(color, 0, 100)` - which, perhaps
+/// surprisingly, wraps around every 101 rather than every 100.
+fn store_pen_color(func: &StepFunc, wasm_target_index: u32) -> HQResult<Vec<InternalInstruction>> {
+    store_wrapped(func, wasm_target_index, sprite_layout::PEN_COLOR, 101.0)
+}
+
+/// Stores the f64 at the top of the stack as the legacy pen shade of the given sprite, wrapped
+/// into [0, 200) like Scratch 2 did.
+fn store_pen_shade(func: &StepFunc, wasm_target_index: u32) -> HQResult<Vec<InternalInstruction>> {
+    store_wrapped(func, wasm_target_index, sprite_layout::PEN_SHADE, 200.0)
+}
+
+fn store_wrapped(
+    func: &StepFunc,
+    wasm_target_index: u32,
+    field: u32,
+    range: f64,
+) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let value_local = func.local(ValType::F64)?;
+    func.free_local(value_local)?;
+    Ok(wasm![
+        LocalSet(value_local),
+        #LazyGlobalGet(mem_offset),
+        // value - floor(value / range) * range
+        LocalGet(value_local),
+        LocalGet(value_local),
+        F64Const(range.into()),
+        F64Div,
+        F64Floor,
+        F64Const(range.into()),
+        F64Mul,
+        F64Sub,
+        F32DemoteF64,
+        F32Store(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + field).into(),
+            align: 2,
+            memory_index: 0,
+        }),
+    ])
+}
+
+/// Updates the pen colour of the given sprite from its hue and legacy shade, as the Scratch 2
+/// hue and shade blocks do.
+fn update_pen_color_from_shade(
+    func: &StepFunc,
+    wasm_target_index: u32,
+) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let mem_pos: i32 = mem_layout::sprite_offset(wasm_target_index)
+        .try_into()
+        .map_err(|_| make_hq_bug!("memory position out of bounds"))?;
+    let shade_func = func
+        .registries()
+        .static_functions()
+        .register::<UpdatePenColorFromShade, _>()?;
+    let hsv2rgb_func = func
+        .registries()
+        .static_functions()
+        .register::<UpdatePenColorFromHSV, _>()?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        I32Const(mem_pos),
+        I32Add,
+        #StaticFunctionCall(shade_func),
+        #LazyGlobalGet(mem_offset),
+        I32Const(mem_pos),
+        I32Add,
+        #StaticFunctionCall(hsv2rgb_func),
+    ])
+}
@@ -9,8 +9,8 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setsizeto called in stage")
     };
-    let mem_pos =
-        mem_layout::stage::BLOCK_SIZE + wasm_target_index * mem_layout::sprite::BLOCK_SIZE;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let mem_pos = mem_layout::sprite_offset(wasm_target_index);
     let hsv2rgb_func = func
         .registries()
         .static_functions()
@@ -29,9 +29,9 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 F32Load(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_COLOR).into(),
                     align: 2,
@@ -53,9 +53,9 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 F32Load(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_SATURATION).into(),
                     align: 2,
@@ -77,9 +77,9 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 F32Load(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_BRIGHTNESS).into(),
                     align: 2,
@@ -101,9 +101,9 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 F32Load(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_TRANSPARENCY).into(),
                     align: 2,
@@ -119,11 +119,13 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
                 End,
                 End,
                 End,
+                #LazyGlobalGet(mem_offset),
                 I32Const(
-                    wasm_target_index
+                    mem_pos
                         .try_into()
-                        .map_err(|_| make_hq_bug!("target index out of bounds"))?
+                        .map_err(|_| make_hq_bug!("memory position out of bounds"))?
                 ),
+                I32Add,
                 #StaticFunctionCall(hsv2rgb_func),
             ])
             .collect(),
@@ -0,0 +1,50 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::{store_pen_shade, update_pen_color_from_shade};
+use crate::wasm::{StepTarget, mem_layout};
+
+pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let t1 = inputs[0];
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("pen_changePenShadeBy called in stage")
+    };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(wasm![
+        @nanreduce(t1),
+        #LazyGlobalGet(mem_offset),
+        F32Load(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::PEN_SHADE)
+                .into(),
+            align: 2,
+            memory_index: 0,
+        }),
+        F64PromoteF32,
+        F64Add,
+    ]
+    .into_iter()
+    .chain(store_pen_shade(func, wasm_target_index)?)
+    .chain(update_pen_color_from_shade(func, wasm_target_index)?)
+    .collect())
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Float]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for pen_changepenshadeby(t) {}
+);
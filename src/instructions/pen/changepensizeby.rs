@@ -0,0 +1,60 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use crate::wasm::{StepTarget, mem_layout};
+
+pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let t1 = inputs[0];
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("pen_changePenSizeBy called in stage")
+    };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let offset = mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::PEN_SIZE;
+    let local_index = func.local(ValType::F64)?;
+    let w = wasm![
+        @nanreduce(t1),
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: offset.into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        F64Add,
+        // see https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/extensions/scratch3_pen/index.js
+        F64Const(1.0.into()),
+        F64Max,
+        F64Const(1200.0.into()),
+        F64Min,
+        LocalSet(local_index),
+        #LazyGlobalGet(mem_offset),
+        LocalGet(local_index),
+        F64Store(MemArg {
+            offset: offset.into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ];
+    func.free_local(local_index)?;
+    Ok(w)
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Float]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for pen_changepensizeby(t) {}
+);
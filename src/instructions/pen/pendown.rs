@@ -22,53 +22,53 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setpendown called in stage")
     };
-    let sprite_offset =
-        mem_layout::stage::BLOCK_SIZE + wasm_target_index * mem_layout::sprite::BLOCK_SIZE;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let sprite_offset = mem_layout::sprite_offset(wasm_target_index);
     Ok(wasm![
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         I32Const(1),
         I32Store8(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_DOWN).into(),
             align: 0,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_SIZE).into(),
             align: 3,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::X).into(),
             align: 3,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F64Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::Y).into(),
             align: 3,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_COLOR_R).into(),
             align: 2,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_COLOR_G).into(),
             align: 2,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_COLOR_B).into(),
             align: 2,
             memory_index: 0,
         }),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         F32Load(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_COLOR_A).into(),
             align: 2,
@@ -7,10 +7,10 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setpendown called in stage")
     };
-    let sprite_offset =
-        mem_layout::stage::BLOCK_SIZE + wasm_target_index * mem_layout::sprite::BLOCK_SIZE;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let sprite_offset = mem_layout::sprite_offset(wasm_target_index);
     Ok(wasm![
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         I32Const(0),
         I32Store8(MemArg {
             offset: (sprite_offset + mem_layout::sprite::PEN_DOWN).into(),
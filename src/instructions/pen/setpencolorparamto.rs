@@ -9,8 +9,8 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setsizeto called in stage")
     };
-    let mem_pos =
-        mem_layout::stage::BLOCK_SIZE + wasm_target_index * mem_layout::sprite::BLOCK_SIZE;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let mem_pos = mem_layout::sprite_offset(wasm_target_index);
     let hsv2rgb_func = func
         .registries()
         .static_functions()
@@ -29,7 +29,7 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
                 F32Store(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_COLOR).into(),
@@ -46,7 +46,7 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
                 F32Store(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_SATURATION).into(),
@@ -63,7 +63,7 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
                 F32Store(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_BRIGHTNESS).into(),
@@ -80,7 +80,7 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
             )
             .chain(wasm![
                 If(WasmBlockType::Empty),
-                I32Const(0),
+                #LazyGlobalGet(mem_offset),
                 LocalGet(value_local),
                 F32Store(MemArg {
                     offset: (mem_pos + mem_layout::sprite::PEN_TRANSPARENCY).into(),
@@ -91,11 +91,13 @@ pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInst
                 End,
                 End,
                 End,
+                #LazyGlobalGet(mem_offset),
                 I32Const(
-                    wasm_target_index
+                    mem_pos
                         .try_into()
-                        .map_err(|_| make_hq_bug!("target index out of bounds"))?
+                        .map_err(|_| make_hq_bug!("memory position out of bounds"))?
                 ),
+                I32Add,
                 #StaticFunctionCall(hsv2rgb_func),
             ])
             .collect(),
@@ -9,14 +9,14 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setsizeto called in stage")
     };
-    let mem_pos =
-        mem_layout::stage::BLOCK_SIZE + wasm_target_index * mem_layout::sprite::BLOCK_SIZE;
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let mem_pos = mem_layout::sprite_offset(wasm_target_index);
     let local_index = func.local(ValType::I32)?;
     let rgb2hsv_func = func
         .registries()
         .static_functions()
         .register::<UpdatePenColorFromRGB, _>()?;
-    let instrs = wasm![LocalSet(local_index), I32Const(0),]
+    let instrs = wasm![LocalSet(local_index), #LazyGlobalGet(mem_offset),]
         .into_iter()
         .chain(match t1 {
             IrType::ColorARGB => {
@@ -51,7 +51,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 align: 2,
                 memory_index: 0
             }),
-            I32Const(0),
+            #LazyGlobalGet(mem_offset),
             LocalGet(local_index),
             I32Const(16),
             I32ShrS,
@@ -65,7 +65,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 align: 2,
                 memory_index: 0
             }),
-            I32Const(0),
+            #LazyGlobalGet(mem_offset),
             LocalGet(local_index),
             I32Const(8),
             I32ShrS,
@@ -79,7 +79,7 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 align: 2,
                 memory_index: 0
             }),
-            I32Const(0),
+            #LazyGlobalGet(mem_offset),
             LocalGet(local_index),
             I32Const(0xFF),
             I32And,
@@ -91,11 +91,13 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
                 align: 2,
                 memory_index: 0
             }),
-            I32Const(
-                wasm_target_index
-                    .try_into()
-                    .map_err(|_| make_hq_bug!("target index out of bounds"))?
-            ),
+            #LazyGlobalGet(mem_offset),
+                I32Const(
+                    mem_pos
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("memory position out of bounds"))?
+                ),
+                I32Add,
             #StaticFunctionCall(rgb2hsv_func),
         ])
         .collect();
@@ -8,16 +8,15 @@ pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstr
     let StepTarget::Sprite(wasm_target_index) = func.target() else {
         hq_bad_proj!("looks_setsizeto called in stage")
     };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
     let local_index = func.local(ValType::F64)?;
     let w = wasm![
         @nanreduce(t1),
         LocalSet(local_index),
-        I32Const(0),
+        #LazyGlobalGet(mem_offset),
         LocalGet(local_index),
         F64Store(MemArg {
-            offset: (mem_layout::stage::BLOCK_SIZE
-                + wasm_target_index * mem_layout::sprite::BLOCK_SIZE
-                + mem_layout::sprite::PEN_SIZE)
+            offset: (mem_layout::sprite_offset(wasm_target_index) + mem_layout::sprite::PEN_SIZE)
                 .into(),
             align: 3,
             memory_index: 0,
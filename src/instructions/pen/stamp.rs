@@ -0,0 +1,36 @@
+use super::super::prelude::*;
+use crate::wasm::StepTarget;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    if !matches!(func.target(), StepTarget::Sprite(_)) {
+        hq_bad_proj!("pen_stamp called in stage")
+    }
+    let current_instance = func.registries().globals().current_instance()?;
+    // the renderer already knows the costume, size, rotation etc of the instance's drawable
+    let func_index = func
+        .registries()
+        .external_functions()
+        .register(("pen", "stamp".into()), (vec![ValType::I32], vec![]))?;
+    Ok(wasm![#LazyGlobalGet(current_instance), Call(func_index),])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = true;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for pen_stamp {}
+);
@@ -10,6 +10,8 @@ use crate::wasm::{StepFunc, WasmProject};
 pub struct Fields {
     pub proc: Rc<Proc>,
     pub next_step: StepIndex,
+    /// whether to call the warp-timed variant of the procedure, rather than the non-warped one
+    pub warp_timer: bool,
 }
 
 impl fmt::Display for Fields {
@@ -18,10 +20,12 @@ impl fmt::Display for Fields {
             f,
             r#"{{
         "proc": {:?},
-        "next_step": {}
+        "next_step": {},
+        "warp_timer": {}
     }}"#,
             self.proc.proccode(),
             self.next_step.0,
+            self.warp_timer,
         )
     }
 }
@@ -29,9 +33,13 @@ impl fmt::Display for Fields {
 pub fn wasm(
     func: &StepFunc,
     inputs: Rc<[IrType]>,
-    Fields { proc, next_step }: &Fields,
+    Fields {
+        proc,
+        next_step,
+        warp_timer,
+    }: &Fields,
 ) -> HQResult<Vec<InternalInstruction>> {
-    let Some(ref nonwarped_specific_proc) = *proc.nonwarped_specific_proc() else {
+    let Some(ref nonwarped_specific_proc) = *proc.nonwarped_specific_proc_for(*warp_timer) else {
         hq_bug!("nonwarped_specific_proc didn't exist for call_nonwarp")
     };
 
@@ -81,7 +89,11 @@ pub fn wasm(
             .map(|var| **var.possible_types().borrow()),
     ) {
         wasm.extend(if param.is_base_type() {
+            // the arguments outlive the current tick if the procedure yields
             wasm![LocalGet(local)]
+                .into_iter()
+                .chain(func.persist_string(param)?)
+                .collect()
         } else {
             wasm![
                 LocalGet(local),
@@ -95,21 +107,27 @@ pub fn wasm(
         StructNew(arg_struct_type),
         LocalSet(arg_struct_local),
         LocalGet((func.params().len() - 2).try_into().map_err(|_| make_hq_bug!("local index out of bounds"))?),
-        #LazyNonWarpedProcRef(Rc::clone(proc)),
+        #LazyNonWarpedProcRef((Rc::clone(proc), *warp_timer)),
         LocalGet(arg_struct_local),
         #LazyStepRef(*next_step),
         #StaticFunctionCall(spawn_thread_in_stack),
         LocalGet((func.params().len() - 2).try_into().map_err(|_| make_hq_bug!("local index out of bounds"))?),
         LocalGet(arg_struct_local),
-        #LazyNonWarpedProcRef(Rc::clone(proc)),
+        #LazyNonWarpedProcRef((Rc::clone(proc), *warp_timer)),
         ReturnCallRef(func.registries().types().step_func_type()?)
     ]);
 
     Ok(wasm)
 }
 
-pub fn acceptable_inputs(Fields { proc, .. }: &Fields) -> HQResult<Rc<[IrType]>> {
-    let Some(ref nonwarped_specific_proc) = *proc.nonwarped_specific_proc() else {
+pub fn acceptable_inputs(
+    Fields {
+        proc,
+        warp_timer,
+        ..
+    }: &Fields,
+) -> HQResult<Rc<[IrType]>> {
+    let Some(ref nonwarped_specific_proc) = *proc.nonwarped_specific_proc_for(*warp_timer) else {
         hq_bug!("nonwarped_specific_proc didn't exist for call_nonwarp")
     };
 
@@ -158,6 +176,7 @@ mod test {
             flags,
             crate::wasm::StepTarget::Sprite(0),
             0,
+            1,
             Rc::new(vec![]),
         );
         wasm_proj.steps().borrow_mut().push(proc_step_func);
@@ -184,6 +203,7 @@ mod test {
                 proc
             },
             next_step: super::StepIndex(0),
+            warp_timer: false,
         }
     }
 }
@@ -138,6 +138,7 @@ mod test {
             flags,
             crate::wasm::StepTarget::Sprite(0),
             0,
+            1,
             Rc::new(vec![]),
         );
         wasm_proj.steps().borrow_mut().push(proc_step_func);
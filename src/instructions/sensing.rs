@@ -1,9 +1,47 @@
+use crate::prelude::*;
+
 pub mod answer;
 pub mod askandwait;
+pub mod coloristouchingcolor;
 pub mod dayssince2000;
 pub mod keypressed;
 pub mod mousedown;
 pub mod mousex;
 pub mod mousey;
+pub mod of;
+pub mod of_variable;
 pub mod reset_timer;
+pub mod setdragmode;
 pub mod timer;
+pub mod touchingcolor;
+pub mod touchingobject;
+
+/// A built-in attribute of another target which can be read with the `of` block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetAttribute {
+    Direction,
+    CostumeNumber,
+    CostumeName,
+    Size,
+    Volume,
+}
+
+impl TargetAttribute {
+    /// The name of this attribute
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Direction => "direction",
+            Self::CostumeNumber => "costume number",
+            Self::CostumeName => "costume name",
+            Self::Size => "size",
+            Self::Volume => "volume",
+        }
+    }
+}
+
+impl fmt::Display for TargetAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
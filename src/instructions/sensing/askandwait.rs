@@ -121,6 +121,7 @@ mod test {
             flags,
             StepTarget::Sprite(0),
             0,
+            1,
             Rc::new(vec![]),
         );
         wasm_proj.steps().borrow_mut().push(step_func);
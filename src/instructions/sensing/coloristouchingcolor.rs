@@ -0,0 +1,54 @@
+use super::super::prelude::*;
+use crate::wasm::StepTarget;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    if matches!(func.target(), StepTarget::Stage) {
+        hq_bad_proj!("sensing_coloristouchingcolor called in stage")
+    }
+    let func_index = func.registries().external_functions().register(
+        ("sensing", "coloristouchingcolor".into()),
+        (
+            vec![ValType::I32, ValType::I32, ValType::I32],
+            vec![ValType::I32],
+        ),
+    )?;
+    let current_instance = func.registries().globals().current_instance()?;
+    let mask_local = func.local(ValType::I32)?;
+    let target_local = func.local(ValType::I32)?;
+    func.free_local(mask_local)?;
+    func.free_local(target_local)?;
+    Ok(wasm![
+        // only the rgb components are used for collision checking
+        I32Const(0x00FF_FFFF),
+        I32And,
+        LocalSet(target_local),
+        I32Const(0x00FF_FFFF),
+        I32And,
+        LocalSet(mask_local),
+        #LazyGlobalGet(current_instance),
+        LocalGet(mask_local),
+        LocalGet(target_local),
+        Call(func_index),
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Color, IrType::Color]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(Singleton(IrType::Boolean))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sensing_coloristouchingcolor(t1, t2) {}
+);
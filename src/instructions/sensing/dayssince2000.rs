@@ -1,7 +1,20 @@
 use super::super::prelude::*;
+use crate::wasm::flags::ClockSource;
 
 pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
     hq_assert_eq!(inputs.len(), 0);
+    if func.flags().clock == ClockSource::Virtual {
+        // the virtual epoch, advanced by however long the virtual clock has been running; this
+        // is clamped to 0 so that the output stays positive
+        let virtual_time = func.registries().globals().virtual_time()?;
+        return Ok(wasm![
+            F64Const(func.flags().virtual_epoch.max(0.0).into()),
+            #LazyGlobalGet(virtual_time),
+            F64Const(86400.0.into()),
+            F64Div,
+            F64Add,
+        ]);
+    }
     let func_index = func.registries().external_functions().register(
         ("sensing", "dayssince2000".into()),
         (vec![], vec![ValType::F64]),
@@ -29,3 +42,13 @@ pub const fn const_fold(
 crate::instructions_test! (
     mod tests for sensing_dayssince2000 {}
 );
+
+crate::instructions_test! (
+    mod tests_virtual for sensing_dayssince2000 {
+        flags = {
+            let mut flags = WasmFlags::new(unit_test_wasm_features());
+            flags.clock = crate::wasm::flags::ClockSource::Virtual;
+            flags
+        };
+    }
+);
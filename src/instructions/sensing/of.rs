@@ -0,0 +1,191 @@
+//! Reads a built-in attribute of the original (i.e. non-clone) instance of another target.
+
+use mem_layout::{sprite as sprite_layout, stage as stage_layout};
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::TargetAttribute;
+use crate::ir::Target;
+use crate::wasm::mem_layout;
+use crate::wasm::registries::tables::TableOptions;
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub target: Rc<Target>,
+    pub attribute: TargetAttribute,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target": {},
+        "attribute": "{}"
+    }}"#,
+            self.target.index(),
+            self.attribute
+        )
+    }
+}
+
+/// Pushes the 0-indexed costume number of the target whose costume is stored at `offset`
+fn costume_index(offset: u32) -> Vec<InternalInstruction> {
+    wasm![
+        I32Const(0),
+        I32Load(MemArg {
+            offset: offset.into(),
+            align: 2,
+            memory_index: 0,
+        }),
+    ]
+}
+
+fn costume_name(
+    func: &StepFunc,
+    target: &Target,
+    offset: u32,
+) -> HQResult<Vec<InternalInstruction>> {
+    let costume_names = func
+        .costume_names()
+        .get(target.index() as usize)
+        .ok_or_else(|| make_hq_bug!("target index out of bounds in costume names vec"))?;
+
+    let costume_name_table = func.registries().tables().register_dyn(
+        format!("costume_names_{}", target.index()).into_boxed_str(),
+        TableOptions {
+            element_type: RefType::EXTERNREF,
+            min: costume_names.len() as u64,
+            max: Some(costume_names.len() as u64),
+            init: None,
+            export_name: None,
+        },
+    )?;
+
+    for costume_name in costume_names {
+        // make sure that strings are all registered so that the element segment can refer to globals that actually exist
+        func.registries()
+            .strings()
+            .register_default::<usize>(costume_name.clone())?;
+    }
+
+    Ok(costume_index(offset)
+        .into_iter()
+        .chain(wasm![TableGet(costume_name_table)])
+        .collect())
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    Fields { target, attribute }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let (costume_offset, volume_offset, sprite_offset) = if target.is_stage() {
+        (stage_layout::COSTUME, stage_layout::VOLUME, None)
+    } else {
+        let sprite_offset = mem_layout::sprite_offset(
+            func.registries()
+                .sprites()
+                .register_default(Rc::clone(target))?,
+        );
+        (
+            sprite_offset + sprite_layout::COSTUME,
+            sprite_offset + sprite_layout::VOLUME,
+            Some(sprite_offset),
+        )
+    };
+    let load_f64 = |offset: u32| {
+        wasm![
+            I32Const(0),
+            F64Load(MemArg {
+                offset: offset.into(),
+                align: 3,
+                memory_index: 0,
+            }),
+        ]
+    };
+    Ok(match attribute {
+        TargetAttribute::Direction => {
+            let Some(sprite_offset) = sprite_offset else {
+                hq_bug!("tried to get the direction of the stage")
+            };
+            load_f64(sprite_offset + sprite_layout::ROTATION)
+        }
+        TargetAttribute::Size => {
+            let Some(sprite_offset) = sprite_offset else {
+                hq_bug!("tried to get the size of the stage")
+            };
+            load_f64(sprite_offset + sprite_layout::SIZE)
+        }
+        TargetAttribute::CostumeNumber => costume_index(costume_offset)
+            .into_iter()
+            .chain(wasm![I32Const(1), I32Add])
+            .collect(),
+        TargetAttribute::CostumeName => costume_name(func, target, costume_offset)?,
+        TargetAttribute::Volume => load_f64(volume_offset),
+    })
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(
+    _inputs: Rc<[IrType]>,
+    Fields { attribute, .. }: &Fields,
+) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(match attribute {
+        TargetAttribute::Direction => IrType::FloatReal,
+        TargetAttribute::CostumeNumber => IrType::IntPos,
+        TargetAttribute::CostumeName => IrType::String,
+        TargetAttribute::Size => IrType::FloatPos,
+        TargetAttribute::Volume => IrType::FloatPos.or(IrType::FloatPosZero),
+    }))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::{assert_valid_json, make_target};
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields(TargetAttribute::CostumeName);
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields(attribute: TargetAttribute) -> Fields {
+        Fields {
+            target: make_target(),
+            attribute,
+        }
+    }
+}
+
+crate::instructions_test!(
+    mod tests_direction for sensing_of {
+        fields = super::test::make_fields(super::TargetAttribute::Direction);
+    }
+);
+
+crate::instructions_test!(
+    mod tests_costume_number for sensing_of {
+        fields = super::test::make_fields(super::TargetAttribute::CostumeNumber);
+    }
+);
+
+crate::instructions_test!(
+    mod tests_volume for sensing_of {
+        fields = super::test::make_fields(super::TargetAttribute::Volume);
+    }
+);
@@ -0,0 +1,136 @@
+//! Takes the value of one of a sprite's variables (as read from the variable's global), and
+//! replaces it with the value that the original (i.e. non-clone) instance of that sprite holds.
+//!
+//! Variables of sprites which can be cloned have a single global, which holds the value for
+//! whichever instance is currently active; if that isn't the original sprite, its value is read
+//! from slot 0 of the variable's clone store instead (see
+//! [`CloneRegistry`](crate::wasm::registries::CloneRegistry)).
+
+use super::super::prelude::*;
+use crate::ir::{RcVar, Target};
+use crate::wasm::WasmProject;
+
+#[derive(Clone, Debug)]
+pub struct Fields {
+    pub target: Rc<Target>,
+    pub var: RcVar,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "target": {},
+        "variable": {}
+    }}"#,
+            self.target.index(),
+            self.var
+        )
+    }
+}
+
+fn var_type(var: &RcVar) -> IrType {
+    if var.possible_types().is_none() {
+        IrType::Any
+    } else {
+        *var.possible_types()
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    inputs: Rc<[IrType]>,
+    Fields { target, var }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let t1 = inputs[0];
+    let mut instructions = if var.possible_types().is_base_type() {
+        wasm![]
+    } else {
+        wasm![@boxed(t1)]
+    };
+    if !target.is_clonable()? {
+        // the variable's global always holds the original sprite's value
+        return Ok(instructions);
+    }
+    let clones = func.registries().clones();
+    let active_slot = clones.active_slot_global(target)?;
+    let (store, store_ty) = clones.variable_store_global(var)?;
+    instructions.extend(wasm![
+        #LazyGlobalGet(store),
+        I32Const(0),
+        ArrayGet(store_ty),
+        #LazyGlobalGet(active_slot),
+        I32Eqz,
+        TypedSelect(WasmProject::ir_type_to_wasm(var_type(var))),
+    ]);
+    Ok(instructions)
+}
+
+pub fn acceptable_inputs(Fields { var, .. }: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([var_type(var)]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, Fields { var, .. }: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(var_type(var)))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::{assert_valid_json, make_target};
+    use crate::sb3::VarVal;
+    use crate::wasm::WasmFlags;
+    use crate::wasm::flags::unit_test_wasm_features;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        let fields = make_fields(IrType::Any, false);
+        assert_valid_json(format!("{fields}"));
+    }
+
+    pub fn make_fields(ty: IrType, clonable: bool) -> Fields {
+        let target = make_target();
+        if clonable {
+            target.mark_clonable().unwrap();
+        }
+        Fields {
+            target,
+            var: RcVar::new(
+                ty,
+                &VarVal::Float(0.0),
+                None,
+                &WasmFlags::new(unit_test_wasm_features()),
+            )
+            .unwrap(),
+        }
+    }
+}
+
+crate::instructions_test!(
+    mod tests_float for sensing_of_variable(t) {
+        fields = super::test::make_fields(IrType::Float, false);
+    }
+);
+
+crate::instructions_test!(
+    mod tests_any_clonable for sensing_of_variable(t) {
+        fields = super::test::make_fields(IrType::Any, true);
+    }
+);
+
+crate::instructions_test!(
+    mod tests_float_clonable for sensing_of_variable(t) {
+        fields = super::test::make_fields(IrType::Float, true);
+    }
+);
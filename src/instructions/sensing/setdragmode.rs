@@ -0,0 +1,84 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use crate::wasm::{StepTarget, mem_layout};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields {
+    /// whether the sprite should be draggable in the player
+    pub draggable: bool,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "draggable": {}
+    }}"#,
+            self.draggable
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    _inputs: Rc<[IrType]>,
+    &Fields { draggable }: &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let StepTarget::Sprite(wasm_target_index) = func.target() else {
+        hq_bad_proj!("sensing_setdragmode called in stage")
+    };
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        I32Const(draggable.into()),
+        I32Store8(MemArg {
+            offset: (mem_layout::sprite_offset(wasm_target_index) + sprite_layout::DRAGGABLE)
+                .into(),
+            align: 0,
+            memory_index: 0
+        }),
+    ])
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields { draggable: true }));
+    }
+}
+
+crate::instructions_test! (
+    mod tests for sensing_setdragmode {
+        fields = super::Fields { draggable: true };
+    }
+);
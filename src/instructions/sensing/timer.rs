@@ -1,18 +1,7 @@
-use wasm_encoder::ConstExpr;
-
 use super::super::prelude::*;
-use crate::wasm::{GlobalExportable, GlobalMutable};
 
 pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
-    let global_index = func.registries().globals().register(
-        "sensing_timer".into(),
-        (
-            ValType::F64,
-            ConstExpr::f64_const(0.0.into()),
-            GlobalMutable(true),
-            GlobalExportable(true),
-        ),
-    )?;
+    let global_index = func.registries().globals().sensing_timer()?;
     Ok(wasm![
         #LazyGlobalGet(global_index),
     ])
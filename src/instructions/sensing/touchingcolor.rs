@@ -0,0 +1,45 @@
+use super::super::prelude::*;
+use crate::wasm::StepTarget;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    if matches!(func.target(), StepTarget::Stage) {
+        hq_bad_proj!("sensing_touchingcolor called in stage")
+    }
+    let func_index = func.registries().external_functions().register(
+        ("sensing", "touchingcolor".into()),
+        (vec![ValType::I32, ValType::I32], vec![ValType::I32]),
+    )?;
+    let current_instance = func.registries().globals().current_instance()?;
+    let color_local = func.local(ValType::I32)?;
+    func.free_local(color_local)?;
+    Ok(wasm![
+        // only the rgb components are used for collision checking
+        I32Const(0x00FF_FFFF),
+        I32And,
+        LocalSet(color_local),
+        #LazyGlobalGet(current_instance),
+        LocalGet(color_local),
+        Call(func_index),
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Color]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(Singleton(IrType::Boolean))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sensing_touchingcolor(t) {}
+);
@@ -0,0 +1,62 @@
+use wasm_encoder::ConstExpr;
+
+use super::super::prelude::*;
+use crate::wasm::{GlobalExportable, GlobalMutable, StepTarget};
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    if matches!(func.target(), StepTarget::Stage) {
+        hq_bad_proj!("sensing_touchingobject called in stage")
+    }
+    let func_index = func.registries().external_functions().register(
+        ("sensing", "touchingobject".into()),
+        (
+            vec![ValType::I32, ValType::EXTERNREF, ValType::F64, ValType::F64],
+            vec![ValType::I32],
+        ),
+    )?;
+    let current_instance = func.registries().globals().current_instance()?;
+    let mouse_global = |name: &str| {
+        func.registries().globals().register::<u32>(
+            name.into(),
+            (
+                ValType::F64,
+                ConstExpr::f64_const(0.0.into()),
+                GlobalMutable(true),
+                GlobalExportable(true),
+            ),
+        )
+    };
+    let mouse_x = mouse_global("mouseX")?;
+    let mouse_y = mouse_global("mouseY")?;
+    let object_local = func.local(ValType::EXTERNREF)?;
+    func.free_local(object_local)?;
+    Ok(wasm![
+        LocalSet(object_local),
+        #LazyGlobalGet(current_instance),
+        LocalGet(object_local),
+        #LazyGlobalGet(mouse_x),
+        #LazyGlobalGet(mouse_y),
+        Call(func_index),
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::String]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(Singleton(IrType::Boolean))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sensing_touchingobject(t) {}
+);
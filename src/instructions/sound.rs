@@ -0,0 +1,121 @@
+use wasm_encoder::MemArg;
+
+use super::prelude::*;
+use crate::wasm::{StepTarget, mem_layout};
+
+pub mod cleareffects;
+pub mod effect;
+pub mod play;
+pub mod playuntildone;
+pub mod seteffectto;
+pub mod setvolumeto;
+pub mod stopallsounds;
+pub mod volume;
+
+/// A sound effect which can be applied to a target's sounds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundEffect {
+    Pitch,
+    Pan,
+}
+
+impl SoundEffect {
+    /// Parses the value of a `sound_seteffectto` or `sound_changeeffectby` block's `EFFECT`
+    /// field.
+    pub fn from_field(effect: &str) -> HQResult<Self> {
+        Ok(match effect.to_lowercase().as_str() {
+            "pitch" => Self::Pitch,
+            "pan" => Self::Pan,
+            _ => hq_bad_proj!("unknown sound effect {effect}"),
+        })
+    }
+
+    /// The (minimum, maximum) values of this effect
+    #[must_use]
+    pub const fn range(self) -> (f64, f64) {
+        match self {
+            Self::Pitch => (-360.0, 360.0),
+            Self::Pan => (-100.0, 100.0),
+        }
+    }
+
+    /// The memory offset of this effect for the given target, relative to the memory offset of
+    /// the current instance.
+    #[must_use]
+    pub const fn offset(self, target: StepTarget) -> u32 {
+        match (self, target) {
+            (Self::Pitch, StepTarget::Sprite(index)) => {
+                mem_layout::sprite_offset(index) + mem_layout::sprite::PITCH
+            }
+            (Self::Pan, StepTarget::Sprite(index)) => {
+                mem_layout::sprite_offset(index) + mem_layout::sprite::PAN
+            }
+            (Self::Pitch, StepTarget::Stage) => mem_layout::stage::PITCH,
+            (Self::Pan, StepTarget::Stage) => mem_layout::stage::PAN,
+        }
+    }
+}
+
+impl fmt::Display for SoundEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pitch => write!(f, "pitch"),
+            Self::Pan => write!(f, "pan"),
+        }
+    }
+}
+
+/// The memory offset of the volume of the given target, relative to the memory offset of the
+/// current instance.
+#[must_use]
+pub const fn volume_offset(target: StepTarget) -> u32 {
+    match target {
+        StepTarget::Sprite(index) => mem_layout::sprite_offset(index) + mem_layout::sprite::VOLUME,
+        StepTarget::Stage => mem_layout::stage::VOLUME,
+    }
+}
+
+/// Pushes the volume, pitch and pan of the current instance, followed by the index of the
+/// current instance; this is the state that the `sound` imports need to play a sound.
+fn sound_state(func: &StepFunc) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let current_instance = func.registries().globals().current_instance()?;
+    let target = func.target();
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: volume_offset(target).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: SoundEffect::Pitch.offset(target).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: SoundEffect::Pan.offset(target).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(current_instance),
+    ])
+}
+
+/// Tells the host about the current instance's new sound state, so that it can be applied to
+/// any sounds which are currently playing.
+fn update_sound_state(func: &StepFunc) -> HQResult<Vec<InternalInstruction>> {
+    let func_index = func.registries().external_functions().register(
+        ("sound", "update_state".into()),
+        (
+            vec![ValType::F64, ValType::F64, ValType::F64, ValType::I32],
+            vec![],
+        ),
+    )?;
+    Ok(sound_state(func)?
+        .into_iter()
+        .chain(wasm![Call(func_index)])
+        .collect())
+}
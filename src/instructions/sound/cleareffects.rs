@@ -0,0 +1,48 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::{SoundEffect, update_sound_state};
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        F64Const(0.0.into()),
+        F64Store(MemArg {
+            offset: SoundEffect::Pitch.offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+        #LazyGlobalGet(mem_offset),
+        F64Const(0.0.into()),
+        F64Store(MemArg {
+            offset: SoundEffect::Pan.offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ]
+    .into_iter()
+    .chain(update_sound_state(func)?)
+    .collect())
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sound_cleareffects {}
+);
@@ -0,0 +1,46 @@
+use super::super::prelude::*;
+use super::sound_state;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let func_index = func.registries().external_functions().register(
+        ("sound", "play".into()),
+        (
+            vec![
+                ValType::I32,
+                ValType::F64,
+                ValType::F64,
+                ValType::F64,
+                ValType::I32,
+            ],
+            vec![],
+        ),
+    )?;
+    // sound numbers are 1-indexed; out-of-range sounds are wrapped around by the host, as in
+    // scratch
+    Ok(wasm![I32Const(1), I32Sub]
+        .into_iter()
+        .chain(sound_state(func)?)
+        .chain(wasm![Call(func_index)])
+        .collect())
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Int]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sound_play(t) {}
+);
@@ -0,0 +1,95 @@
+#![allow(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "Fields should be passed by reference for type signature consistency"
+)]
+
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::{SoundEffect, update_sound_state};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fields(pub SoundEffect);
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{
+        "effect": "{}"
+    }}"#,
+            self.0
+        )
+    }
+}
+
+pub fn wasm(
+    func: &StepFunc,
+    inputs: Rc<[IrType]>,
+    &Fields(effect): &Fields,
+) -> HQResult<Vec<InternalInstruction>> {
+    let t1 = inputs[0];
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let local_index = func.local(ValType::F64)?;
+    func.free_local(local_index)?;
+    let (min, max) = effect.range();
+    Ok(wasm![
+        @nanreduce(t1),
+        F64Const(min.into()),
+        F64Max,
+        F64Const(max.into()),
+        F64Min,
+        LocalSet(local_index),
+        #LazyGlobalGet(mem_offset),
+        LocalGet(local_index),
+        F64Store(MemArg {
+            offset: effect.offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ]
+    .into_iter()
+    .chain(update_sound_state(func)?)
+    .collect())
+}
+
+pub fn acceptable_inputs(_fields: &Fields) -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Float]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>, _fields: &Fields) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+    _fields: &Fields,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::tests::assert_valid_json;
+
+    #[test]
+    fn fields_display_is_valid_json() {
+        assert_valid_json(format!("{}", Fields(SoundEffect::Pitch)));
+    }
+}
+
+crate::instructions_test! (
+    mod tests_pitch for sound_seteffectto(t) {
+        fields = super::Fields(super::SoundEffect::Pitch);
+    }
+);
+
+crate::instructions_test! (
+    mod tests_pan for sound_seteffectto(t) {
+        fields = super::Fields(super::SoundEffect::Pan);
+    }
+);
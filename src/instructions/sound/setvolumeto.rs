@@ -0,0 +1,50 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::{update_sound_state, volume_offset};
+
+pub fn wasm(func: &StepFunc, inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let t1 = inputs[0];
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    let local_index = func.local(ValType::F64)?;
+    func.free_local(local_index)?;
+    Ok(wasm![
+        @nanreduce(t1),
+        F64Const(0.0.into()),
+        F64Max,
+        F64Const(100.0.into()),
+        F64Min,
+        LocalSet(local_index),
+        #LazyGlobalGet(mem_offset),
+        LocalGet(local_index),
+        F64Store(MemArg {
+            offset: volume_offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ]
+    .into_iter()
+    .chain(update_sound_state(func)?)
+    .collect())
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([IrType::Float]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::None)
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sound_setvolumeto(t) {}
+);
@@ -0,0 +1,39 @@
+use wasm_encoder::MemArg;
+
+use super::super::prelude::*;
+use super::volume_offset;
+
+pub fn wasm(func: &StepFunc, _inputs: Rc<[IrType]>) -> HQResult<Vec<InternalInstruction>> {
+    let mem_offset = func.registries().globals().instance_mem_offset()?;
+    Ok(wasm![
+        #LazyGlobalGet(mem_offset),
+        F64Load(MemArg {
+            offset: volume_offset(func.target()).into(),
+            align: 3,
+            memory_index: 0,
+        }),
+    ])
+}
+
+pub fn acceptable_inputs() -> HQResult<Rc<[IrType]>> {
+    Ok(Rc::from([]))
+}
+
+pub fn output_type(_inputs: Rc<[IrType]>) -> HQResult<ReturnType> {
+    Ok(ReturnType::Singleton(
+        IrType::FloatPos.or(IrType::FloatPosZero),
+    ))
+}
+
+pub const REQUESTS_SCREEN_REFRESH: bool = false;
+
+pub const fn const_fold(
+    _inputs: &[ConstFoldItem],
+    _state: &mut ConstFoldState,
+) -> HQResult<ConstFold> {
+    Ok(NotFoldable)
+}
+
+crate::instructions_test! (
+    mod tests for sound_volume {}
+);
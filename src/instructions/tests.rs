@@ -81,7 +81,7 @@ macro_rules! instructions_test {
                     $($setup(&mut proj, flags());)?
                     let output_type_result = output_type(types.clone().into(), $(&$fields)?);
                     let registries = Rc::new(Registries::default());
-                    let step_func = StepFunc::new(Rc::clone(&registries), flags(), StepTarget::Sprite(0), 0, Rc::clone(proj.costume_names()));
+                    let step_func = StepFunc::new(Rc::clone(&registries), flags(), StepTarget::Sprite(0), 0, 1, Rc::clone(proj.costume_names()));
                     let wasm_result = wasm(&step_func, types);
                     match (output_type_result.clone(), wasm_result.clone()) {
                         (Err(..), Ok(..)) | (Ok(..), Err(..)) => panic!("output_type result doesn't match wasm result for type(s) {:?}:\noutput_type: {:?},\nwasm: {:?}", ($($($type_arg,)*)?), output_type_result, wasm_result),
@@ -98,15 +98,20 @@ macro_rules! instructions_test {
             }
 
 
+            // every environment is tested, as strings are represented differently in each
+            const ENVIRONMENTS: [ExternalEnvironment; 2] = [ExternalEnvironment::WebBrowser, ExternalEnvironment::Headless];
+
             #[test]
             fn wasm_output_type_matches_expected_output_type() -> HQResult<()> {
                 for ($($($type_arg,)*)?) in types_iter(true) {
-                    let types: &[IrType] = &[$($($type_arg,)*)?];
-                    #[allow(unused_mut, reason = "may not be unused")]
-                    let mut proj = WasmProject::new(flags(), ExternalEnvironment::WebBrowser, vec![vec!["".into()]]);
-                    $($setup(&mut proj, flags());)?
-                    let output_type2 = || output_type(Rc::from([$($($type_arg,)*)?]), $(&$fields)?);
-                    $crate::instructions::tests::wasm_output_type_matches_expected_output_type(proj, types, output_type2, wasm, flags())?;
+                    for environment in ENVIRONMENTS {
+                        let types: &[IrType] = &[$($($type_arg,)*)?];
+                        #[allow(unused_mut, reason = "may not be unused")]
+                        let mut proj = WasmProject::new(flags(), environment, vec![vec!["".into()]]);
+                        $($setup(&mut proj, flags());)?
+                        let output_type2 = || output_type(Rc::from([$($($type_arg,)*)?]), $(&$fields)?);
+                        $crate::instructions::tests::wasm_output_type_matches_expected_output_type(proj, types, output_type2, wasm, flags())?;
+                    }
                 }
                 Ok(())
             }
@@ -118,15 +123,17 @@ macro_rules! instructions_test {
             #[test]
             fn wasm_output_type_matches_wrapped_expected_output_type() -> HQResult<()> {
                 for ($($($type_arg,)*)?) in types_iter(false) {
-                    let types: &[IrType] = &[$($($type_arg,)*)?];
-                    #[allow(unused_mut, reason = "may not be unused")]
-                    let mut proj = WasmProject::new(flags(), ExternalEnvironment::WebBrowser, vec![vec!["".into()]]);
-                    $($setup(&mut proj, flags());)?
-                    let output_type2 = || $crate::instructions::boxed_output_type(
-                        |inputs| output_type(inputs, $(&$fields)?),
-                        types.clone().into(),
-                    );
-                    $crate::instructions::tests::wasm_output_type_matches_wrapped_expected_output_type(proj, types, output_type2, wrap_instructions, flags(), IrOpcode::$opcode$(($fields))?)?;
+                    for environment in ENVIRONMENTS {
+                        let types: &[IrType] = &[$($($type_arg,)*)?];
+                        #[allow(unused_mut, reason = "may not be unused")]
+                        let mut proj = WasmProject::new(flags(), environment, vec![vec!["".into()]]);
+                        $($setup(&mut proj, flags());)?
+                        let output_type2 = || $crate::instructions::boxed_output_type(
+                            |inputs| output_type(inputs, $(&$fields)?),
+                            types.clone().into(),
+                        );
+                        $crate::instructions::tests::wasm_output_type_matches_wrapped_expected_output_type(proj, types, output_type2, wrap_instructions, flags(), IrOpcode::$opcode$(($fields))?)?;
+                    }
                 }
                 Ok(())
             }
@@ -230,7 +237,7 @@ pub use test_util::*;
 mod test_util {
     use wasm_encoder::ValType;
 
-    use crate::instructions::IrOpcode;
+    use crate::instructions::{IrOpcode, RotationStyle};
     use crate::ir::{IrType, ReturnType, Step, StepContext, Target};
     use crate::prelude::*;
     use crate::wasm::registries::TypeRegistry;
@@ -239,12 +246,18 @@ mod test_util {
     pub fn make_target() -> Rc<Target> {
         Rc::new(Target::new(
             false,
+            "Sprite1".into(),
             BTreeMap::default(),
             BTreeMap::default(),
             Weak::new(),
             RefCell::default(),
             0,
             Box::default(),
+            Box::default(),
+            100.0,
+            1,
+            RotationStyle::AllAround,
+            false,
         ))
     }
 
@@ -252,6 +265,7 @@ mod test_util {
         StepContext {
             target: Rc::clone(target),
             warp: false,
+            warp_timer: false,
             proc_context: None,
             debug: false,
         }
@@ -307,6 +321,7 @@ mod test_util {
             flags,
             StepTarget::Sprite(0),
             0,
+            1,
             Rc::clone(proj.costume_names()),
         );
         let Ok(wasm) = wasm(&step_func, types) else {
@@ -369,6 +384,7 @@ mod test_util {
             flags,
             StepTarget::Sprite(0),
             0,
+            1,
             Rc::clone(proj.costume_names()),
         );
         let drops = match output_type {
@@ -1,23 +1,30 @@
 mod blocks;
+mod compatibility;
 mod context;
 mod event;
 mod proc;
 mod project;
 mod step;
 mod target;
+#[cfg(test)]
+mod test_utils;
 mod thread;
 mod types;
+mod unsupported;
 mod variable;
 
 pub use blocks::insert_casts;
+pub use compatibility::{CompatibilityReport, InertHat, OpcodeUsage};
 pub use context::{ProcContext, StepContext};
-pub use event::Event;
+pub use event::{Event, EventThreshold, GreaterThanMenu};
 pub use proc::{PartialStep, Proc};
 pub use project::IrProject;
 pub use step::{InlinedStep, MaybeInlinedStep, Step, StepIndex};
 pub use target::Target;
+pub use thread::InertHatReason;
 use thread::Thread;
 pub use types::{
     ReturnType, Type as IrType, TypeStack, base_types, var_val_instruction, var_val_type,
 };
-pub use variable::{IrMonitor, RcList, RcVar, used_vars};
+pub use unsupported::{check_unsupported_blocks, unsupported_blocks};
+pub use variable::{IrMonitor, RcList, RcVar, used_lists, used_vars};
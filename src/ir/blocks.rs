@@ -2,38 +2,60 @@ mod cast;
 mod control_flow;
 mod inputs;
 mod list_op;
+mod looks;
+mod motion;
 mod next;
 mod proc_arg;
+mod sensing;
+mod sound;
 mod special;
 
 pub use cast::insert_casts;
 use control_flow::{generate_exhaustive_string_comparison, generate_if_else, generate_loop};
 use inputs::inputs;
+pub use inputs::is_supported_opcode;
 use list_op::generate_list_index_op;
+use looks::{generate_backdrop_number, generate_bubble_for_secs};
+use motion::{
+    generate_distanceto, generate_glide, generate_glideto, generate_goto, generate_pointtowards,
+};
 pub use next::NextBlocks;
-use next::{NextBlock, NextBlockInfo, generate_next_step_inlined, generate_next_step_non_inlined};
+use next::{
+    NextBlock, NextBlockInfo, generate_next_step_inlined, generate_next_step_non_inlined,
+    loop_yield,
+};
 use proc_arg::{ProcArgType, procedure_argument};
+use sensing::generate_of;
+use sound::generate_sound_number;
 use special::from_special_block;
 
 use super::context::StepContext;
-use super::{IrProject, IrType, RcVar, Step};
+use super::target::IrCostume;
+use super::{IrProject, IrType, RcVar, Step, Target};
 use crate::instructions::{
+    ControlCreateCloneOfFields, ControlDeleteThisCloneFields, ControlIfElseFields,
     ControlLoopFields, ControlWaitFields, DataAddtolistFields, DataDeletealloflistFields,
     DataDeleteoflistFields, DataInsertatlistFields, DataItemoflistFields, DataLengthoflistFields,
     DataListcontentsFields, DataReplaceitemoflistFields, DataSetvariabletoFields,
     DataTeevariableFields, DataVariableFields, DataVisvariableFields, EventBroadcastAndWaitFields,
-    EventBroadcastFields, HqBooleanFields, HqCastFields, HqFloatFields, HqIntegerFields,
-    HqTextFields, HqYieldFields, IrOpcode, LooksSayFields, LooksThinkFields,
-    ProceduresCallNonwarpFields, ProceduresCallWarpFields, SensingAskandwaitFields, YieldMode,
+    EventBroadcastFields, GraphicEffect, HqBooleanFields, HqCastFields, HqFloatFields,
+    HqIntegerFields, HqTextFields, HqYieldFields, IrOpcode, LooksEffectFields,
+    LooksGoforwardbackwardlayersFields, LooksGotofrontbackFields, LooksSayFields,
+    LooksSeteffecttoFields, LooksSwitchbackdroptoandwaitFields, LooksThinkFields,
+    MotionIfonedgebounceFields, MotionSetrotationstyleFields, ProceduresCallNonwarpFields,
+    ProceduresCallWarpFields, RotationStyle, SensingAskandwaitFields, SensingSetdragmodeFields,
+    SoundEffect, SoundEffectFields, SoundPlayuntildoneFields, SoundSeteffecttoFields, YieldMode,
 };
 use crate::prelude::*;
 use crate::sb3::{
-    Block, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, Field as Sb3Field, VarVal,
+    Block, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, Field as Sb3Field, Input, VarVal,
 };
 use crate::wasm::WasmFlags;
+use crate::wasm::flags::Switch;
 
 pub fn from_block(
     block: &Block,
+    block_id: &str,
     blocks: &BlockMap,
     context: &StepContext,
     project: &Weak<IrProject>,
@@ -42,6 +64,7 @@ pub fn from_block(
 ) -> HQResult<Vec<IrOpcode>> {
     let mut opcodes = match block {
         Block::Normal { block_info, .. } => from_normal_block(
+            block_id,
             block_info,
             blocks,
             context,
@@ -50,13 +73,17 @@ pub fn from_block(
             flags,
         )?
         .to_vec(),
-        Block::Special(block_array) => vec![from_special_block(block_array, context, flags)?],
+        Block::Special(block_array) => vec![
+            from_special_block(block_array, context, flags)
+                .map_err(|err| err.with_location(context.block_location(block_id)))?,
+        ],
     };
     insert_casts(&mut opcodes, true, false)?;
     Ok(opcodes)
 }
 
 fn from_normal_block(
+    init_block_id: &str,
     init_block_info: &BlockInfo,
     blocks: &BlockMap,
     context: &StepContext,
@@ -64,25 +91,34 @@ fn from_normal_block(
     final_next_blocks: NextBlocks,
     flags: &WasmFlags,
 ) -> HQResult<Box<[IrOpcode]>> {
-    let mut curr_block = Some(init_block_info);
+    let mut curr_block = Some((Box::from(init_block_id), init_block_info));
     let mut final_next_blocks = final_next_blocks;
     let mut opcodes = vec![];
     let mut should_break = false;
-    while let Some(block_info) = curr_block {
-        opcodes.append(
-            &mut inputs(block_info, blocks, context, project, flags)?
-                .into_iter()
-                .chain(block_to_ir(
-                    block_info,
-                    blocks,
-                    context,
-                    project,
-                    &final_next_blocks,
-                    flags,
-                    &mut should_break,
-                )?)
-                .collect(),
-        );
+    while let Some((block_id, block_info)) = curr_block {
+        // errors from nested blocks will already have been given a location by this point
+        let locate = |err: HQError| err.with_location(context.block_location(&block_id));
+        // unsupported stack blocks are skipped entirely; see also `inputs`
+        if flags.skip_unsupported_blocks == Switch::Off || is_supported_opcode(&block_info.opcode) {
+            opcodes.append(
+                &mut inputs(block_info, blocks, context, project, flags)
+                    .map_err(locate)?
+                    .into_iter()
+                    .chain(
+                        block_to_ir(
+                            block_info,
+                            blocks,
+                            context,
+                            project,
+                            &final_next_blocks,
+                            flags,
+                            &mut should_break,
+                        )
+                        .map_err(locate)?,
+                    )
+                    .collect(),
+            );
+        }
         if should_break {
             break;
         }
@@ -90,7 +126,9 @@ fn from_normal_block(
             let next_block = blocks
                 .get(next_id)
                 .ok_or_else(|| make_hq_bad_proj!("missing next block"))?;
-            next_block.block_info()
+            next_block
+                .block_info()
+                .map(|next_info| (next_id.clone(), next_info))
         } else if let (Some(popped_next), new_next_blocks_stack) =
             final_next_blocks.clone().pop_inner()
         {
@@ -100,8 +138,9 @@ fn from_normal_block(
                         .get(&id)
                         .ok_or_else(|| make_hq_bad_proj!("missing next block"))?;
                     if (popped_next.yield_first) && !context.warp {
-                        opcodes.push(IrOpcode::hq_yield(HqYieldFields {
-                            mode: YieldMode::Schedule(Step::from_block_non_inlined(
+                        opcodes.push(loop_yield(
+                            context,
+                            Step::from_block_non_inlined(
                                 next_block,
                                 id.clone(),
                                 blocks,
@@ -109,19 +148,17 @@ fn from_normal_block(
                                 project,
                                 new_next_blocks_stack,
                                 flags,
-                            )?),
-                        }));
+                            )?,
+                        ));
                         None
                     } else {
                         final_next_blocks = new_next_blocks_stack;
-                        next_block.block_info()
+                        next_block.block_info().map(|next_info| (id, next_info))
                     }
                 }
                 NextBlock::Step(mut step) => {
                     if popped_next.yield_first && !context.warp {
-                        opcodes.push(IrOpcode::hq_yield(HqYieldFields {
-                            mode: YieldMode::Schedule(context.project()?.new_owned_step(step)?),
-                        }));
+                        opcodes.push(loop_yield(context, context.project()?.new_owned_step(step)?));
                     } else {
                         step.make_inlined();
                         opcodes.push(IrOpcode::hq_yield(HqYieldFields {
@@ -132,9 +169,7 @@ fn from_normal_block(
                 }
                 NextBlock::StepIndex(step_index) => {
                     if popped_next.yield_first && !context.warp {
-                        opcodes.push(IrOpcode::hq_yield(HqYieldFields {
-                            mode: YieldMode::Schedule(step_index),
-                        }));
+                        opcodes.push(loop_yield(context, step_index));
                     } else {
                         let mut step = context
                             .project()?
@@ -218,6 +253,42 @@ fn block_to_ir(
             IrOpcode::operator_add,
             IrOpcode::motion_gotoxy,
         ],
+        BlockOpcode::motion_glidesecstoxy => generate_glide(
+            should_break,
+            block_info,
+            blocks,
+            context,
+            final_next_blocks.clone(),
+            flags,
+        )?,
+        BlockOpcode::motion_goto_menu | BlockOpcode::motion_glideto_menu => {
+            vec![IrOpcode::hq_text(HqTextFields(string_field(block_info, "TO")?))]
+        }
+        BlockOpcode::motion_pointtowards_menu => {
+            vec![IrOpcode::hq_text(HqTextFields(string_field(block_info, "TOWARDS")?))]
+        }
+        BlockOpcode::sensing_distancetomenu => {
+            vec![IrOpcode::hq_text(HqTextFields(string_field(block_info, "DISTANCETOMENU")?))]
+        }
+        BlockOpcode::motion_goto => generate_goto(block_info, blocks, context, project, flags)?,
+        BlockOpcode::motion_glideto => generate_glideto(
+            should_break,
+            block_info,
+            blocks,
+            context,
+            final_next_blocks.clone(),
+            flags,
+        )?,
+        BlockOpcode::motion_pointtowards => {
+            generate_pointtowards(block_info, blocks, context, project, flags)?
+        }
+        BlockOpcode::sensing_distanceto => {
+            generate_distanceto(block_info, blocks, context, project, flags)?
+        }
+        BlockOpcode::sensing_of_object_menu => {
+            vec![IrOpcode::hq_text(HqTextFields(string_field(block_info, "OBJECT")?))]
+        }
+        BlockOpcode::sensing_of => generate_of(block_info, blocks, context, project, flags)?,
         BlockOpcode::motion_direction => vec![IrOpcode::motion_direction],
         BlockOpcode::motion_pointindirection => {
             vec![IrOpcode::motion_pointindirection]
@@ -234,6 +305,24 @@ fn block_to_ir(
             IrOpcode::operator_multiply,
             IrOpcode::motion_pointindirection,
         ],
+        BlockOpcode::motion_ifonedgebounce => {
+            vec![IrOpcode::motion_ifonedgebounce(MotionIfonedgebounceFields {
+                costume_bounds: context
+                    .target()
+                    .costumes()
+                    .iter()
+                    .map(IrCostume::bounds)
+                    .collect(),
+            })]
+        }
+        BlockOpcode::motion_setrotationstyle => {
+            // scratch ignores unknown rotation styles
+            if let Some(style) = RotationStyle::from_field(&string_field(block_info, "STYLE")?) {
+                vec![IrOpcode::motion_setrotationstyle(MotionSetrotationstyleFields(style))]
+            } else {
+                vec![]
+            }
+        }
         BlockOpcode::looks_say => vec![IrOpcode::looks_say(LooksSayFields {
             debug: context.debug,
             target_idx: context.target().index(),
@@ -242,6 +331,16 @@ fn block_to_ir(
             debug: context.debug,
             target_idx: context.target().index(),
         })],
+        BlockOpcode::looks_sayforsecs | BlockOpcode::looks_thinkforsecs => generate_bubble_for_secs(
+            block_info.opcode == BlockOpcode::looks_thinkforsecs,
+            should_break,
+            block_info,
+            blocks,
+            context,
+            project,
+            final_next_blocks.clone(),
+            flags,
+        )?,
         BlockOpcode::operator_join => vec![IrOpcode::operator_join],
         BlockOpcode::operator_length => vec![IrOpcode::operator_length],
         BlockOpcode::operator_contains => vec![IrOpcode::operator_contains],
@@ -326,7 +425,7 @@ fn block_to_ir(
         }
         BlockOpcode::event_broadcast => generate_exhaustive_string_comparison(
             context.project()?.broadcasts().iter().cloned(),
-            |broadcast| IrOpcode::event_broadcast(EventBroadcastFields(broadcast)),
+            |broadcast| vec![IrOpcode::event_broadcast(EventBroadcastFields(broadcast))],
             vec![],
             context,
             project,
@@ -350,11 +449,11 @@ fn block_to_ir(
             generate_exhaustive_string_comparison(
                 context.project()?.broadcasts().iter().cloned(),
                 |broadcast| {
-                    IrOpcode::event_broadcast_and_wait(EventBroadcastAndWaitFields {
+                    vec![IrOpcode::event_broadcast_and_wait(EventBroadcastAndWaitFields {
                         broadcast,
                         poll_step,
                         next_step,
-                    })
+                    })]
                 },
                 vec![IrOpcode::hq_yield(HqYieldFields {
                     mode: YieldMode::Schedule(next_step),
@@ -422,6 +521,35 @@ fn block_to_ir(
                 ]
             }
         }
+        BlockOpcode::sound_playuntildone => 'sound_block: {
+            let Some(sound_number) =
+                generate_sound_number(block_info, blocks, context, project, flags)?
+            else {
+                // the sound doesn't exist, so we carry straight on without waiting
+                break 'sound_block vec![IrOpcode::hq_drop];
+            };
+            let poll_step = context
+                .project()?
+                .new_owned_step(Step::new_poll_waiting_event(
+                    context.clone(),
+                    Weak::clone(project),
+                ))?;
+            *should_break = true;
+            let next_step = generate_next_step_non_inlined(
+                block_info,
+                blocks,
+                context,
+                final_next_blocks.clone(),
+                flags,
+            )?;
+            sound_number
+                .into_iter()
+                .chain([IrOpcode::sound_playuntildone(SoundPlayuntildoneFields {
+                    poll_step,
+                    next_step,
+                })])
+                .collect()
+        }
         BlockOpcode::control_wait => {
             let poll_step = context
                 .project()?
@@ -1169,6 +1297,106 @@ fn block_to_ir(
                 list: list.list.clone(),
             })]
         }
+        BlockOpcode::control_create_clone_of_menu => {
+            let (Sb3Field::Value((Some(val),)) | Sb3Field::ValueId(Some(val), _)) =
+                block_info.fields.get("CLONE_OPTION").ok_or_else(|| {
+                    make_hq_bad_proj!("invalid project.json - missing field CLONE_OPTION")
+                })?
+            else {
+                hq_bad_proj!("invalid project.json - missing value for CLONE_OPTION field")
+            };
+            let VarVal::String(name) = val else {
+                hq_bad_proj!("invalid project.json - non-string value for CLONE_OPTION field")
+            };
+            vec![IrOpcode::hq_text(HqTextFields(name.clone()))]
+        }
+        BlockOpcode::control_create_clone_of => 'clone_block: {
+            let clone_targets: IndexMap<Box<str>, Rc<Target>> = (!context.target().is_stage())
+                .then(|| ("_myself_".into(), Rc::clone(context.target())))
+                .into_iter()
+                .chain(
+                    context
+                        .project()?
+                        .targets()
+                        .try_borrow()?
+                        .iter()
+                        .filter(|(_, target)| !target.is_stage())
+                        .map(|(name, target)| (name.clone(), Rc::clone(target))),
+                )
+                .collect();
+            if let Some(name) = constant_menu_value(
+                block_info,
+                blocks,
+                "CLONE_OPTION",
+                &BlockOpcode::control_create_clone_of_menu,
+                "CLONE_OPTION",
+            )? {
+                // only the sprite named in the menu can be cloned here, so there's no need to
+                // make every other sprite clonable
+                let Some(target) = clone_targets.get(&name) else {
+                    break 'clone_block vec![IrOpcode::hq_drop];
+                };
+                target.mark_clonable()?;
+                break 'clone_block vec![
+                    IrOpcode::hq_drop,
+                    IrOpcode::control_create_clone_of(ControlCreateCloneOfFields {
+                        target: Rc::clone(target),
+                    }),
+                ];
+            }
+            for target in clone_targets.values() {
+                target.mark_clonable()?;
+            }
+            generate_exhaustive_string_comparison(
+                clone_targets.keys().cloned(),
+                |name| {
+                    vec![IrOpcode::control_create_clone_of(ControlCreateCloneOfFields {
+                        target: Rc::clone(
+                            clone_targets.get(&name).unwrap_or_else(|| context.target()),
+                        ),
+                    })]
+                },
+                vec![],
+                context,
+                project,
+                flags,
+            )?
+        }
+        BlockOpcode::control_delete_this_clone => {
+            if context.target().is_stage() {
+                vec![]
+            } else {
+                // control_delete_this_clone returns true if this is a clone which has just been
+                // deleted, in which case we need to stop the current script.
+                vec![
+                    IrOpcode::control_delete_this_clone(ControlDeleteThisCloneFields {
+                        target: Rc::clone(context.target()),
+                    }),
+                    IrOpcode::control_if_else(ControlIfElseFields {
+                        branch_if: Rc::new(RefCell::new(Step::new(
+                            None,
+                            context.clone(),
+                            vec![IrOpcode::hq_yield(HqYieldFields {
+                                mode: if context.warp {
+                                    YieldMode::Return
+                                } else {
+                                    YieldMode::None
+                                },
+                            })],
+                            Weak::clone(project),
+                            false,
+                        ))),
+                        branch_else: Rc::new(RefCell::new(Step::new(
+                            None,
+                            context.clone(),
+                            vec![],
+                            Weak::clone(project),
+                            false,
+                        ))),
+                    }),
+                ]
+            }
+        }
         BlockOpcode::control_stop => {
             let (Sb3Field::Value((Some(val),)) | Sb3Field::ValueId(Some(val), _)) =
                 block_info.fields.get("STOP_OPTION").ok_or_else(|| {
@@ -1189,8 +1417,8 @@ fn block_to_ir(
                         YieldMode::None
                     },
                 })],
-                "other scripts in sprite" => {
-                    hq_todo!("control_stop other scripts in sprite")
+                "other scripts in sprite" | "other scripts in stage" => {
+                    vec![IrOpcode::control_stop_other_scripts_in_sprite]
                 }
                 other => hq_bad_proj!("unknown mathop {}", other),
             }
@@ -1310,6 +1538,7 @@ fn block_to_ir(
                 None,
                 false,
                 vec![],
+                vec![],
                 flags,
             )?
         }
@@ -1353,6 +1582,7 @@ fn block_to_ir(
                 None,
                 false,
                 setup_instructions,
+                vec![],
                 flags,
             )?
         }
@@ -1457,6 +1687,7 @@ fn block_to_ir(
                 pre_body_instructions,
                 false,
                 setup_instructions,
+                vec![],
                 flags,
             )?
         }
@@ -1482,6 +1713,7 @@ fn block_to_ir(
                 None,
                 true,
                 setup_instructions,
+                vec![],
                 flags,
             )?
         }
@@ -1507,6 +1739,7 @@ fn block_to_ir(
                 None,
                 false,
                 setup_instructions,
+                vec![],
                 flags,
             )?
         }
@@ -1525,7 +1758,10 @@ fn block_to_ir(
                 break 'proc_block vec![];
             };
             let warp = context.warp || proc.always_warped();
-            if warp {
+            // with the warp timer enabled, warped procedures are compiled like non-warped ones
+            // so that they can yield if they run for too long
+            let warp_timer = context.warp_timer || (warp && flags.warp_timer > 0);
+            if warp && !warp_timer {
                 proc.compile_warped(blocks, flags)?;
                 vec![IrOpcode::procedures_call_warp(ProceduresCallWarpFields {
                     proc: Rc::clone(proc),
@@ -1539,11 +1775,16 @@ fn block_to_ir(
                     final_next_blocks.clone(),
                     flags,
                 )?;
-                proc.compile_nonwarped(blocks, flags)?;
+                if warp_timer {
+                    proc.compile_warp_timed(blocks, flags)?;
+                } else {
+                    proc.compile_nonwarped(blocks, flags)?;
+                }
                 vec![IrOpcode::procedures_call_nonwarp(
                     ProceduresCallNonwarpFields {
                         proc: Rc::clone(proc),
                         next_step,
+                        warp_timer,
                     },
                 )]
             }
@@ -1566,6 +1807,12 @@ fn block_to_ir(
         BlockOpcode::pen_penDown => vec![IrOpcode::pen_pendown],
         BlockOpcode::pen_penUp => vec![IrOpcode::pen_penup],
         BlockOpcode::pen_setPenSizeTo => vec![IrOpcode::pen_setpensizeto],
+        BlockOpcode::pen_changePenSizeBy => vec![IrOpcode::pen_changepensizeby],
+        BlockOpcode::pen_stamp => vec![IrOpcode::pen_stamp],
+        BlockOpcode::pen_setPenHueToNumber => vec![IrOpcode::pen_setpenhuetonumber],
+        BlockOpcode::pen_changePenHueBy => vec![IrOpcode::pen_changepenhueby],
+        BlockOpcode::pen_setPenShadeToNumber => vec![IrOpcode::pen_setpenshadetonumber],
+        BlockOpcode::pen_changePenShadeBy => vec![IrOpcode::pen_changepenshadeby],
         BlockOpcode::pen_setPenColorToColor => {
             vec![IrOpcode::pen_setpencolortocolor]
         }
@@ -1597,9 +1844,80 @@ fn block_to_ir(
             IrOpcode::looks_setsizeto,
         ],
         BlockOpcode::looks_switchcostumeto => vec![IrOpcode::looks_switchcostumeto],
+        BlockOpcode::looks_cleargraphiceffects => vec![IrOpcode::looks_cleargraphiceffects],
+        BlockOpcode::sensing_setdragmode => {
+            vec![IrOpcode::sensing_setdragmode(SensingSetdragmodeFields {
+                draggable: &*string_field(block_info, "DRAG_MODE")? == "draggable",
+            })]
+        }
+        BlockOpcode::looks_gotofrontback => {
+            let front = match &*string_field(block_info, "FRONT_BACK")? {
+                "front" => true,
+                "back" => false,
+                other => hq_bad_proj!("unknown FRONT_BACK value {other}"),
+            };
+            vec![IrOpcode::looks_gotofrontback(LooksGotofrontbackFields {
+                front,
+            })]
+        }
+        BlockOpcode::looks_goforwardbackwardlayers => {
+            let forward = match &*string_field(block_info, "FORWARD_BACKWARD")? {
+                "forward" => true,
+                "backward" => false,
+                other => hq_bad_proj!("unknown FORWARD_BACKWARD value {other}"),
+            };
+            vec![IrOpcode::looks_goforwardbackwardlayers(
+                LooksGoforwardbackwardlayersFields { forward },
+            )]
+        }
+        BlockOpcode::looks_seteffectto => {
+            vec![IrOpcode::looks_seteffectto(LooksSeteffecttoFields(
+                GraphicEffect::from_field(&string_field(block_info, "EFFECT")?)?,
+            ))]
+        }
+        BlockOpcode::looks_changeeffectby => {
+            let effect = GraphicEffect::from_field(&string_field(block_info, "EFFECT")?)?;
+            vec![
+                IrOpcode::looks_effect(LooksEffectFields(effect)),
+                IrOpcode::operator_add,
+                IrOpcode::looks_seteffectto(LooksSeteffecttoFields(effect)),
+            ]
+        }
         BlockOpcode::looks_switchbackdropto => {
             vec![IrOpcode::looks_switchbackdropto]
         }
+        BlockOpcode::looks_switchbackdroptoandwait => {
+            let poll_step = context
+                .project()?
+                .new_owned_step(Step::new_poll_waiting_threads(
+                    context.clone(),
+                    Weak::clone(project),
+                ))?;
+            *should_break = true;
+            let next_step = generate_next_step_non_inlined(
+                block_info,
+                blocks,
+                context,
+                final_next_blocks.clone(),
+                flags,
+            )?;
+            let backdrop_count = context
+                .project()?
+                .backdrops()
+                .len()
+                .try_into()
+                .map_err(|_| make_hq_bug!("backdrops length out of bounds"))?;
+            generate_backdrop_number(block_info, blocks, context, project, flags)?
+                .into_iter()
+                .chain([IrOpcode::looks_switchbackdroptoandwait(
+                    LooksSwitchbackdroptoandwaitFields {
+                        backdrop_count,
+                        poll_step,
+                        next_step,
+                    },
+                )])
+                .collect()
+        }
         BlockOpcode::looks_costumenumbername => {
             let (Sb3Field::Value((val,)) | Sb3Field::ValueId(val, _)) =
                 block_info.fields.get("NUMBER_NAME").ok_or_else(|| {
@@ -1645,16 +1963,22 @@ fn block_to_ir(
             else {
                 hq_bad_proj!("invalid project.json - BACKDROP field is not of type String");
             };
-            let backdrop_index: i32 = context
+            // special values such as "next backdrop" are handled by the block using the menu
+            let backdrop_index = context
                 .project()?
                 .backdrops()
                 .iter()
                 .find_position(|costume| costume.name == backdrop_name)
-                .ok_or_else(|| make_hq_bug!("backdrop index not found"))?
-                .0
-                .try_into()
-                .map_err(|_| make_hq_bug!("backdrop index out of bounds"))?;
-            vec![IrOpcode::hq_integer(HqIntegerFields(backdrop_index))]
+                .map(|(index, _)| index);
+            if let Some(backdrop_index) = backdrop_index {
+                vec![IrOpcode::hq_integer(HqIntegerFields(
+                    backdrop_index
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("backdrop index out of bounds"))?,
+                ))]
+            } else {
+                vec![IrOpcode::hq_text(HqTextFields(backdrop_name))]
+            }
         }
         BlockOpcode::looks_nextcostume => {
             vec![
@@ -1713,6 +2037,373 @@ fn block_to_ir(
                     .map_err(|_| make_hq_bug!("costume index out of bounds"))?,
             ))]
         }
+        BlockOpcode::sensing_touchingobjectmenu => {
+            let (Sb3Field::Value((Some(val),)) | Sb3Field::ValueId(Some(val), _)) =
+                block_info.fields.get("TOUCHINGOBJECTMENU").ok_or_else(|| {
+                    make_hq_bad_proj!("invalid project.json - missing field TOUCHINGOBJECTMENU")
+                })?
+            else {
+                hq_bad_proj!("invalid project.json - missing value for TOUCHINGOBJECTMENU field")
+            };
+            let VarVal::String(name) = val else {
+                hq_bad_proj!("invalid project.json - non-string value for TOUCHINGOBJECTMENU field")
+            };
+            vec![IrOpcode::hq_text(HqTextFields(name.clone()))]
+        }
+        BlockOpcode::sensing_touchingobject => vec![IrOpcode::sensing_touchingobject],
+        BlockOpcode::sensing_touchingcolor => vec![IrOpcode::sensing_touchingcolor],
+        BlockOpcode::sensing_coloristouchingcolor => {
+            vec![IrOpcode::sensing_coloristouchingcolor]
+        }
+        BlockOpcode::sound_sounds_menu => {
+            let (Sb3Field::Value((val,)) | Sb3Field::ValueId(val, _)) =
+                block_info.fields.get("SOUND_MENU").ok_or_else(|| {
+                    make_hq_bad_proj!("invalid project.json - missing field SOUND_MENU")
+                })?;
+            let VarVal::String(name) = val.clone().ok_or_else(|| {
+                make_hq_bad_proj!("invalid project.json - null sound name for SOUND_MENU field")
+            })?
+            else {
+                hq_bad_proj!("invalid project.json - SOUND_MENU field is not of type String");
+            };
+            // the sound is looked up by the block using the menu (see `generate_sound_number`)
+            vec![IrOpcode::hq_text(HqTextFields(name))]
+        }
+        BlockOpcode::sound_play => {
+            generate_sound_number(block_info, blocks, context, project, flags)?.map_or_else(
+                || vec![IrOpcode::hq_drop],
+                |sound_number| {
+                    sound_number
+                        .into_iter()
+                        .chain([IrOpcode::sound_play])
+                        .collect()
+                },
+            )
+        }
+        BlockOpcode::sound_stopallsounds => vec![IrOpcode::sound_stopallsounds],
+        BlockOpcode::sound_volume => vec![IrOpcode::sound_volume],
+        BlockOpcode::sound_setvolumeto => vec![IrOpcode::sound_setvolumeto],
+        BlockOpcode::sound_changevolumeby => vec![
+            IrOpcode::sound_volume,
+            IrOpcode::operator_add,
+            IrOpcode::sound_setvolumeto,
+        ],
+        BlockOpcode::sound_cleareffects => vec![IrOpcode::sound_cleareffects],
+        BlockOpcode::sound_seteffectto => {
+            vec![IrOpcode::sound_seteffectto(SoundSeteffecttoFields(
+                SoundEffect::from_field(&string_field(block_info, "EFFECT")?)?,
+            ))]
+        }
+        BlockOpcode::sound_changeeffectby => {
+            let effect = SoundEffect::from_field(&string_field(block_info, "EFFECT")?)?;
+            vec![
+                IrOpcode::sound_effect(SoundEffectFields(effect)),
+                IrOpcode::operator_add,
+                IrOpcode::sound_seteffectto(SoundSeteffecttoFields(effect)),
+            ]
+        }
         other => hq_todo!("unimplemented block: {:?}", other),
     })
 }
+
+/// The value of a block's string field, e.g. the `EFFECT` field of a `seteffectto` block
+fn string_field(block_info: &BlockInfo, name: &str) -> HQResult<Box<str>> {
+    let (Sb3Field::Value((val,)) | Sb3Field::ValueId(val, _)) = block_info
+        .fields
+        .get(name)
+        .ok_or_else(|| make_hq_bad_proj!("invalid project.json - missing field {name}"))?;
+    let VarVal::String(value) = val
+        .clone()
+        .ok_or_else(|| make_hq_bad_proj!("invalid project.json - null value for {name} field"))?
+    else {
+        hq_bad_proj!("invalid project.json - {name} field is not of type String");
+    };
+    Ok(value)
+}
+
+/// The value of a menu input, if it is known at compile time, i.e. if the input is the menu block
+/// itself rather than a reporter which has been dropped on top of it.
+fn constant_menu_value(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    input_name: &str,
+    menu_opcode: &BlockOpcode,
+    field_name: &str,
+) -> HQResult<Option<Box<str>>> {
+    let Some(
+        Input::NoShadow(_, Some(BlockArrayOrId::Id(id)))
+        | Input::Shadow(_, Some(BlockArrayOrId::Id(id)), _),
+    ) = block_info.inputs.get(input_name)
+    else {
+        return Ok(None);
+    };
+    let Some(menu_info) = blocks.get(id).and_then(Block::block_info) else {
+        return Ok(None);
+    };
+    if menu_info.opcode != *menu_opcode {
+        return Ok(None);
+    }
+    Ok(Some(string_field(menu_info, field_name)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sb3::Mutation;
+    use crate::wasm::flags::unit_test_wasm_features;
+
+    /// Defines [`known_opcodes`] from a list of opcodes. The list is checked against the
+    /// definition of [`BlockOpcode`] by an exhaustive match, so that this fails to compile if
+    /// an opcode is added without being added here.
+    macro_rules! known_opcodes {
+        ($($opcode:ident),* $(,)?) => {
+            /// Every known opcode, i.e. every variant of [`BlockOpcode`] except `Unknown`.
+            fn known_opcodes() -> Vec<BlockOpcode> {
+                const fn _is_exhaustive(opcode: &BlockOpcode) {
+                    match opcode {
+                        $(BlockOpcode::$opcode)|* | BlockOpcode::Unknown(_) => (),
+                    }
+                }
+                vec![$(BlockOpcode::$opcode),*]
+            }
+        };
+    }
+
+    known_opcodes!(
+        control_repeat,
+        control_repeat_until,
+        control_while,
+        control_for_each,
+        control_forever,
+        control_wait,
+        control_wait_until,
+        control_if,
+        control_if_else,
+        control_stop,
+        control_create_clone_of,
+        control_delete_this_clone,
+        control_get_counter,
+        control_incr_counter,
+        control_clear_counter,
+        control_all_at_once,
+        control_start_as_clone,
+        control_create_clone_of_menu,
+        data_variable,
+        data_setvariableto,
+        data_changevariableby,
+        data_hidevariable,
+        data_showvariable,
+        data_listcontents,
+        data_addtolist,
+        data_deleteoflist,
+        data_deletealloflist,
+        data_insertatlist,
+        data_replaceitemoflist,
+        data_itemoflist,
+        data_itemnumoflist,
+        data_lengthoflist,
+        data_listcontainsitem,
+        data_hidelist,
+        data_showlist,
+        event_broadcast,
+        event_broadcast_menu,
+        event_broadcastandwait,
+        event_whenflagclicked,
+        event_whenkeypressed,
+        event_whenthisspriteclicked,
+        event_whentouchingobject,
+        event_touchingobjectmenu,
+        event_whenstageclicked,
+        event_whenbackdropswitchesto,
+        event_whengreaterthan,
+        event_whenbroadcastreceived,
+        looks_say,
+        looks_sayforsecs,
+        looks_think,
+        looks_thinkforsecs,
+        looks_show,
+        looks_hide,
+        looks_hideallsprites,
+        looks_switchcostumeto,
+        looks_switchbackdropto,
+        looks_switchbackdroptoandwait,
+        looks_nextcostume,
+        looks_nextbackdrop,
+        looks_changeeffectby,
+        looks_seteffectto,
+        looks_cleargraphiceffects,
+        looks_changesizeby,
+        looks_setsizeto,
+        looks_changestretchby,
+        looks_setstretchto,
+        looks_gotofrontback,
+        looks_goforwardbackwardlayers,
+        looks_size,
+        looks_costumenumbername,
+        looks_backdropnumbername,
+        looks_costume,
+        looks_backdrops,
+        motion_movesteps,
+        motion_gotoxy,
+        motion_goto,
+        motion_goto_menu,
+        motion_turnright,
+        motion_turnleft,
+        motion_pointindirection,
+        motion_pointtowards,
+        motion_glidesecstoxy,
+        motion_glideto,
+        motion_glideto_menu,
+        motion_ifonedgebounce,
+        motion_setrotationstyle,
+        motion_changexby,
+        motion_setx,
+        motion_changeyby,
+        motion_sety,
+        motion_xposition,
+        motion_yposition,
+        motion_direction,
+        motion_scroll_right,
+        motion_scroll_up,
+        motion_align_scene,
+        motion_xscroll,
+        motion_yscroll,
+        motion_pointtowards_menu,
+        operator_add,
+        operator_subtract,
+        operator_multiply,
+        operator_divide,
+        operator_lt,
+        operator_equals,
+        operator_gt,
+        operator_and,
+        operator_or,
+        operator_not,
+        operator_random,
+        operator_join,
+        operator_letter_of,
+        operator_length,
+        operator_contains,
+        operator_mod,
+        operator_round,
+        operator_mathop,
+        pen_clear,
+        pen_stamp,
+        pen_penDown,
+        pen_penUp,
+        pen_setPenColorToColor,
+        pen_changePenColorParamBy,
+        pen_setPenColorParamTo,
+        pen_changePenSizeBy,
+        pen_setPenSizeTo,
+        pen_setPenShadeToNumber,
+        pen_changePenShadeBy,
+        pen_setPenHueToNumber,
+        pen_changePenHueBy,
+        pen_menu_colorParam,
+        procedures_definition,
+        procedures_call,
+        procedures_prototype,
+        argument_reporter_string_number,
+        argument_reporter_boolean,
+        sensing_touchingobject,
+        sensing_touchingcolor,
+        sensing_coloristouchingcolor,
+        sensing_distanceto,
+        sensing_distancetomenu,
+        sensing_timer,
+        sensing_resettimer,
+        sensing_of,
+        sensing_mousex,
+        sensing_mousey,
+        sensing_setdragmode,
+        sensing_mousedown,
+        sensing_keypressed,
+        sensing_current,
+        sensing_dayssince2000,
+        sensing_loudness,
+        sensing_loud,
+        sensing_askandwait,
+        sensing_answer,
+        sensing_username,
+        sensing_userid,
+        sensing_touchingobjectmenu,
+        sensing_keyoptions,
+        sensing_of_object_menu,
+        sound_play,
+        sound_playuntildone,
+        sound_stopallsounds,
+        sound_seteffectto,
+        sound_changeeffectby,
+        sound_cleareffects,
+        sound_sounds_menu,
+        sound_beats_menu,
+        sound_effects_menu,
+        sound_setvolumeto,
+        sound_changevolumeby,
+        sound_volume,
+    );
+
+    #[test]
+    fn supported_opcodes_are_those_handled_by_block_to_ir() {
+        let context = StepContext {
+            target: Rc::new(Target::new(
+                false,
+                "Sprite1".into(),
+                BTreeMap::default(),
+                BTreeMap::default(),
+                Weak::new(),
+                RefCell::default(),
+                0,
+                Box::default(),
+                Box::default(),
+                100.0,
+                1,
+                RotationStyle::AllAround,
+                false,
+            )),
+            warp: false,
+            warp_timer: false,
+            proc_context: None,
+            debug: false,
+        };
+        let flags = WasmFlags::new(unit_test_wasm_features());
+        let opcodes = known_opcodes();
+        assert!(opcodes.len() > 100);
+        for opcode in opcodes {
+            assert!(
+                !matches!(opcode, BlockOpcode::Unknown(_)),
+                "{opcode:?} should be a known opcode"
+            );
+            let block_info = BlockInfo {
+                opcode: opcode.clone(),
+                next: None,
+                parent: None,
+                inputs: BTreeMap::default(),
+                fields: BTreeMap::default(),
+                shadow: false,
+                top_level: false,
+                mutation: Mutation::default(),
+            };
+            // blocks with missing fields may well fail to compile, but they shouldn't fall
+            // through to the catch-all arm if they're meant to be supported
+            let handled = !matches!(
+                block_to_ir(
+                    &block_info,
+                    &BlockMap::default(),
+                    &context,
+                    &Weak::new(),
+                    &NextBlocks::new(false),
+                    &flags,
+                    &mut false,
+                ),
+                Err(ref err) if err.msg.starts_with("unimplemented block:")
+            );
+            assert_eq!(
+                is_supported_opcode(&opcode),
+                handled,
+                "is_supported_opcode disagrees with block_to_ir for {opcode:?}"
+            );
+        }
+    }
+}
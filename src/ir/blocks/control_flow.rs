@@ -1,4 +1,6 @@
-use super::{NextBlock, NextBlockInfo, NextBlocks, from_block, generate_next_step_inlined};
+use super::{
+    NextBlock, NextBlockInfo, NextBlocks, from_block, generate_next_step_inlined, loop_yield,
+};
 use crate::instructions::{
     ControlIfElseFields, ControlLoopFields, DataSetvariabletoFields, DataVariableFields,
     HqCastFields, HqTextFields, HqYieldFields, IrOpcode, YieldMode,
@@ -22,6 +24,7 @@ pub fn generate_loop(
     pre_body_instructions: Option<Vec<IrOpcode>>,
     flip_if: bool,
     setup_instructions: Vec<IrOpcode>,
+    post_loop_instructions: Vec<IrOpcode>,
     flags: &WasmFlags,
 ) -> HQResult<Vec<IrOpcode>> {
     let substack_id = match block_info.inputs.get("SUBSTACK") {
@@ -37,20 +40,22 @@ pub fn generate_loop(
     };
 
     let substack_block = if let Some(id) = substack_id {
-        Some(
+        Some((
+            id,
             blocks
                 .get(id)
                 .ok_or_else(|| make_hq_bad_proj!("SUBSTACK block doesn't seem to exist"))?,
-        )
+        ))
     } else {
         None
     };
     if warp {
         // TODO: can this be expressed in the same way as non-warping loops,
         // just with yield_first: false?
-        let substack_blocks = if let Some(block) = substack_block {
+        let substack_blocks = if let Some((id, block)) = substack_block {
             from_block(
                 block,
+                id,
                 blocks,
                 context,
                 &context.target().project(),
@@ -101,11 +106,16 @@ pub fn generate_loop(
                 pre_body: pre_body_step,
                 flip_if,
             })])
+            .chain(post_loop_instructions)
             .collect())
     } else {
         *should_break = true;
         let next_step =
             generate_next_step_inlined(block_info, blocks, context, final_next_blocks, flags)?;
+        next_step
+            .try_borrow_mut()?
+            .opcodes_mut()
+            .splice(0..0, post_loop_instructions);
         let project = context.project()?;
         let mut condition_step = Step::new(
             None,
@@ -134,9 +144,10 @@ pub fn generate_loop(
                 .opcodes_mut()
                 .extend(pre_body_blocks);
         }
-        if let Some(block) = substack_block {
+        if let Some((id, block)) = substack_block {
             let substack_blocks = from_block(
                 block,
+                id,
                 blocks,
                 context,
                 &context.target().project(),
@@ -154,9 +165,7 @@ pub fn generate_loop(
             substack_step
                 .try_borrow_mut()?
                 .opcodes_mut()
-                .push(IrOpcode::hq_yield(HqYieldFields {
-                    mode: YieldMode::Schedule(condition_step_index),
-                }));
+                .push(loop_yield(context, condition_step_index));
         }
         Ok(setup_instructions
             .into_iter()
@@ -191,12 +200,18 @@ pub fn generate_if_else(
         ));
         let dummy_target = Rc::new(Target::new(
             false,
+            context.target().name().into(),
             context.target().variables().clone(),
             context.target().lists().clone(),
             Rc::downgrade(&dummy_project),
             RefCell::new(context.target().procedures()?.clone()),
             0,
             context.target().costumes().into(),
+            context.target().sounds().into(),
+            context.target().volume(),
+            context.target().layer_order(),
+            context.target().rotation_style(),
+            context.target().draggable(),
         ));
         dummy_project
             .targets()
@@ -393,7 +408,7 @@ pub fn generate_exhaustive_string_comparison<I, S, F>(
 where
     I: IntoIterator<Item = S>,
     S: Into<Box<str>> + Clone,
-    F: Fn(Box<str>) -> IrOpcode,
+    F: Fn(Box<str>) -> Vec<IrOpcode>,
 {
     let var = RcVar::new(IrType::String, &VarVal::String("".into()), None, flags)?;
     Ok(vec![
@@ -420,7 +435,7 @@ where
                     let branch_if = Rc::new(RefCell::new(Step::new(
                         None,
                         context.clone(),
-                        vec![instruction(string.clone().into())],
+                        instruction(string.clone().into()),
                         Weak::clone(project),
                         false,
                     )));
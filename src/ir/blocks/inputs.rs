@@ -1,135 +1,185 @@
 use super::special::from_special_block;
 use super::{NextBlocks, from_block};
-use crate::instructions::IrOpcode;
+use crate::instructions::{HqTextFields, IrOpcode};
 use crate::ir::{IrProject, StepContext};
 use crate::prelude::*;
 use crate::sb3::{BlockArray, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, Input};
 use crate::wasm::WasmFlags;
+use crate::wasm::flags::Switch;
+
+/// The names of the inputs of a block with the given opcode, in the order in which they should
+/// be compiled. Returns `None` if blocks with this opcode can't be compiled.
+///
+/// Procedure calls aren't included here, as their inputs depend on the procedure being called;
+/// see [`input_names`].
+pub const fn opcode_input_names(opcode: &BlockOpcode) -> Option<&'static [&'static str]> {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "too many opcodes to match individually"
+    )]
+    Some(match opcode {
+        BlockOpcode::looks_say | BlockOpcode::looks_think => &["MESSAGE"],
+        BlockOpcode::looks_sayforsecs | BlockOpcode::looks_thinkforsecs => &["MESSAGE", "SECS"],
+        BlockOpcode::operator_add
+        | BlockOpcode::operator_divide
+        | BlockOpcode::operator_subtract
+        | BlockOpcode::operator_multiply
+        | BlockOpcode::operator_mod => &["NUM1", "NUM2"],
+        BlockOpcode::operator_mathop | BlockOpcode::operator_round => &["NUM"],
+        BlockOpcode::operator_lt
+        | BlockOpcode::operator_gt
+        | BlockOpcode::operator_equals
+        | BlockOpcode::operator_and
+        | BlockOpcode::operator_or => &["OPERAND1", "OPERAND2"],
+        BlockOpcode::operator_join | BlockOpcode::operator_contains => &["STRING1", "STRING2"],
+        BlockOpcode::operator_letter_of => &["LETTER", "STRING"],
+        BlockOpcode::motion_gotoxy => &["X", "Y"],
+        BlockOpcode::motion_glidesecstoxy => &["SECS", "X", "Y"],
+        BlockOpcode::motion_glideto => &["SECS", "TO"],
+        BlockOpcode::motion_goto => &["TO"],
+        BlockOpcode::motion_pointtowards => &["TOWARDS"],
+        BlockOpcode::sensing_distanceto => &["DISTANCETOMENU"],
+        BlockOpcode::sensing_of => &["OBJECT"],
+        BlockOpcode::motion_movesteps => &["STEPS"],
+        BlockOpcode::motion_pointindirection => &["DIRECTION"],
+        BlockOpcode::motion_turnleft | BlockOpcode::motion_turnright => &["DEGREES"],
+        BlockOpcode::sensing_keypressed => &["KEY_OPTION"],
+        BlockOpcode::sensing_dayssince2000
+        | BlockOpcode::data_variable
+        | BlockOpcode::argument_reporter_boolean
+        | BlockOpcode::argument_reporter_string_number
+        | BlockOpcode::looks_costume
+        | BlockOpcode::looks_size
+        | BlockOpcode::looks_nextcostume
+        | BlockOpcode::looks_costumenumbername
+        | BlockOpcode::looks_backdrops
+        | BlockOpcode::looks_hide
+        | BlockOpcode::looks_show
+        | BlockOpcode::pen_penDown
+        | BlockOpcode::pen_penUp
+        | BlockOpcode::pen_clear
+        | BlockOpcode::pen_stamp
+        | BlockOpcode::control_forever
+        | BlockOpcode::pen_menu_colorParam
+        | BlockOpcode::motion_direction
+        | BlockOpcode::motion_glideto_menu
+        | BlockOpcode::motion_goto_menu
+        | BlockOpcode::motion_pointtowards_menu
+        | BlockOpcode::sensing_distancetomenu
+        | BlockOpcode::sensing_of_object_menu
+        | BlockOpcode::data_deletealloflist
+        | BlockOpcode::data_lengthoflist
+        | BlockOpcode::data_listcontents
+        | BlockOpcode::control_stop
+        | BlockOpcode::event_broadcast_menu
+        | BlockOpcode::control_create_clone_of_menu
+        | BlockOpcode::control_delete_this_clone
+        | BlockOpcode::sensing_timer
+        | BlockOpcode::sensing_resettimer
+        | BlockOpcode::sensing_answer
+        | BlockOpcode::looks_backdropnumbername
+        | BlockOpcode::looks_nextbackdrop
+        | BlockOpcode::data_showvariable
+        | BlockOpcode::data_hidevariable
+        | BlockOpcode::sensing_mousex
+        | BlockOpcode::sensing_mousey
+        | BlockOpcode::sensing_mousedown
+        | BlockOpcode::motion_xposition
+        | BlockOpcode::motion_yposition
+        | BlockOpcode::sensing_keyoptions
+        | BlockOpcode::sound_sounds_menu
+        | BlockOpcode::sensing_touchingobjectmenu
+        | BlockOpcode::sound_stopallsounds
+        | BlockOpcode::sound_cleareffects
+        | BlockOpcode::looks_cleargraphiceffects
+        | BlockOpcode::looks_gotofrontback
+        | BlockOpcode::motion_ifonedgebounce
+        | BlockOpcode::motion_setrotationstyle
+        | BlockOpcode::sensing_setdragmode
+        | BlockOpcode::sound_volume => &[],
+        BlockOpcode::sound_play | BlockOpcode::sound_playuntildone => &["SOUND_MENU"],
+        BlockOpcode::sound_setvolumeto | BlockOpcode::sound_changevolumeby => &["VOLUME"],
+        BlockOpcode::sensing_askandwait => &["QUESTION"],
+        BlockOpcode::sensing_touchingobject => &["TOUCHINGOBJECTMENU"],
+        BlockOpcode::sensing_coloristouchingcolor => &["COLOR", "COLOR2"],
+        BlockOpcode::event_broadcast | BlockOpcode::event_broadcastandwait => &["BROADCAST_INPUT"],
+        BlockOpcode::control_wait => &["DURATION"],
+        BlockOpcode::looks_goforwardbackwardlayers => &["NUM"],
+        BlockOpcode::control_create_clone_of => &["CLONE_OPTION"],
+        BlockOpcode::data_setvariableto
+        | BlockOpcode::data_changevariableby
+        | BlockOpcode::control_for_each
+        | BlockOpcode::sound_seteffectto
+        | BlockOpcode::sound_changeeffectby
+        | BlockOpcode::looks_seteffectto
+        | BlockOpcode::looks_changeeffectby => &["VALUE"],
+        BlockOpcode::operator_random => &["FROM", "TO"],
+        BlockOpcode::pen_setPenColorParamTo | BlockOpcode::pen_changePenColorParamBy => {
+            &["COLOR_PARAM", "VALUE"]
+        }
+        BlockOpcode::control_if
+        | BlockOpcode::control_if_else
+        | BlockOpcode::control_repeat_until
+        | BlockOpcode::control_while
+        | BlockOpcode::control_wait_until => &["CONDITION"],
+        BlockOpcode::operator_not => &["OPERAND"],
+        BlockOpcode::control_repeat => &["TIMES"],
+        BlockOpcode::operator_length => &["STRING"],
+        BlockOpcode::looks_switchcostumeto => &["COSTUME"],
+        BlockOpcode::looks_switchbackdropto | BlockOpcode::looks_switchbackdroptoandwait => {
+            &["BACKDROP"]
+        }
+        BlockOpcode::looks_setsizeto
+        | BlockOpcode::pen_setPenSizeTo
+        | BlockOpcode::pen_changePenSizeBy => &["SIZE"],
+        BlockOpcode::pen_setPenHueToNumber | BlockOpcode::pen_changePenHueBy => &["HUE"],
+        BlockOpcode::pen_setPenShadeToNumber | BlockOpcode::pen_changePenShadeBy => &["SHADE"],
+        BlockOpcode::looks_changesizeby => &["CHANGE"],
+        BlockOpcode::pen_setPenColorToColor | BlockOpcode::sensing_touchingcolor => &["COLOR"],
+        BlockOpcode::data_addtolist
+        | BlockOpcode::data_itemnumoflist
+        | BlockOpcode::data_listcontainsitem => &["ITEM"],
+        BlockOpcode::data_itemoflist | BlockOpcode::data_deleteoflist => &["INDEX"],
+        BlockOpcode::data_replaceitemoflist | BlockOpcode::data_insertatlist => &["INDEX", "ITEM"],
+        BlockOpcode::motion_changexby => &["DX"],
+        BlockOpcode::motion_changeyby => &["DY"],
+        BlockOpcode::motion_setx => &["X"],
+        BlockOpcode::motion_sety => &["Y"],
+        _ => return None,
+    })
+}
+
+/// Whether blocks with the given opcode can be compiled.
+pub fn is_supported_opcode(opcode: &BlockOpcode) -> bool {
+    *opcode == BlockOpcode::procedures_call || opcode_input_names(opcode).is_some()
+}
 
 fn input_names(block_info: &BlockInfo, context: &StepContext) -> HQResult<Vec<String>> {
     let opcode = &block_info.opcode;
-    // target and procs need to be declared outside of the match block
-    // to prevent lifetime issues
+    if *opcode != BlockOpcode::procedures_call {
+        let Some(names) = opcode_input_names(opcode) else {
+            hq_todo!("unimplemented input_names for {:?}", opcode)
+        };
+        return Ok(names.iter().copied().map(String::from).collect());
+    }
+    let serde_json::Value::String(proccode) = block_info
+        .mutation
+        .mutations
+        .get("proccode")
+        .ok_or_else(|| make_hq_bad_proj!("missing proccode on procedures_call"))?
+    else {
+        hq_bad_proj!("non-string proccode on procedures_call");
+    };
     let target = context.target();
     let procs = target.procedures()?;
-    Ok(
-        #[expect(
-            clippy::wildcard_enum_match_arm,
-            reason = "too many opcodes to match individually"
-        )]
-        match opcode {
-            BlockOpcode::looks_say | BlockOpcode::looks_think => vec!["MESSAGE"],
-            BlockOpcode::operator_add
-            | BlockOpcode::operator_divide
-            | BlockOpcode::operator_subtract
-            | BlockOpcode::operator_multiply
-            | BlockOpcode::operator_mod => vec!["NUM1", "NUM2"],
-            BlockOpcode::operator_mathop | BlockOpcode::operator_round => vec!["NUM"],
-            BlockOpcode::operator_lt
-            | BlockOpcode::operator_gt
-            | BlockOpcode::operator_equals
-            | BlockOpcode::operator_and
-            | BlockOpcode::operator_or => vec!["OPERAND1", "OPERAND2"],
-            BlockOpcode::operator_join | BlockOpcode::operator_contains => {
-                vec!["STRING1", "STRING2"]
-            }
-            BlockOpcode::operator_letter_of => vec!["LETTER", "STRING"],
-            BlockOpcode::motion_gotoxy => vec!["X", "Y"],
-            BlockOpcode::motion_movesteps => vec!["STEPS"],
-            BlockOpcode::motion_pointindirection => vec!["DIRECTION"],
-            BlockOpcode::motion_turnleft | BlockOpcode::motion_turnright => vec!["DEGREES"],
-            BlockOpcode::sensing_keypressed => vec!["KEY_OPTION"],
-            BlockOpcode::sensing_dayssince2000
-            | BlockOpcode::data_variable
-            | BlockOpcode::argument_reporter_boolean
-            | BlockOpcode::argument_reporter_string_number
-            | BlockOpcode::looks_costume
-            | BlockOpcode::looks_size
-            | BlockOpcode::looks_nextcostume
-            | BlockOpcode::looks_costumenumbername
-            | BlockOpcode::looks_backdrops
-            | BlockOpcode::looks_hide
-            | BlockOpcode::looks_show
-            | BlockOpcode::pen_penDown
-            | BlockOpcode::pen_penUp
-            | BlockOpcode::pen_clear
-            | BlockOpcode::control_forever
-            | BlockOpcode::pen_menu_colorParam
-            | BlockOpcode::motion_direction
-            | BlockOpcode::data_deletealloflist
-            | BlockOpcode::data_lengthoflist
-            | BlockOpcode::data_listcontents
-            | BlockOpcode::control_stop
-            | BlockOpcode::event_broadcast_menu
-            | BlockOpcode::sensing_timer
-            | BlockOpcode::sensing_resettimer
-            | BlockOpcode::sensing_answer
-            | BlockOpcode::looks_backdropnumbername
-            | BlockOpcode::looks_nextbackdrop
-            | BlockOpcode::data_showvariable
-            | BlockOpcode::data_hidevariable
-            | BlockOpcode::sensing_mousex
-            | BlockOpcode::sensing_mousey
-            | BlockOpcode::sensing_mousedown
-            | BlockOpcode::motion_xposition
-            | BlockOpcode::motion_yposition
-            | BlockOpcode::sensing_keyoptions => vec![],
-            BlockOpcode::sensing_askandwait => vec!["QUESTION"],
-            BlockOpcode::event_broadcast | BlockOpcode::event_broadcastandwait => {
-                vec!["BROADCAST_INPUT"]
-            }
-            BlockOpcode::control_wait => vec!["DURATION"],
-            BlockOpcode::data_setvariableto
-            | BlockOpcode::data_changevariableby
-            | BlockOpcode::control_for_each => vec!["VALUE"],
-            BlockOpcode::operator_random => vec!["FROM", "TO"],
-            BlockOpcode::pen_setPenColorParamTo | BlockOpcode::pen_changePenColorParamBy => {
-                vec!["COLOR_PARAM", "VALUE"]
-            }
-            BlockOpcode::control_if
-            | BlockOpcode::control_if_else
-            | BlockOpcode::control_repeat_until
-            | BlockOpcode::control_while
-            | BlockOpcode::control_wait_until => vec!["CONDITION"],
-            BlockOpcode::operator_not => vec!["OPERAND"],
-            BlockOpcode::control_repeat => vec!["TIMES"],
-            BlockOpcode::operator_length => vec!["STRING"],
-            BlockOpcode::looks_switchcostumeto => vec!["COSTUME"],
-            BlockOpcode::looks_switchbackdropto => vec!["BACKDROP"],
-            BlockOpcode::looks_setsizeto | BlockOpcode::pen_setPenSizeTo => vec!["SIZE"],
-            BlockOpcode::looks_changesizeby => vec!["CHANGE"],
-            BlockOpcode::pen_setPenColorToColor => vec!["COLOR"],
-            BlockOpcode::data_addtolist
-            | BlockOpcode::data_itemnumoflist
-            | BlockOpcode::data_listcontainsitem => vec!["ITEM"],
-            BlockOpcode::data_itemoflist | BlockOpcode::data_deleteoflist => vec!["INDEX"],
-            BlockOpcode::data_replaceitemoflist | BlockOpcode::data_insertatlist => {
-                vec!["INDEX", "ITEM"]
-            }
-            BlockOpcode::motion_changexby => vec!["DX"],
-            BlockOpcode::motion_changeyby => vec!["DY"],
-            BlockOpcode::motion_setx => vec!["X"],
-            BlockOpcode::motion_sety => vec!["Y"],
-            BlockOpcode::procedures_call => 'proc_block: {
-                let serde_json::Value::String(proccode) = block_info
-                    .mutation
-                    .mutations
-                    .get("proccode")
-                    .ok_or_else(|| make_hq_bad_proj!("missing proccode on procedures_call"))?
-                else {
-                    hq_bad_proj!("non-string proccode on procedures_call");
-                };
-                let Some(proc) = procs.get(proccode.as_str()) else {
-                    break 'proc_block vec![];
-                };
-                proc.arg_ids().iter().map(|b| &**b).collect()
-            }
-            other => hq_todo!("unimplemented input_names for {:?}", other),
-        }
-        .into_iter()
-        .map(String::from)
-        .collect(),
-    )
+    let Some(proc) = procs.get(proccode.as_str()) else {
+        return Ok(vec![]);
+    };
+    Ok(proc
+        .arg_ids()
+        .iter()
+        .map(|id| String::from(&**id))
+        .collect())
 }
 
 pub fn inputs(
@@ -162,16 +212,27 @@ pub fn inputs(
                     BlockArrayOrId::Array(arr) => {
                         Ok(vec![from_special_block(arr, context, flags)?])
                     }
-                    BlockArrayOrId::Id(id) => from_block(
-                        blocks.get(id).ok_or_else(|| {
+                    BlockArrayOrId::Id(id) => {
+                        let input_block = blocks.get(id).ok_or_else(|| {
                             make_hq_bad_proj!("block for input {} doesn't exist", name)
-                        })?,
-                        blocks,
-                        context,
-                        project,
-                        NextBlocks::new(false),
-                        flags,
-                    ),
+                        })?;
+                        if flags.skip_unsupported_blocks == Switch::On
+                            && let Some(input_info) = input_block.block_info()
+                            && !is_supported_opcode(&input_info.opcode)
+                        {
+                            // unsupported reporters are replaced by empty strings
+                            return Ok(vec![IrOpcode::hq_text(HqTextFields("".into()))]);
+                        }
+                        from_block(
+                            input_block,
+                            id,
+                            blocks,
+                            context,
+                            project,
+                            NextBlocks::new(false),
+                            flags,
+                        )
+                    }
                 },
                 _ => hq_bad_proj!("missing input block for {}", name),
             }
@@ -0,0 +1,251 @@
+use super::{
+    NextBlocks, constant_menu_value, generate_exhaustive_string_comparison,
+    generate_next_step_inlined,
+};
+use crate::instructions::{
+    ControlIfElseFields, ControlWaitFields, DataSetvariabletoFields, DataTeevariableFields,
+    DataVariableFields, HqCastFields, HqIntegerFields, HqTextFields, HqYieldFields, IrOpcode,
+    LooksBubbleidFields, LooksSayFields, LooksThinkFields, YieldMode,
+};
+use crate::ir::target::IrCostume;
+use crate::ir::{IrProject, IrType, RcVar, Step, StepContext};
+use crate::prelude::*;
+use crate::sb3::{BlockInfo, BlockMap, BlockOpcode, VarVal};
+use crate::wasm::WasmFlags;
+
+/// Generates a `say for secs` or `think for secs` block, with the message and then the duration
+/// at the top of the stack.
+///
+/// The bubble is shown, and then we wait in the same way as `control_wait`. Afterwards, the
+/// bubble is only cleared if it hasn't been replaced in the meantime, which we can tell by
+/// whether its bubble id has changed.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_looks.js#L344>
+#[expect(clippy::too_many_arguments, reason = "too many arguments!")]
+pub fn generate_bubble_for_secs(
+    think: bool,
+    should_break: &mut bool,
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    final_next_blocks: NextBlocks,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let target_idx = context.target().index();
+    let bubble = |debug: bool| {
+        if think {
+            IrOpcode::looks_think(LooksThinkFields { debug, target_idx })
+        } else {
+            IrOpcode::looks_say(LooksSayFields { debug, target_idx })
+        }
+    };
+    let duration = RcVar::new(IrType::Float, &VarVal::Float(0.0), None, flags)?;
+    // this has to survive the wait, so it can't be stored in a local
+    let bubble_id = RcVar::new(IrType::Int, &VarVal::Int(0), None, flags)?;
+
+    let poll_step = context
+        .project()?
+        .new_owned_step(Step::new_poll_timer(context.clone(), Weak::clone(project)))?;
+    *should_break = true;
+    let next_step =
+        generate_next_step_inlined(block_info, blocks, context, final_next_blocks, flags)?;
+    let real_next_step = Step::new(
+        None,
+        context.clone(),
+        vec![
+            IrOpcode::looks_bubbleid(LooksBubbleidFields { target_idx }),
+            IrOpcode::data_variable(DataVariableFields {
+                var: RefCell::new(bubble_id.clone()),
+                local_read: RefCell::new(false),
+            }),
+            IrOpcode::operator_equals,
+            IrOpcode::control_if_else(ControlIfElseFields {
+                branch_if: Rc::new(RefCell::new(Step::new(
+                    None,
+                    context.clone(),
+                    vec![IrOpcode::hq_text(HqTextFields("".into())), bubble(false)],
+                    Weak::clone(project),
+                    false,
+                ))),
+                branch_else: Rc::new(RefCell::new(Step::new(
+                    None,
+                    context.clone(),
+                    vec![],
+                    Weak::clone(project),
+                    false,
+                ))),
+            }),
+            IrOpcode::hq_yield(HqYieldFields {
+                mode: YieldMode::Inline(next_step),
+            }),
+        ],
+        Weak::clone(project),
+        true,
+    )
+    .clone_to_non_inlined(project)?;
+
+    Ok(vec![
+        IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+        IrOpcode::data_setvariableto(DataSetvariabletoFields {
+            var: RefCell::new(duration.clone()),
+            local_write: RefCell::new(true),
+            first_write: RefCell::new(true),
+        }),
+        bubble(context.debug),
+        IrOpcode::looks_bubbleid(LooksBubbleidFields { target_idx }),
+        IrOpcode::data_setvariableto(DataSetvariabletoFields {
+            var: RefCell::new(bubble_id),
+            local_write: RefCell::new(false),
+            first_write: RefCell::new(false),
+        }),
+        IrOpcode::data_variable(DataVariableFields {
+            var: RefCell::new(duration),
+            local_read: RefCell::new(true),
+        }),
+        IrOpcode::control_wait(ControlWaitFields {
+            poll_step,
+            next_step: real_next_step,
+        }),
+    ])
+}
+
+/// Generates the backdrop number (starting from 1) that a `switch backdrop to` value refers to,
+/// or `None` if it doesn't refer to a backdrop. Backdrop names take precedence over the special
+/// `next backdrop`, `previous backdrop` and `random backdrop` values.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_looks.js#L384>
+fn backdrop_number(name: &str, backdrops: &[IrCostume]) -> HQResult<Option<Vec<IrOpcode>>> {
+    if let Some(index) = backdrops
+        .iter()
+        .position(|backdrop| *backdrop.name == *name)
+    {
+        return Ok(Some(vec![IrOpcode::hq_integer(HqIntegerFields(
+            (index + 1)
+                .try_into()
+                .map_err(|_| make_hq_bug!("backdrop index out of bounds"))?,
+        ))]));
+    }
+    let count: i32 = backdrops
+        .len()
+        .try_into()
+        .map_err(|_| make_hq_bug!("backdrops length out of bounds"))?;
+    // the current backdrop is stored starting from 0, so each of these is
+    // `(current + offset) mod count + 1`
+    let offset = match name {
+        "next backdrop" => vec![IrOpcode::hq_integer(HqIntegerFields(1))],
+        "previous backdrop" => vec![IrOpcode::hq_integer(HqIntegerFields(count - 1))],
+        // any backdrop other than the current one, if there is one
+        "random backdrop" if count > 1 => vec![
+            IrOpcode::hq_integer(HqIntegerFields(1)),
+            IrOpcode::hq_integer(HqIntegerFields(count - 1)),
+            IrOpcode::operator_random,
+        ],
+        "random backdrop" => vec![IrOpcode::hq_integer(HqIntegerFields(0))],
+        _ => return Ok(None),
+    };
+    Ok(Some(
+        [IrOpcode::looks_backdropnumber]
+            .into_iter()
+            .chain(offset)
+            .chain([
+                IrOpcode::operator_add,
+                IrOpcode::hq_integer(HqIntegerFields(count)),
+                IrOpcode::operator_modulo,
+                IrOpcode::hq_integer(HqIntegerFields(1)),
+                IrOpcode::operator_add,
+            ])
+            .collect(),
+    ))
+}
+
+/// Generates the backdrop number (starting from 1) that the `BACKDROP` input of a
+/// `switch backdrop to` block refers to, with the value of that input at the top of the stack.
+///
+/// Strings are looked up by name (see [`backdrop_number`]), falling back to being treated as a
+/// number; values which don't refer to any backdrop give a number which is out of range, so
+/// that the backdrop isn't switched.
+pub fn generate_backdrop_number(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let backdrops = context.project()?.backdrops().clone();
+    if let Some(name) = constant_menu_value(
+        block_info,
+        blocks,
+        "BACKDROP",
+        &BlockOpcode::looks_backdrops,
+        "BACKDROP",
+    )? {
+        return Ok(core::iter::once(IrOpcode::hq_drop)
+            .chain(
+                backdrop_number(&name, &backdrops)?
+                    .unwrap_or_else(|| vec![IrOpcode::hq_integer(HqIntegerFields(0))]),
+            )
+            .collect());
+    }
+
+    let mut numbers: IndexMap<Box<str>, Vec<IrOpcode>> = IndexMap::default();
+    for name in backdrops
+        .iter()
+        .map(|backdrop| backdrop.name.clone())
+        .chain([
+            "next backdrop".into(),
+            "previous backdrop".into(),
+            "random backdrop".into(),
+        ])
+    {
+        if !numbers.contains_key(&name)
+            && let Some(number) = backdrop_number(&name, &backdrops)?
+        {
+            numbers.insert(name, number);
+        }
+    }
+
+    let text_var = RcVar::new(IrType::String, &VarVal::String("".into()), None, flags)?;
+    let number_var = RcVar::new(IrType::Int, &VarVal::Int(0), None, flags)?;
+    let set_number = || {
+        IrOpcode::data_setvariableto(DataSetvariabletoFields {
+            var: RefCell::new(number_var.clone()),
+            local_write: RefCell::new(true),
+            first_write: RefCell::new(false),
+        })
+    };
+    Ok(vec![
+        IrOpcode::hq_cast(HqCastFields(IrType::String)),
+        IrOpcode::data_teevariable(DataTeevariableFields {
+            var: RefCell::new(text_var.clone()),
+            local_read_write: RefCell::new(true),
+        }),
+    ]
+    .into_iter()
+    .chain(generate_exhaustive_string_comparison(
+        numbers.keys().cloned(),
+        |name| {
+            numbers
+                .get(&name)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .chain([set_number()])
+                .collect()
+        },
+        vec![
+            IrOpcode::data_variable(DataVariableFields {
+                var: RefCell::new(text_var),
+                local_read: RefCell::new(true),
+            }),
+            IrOpcode::hq_cast(HqCastFields(IrType::Int)),
+            set_number(),
+        ],
+        context,
+        project,
+        flags,
+    )?)
+    .chain([IrOpcode::data_variable(DataVariableFields {
+        var: RefCell::new(number_var),
+        local_read: RefCell::new(true),
+    })])
+    .collect())
+}
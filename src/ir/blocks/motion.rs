@@ -0,0 +1,404 @@
+use super::NextBlocks;
+use super::constant_menu_value;
+use super::control_flow::{generate_exhaustive_string_comparison, generate_loop};
+use crate::instructions::{
+    DataSetvariabletoFields, DataTeevariableFields, DataVariableFields, HqCastFields,
+    HqFloatFields, HqIntegerFields, IrOpcode, MotionXpositionofFields, MotionYpositionofFields,
+};
+use crate::ir::{IrProject, IrType, RcVar, StepContext, Target};
+use crate::prelude::*;
+use crate::sb3::{BlockInfo, BlockMap, BlockOpcode, VarVal};
+use crate::wasm::WasmFlags;
+
+/// Instructions which push a random position on the stage.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_motion.js#L98>
+fn random_position() -> Vec<IrOpcode> {
+    vec![
+        IrOpcode::hq_integer(HqIntegerFields(-240)),
+        IrOpcode::hq_integer(HqIntegerFields(240)),
+        IrOpcode::operator_random,
+        IrOpcode::hq_integer(HqIntegerFields(-180)),
+        IrOpcode::hq_integer(HqIntegerFields(180)),
+        IrOpcode::operator_random,
+    ]
+}
+
+/// Generates instructions which act upon the position named by a menu (`_mouse_` or the name of
+/// a sprite), which is at the top of the stack.
+///
+/// `at_position` is given instructions which push the x and y coordinates of the named position,
+/// and returns the instructions to run with them. `_random_` runs `random` if it is given;
+/// otherwise, like any other name which doesn't refer to anything, it runs `fallback`.
+///
+/// If the name is known at compile time (`constant`), it is resolved directly; otherwise it is
+/// compared against every possible name at runtime.
+fn generate_position_menu<F>(
+    constant: Option<Box<str>>,
+    at_position: F,
+    random: Option<Vec<IrOpcode>>,
+    fallback: Vec<IrOpcode>,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>>
+where
+    F: Fn(Vec<IrOpcode>) -> Vec<IrOpcode>,
+{
+    // scratch only looks at original sprites, and never the stage
+    let sprites: IndexMap<Box<str>, Rc<Target>> = context
+        .project()?
+        .targets()
+        .try_borrow()?
+        .iter()
+        .filter(|(_, target)| !target.is_stage())
+        .map(|(name, target)| (name.clone(), Rc::clone(target)))
+        .collect();
+    let branch = |name: &str| match name {
+        "_mouse_" => Some(at_position(vec![IrOpcode::sensing_mousex, IrOpcode::sensing_mousey])),
+        "_random_" if random.is_some() => random.clone(),
+        _ => sprites.get(name).map(|target| {
+            at_position(vec![
+                IrOpcode::motion_xpositionof(MotionXpositionofFields {
+                    target: Rc::clone(target),
+                }),
+                IrOpcode::motion_ypositionof(MotionYpositionofFields {
+                    target: Rc::clone(target),
+                }),
+            ])
+        }),
+    };
+    if let Some(name) = constant {
+        return Ok(core::iter::once(IrOpcode::hq_drop)
+            .chain(branch(&name).unwrap_or(fallback))
+            .collect());
+    }
+    let names = core::iter::once("_mouse_".into())
+        .chain(random.is_some().then(|| "_random_".into()))
+        .chain(sprites.keys().cloned())
+        .collect::<Vec<Box<str>>>();
+    generate_exhaustive_string_comparison(
+        names,
+        |name| branch(&name).unwrap_or_default(),
+        fallback,
+        context,
+        project,
+        flags,
+    )
+}
+
+/// Generates a `go to` block, with the menu's value at the top of the stack.
+pub fn generate_goto(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let goto = |position: Vec<IrOpcode>| {
+        position
+            .into_iter()
+            .chain(vec![IrOpcode::motion_gotoxy])
+            .collect::<Vec<_>>()
+    };
+    generate_position_menu(
+        constant_menu_value(block_info, blocks, "TO", &BlockOpcode::motion_goto_menu, "TO")?,
+        goto,
+        Some(goto(random_position())),
+        vec![],
+        context,
+        project,
+        flags,
+    )
+}
+
+/// Generates a `point towards` block, with the menu's value at the top of the stack.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_motion.js#L168>
+pub fn generate_pointtowards(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let point_towards = |position: Vec<IrOpcode>| {
+        position
+            .into_iter()
+            .chain(vec![
+                // direction = 90 - atan2(target_y - y, target_x - x)
+                IrOpcode::motion_yposition,
+                IrOpcode::operator_subtract,
+                IrOpcode::hq_swap,
+                IrOpcode::motion_xposition,
+                IrOpcode::operator_subtract,
+                IrOpcode::operator_atan2,
+                IrOpcode::hq_float(HqFloatFields(90.0)),
+                IrOpcode::hq_swap,
+                IrOpcode::operator_subtract,
+                IrOpcode::motion_pointindirection,
+            ])
+            .collect::<Vec<_>>()
+    };
+    generate_position_menu(
+        constant_menu_value(
+            block_info,
+            blocks,
+            "TOWARDS",
+            &BlockOpcode::motion_pointtowards_menu,
+            "TOWARDS",
+        )?,
+        point_towards,
+        Some(vec![
+            IrOpcode::hq_integer(HqIntegerFields(-180)),
+            IrOpcode::hq_integer(HqIntegerFields(180)),
+            IrOpcode::operator_random,
+            IrOpcode::motion_pointindirection,
+        ]),
+        vec![],
+        context,
+        project,
+        flags,
+    )
+}
+
+/// Generates a `distance to` block, with the menu's value at the top of the stack.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_sensing.js#L234>
+pub fn generate_distanceto(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    if context.target().is_stage() {
+        return Ok(vec![IrOpcode::hq_drop, IrOpcode::hq_integer(HqIntegerFields(10000))]);
+    }
+    let distance = RcVar::new(IrType::Float, &VarVal::Float(0.0), None, flags)?;
+    let set_distance = IrOpcode::data_setvariableto(DataSetvariabletoFields {
+        var: RefCell::new(distance.clone()),
+        local_write: RefCell::new(true),
+        first_write: RefCell::new(false),
+    });
+    let distance_to = |position: Vec<IrOpcode>| {
+        position
+            .into_iter()
+            .chain(vec![
+                IrOpcode::motion_yposition,
+                IrOpcode::operator_subtract,
+                IrOpcode::hq_dup,
+                IrOpcode::operator_multiply,
+                IrOpcode::hq_swap,
+                IrOpcode::motion_xposition,
+                IrOpcode::operator_subtract,
+                IrOpcode::hq_dup,
+                IrOpcode::operator_multiply,
+                IrOpcode::operator_add,
+                IrOpcode::operator_sqrt,
+                IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+                set_distance.clone(),
+            ])
+            .collect::<Vec<_>>()
+    };
+    Ok(generate_position_menu(
+        constant_menu_value(
+            block_info,
+            blocks,
+            "DISTANCETOMENU",
+            &BlockOpcode::sensing_distancetomenu,
+            "DISTANCETOMENU",
+        )?,
+        distance_to,
+        None,
+        vec![IrOpcode::hq_float(HqFloatFields(10000.0)), set_distance.clone()],
+        context,
+        project,
+        flags,
+    )?
+    .into_iter()
+    .chain(vec![IrOpcode::data_variable(DataVariableFields {
+        var: RefCell::new(distance),
+        local_read: RefCell::new(true),
+    })])
+    .collect())
+}
+
+/// Generates a `glide to` block, with the number of seconds followed by the menu's value at the
+/// top of the stack.
+pub fn generate_glideto(
+    should_break: &mut bool,
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    final_next_blocks: NextBlocks,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let x_var = RcVar::new(IrType::Float, &VarVal::Float(0.0), None, flags)?;
+    let y_var = RcVar::new(IrType::Float, &VarVal::Float(0.0), None, flags)?;
+    let set_position = |position: Vec<IrOpcode>| {
+        position
+            .into_iter()
+            .chain(vec![
+                IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+                IrOpcode::data_setvariableto(DataSetvariabletoFields {
+                    var: RefCell::new(y_var.clone()),
+                    local_write: RefCell::new(true),
+                    first_write: RefCell::new(false),
+                }),
+                IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+                IrOpcode::data_setvariableto(DataSetvariabletoFields {
+                    var: RefCell::new(x_var.clone()),
+                    local_write: RefCell::new(true),
+                    first_write: RefCell::new(false),
+                }),
+            ])
+            .collect::<Vec<_>>()
+    };
+    // scratch doesn't glide at all if the position doesn't exist, which is the same as gliding
+    // to where the sprite already is
+    let fallback = set_position(vec![IrOpcode::motion_xposition, IrOpcode::motion_yposition]);
+    Ok(generate_position_menu(
+        constant_menu_value(block_info, blocks, "TO", &BlockOpcode::motion_glideto_menu, "TO")?,
+        set_position,
+        Some(set_position(random_position())),
+        fallback,
+        context,
+        &context.target().project(),
+        flags,
+    )?
+    .into_iter()
+    .chain(vec![
+        IrOpcode::data_variable(DataVariableFields {
+            var: RefCell::new(x_var),
+            local_read: RefCell::new(true),
+        }),
+        IrOpcode::data_variable(DataVariableFields {
+            var: RefCell::new(y_var),
+            local_read: RefCell::new(true),
+        }),
+    ])
+    .chain(generate_glide(
+        should_break,
+        block_info,
+        blocks,
+        context,
+        final_next_blocks,
+        flags,
+    )?)
+    .collect())
+}
+
+/// Generates a glide from the sprite's current position to the x and y coordinates at the top of
+/// the stack, over the number of seconds beneath them.
+///
+/// Each frame, the sprite is moved to the position interpolated by the time elapsed since the
+/// glide started (according to the `sensing_timer` global), before finally being moved to the end
+/// position once the glide's duration has passed.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_motion.js#L213>
+pub fn generate_glide(
+    should_break: &mut bool,
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    final_next_blocks: NextBlocks,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let float_var = || RcVar::new(IrType::Float, &VarVal::Float(0.0), None, flags);
+    let end_y = float_var()?;
+    if context.warp {
+        // nothing is drawn until a warped script yields, so only the final position is
+        // observable. The timer doesn't advance within a tick either, so a warped glide loop
+        // would never finish.
+        return Ok(vec![
+            IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+            IrOpcode::data_setvariableto(DataSetvariabletoFields {
+                var: RefCell::new(end_y.clone()),
+                local_write: RefCell::new(true),
+                first_write: RefCell::new(true),
+            }),
+            IrOpcode::hq_swap,
+            IrOpcode::hq_drop,
+            IrOpcode::data_variable(DataVariableFields {
+                var: RefCell::new(end_y),
+                local_read: RefCell::new(true),
+            }),
+            IrOpcode::motion_gotoxy,
+        ]);
+    }
+    let end_x = float_var()?;
+    let duration = float_var()?;
+    let start_time = float_var()?;
+    let elapsed = float_var()?;
+    let start_x = float_var()?;
+    let start_y = float_var()?;
+    let read = |var: &RcVar| {
+        IrOpcode::data_variable(DataVariableFields {
+            var: RefCell::new(var.clone()),
+            local_read: RefCell::new(false),
+        })
+    };
+    let write = |var: &RcVar| {
+        IrOpcode::data_setvariableto(DataSetvariabletoFields {
+            var: RefCell::new(var.clone()),
+            local_write: RefCell::new(false),
+            first_write: RefCell::new(false),
+        })
+    };
+    let interpolate = |start: &RcVar, end: &RcVar| {
+        vec![
+            read(&elapsed),
+            read(&duration),
+            IrOpcode::operator_divide,
+            read(end),
+            read(start),
+            IrOpcode::operator_subtract,
+            IrOpcode::operator_multiply,
+            read(start),
+            IrOpcode::operator_add,
+        ]
+    };
+    let setup_instructions = vec![
+        IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+        write(&end_y),
+        IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+        write(&end_x),
+        IrOpcode::hq_cast(HqCastFields(IrType::Float)),
+        write(&duration),
+        IrOpcode::sensing_timer,
+        write(&start_time),
+        IrOpcode::motion_xposition,
+        write(&start_x),
+        IrOpcode::motion_yposition,
+        write(&start_y),
+    ];
+    let condition_instructions = vec![
+        IrOpcode::sensing_timer,
+        read(&start_time),
+        IrOpcode::operator_subtract,
+        IrOpcode::data_teevariable(DataTeevariableFields {
+            var: RefCell::new(elapsed.clone()),
+            local_read_write: RefCell::new(false),
+        }),
+        read(&duration),
+        IrOpcode::operator_lt,
+    ];
+    let pre_body_instructions = interpolate(&start_x, &end_x)
+        .into_iter()
+        .chain(interpolate(&start_y, &end_y))
+        .chain(vec![IrOpcode::motion_gotoxy])
+        .collect();
+    let post_loop_instructions = vec![read(&end_x), read(&end_y), IrOpcode::motion_gotoxy];
+    generate_loop(
+        false,
+        should_break,
+        block_info,
+        blocks,
+        context,
+        final_next_blocks,
+        None,
+        condition_instructions,
+        Some(pre_body_instructions),
+        false,
+        setup_instructions,
+        post_loop_instructions,
+        flags,
+    )
+}
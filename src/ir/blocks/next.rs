@@ -13,6 +13,8 @@ pub enum NextBlock {
 
 #[derive(Clone, Debug)]
 pub struct NextBlockInfo {
+    /// whether to yield (if a redraw is needed, when not in warp mode) before running this block,
+    /// e.g. at the end of each loop iteration
     pub yield_first: bool,
     pub block: NextBlock,
 }
@@ -103,6 +105,17 @@ fn generate_next_step(
     Ok((next_step, yield_first))
 }
 
+/// The yield at the end of each iteration of a loop which isn't warped.
+pub fn loop_yield(context: &StepContext, next_step: StepIndex) -> IrOpcode {
+    IrOpcode::hq_yield(HqYieldFields {
+        mode: if context.warp_timer {
+            YieldMode::WarpTimer(next_step)
+        } else {
+            YieldMode::MaybeSchedule(next_step)
+        },
+    })
+}
+
 pub fn generate_next_step_inlined(
     block_info: &BlockInfo,
     blocks: &BTreeMap<Box<str>, Block>,
@@ -121,9 +134,7 @@ pub fn generate_next_step_inlined(
                 Rc::new(RefCell::new(Step::new(
                     None,
                     context.clone(),
-                    vec![IrOpcode::hq_yield(HqYieldFields {
-                        mode: YieldMode::Schedule(next_step_index),
-                    })],
+                    vec![loop_yield(context, next_step_index)],
                     context.target().project(),
                     false,
                 )))
@@ -136,9 +147,7 @@ pub fn generate_next_step_inlined(
                 Rc::new(RefCell::new(Step::new(
                     None,
                     context.clone(),
-                    vec![IrOpcode::hq_yield(HqYieldFields {
-                        mode: YieldMode::Schedule(step_index),
-                    })],
+                    vec![loop_yield(context, step_index)],
                     context.target().project(),
                     false,
                 )))
@@ -161,9 +170,7 @@ pub fn generate_next_step_inlined(
                 Rc::new(RefCell::new(Step::new(
                     None,
                     context.clone(),
-                    vec![IrOpcode::hq_yield(HqYieldFields {
-                        mode: YieldMode::Schedule(step_index),
-                    })],
+                    vec![loop_yield(context, step_index)],
                     context.target().project(),
                     false,
                 )))
@@ -0,0 +1,172 @@
+use super::constant_menu_value;
+use super::control_flow::generate_exhaustive_string_comparison;
+use super::string_field;
+use crate::instructions::{
+    DataSetvariabletoFields, DataVariableFields, HqIntegerFields, IrOpcode,
+    MotionXpositionofFields, MotionYpositionofFields, SensingOfFields, SensingOfVariableFields,
+    TargetAttribute,
+};
+use crate::ir::variable::TargetVars;
+use crate::ir::{IrProject, IrType, RcVar, StepContext, Target};
+use crate::prelude::*;
+use crate::sb3::{BlockInfo, BlockMap, BlockOpcode, VarVal};
+use crate::wasm::WasmFlags;
+
+/// Instructions which push the value of the variable with the given name, if there is one.
+///
+/// `sprite` is the sprite which the variables belong to, or `None` for global variables.
+fn variable_named(
+    variables: &TargetVars,
+    name: &str,
+    sprite: Option<&Rc<Target>>,
+) -> HQResult<Option<Vec<IrOpcode>>> {
+    let Some(variable) = variables.values().find(|variable| &*variable.name == name) else {
+        return Ok(None);
+    };
+    *variable.is_used.try_borrow_mut()? = true;
+    let read = IrOpcode::data_variable(DataVariableFields {
+        var: RefCell::new(variable.var.clone()),
+        local_read: RefCell::new(false),
+    });
+    // whether the sprite can be cloned isn't known until the whole project has been converted,
+    // so this is left to `sensing_of_variable` to deal with
+    Ok(Some(match sprite {
+        Some(sprite) => vec![
+            read,
+            IrOpcode::sensing_of_variable(SensingOfVariableFields {
+                target: Rc::clone(sprite),
+                var: variable.var.clone(),
+            }),
+        ],
+        None => vec![read],
+    }))
+}
+
+/// Instructions which push the value of the named property of a target, if it has one.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_sensing.js#L261>
+fn property_of(
+    target: &Rc<Target>,
+    property: &str,
+    project: &IrProject,
+) -> HQResult<Option<Vec<IrOpcode>>> {
+    let attribute = |attribute: TargetAttribute| {
+        Ok(Some(vec![IrOpcode::sensing_of(SensingOfFields {
+            target: Rc::clone(target),
+            attribute,
+        })]))
+    };
+    if target.is_stage() {
+        return match property {
+            "background #" | "backdrop #" => attribute(TargetAttribute::CostumeNumber),
+            "backdrop name" => attribute(TargetAttribute::CostumeName),
+            "volume" => attribute(TargetAttribute::Volume),
+            _ => variable_named(project.global_variables(), property, None),
+        };
+    }
+    match property {
+        "x position" => Ok(Some(vec![IrOpcode::motion_xpositionof(
+            MotionXpositionofFields {
+                target: Rc::clone(target),
+            },
+        )])),
+        "y position" => Ok(Some(vec![IrOpcode::motion_ypositionof(
+            MotionYpositionofFields {
+                target: Rc::clone(target),
+            },
+        )])),
+        "direction" => attribute(TargetAttribute::Direction),
+        "costume #" => attribute(TargetAttribute::CostumeNumber),
+        "costume name" => attribute(TargetAttribute::CostumeName),
+        "size" => attribute(TargetAttribute::Size),
+        "volume" => attribute(TargetAttribute::Volume),
+        _ => variable_named(target.variables(), property, Some(target)),
+    }
+}
+
+/// Generates an `of` block, with the object menu's value at the top of the stack.
+///
+/// If the object is known at compile time, this reads the property of that target directly;
+/// otherwise the object is compared against the name of every target at runtime. Objects and
+/// properties which don't exist give 0.
+pub fn generate_of(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Vec<IrOpcode>> {
+    let property = string_field(block_info, "PROPERTY")?;
+    let ir_project = context.project()?;
+    // the stage is referred to as `_stage_` rather than by its name
+    let targets: IndexMap<Box<str>, Rc<Target>> = ir_project
+        .targets()
+        .try_borrow()?
+        .iter()
+        .map(|(name, target)| {
+            let name = if target.is_stage() {
+                "_stage_".into()
+            } else {
+                name.clone()
+            };
+            (name, Rc::clone(target))
+        })
+        .collect();
+    let fallback = || vec![IrOpcode::hq_integer(HqIntegerFields(0))];
+
+    if let Some(object) = constant_menu_value(
+        block_info,
+        blocks,
+        "OBJECT",
+        &BlockOpcode::sensing_of_object_menu,
+        "OBJECT",
+    )? {
+        let value = match targets.get(&object) {
+            Some(target) => property_of(target, &property, &ir_project)?,
+            None => None,
+        };
+        return Ok(core::iter::once(IrOpcode::hq_drop)
+            .chain(value.unwrap_or_else(fallback))
+            .collect());
+    }
+
+    let result = RcVar::new(IrType::Any, &VarVal::Float(0.0), None, flags)?;
+    let set_result = IrOpcode::data_setvariableto(DataSetvariabletoFields {
+        var: RefCell::new(result.clone()),
+        local_write: RefCell::new(true),
+        first_write: RefCell::new(false),
+    });
+    let branches = targets
+        .iter()
+        .map(|(name, target)| {
+            Ok((
+                name.clone(),
+                property_of(target, &property, &ir_project)?.unwrap_or_else(fallback),
+            ))
+        })
+        .collect::<HQResult<IndexMap<_, _>>>()?;
+    Ok(generate_exhaustive_string_comparison(
+        branches.keys().cloned().collect::<Vec<_>>(),
+        |name| {
+            branches
+                .get(&name)
+                .cloned()
+                .unwrap_or_else(fallback)
+                .into_iter()
+                .chain(core::iter::once(set_result.clone()))
+                .collect()
+        },
+        fallback()
+            .into_iter()
+            .chain(core::iter::once(set_result.clone()))
+            .collect(),
+        context,
+        project,
+        flags,
+    )?
+    .into_iter()
+    .chain(core::iter::once(IrOpcode::data_variable(DataVariableFields {
+        var: RefCell::new(result),
+        local_read: RefCell::new(true),
+    })))
+    .collect())
+}
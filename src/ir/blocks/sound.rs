@@ -0,0 +1,96 @@
+use super::constant_menu_value;
+use super::control_flow::generate_exhaustive_string_comparison;
+use crate::instructions::{
+    DataSetvariabletoFields, DataTeevariableFields, DataVariableFields, HqCastFields,
+    HqIntegerFields, IrOpcode,
+};
+use crate::ir::{IrProject, IrType, RcVar, StepContext};
+use crate::prelude::*;
+use crate::sb3::{BlockInfo, BlockMap, BlockOpcode, VarVal};
+use crate::wasm::WasmFlags;
+
+/// Generates the sound number (starting from 1) that the `SOUND_MENU` input of a `play sound`
+/// or `play sound until done` block refers to, with the value of that input at the top of the
+/// stack.
+///
+/// Strings are looked up by sound name, falling back to being treated as a sound number (which
+/// is wrapped around by the host). Returns `None` if the menu names a sound which doesn't exist,
+/// in which case the input should just be dropped, and the block does nothing.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/blocks/scratch3_sound.js>
+pub fn generate_sound_number(
+    block_info: &BlockInfo,
+    blocks: &BlockMap,
+    context: &StepContext,
+    project: &Weak<IrProject>,
+    flags: &WasmFlags,
+) -> HQResult<Option<Vec<IrOpcode>>> {
+    let mut numbers: IndexMap<Box<str>, i32> = IndexMap::default();
+    for (index, sound) in context.target().sounds().iter().enumerate() {
+        let number = (index + 1)
+            .try_into()
+            .map_err(|_| make_hq_bug!("sound index out of bounds"))?;
+        numbers.entry(sound.name.clone()).or_insert(number);
+    }
+
+    if let Some(name) = constant_menu_value(
+        block_info,
+        blocks,
+        "SOUND_MENU",
+        &BlockOpcode::sound_sounds_menu,
+        "SOUND_MENU",
+    )? {
+        return Ok(numbers.get(&name).map(|number| {
+            vec![
+                IrOpcode::hq_drop,
+                IrOpcode::hq_integer(HqIntegerFields(*number)),
+            ]
+        }));
+    }
+
+    let text_var = RcVar::new(IrType::String, &VarVal::String("".into()), None, flags)?;
+    let number_var = RcVar::new(IrType::Int, &VarVal::Int(0), None, flags)?;
+    let set_number = || {
+        IrOpcode::data_setvariableto(DataSetvariabletoFields {
+            var: RefCell::new(number_var.clone()),
+            local_write: RefCell::new(true),
+            first_write: RefCell::new(false),
+        })
+    };
+    Ok(Some(
+        vec![
+            IrOpcode::hq_cast(HqCastFields(IrType::String)),
+            IrOpcode::data_teevariable(DataTeevariableFields {
+                var: RefCell::new(text_var.clone()),
+                local_read_write: RefCell::new(true),
+            }),
+        ]
+        .into_iter()
+        .chain(generate_exhaustive_string_comparison(
+            numbers.keys().cloned(),
+            |name| {
+                vec![
+                    IrOpcode::hq_integer(HqIntegerFields(
+                        numbers.get(&name).copied().unwrap_or_default(),
+                    )),
+                    set_number(),
+                ]
+            },
+            vec![
+                IrOpcode::data_variable(DataVariableFields {
+                    var: RefCell::new(text_var),
+                    local_read: RefCell::new(true),
+                }),
+                IrOpcode::hq_cast(HqCastFields(IrType::Int)),
+                set_number(),
+            ],
+            context,
+            project,
+            flags,
+        )?)
+        .chain([IrOpcode::data_variable(DataVariableFields {
+            var: RefCell::new(number_var),
+            local_read: RefCell::new(true),
+        })])
+        .collect(),
+    ))
+}
@@ -0,0 +1,284 @@
+//! Summarises how well a project is supported, so that users can find out whether a project will
+//! work before trying to run it.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use super::blocks::is_supported_opcode;
+use super::{InertHatReason, IrProject, Thread};
+use crate::prelude::*;
+use crate::sb3::{Block, BlockMap, BlockOpcode, Sb3Project};
+use crate::wasm::WasmFlags;
+use crate::wasm::flags::WasmFeature;
+use crate::{BlockLocation, HQErrorType};
+
+/// How many times an opcode is used in a project, and how well it is supported.
+#[derive(Clone, Debug, Serialize)]
+pub struct OpcodeUsage {
+    pub opcode: Box<str>,
+    pub count: usize,
+    /// whether blocks with this opcode can be compiled at all
+    pub supported: bool,
+    /// whether compiling any of these blocks failed with an [`HQErrorType::Unimplemented`] error,
+    /// i.e. whether it hit a `hq_todo!`
+    pub hits_todo: bool,
+}
+
+/// A supported hat block which can never start the script under it.
+#[derive(Clone, Debug, Serialize)]
+pub struct InertHat {
+    pub opcode: Box<str>,
+    pub location: BlockLocation,
+    pub reason: InertHatReason,
+}
+
+/// A summary of how well a project is supported by `HyperQuark`.
+///
+/// This can be passed to JS as a plain object using [`CompatibilityReport::to_js`].
+#[derive(Clone, Debug, Serialize)]
+#[wasm_bindgen]
+pub struct CompatibilityReport {
+    opcodes: Box<[OpcodeUsage]>,
+    unsupported_events: Box<[Box<str>]>,
+    inert_hats: Box<[InertHat]>,
+    errors: Box<[HQError]>,
+    wasm_features: Box<[WasmFeature]>,
+}
+
+#[wasm_bindgen]
+impl CompatibilityReport {
+    /// Whether the project is expected to compile and for all of its scripts to be able to run.
+    /// Hats which can't start their scripts in Scratch either don't count against this.
+    #[wasm_bindgen]
+    #[must_use]
+    pub fn is_supported(&self) -> bool {
+        self.errors.is_empty()
+            && self.unsupported_events.is_empty()
+            && !self
+                .inert_hats
+                .iter()
+                .any(|hat| hat.reason.fires_in_scratch())
+    }
+
+    #[wasm_bindgen]
+    pub fn to_js(&self) -> HQResult<JsValue> {
+        serde_wasm_bindgen::to_value(&self)
+            .map_err(|_| make_hq_bug!("couldn't convert CompatibilityReport to JsValue"))
+    }
+}
+
+impl CompatibilityReport {
+    /// Every opcode used by a block in the project, in order of first use. Menus are left out,
+    /// as they are compiled as part of their parent block.
+    #[must_use]
+    pub const fn opcodes(&self) -> &[OpcodeUsage] {
+        &self.opcodes
+    }
+
+    /// Opcodes of hat blocks which can never start a script, meaning that the scripts under them
+    /// will never be run.
+    #[must_use]
+    pub const fn unsupported_events(&self) -> &[Box<str>] {
+        &self.unsupported_events
+    }
+
+    /// Hat blocks of known opcodes which can never start the scripts under them, e.g.
+    /// `when I start as a clone` on the stage; see [`InertHatReason`].
+    #[must_use]
+    pub const fn inert_hats(&self) -> &[InertHat] {
+        &self.inert_hats
+    }
+
+    /// Errors found whilst compiling the project; see [`IrProject::check_sb3`].
+    #[must_use]
+    pub const fn errors(&self) -> &[HQError] {
+        &self.errors
+    }
+
+    /// The WASM features needed to run the project, when compiled with the flags that it was
+    /// analysed with.
+    #[must_use]
+    pub const fn wasm_features(&self) -> &[WasmFeature] {
+        &self.wasm_features
+    }
+
+    pub fn analyze(sb3: &Sb3Project, flags: &WasmFlags) -> HQResult<Self> {
+        let errors = IrProject::check_sb3(sb3, flags)?;
+
+        let backdrops = sb3
+            .targets
+            .iter()
+            .filter(|target| target.is_stage)
+            .flat_map(|target| target.costumes.iter())
+            .map(|costume| &*costume.name)
+            .collect::<Vec<_>>();
+
+        let mut opcodes: IndexMap<Box<str>, OpcodeUsage> = IndexMap::default();
+        let mut unsupported_events: IndexSet<Box<str>> = IndexSet::default();
+        let mut inert_hats = vec![];
+        for target in &sb3.targets {
+            for (id, block) in &target.blocks {
+                let Block::Normal { block_info, .. } = block else {
+                    continue;
+                };
+                if block_info.shadow {
+                    continue;
+                }
+                let name = opcode_name(&block_info.opcode);
+                if block_info.top_level && matches!(block_info.opcode, BlockOpcode::Unknown(_)) {
+                    // probably a hat block from an extension; see `unsupported_blocks`
+                    unsupported_events.insert(name.clone());
+                }
+                // malformed hats are reported amongst the errors instead
+                if block_info.top_level
+                    && Thread::is_hat(&block_info.opcode)
+                    && let Ok(Some(reason)) = Thread::inert_hat_reason(
+                        block_info,
+                        &target.blocks,
+                        target.is_stage,
+                        |name| backdrops.contains(&name),
+                    )
+                {
+                    inert_hats.push(InertHat {
+                        opcode: name.clone(),
+                        location: BlockLocation {
+                            block_id: id.clone(),
+                            target: target.name.clone(),
+                            proccode: None,
+                        },
+                        reason,
+                    });
+                }
+                opcodes
+                    .entry(name.clone())
+                    .or_insert_with(|| OpcodeUsage {
+                        opcode: name,
+                        count: 0,
+                        supported: is_supported_opcode(&block_info.opcode)
+                            || Thread::is_hat(&block_info.opcode)
+                            || block_info.opcode == BlockOpcode::procedures_definition,
+                        hits_todo: false,
+                    })
+                    .count += 1;
+            }
+        }
+
+        for error in &errors {
+            if error.err_type != HQErrorType::Unimplemented {
+                continue;
+            }
+            let Some(location) = &error.location else {
+                continue;
+            };
+            let Some(target) = sb3
+                .targets
+                .iter()
+                .find(|target| target.name == location.target)
+            else {
+                continue;
+            };
+            if let Some(opcode) = non_shadow_opcode(&target.blocks, &location.block_id)
+                && let Some(usage) = opcodes.get_mut(&opcode_name(opcode))
+            {
+                usage.hits_todo = true;
+            }
+        }
+
+        Ok(Self {
+            opcodes: opcodes.into_values().collect(),
+            unsupported_events: unsupported_events.into_iter().collect(),
+            inert_hats: inert_hats.into(),
+            errors: errors.into(),
+            wasm_features: flags.required_wasm_features().into(),
+        })
+    }
+}
+
+fn opcode_name(opcode: &BlockOpcode) -> Box<str> {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "too many opcodes to match individually"
+    )]
+    match opcode {
+        BlockOpcode::Unknown(name) => name.clone(),
+        known => format!("{known:?}").into(),
+    }
+}
+
+/// The opcode of the given block or, if it is a menu, of the block that it belongs to.
+fn non_shadow_opcode<'a>(blocks: &'a BlockMap, block_id: &str) -> Option<&'a BlockOpcode> {
+    let mut block_info = blocks.get(block_id)?.block_info()?;
+    while block_info.shadow {
+        block_info = blocks.get(block_info.parent.as_deref()?)?.block_info()?;
+    }
+    Some(&block_info.opcode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::test_utils::{block, stage_with_blocks};
+    use crate::wasm::flags::unit_test_wasm_features;
+
+    #[test]
+    fn report_lists_opcodes_and_unsupported_events() {
+        let sb3 = stage_with_blocks(
+            &format!(
+                r#"{{
+                    "hat": {},
+                    "a": {},
+                    "b": {},
+                    "extension_hat": {},
+                    "c": {}
+                }}"#,
+                block("event_whenflagclicked", Some("a"), true),
+                block("foo_bar", Some("b"), false),
+                block("foo_bar", None, false),
+                block("makeymakey_whenMakeyKeyPressed", Some("c"), true),
+                block("control_stop", None, false),
+            ),
+            &["makeymakey"],
+        );
+        let report =
+            CompatibilityReport::analyze(&sb3, &WasmFlags::new(unit_test_wasm_features())).unwrap();
+        let usage = |opcode: &str| {
+            report
+                .opcodes()
+                .iter()
+                .find(|usage| &*usage.opcode == opcode)
+                .unwrap()
+        };
+        assert_eq!(usage("event_whenflagclicked").count, 1);
+        assert!(usage("event_whenflagclicked").supported);
+        assert_eq!(usage("foo_bar").count, 2);
+        assert!(!usage("foo_bar").supported);
+        assert_eq!(
+            report.unsupported_events(),
+            [Box::<str>::from("makeymakey_whenMakeyKeyPressed")]
+        );
+        assert!(!report.errors().is_empty());
+        assert!(!report.is_supported());
+        assert!(report.wasm_features().contains(&WasmFeature::GC));
+    }
+    #[test]
+    fn report_lists_hats_which_can_never_fire() {
+        let sb3 = stage_with_blocks(
+            &format!(
+                r#"{{
+                    "clone_hat": {}
+                }}"#,
+                block("control_start_as_clone", None, true),
+            ),
+            &[],
+        );
+        let report =
+            CompatibilityReport::analyze(&sb3, &WasmFlags::new(unit_test_wasm_features())).unwrap();
+        let [hat] = report.inert_hats() else {
+            panic!("expected exactly one inert hat");
+        };
+        assert_eq!(&*hat.opcode, "control_start_as_clone");
+        assert_eq!(&*hat.location.block_id, "clone_hat");
+        assert_eq!(hat.reason, InertHatReason::WrongTarget);
+        assert!(report.is_supported());
+    }
+}
@@ -1,4 +1,5 @@
 use super::{IrProject, Target};
+use crate::BlockLocation;
 use crate::ir::RcVar;
 use crate::prelude::*;
 
@@ -6,6 +7,7 @@ use crate::prelude::*;
 pub struct ProcContext {
     pub arg_vars: Rc<RefCell<Vec<RcVar>>>,
     pub ret_vars: Rc<RefCell<Vec<RcVar>>>,
+    pub proccode: Box<str>,
     pub arg_names: Box<[Box<str>]>,
 }
 
@@ -15,6 +17,10 @@ pub struct StepContext {
     /// whether or not the current thread is warped. this may be because the current
     /// procedure is warped, or because a procedure higher up the call stack was warped.
     pub warp: bool,
+    /// whether or not the current thread is warped, but is being compiled as if it weren't so
+    /// that its loops can yield once the warp timer runs out. `warp` is always false when this
+    /// is true.
+    pub warp_timer: bool,
     pub proc_context: Option<ProcContext>,
     /// enables certain behaviours such as `console.log` say/think rather than
     /// displaying in bubbles
@@ -33,4 +39,17 @@ impl StepContext {
     pub const fn target(&self) -> &Rc<Target> {
         &self.target
     }
+
+    /// The location of a block compiled in this context, for attaching to errors.
+    #[must_use]
+    pub fn block_location(&self, block_id: &str) -> BlockLocation {
+        BlockLocation {
+            block_id: block_id.into(),
+            target: self.target().name().into(),
+            proccode: self
+                .proc_context
+                .as_ref()
+                .map(|proc_context| proc_context.proccode.clone()),
+        }
+    }
 }
@@ -1,11 +1,63 @@
+use core::cmp::Ordering;
 use core::fmt;
 
+/// The value that is compared against in a `when [loudness/timer] > (value)` hat.
+///
+/// This is a wrapper around an `f64` so that it can be totally ordered (using [`f64::total_cmp`]),
+/// which is needed for it to be part of an [`Event`].
+#[derive(Clone, Copy, Debug)]
+pub struct EventThreshold(pub f64);
+
+impl PartialEq for EventThreshold {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for EventThreshold {}
+
+impl PartialOrd for EventThreshold {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EventThreshold {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// The quantity that is compared in a `when [loudness/timer] > (value)` hat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GreaterThanMenu {
+    Loudness,
+    Timer,
+}
+
 // Ord is required to be used in a BTreeMap; Ord requires PartialOrd, Eq and PartialEq
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Event {
     FlagClicked,
     Broadcast(Box<str>),
     SpriteClicked(u32),
+    CloneStart(u32),
+    /// the (lowercase) name of the key, or `any`
+    KeyPressed(Box<str>),
+    /// the index of the backdrop
+    BackdropSwitchesTo(u32),
+    GreaterThan(GreaterThanMenu, EventThreshold),
+    /// the index of the target, and the object (`_mouse_`, `_edge_` or a sprite name) to check
+    TouchingObject(u32, Box<str>),
+}
+
+impl Event {
+    /// Whether this event is triggered by a predicate becoming true (rather than being triggered
+    /// directly), in which case it must be checked every tick.
+    #[must_use]
+    pub const fn is_edge_activated(&self) -> bool {
+        matches!(self, Self::GreaterThan(..) | Self::TouchingObject(..))
+    }
 }
 
 impl fmt::Display for Event {
@@ -14,6 +66,13 @@ impl fmt::Display for Event {
             Self::FlagClicked => write!(f, "FlagClicked"),
             Self::Broadcast(name) => write!(f, "Broadcast({name})"),
             Self::SpriteClicked(idx) => write!(f, "SpriteClicked({idx})"),
+            Self::CloneStart(idx) => write!(f, "CloneStart({idx})"),
+            Self::KeyPressed(key) => write!(f, "KeyPressed({key})"),
+            Self::BackdropSwitchesTo(idx) => write!(f, "BackdropSwitchesTo({idx})"),
+            Self::GreaterThan(menu, EventThreshold(value)) => {
+                write!(f, "GreaterThan({menu:?}, {value})")
+            }
+            Self::TouchingObject(idx, object) => write!(f, "TouchingObject({idx}, {object})"),
         }
     }
 }
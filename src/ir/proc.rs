@@ -49,10 +49,11 @@ impl SpecificProc {
         Rc::clone(&self.return_vars)
     }
 
-    fn proc_context(&self, arg_names: Box<[Box<str>]>) -> ProcContext {
+    fn proc_context(&self, proccode: Box<str>, arg_names: Box<[Box<str>]>) -> ProcContext {
         ProcContext {
             arg_vars: Rc::clone(&self.arg_vars),
             ret_vars: Rc::clone(&self.return_vars),
+            proccode,
             arg_names,
         }
     }
@@ -64,6 +65,10 @@ pub struct Proc {
     warped_specific_proc: RefCell<Option<SpecificProc>>,
     #[expect(clippy::struct_field_names, reason = "i like it")]
     nonwarped_specific_proc: RefCell<Option<SpecificProc>>,
+    /// the variant used in warped threads when the warp timer is enabled; this is compiled in the
+    /// same way as the non-warped variant, but its loops only yield once the warp timer runs out.
+    #[expect(clippy::struct_field_names, reason = "i like it")]
+    warp_timed_specific_proc: RefCell<Option<SpecificProc>>,
     first_step_id: Option<Box<str>>,
     proccode: Box<str>,
     debug: bool,
@@ -93,6 +98,20 @@ impl Proc {
         self.nonwarped_specific_proc.borrow_mut()
     }
 
+    pub fn warp_timed_specific_proc(&self) -> Ref<'_, Option<SpecificProc>> {
+        self.warp_timed_specific_proc.borrow()
+    }
+
+    /// The variant of this procedure which is called by `procedures_call_nonwarp`; this is the
+    /// warp-timed variant if `warp_timer` is true, and the non-warped variant otherwise.
+    pub fn nonwarped_specific_proc_for(&self, warp_timer: bool) -> Ref<'_, Option<SpecificProc>> {
+        if warp_timer {
+            self.warp_timed_specific_proc()
+        } else {
+            self.nonwarped_specific_proc()
+        }
+    }
+
     pub fn proccode(&self) -> &str {
         &self.proccode
     }
@@ -289,6 +308,7 @@ impl Proc {
         Self {
             warped_specific_proc,
             nonwarped_specific_proc,
+            warp_timed_specific_proc: RefCell::new(None),
             first_step_id,
             proccode,
             debug,
@@ -318,6 +338,7 @@ impl Proc {
             || Ok(self.warped_specific_proc.try_borrow()?),
             || Ok(self.warped_specific_proc.try_borrow_mut()?),
             true,
+            false,
         )
     }
 
@@ -332,6 +353,22 @@ impl Proc {
             || Ok(self.nonwarped_specific_proc.try_borrow()?),
             || Ok(self.nonwarped_specific_proc.try_borrow_mut()?),
             false,
+            false,
+        )
+    }
+
+    pub fn compile_warp_timed(
+        &self,
+        blocks: &BTreeMap<Box<str>, Block>,
+        flags: &WasmFlags,
+    ) -> HQResult<()> {
+        self.compile(
+            blocks,
+            flags,
+            || Ok(self.warp_timed_specific_proc.try_borrow()?),
+            || Ok(self.warp_timed_specific_proc.try_borrow_mut()?),
+            false,
+            true,
         )
     }
 
@@ -342,6 +379,7 @@ impl Proc {
         specific_proc: F,
         specific_proc_mut: G,
         warp: bool,
+        warp_timer: bool,
     ) -> HQResult<()>
     where
         F: Fn() -> HQResult<Ref<'a, Option<SpecificProc>>>,
@@ -382,7 +420,10 @@ impl Proc {
 
         let step_context = StepContext {
             warp,
-            proc_context: Some(some_specific_proc.proc_context(self.arg_names.clone())),
+            warp_timer,
+            proc_context: Some(
+                some_specific_proc.proc_context(self.proccode.clone(), self.arg_names.clone()),
+            ),
             target: Rc::clone(&self.target),
             debug: self.debug,
         };
@@ -497,13 +538,20 @@ impl fmt::Display for Proc {
         } else {
             "null".to_string()
         };
+        let warp_timed_specific_proc =
+            if let Some(ref wtsp) = self.warp_timed_specific_proc().clone() {
+                format!("{wtsp}")
+            } else {
+                "null".to_string()
+            };
         write!(
             f,
             r#"{{
             "proccode": "{proccode}",
             "always_warped": {always_warped},
             "warped_specific_proc": {warped_specific_proc},
-            "nonwarped_specific_proc": {nonwarped_specific_proc}
+            "nonwarped_specific_proc": {nonwarped_specific_proc},
+            "warp_timed_specific_proc": {warp_timed_specific_proc}
         }}"#
         )
     }
@@ -1,17 +1,21 @@
 use core::ops::Deref;
 
 use super::proc::{ProcMap, procs_from_target};
+use super::unsupported::{check_unsupported_blocks, unsupported_blocks};
 use super::variable::{TargetLists, TargetVars, lists_from_target, variables_from_target};
 use super::{Step, Target, Thread};
+use crate::HQErrorType;
 use crate::instructions::{
-    DataSetvariabletoFields, DataVariableFields, HqYieldFields, IrOpcode, YieldMode,
+    DataSetvariabletoFields, DataVariableFields, HqYieldFields, IrOpcode, RotationStyle,
+    YieldMode,
 };
 use crate::ir::step::StepIndex;
-use crate::ir::target::IrCostume;
+use crate::ir::target::{IrCostume, IrSound};
 use crate::ir::{PartialStep, RcVar};
 use crate::prelude::*;
 use crate::sb3::Sb3Project;
 use crate::wasm::WasmFlags;
+use crate::wasm::flags::Switch;
 
 #[derive(Clone, Debug)]
 pub struct IrProject {
@@ -87,6 +91,46 @@ impl IrProject {
     }
 
     pub fn try_from_sb3(sb3: &Sb3Project, flags: &WasmFlags) -> HQResult<Rc<Self>> {
+        if flags.skip_unsupported_blocks == Switch::On {
+            for error in unsupported_blocks(sb3) {
+                crate::warn!("skipping unsupported block: {}", error.msg);
+            }
+        } else if let Err(errors) = check_unsupported_blocks(sb3)
+            && let Some(error) = errors.into_iter().next()
+        {
+            // only one error can be returned here; `check_sb3` can be used to find all of them
+            return Err(error);
+        }
+        Self::from_sb3(sb3, flags, None)
+    }
+
+    /// Finds as many of the errors that compiling the project would produce as possible, rather
+    /// than stopping at the first one. Any internal errors are still returned immediately.
+    ///
+    /// Unsupported blocks are reported per opcode and target, as in
+    /// [`unsupported_blocks`], and then skipped so that other errors in the same scripts
+    /// can be found. Beyond that, only the first error in each script is reported.
+    pub fn check_sb3(sb3: &Sb3Project, flags: &WasmFlags) -> HQResult<Vec<HQError>> {
+        let mut errors = unsupported_blocks(sb3);
+        let flags = WasmFlags {
+            skip_unsupported_blocks: Switch::On,
+            ..*flags
+        };
+        match Self::from_sb3(sb3, &flags, Some(&mut errors)) {
+            Err(err) if err.err_type == HQErrorType::InternalError => return Err(err),
+            Err(err) => errors.push(err),
+            Ok(_) => {}
+        }
+        Ok(errors)
+    }
+
+    /// If `errors` is `Some`, scripts which fail to compile (other than due to an internal error)
+    /// have their error pushed to it and are left out of the project.
+    fn from_sb3(
+        sb3: &Sb3Project,
+        flags: &WasmFlags,
+        mut errors: Option<&mut Vec<HQError>>,
+    ) -> HQResult<Rc<Self>> {
         let global_variables = variables_from_target(
             sb3.targets
                 .iter()
@@ -117,15 +161,7 @@ impl IrProject {
             .find_position(|target| target.is_stage)
             .ok_or_else(|| make_hq_bug!("couldn't find stage target"))?;
 
-        let backdrops: Vec<_> = stage_target
-            .costumes
-            .iter()
-            .map(|costume| IrCostume {
-                name: costume.name.clone(),
-                data_format: costume.data_format,
-                md5ext: costume.md5ext.clone(),
-            })
-            .collect();
+        let backdrops: Vec<_> = stage_target.costumes.iter().map(IrCostume::from).collect();
 
         let project = Rc::new(Self::new(
             global_variables,
@@ -135,7 +171,9 @@ impl IrProject {
             backdrops,
         ));
 
-        let (threads_vec, targets): (Vec<_>, Vec<_>) = sb3
+        // all targets are created before any blocks are compiled, so that blocks can refer to
+        // other targets (e.g. `create clone of`)
+        let targets = sb3
             .targets
             .iter()
             .enumerate()
@@ -151,17 +189,18 @@ impl IrProject {
                     lists_from_target(target, flags)?
                 };
                 let procedures = RefCell::new(ProcMap::new());
-                let costumes = target
-                    .costumes
+                let costumes = target.costumes.iter().map(IrCostume::from).collect();
+                let sounds = target
+                    .sounds
                     .iter()
-                    .map(|costume| IrCostume {
-                        name: costume.name.clone(),
-                        data_format: costume.data_format,
-                        md5ext: costume.md5ext.clone(),
+                    .map(|sound| IrSound {
+                        name: sound.name.clone(),
+                        md5ext: sound.md5ext.clone(),
                     })
                     .collect();
                 let ir_target = Rc::new(Target::new(
                     target.is_stage,
+                    target.name.clone(),
                     variables,
                     lists,
                     Rc::downgrade(&project),
@@ -170,43 +209,64 @@ impl IrProject {
                         .try_into()
                         .map_err(|_| make_hq_bug!("target index out of bounds"))?,
                     costumes,
+                    sounds,
+                    target.volume,
+                    target.layer_order,
+                    RotationStyle::from_field(&target.rotation_style).unwrap_or_default(),
+                    target.draggable,
                 ));
                 procs_from_target(target, &ir_target)?;
+                Ok((target.name.clone(), ir_target))
+            })
+            .collect::<HQResult<IndexMap<_, _>>>()?;
+        project
+            .targets
+            .try_borrow_mut()
+            .map_err(|_| make_hq_bug!("couldn't mutably borrow cell"))?
+            .clone_from(&targets);
+        let threads = sb3
+            .targets
+            .iter()
+            .zip(targets.values())
+            .map(|(target, ir_target)| {
                 let blocks = &target.blocks;
-                let threads = blocks
+                blocks
                     .iter()
                     .filter_map(|(id, block)| {
                         let thread = Thread::try_from_top_block(
                             block,
                             blocks,
-                            &Rc::clone(&ir_target),
+                            &Rc::clone(ir_target),
                             &Rc::downgrade(&project),
                             target.comments.clone().iter().any(|(_id, comment)| {
                                 matches!(comment.block_id.clone(), Some(d) if &d == id)
                                     && *comment.text.clone() == *"hq-dbg"
                             }),
                             flags,
-                        )
-                        .transpose()?;
-                        Some(thread)
+                        );
+                        if let Some(errors) = errors.as_deref_mut()
+                            && let Err(err) = &thread
+                            && err.err_type != HQErrorType::InternalError
+                        {
+                            errors.push(err.clone());
+                            return None;
+                        }
+                        thread.transpose()
                     })
-                    .collect::<HQResult<Box<[_]>>>()?;
-                Ok((threads, (target.name.clone(), ir_target)))
+                    .collect::<HQResult<Box<[_]>>>()
             })
             .collect::<HQResult<Box<[_]>>>()?
-            .iter()
-            .cloned()
-            .unzip();
-        let threads = threads_vec.into_iter().flatten().collect::<Box<[_]>>();
+            .into_iter()
+            .flatten()
+            .collect::<Box<[_]>>();
         *project
             .threads
             .try_borrow_mut()
             .map_err(|_| make_hq_bug!("couldn't mutably borrow cell"))? = threads;
-        *project
-            .targets
-            .try_borrow_mut()
-            .map_err(|_| make_hq_bug!("couldn't mutably borrow cell"))? =
-            targets.into_iter().collect();
+        if errors.is_some_and(|errors| !errors.is_empty()) {
+            // scripts which failed to compile may have left steps and procedures half-finished
+            return Ok(project);
+        }
         for target in project.targets().try_borrow()?.values() {
             fixup_proc_types(target)?;
         }
@@ -435,3 +495,168 @@ impl fmt::Display for IrProject {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockLocation;
+    use crate::ir::test_utils::stage_with_blocks;
+    use crate::wasm::flags::unit_test_wasm_features;
+
+    /// A script which waits for a number of seconds given by a `goto` menu with no `TO` field,
+    /// which fails to compile.
+    fn broken_script(hat: &str, wait: &str, menu: &str) -> String {
+        format!(
+            r#"
+                "{hat}": {{
+                    "opcode": "event_whenflagclicked",
+                    "next": "{wait}",
+                    "parent": null,
+                    "inputs": {{}},
+                    "fields": {{}},
+                    "shadow": false,
+                    "topLevel": true,
+                    "x": 0,
+                    "y": 0
+                }},
+                "{wait}": {{
+                    "opcode": "control_wait",
+                    "next": null,
+                    "parent": "{hat}",
+                    "inputs": {{ "DURATION": [3, "{menu}", [5, "1"]] }},
+                    "fields": {{}},
+                    "shadow": false,
+                    "topLevel": false
+                }},
+                "{menu}": {{
+                    "opcode": "motion_goto_menu",
+                    "next": null,
+                    "parent": "{wait}",
+                    "inputs": {{}},
+                    "fields": {{}},
+                    "shadow": true,
+                    "topLevel": false
+                }}
+            "#
+        )
+    }
+
+    #[test]
+    fn errors_are_located_at_the_innermost_block() {
+        let sb3 = stage_with_blocks(&format!("{{ {} }}", broken_script("hat", "a", "b")), &[]);
+        let err = IrProject::try_from_sb3(&sb3, &WasmFlags::new(unit_test_wasm_features()))
+            .unwrap_err();
+        assert_eq!(
+            err.location.as_deref(),
+            Some(&BlockLocation {
+                block_id: "b".into(),
+                target: "Stage".into(),
+                proccode: None,
+            })
+        );
+    }
+
+    #[test]
+    fn check_reports_errors_from_every_script() {
+        let sb3 = stage_with_blocks(
+            &format!(
+                "{{ {}, {} }}",
+                broken_script("hat1", "a1", "b1"),
+                broken_script("hat2", "a2", "b2")
+            ),
+            &[],
+        );
+        let errors =
+            IrProject::check_sb3(&sb3, &WasmFlags::new(unit_test_wasm_features())).unwrap();
+        let mut locations = errors
+            .iter()
+            .map(|err| &*err.location.as_ref().unwrap().block_id)
+            .collect::<Vec<_>>();
+        locations.sort_unstable();
+        assert_eq!(locations, ["b1", "b2"]);
+    }
+    #[test]
+    fn warp_timer_compiles_warped_procedures_so_that_they_can_yield() {
+        let sb3 = stage_with_blocks(
+            r#"{
+                "hat": {
+                    "opcode": "event_whenflagclicked",
+                    "next": "call",
+                    "parent": null,
+                    "inputs": {},
+                    "fields": {},
+                    "shadow": false,
+                    "topLevel": true,
+                    "x": 0,
+                    "y": 0
+                },
+                "call": {
+                    "opcode": "procedures_call",
+                    "next": null,
+                    "parent": "hat",
+                    "inputs": {},
+                    "fields": {},
+                    "shadow": false,
+                    "topLevel": false,
+                    "mutation": {
+                        "tagName": "mutation",
+                        "children": [],
+                        "proccode": "spin",
+                        "argumentids": "[]",
+                        "warp": "true"
+                    }
+                },
+                "def": {
+                    "opcode": "procedures_definition",
+                    "next": "loop",
+                    "parent": null,
+                    "inputs": { "custom_block": [1, "proto"] },
+                    "fields": {},
+                    "shadow": false,
+                    "topLevel": true,
+                    "x": 0,
+                    "y": 200
+                },
+                "proto": {
+                    "opcode": "procedures_prototype",
+                    "next": null,
+                    "parent": "def",
+                    "inputs": {},
+                    "fields": {},
+                    "shadow": true,
+                    "topLevel": false,
+                    "mutation": {
+                        "tagName": "mutation",
+                        "children": [],
+                        "proccode": "spin",
+                        "argumentids": "[]",
+                        "argumentnames": "[]",
+                        "argumentdefaults": "[]",
+                        "warp": "true"
+                    }
+                },
+                "loop": {
+                    "opcode": "control_forever",
+                    "next": null,
+                    "parent": "def",
+                    "inputs": {},
+                    "fields": {},
+                    "shadow": false,
+                    "topLevel": false
+                }
+            }"#,
+            &[],
+        );
+        let mut flags = WasmFlags::new(unit_test_wasm_features());
+        flags.warp_timer = 64;
+        let project = IrProject::try_from_sb3(&sb3, &flags).unwrap();
+        {
+            let targets = project.targets().borrow();
+            let procs = targets.values().next().unwrap().procedures().unwrap();
+            let proc = procs.get("spin").unwrap();
+            assert!(proc.warped_specific_proc().is_none());
+            assert!(proc.warp_timed_specific_proc().is_some());
+        }
+        assert!(format!("{project}").contains(r#""mode": "warp_timer""#));
+    }
+}
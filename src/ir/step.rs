@@ -80,6 +80,7 @@ impl Step {
             StepContext {
                 target,
                 warp: false,
+                warp_timer: false,
                 proc_context: None,
                 debug: false,
             },
@@ -204,10 +205,19 @@ impl Step {
         used_non_inline: bool,
         flags: &WasmFlags,
     ) -> HQResult<Self> {
+        let opcodes = blocks::from_block(
+            block,
+            &block_id,
+            blocks,
+            context,
+            project,
+            final_next_blocks,
+            flags,
+        )?;
         Ok(Self::new(
             Some(block_id),
             context.clone(),
-            blocks::from_block(block, blocks, context, project, final_next_blocks, flags)?,
+            opcodes,
             Weak::clone(project),
             used_non_inline,
         ))
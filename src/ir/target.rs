@@ -3,25 +3,91 @@ use core::cell::{Ref, RefMut};
 use super::IrProject;
 use super::proc::Proc;
 use crate::ir::variable::{TargetLists, TargetVars};
+use crate::instructions::RotationStyle;
 use crate::prelude::*;
-use crate::sb3::CostumeDataFormat;
+use crate::sb3::{Costume, CostumeDataFormat};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IrCostume {
     pub name: Box<str>,
     pub data_format: CostumeDataFormat,
     pub md5ext: Box<str>,
+    /// the (x, y) position of the rotation centre, in costume pixels from the top left corner
+    pub rotation_center: (f64, f64),
+    /// the number of costume pixels per stage pixel; usually 2 for bitmaps and 1 for vectors
+    pub bitmap_resolution: f64,
+    /// the (width, height) of the costume in costume pixels, if known
+    pub size: Option<(f64, f64)>,
+}
+
+impl IrCostume {
+    /// The (left, right, bottom, top) edges of this costume at 100% size and pointing in
+    /// direction 90, in stage units relative to the rotation centre.
+    ///
+    /// If the size of the costume is unknown, the rotation centre is assumed to be in the middle
+    /// of the costume, which is the case for most costumes.
+    #[must_use]
+    pub const fn bounds(&self) -> (f64, f64, f64, f64) {
+        let (center_x, center_y) = self.rotation_center;
+        let (width, height) = match self.size {
+            Some(size) => size,
+            None => (center_x * 2.0, center_y * 2.0),
+        };
+        // older projects may not specify a bitmap resolution
+        let resolution = if self.bitmap_resolution > 0.0 {
+            self.bitmap_resolution
+        } else {
+            1.0
+        };
+        (
+            -center_x / resolution,
+            (width - center_x) / resolution,
+            (center_y - height) / resolution,
+            center_y / resolution,
+        )
+    }
+}
+
+impl From<&Costume> for IrCostume {
+    fn from(costume: &Costume) -> Self {
+        Self {
+            name: costume.name.clone(),
+            data_format: costume.data_format,
+            md5ext: costume.md5ext.clone(),
+            rotation_center: (costume.rotation_center_x, costume.rotation_center_y),
+            bitmap_resolution: costume.bitmap_resolution,
+            size: costume.size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrSound {
+    pub name: Box<str>,
+    pub md5ext: Box<str>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Target {
     is_stage: bool,
+    name: Box<str>,
     variables: TargetVars,
     lists: TargetLists,
     project: Weak<IrProject>,
     procedures: RefCell<BTreeMap<Box<str>, Rc<Proc>>>,
     index: u32,
     costumes: Box<[IrCostume]>,
+    sounds: Box<[IrSound]>,
+    /// the volume that this target starts with (0-100)
+    volume: f64,
+    /// the layer that this target starts on; the stage is always on layer 0, and sprites with
+    /// higher layers are drawn in front of those with lower layers
+    layer_order: i32,
+    /// the rotation style that this target starts with
+    rotation_style: RotationStyle,
+    /// whether this target starts off draggable in the player
+    draggable: bool,
+    clonable: RefCell<bool>,
 }
 
 impl Target {
@@ -29,6 +95,10 @@ impl Target {
         self.is_stage
     }
 
+    pub const fn name(&self) -> &str {
+        &self.name
+    }
+
     pub const fn variables(&self) -> &TargetVars {
         &self.variables
     }
@@ -57,23 +127,75 @@ impl Target {
         &self.costumes
     }
 
+    pub const fn sounds(&self) -> &[IrSound] {
+        &self.sounds
+    }
+
+    pub const fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    pub const fn layer_order(&self) -> i32 {
+        self.layer_order
+    }
+
+    pub const fn rotation_style(&self) -> RotationStyle {
+        self.rotation_style
+    }
+
+    pub const fn draggable(&self) -> bool {
+        self.draggable
+    }
+
+    /// Whether a `create clone of` block anywhere in the project can create a clone of this target
+    pub fn is_clonable(&self) -> HQResult<bool> {
+        Ok(*self.clonable.try_borrow()?)
+    }
+
+    /// Marks this target as one that may be cloned.
+    ///
+    /// Clones get their own copies of the target's lists, which are swapped in and out of the
+    /// list's global, so all of this target's lists are marked as having a mutable length.
+    ///
+    /// This MUST not be called once the `IrProject` is emitted, i.e. once optimisation has begun
+    pub fn mark_clonable(&self) -> HQResult<()> {
+        *self.clonable.try_borrow_mut()? = true;
+        for list in self.lists.values() {
+            *list.list.length_mutable().try_borrow_mut()? = true;
+        }
+        Ok(())
+    }
+
     pub const fn new(
         is_stage: bool,
+        name: Box<str>,
         variables: TargetVars,
         lists: TargetLists,
         project: Weak<IrProject>,
         procedures: RefCell<BTreeMap<Box<str>, Rc<Proc>>>,
         index: u32,
         costumes: Box<[IrCostume]>,
+        sounds: Box<[IrSound]>,
+        volume: f64,
+        layer_order: i32,
+        rotation_style: RotationStyle,
+        draggable: bool,
     ) -> Self {
         Self {
             is_stage,
+            name,
             variables,
             lists,
             project,
             procedures,
             index,
             costumes,
+            sounds,
+            volume,
+            layer_order,
+            rotation_style,
+            draggable,
+            clonable: RefCell::new(false),
         }
     }
 }
@@ -82,6 +204,10 @@ impl fmt::Display for Target {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let is_stage = self.is_stage;
         let index = self.index;
+        let clonable = *self.clonable.borrow();
+        let layer_order = self.layer_order;
+        let rotation_style = self.rotation_style;
+        let draggable = self.draggable;
         let variables = self
             .variables
             .iter()
@@ -103,6 +229,10 @@ impl fmt::Display for Target {
             r#"{{
         "is_stage": {is_stage},
         "index": {index},
+        "clonable": {clonable},
+        "layer_order": {layer_order},
+        "rotation_style": "{rotation_style}",
+        "draggable": {draggable},
         "variables": {{ {variables} }},
         "lists": {{ {lists} }},
         "procedures": {{ {procedures} }}
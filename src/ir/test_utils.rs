@@ -0,0 +1,52 @@
+//! Project fixtures shared between the tests of the IR modules.
+
+use crate::sb3::Sb3Project;
+
+/// A block with no inputs or fields, as it would appear in a `project.json`.
+pub fn block(opcode: &str, next: Option<&str>, top_level: bool) -> String {
+    format!(
+        r#"{{
+            "opcode": "{opcode}",
+            "next": {},
+            "parent": null,
+            "inputs": {{}},
+            "fields": {{}},
+            "shadow": false,
+            "topLevel": {top_level},
+            "x": 0,
+            "y": 0
+        }}"#,
+        next.map_or_else(|| "null".into(), |next| format!(r#""{next}""#))
+    )
+}
+
+/// A project consisting of just a stage, with the given blocks (a JSON object of blocks by id)
+/// and extensions.
+pub fn stage_with_blocks(blocks: &str, extensions: &[&str]) -> Sb3Project {
+    let extensions = serde_json::to_string(extensions).unwrap();
+    Sb3Project::try_from(
+        format!(
+            r#"{{
+                "targets": [{{
+                    "isStage": true,
+                    "name": "Stage",
+                    "variables": {{}},
+                    "lists": {{}},
+                    "broadcasts": {{}},
+                    "blocks": {blocks},
+                    "comments": {{}},
+                    "currentCostume": 0,
+                    "costumes": [],
+                    "sounds": [],
+                    "volume": 100,
+                    "layerOrder": 0
+                }}],
+                "monitors": [],
+                "extensions": {extensions},
+                "meta": {{ "semver": "3.0.0", "vm": "0.2.0", "agent": "" }}
+            }}"#
+        )
+        .as_str(),
+    )
+    .unwrap()
+}
@@ -1,11 +1,35 @@
+use serde::Serialize;
+
 use super::blocks::NextBlocks;
-use super::{Event, IrProject, Step, StepContext, Target};
+use super::{Event, EventThreshold, GreaterThanMenu, IrProject, Step, StepContext, Target};
 use crate::ir::StepIndex;
 use crate::prelude::*;
 use crate::sb3;
-use crate::sb3::{Block, BlockMap, BlockOpcode, VarVal};
+use crate::sb3::{Block, BlockArray, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, VarVal};
 use crate::wasm::WasmFlags;
 
+/// Why a supported hat block can never start the script under it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum InertHatReason {
+    /// the hat can't be triggered for the target that it belongs to, e.g. `when I start as a
+    /// clone` on the stage
+    WrongTarget,
+    /// the backdrop which the hat waits for doesn't exist
+    MissingBackdrop,
+    /// the hat's input isn't constant, so would have to be re-evaluated on every tick, which
+    /// isn't supported
+    NonConstantInput,
+}
+
+impl InertHatReason {
+    /// Whether the hat could start its script in Scratch, i.e. whether this is a limitation of
+    /// `HyperQuark` rather than of the project.
+    #[must_use]
+    pub const fn fires_in_scratch(self) -> bool {
+        matches!(self, Self::NonConstantInput)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Thread {
     event: Event,
@@ -21,8 +45,60 @@ impl Thread {
         self.first_step
     }
 
+    /// Whether a block with this opcode can start a thread (i.e. is a supported hat block).
+    pub const fn is_hat(opcode: &BlockOpcode) -> bool {
+        matches!(
+            opcode,
+            BlockOpcode::event_whenflagclicked
+                | BlockOpcode::event_whenbroadcastreceived
+                | BlockOpcode::event_whenthisspriteclicked
+                | BlockOpcode::event_whenstageclicked
+                | BlockOpcode::control_start_as_clone
+                | BlockOpcode::event_whenkeypressed
+                | BlockOpcode::event_whenbackdropswitchesto
+                | BlockOpcode::event_whengreaterthan
+                | BlockOpcode::event_whentouchingobject
+        )
+    }
+
+    /// Why a supported hat block (see [`Self::is_hat`]) can never start its script, or `None`
+    /// if it can. `has_backdrop` should return whether the project has a backdrop with the given
+    /// name.
+    pub fn inert_hat_reason(
+        block_info: &BlockInfo,
+        blocks: &BlockMap,
+        is_stage: bool,
+        has_backdrop: impl Fn(&str) -> bool,
+    ) -> HQResult<Option<InertHatReason>> {
+        #[expect(clippy::wildcard_enum_match_arm, reason = "too many variants to match")]
+        Ok(match block_info.opcode {
+            // the stage can't be cloned, or touch anything
+            BlockOpcode::control_start_as_clone | BlockOpcode::event_whentouchingobject
+                if is_stage =>
+            {
+                Some(InertHatReason::WrongTarget)
+            }
+            BlockOpcode::event_whenbackdropswitchesto
+                if !has_backdrop(&string_field(block_info, "BACKDROP")?) =>
+            {
+                Some(InertHatReason::MissingBackdrop)
+            }
+            BlockOpcode::event_whengreaterthan if greater_than_threshold(block_info).is_none() => {
+                Some(InertHatReason::NonConstantInput)
+            }
+            BlockOpcode::event_whentouchingobject
+                if touching_object_menu(block_info, blocks)?.opcode
+                    != BlockOpcode::event_touchingobjectmenu =>
+            {
+                Some(InertHatReason::NonConstantInput)
+            }
+            _ => None,
+        })
+    }
+
     /// tries to construct a thread from a top-level block.
-    /// Returns Ok(None) if the top-level block is not a valid event or if there is no next block.
+    /// Returns Ok(None) if the top-level block is not a valid event, if it can never be
+    /// triggered (see [`Self::inert_hat_reason`]) or if there is no next block.
     pub fn try_from_top_block(
         block: &Block,
         blocks: &BlockMap,
@@ -34,6 +110,27 @@ impl Thread {
         let Some(block_info) = block.block_info() else {
             return Ok(None);
         };
+        let backdrops = project
+            .upgrade()
+            .ok_or_else(|| make_hq_bug!("couldn't upgrade Weak<IrProject>"))?
+            .backdrops()
+            .iter()
+            .map(|costume| costume.name.clone())
+            .collect::<Vec<_>>();
+        if let Some(reason) =
+            Self::inert_hat_reason(block_info, blocks, target.is_stage(), |name| {
+                backdrops.iter().any(|backdrop| **backdrop == *name)
+            })?
+        {
+            if reason.fires_in_scratch() {
+                crate::warn!(
+                    "skipping `{:?}` script in target '{}' as its input isn't constant",
+                    block_info.opcode,
+                    target.name()
+                );
+            }
+            return Ok(None);
+        }
         #[expect(clippy::wildcard_enum_match_arm, reason = "too many variants to match")]
         let event = match block_info.opcode {
             BlockOpcode::event_whenflagclicked => Event::FlagClicked,
@@ -60,12 +157,41 @@ impl Thread {
             BlockOpcode::event_whenthisspriteclicked | BlockOpcode::event_whenstageclicked => {
                 Event::SpriteClicked(target.index())
             }
-            BlockOpcode::event_whenbackdropswitchesto
-            | BlockOpcode::event_whengreaterthan
-            | BlockOpcode::event_whenkeypressed
-            | BlockOpcode::event_whentouchingobject => {
-                hq_todo!("unimplemented event {:?}", block_info.opcode)
+            BlockOpcode::control_start_as_clone => Event::CloneStart(target.index()),
+            BlockOpcode::event_whenkeypressed => Event::KeyPressed(
+                string_field(block_info, "KEY_OPTION")?
+                    .to_lowercase()
+                    .into(),
+            ),
+            BlockOpcode::event_whenbackdropswitchesto => {
+                let backdrop_name = string_field(block_info, "BACKDROP")?;
+                let (backdrop_index, _) = backdrops
+                    .iter()
+                    .find_position(|name| **name == *backdrop_name)
+                    .ok_or_else(|| make_hq_bug!("backdrop should exist"))?;
+                Event::BackdropSwitchesTo(
+                    backdrop_index
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("backdrop index out of bounds"))?,
+                )
+            }
+            BlockOpcode::event_whengreaterthan => {
+                let menu = match &*string_field(block_info, "WHENGREATERTHANMENU")?.to_lowercase() {
+                    "loudness" => GreaterThanMenu::Loudness,
+                    "timer" => GreaterThanMenu::Timer,
+                    _ => hq_bad_proj!("invalid value for WHENGREATERTHANMENU field"),
+                };
+                let value = greater_than_threshold(block_info)
+                    .ok_or_else(|| make_hq_bug!("greater than threshold should be constant"))?;
+                Event::GreaterThan(menu, EventThreshold(value))
             }
+            BlockOpcode::event_whentouchingobject => Event::TouchingObject(
+                target.index(),
+                string_field(
+                    touching_object_menu(block_info, blocks)?,
+                    "TOUCHINGOBJECTMENU",
+                )?,
+            ),
             _ => return Ok(None),
         };
         let Some(next_id) = &block_info.next else {
@@ -82,6 +208,7 @@ impl Thread {
                 target: Rc::clone(target),
                 proc_context: None,
                 warp: false, // steps from top blocks are never warped
+                warp_timer: false,
                 debug,
             },
             project,
@@ -92,6 +219,57 @@ impl Thread {
     }
 }
 
+/// The threshold of an `event_whengreaterthan` hat block, or `None` if its `VALUE` input isn't
+/// a constant. Non-constant thresholds would have to be re-evaluated on every tick, which isn't
+/// supported, so scripts under these hats are skipped.
+pub fn greater_than_threshold(block_info: &BlockInfo) -> Option<f64> {
+    match block_info.inputs.get("VALUE") {
+        Some(
+            sb3::Input::Shadow(_, Some(BlockArrayOrId::Array(value)), _)
+            | sb3::Input::NoShadow(_, Some(BlockArrayOrId::Array(value))),
+        ) => match value {
+            BlockArray::NumberOrAngle(_, value) => Some(*value),
+            BlockArray::ColorOrString(_, value) => Some(value.trim().parse::<f64>().unwrap_or(0.0)),
+            BlockArray::Broadcast(..) | BlockArray::VariableOrList(..) => None,
+        },
+        None | Some(sb3::Input::Shadow(_, None, _) | sb3::Input::NoShadow(_, None)) => Some(0.0),
+        Some(_) => None,
+    }
+}
+
+/// The block in the `TOUCHINGOBJECTMENU` input of an `event_whentouchingobject` hat block. This is
+/// only a menu if the input is constant.
+fn touching_object_menu<'a>(
+    block_info: &BlockInfo,
+    blocks: &'a BlockMap,
+) -> HQResult<&'a BlockInfo> {
+    let Some(
+        sb3::Input::Shadow(_, Some(BlockArrayOrId::Id(menu_id)), _)
+        | sb3::Input::NoShadow(_, Some(BlockArrayOrId::Id(menu_id))),
+    ) = block_info.inputs.get("TOUCHINGOBJECTMENU")
+    else {
+        hq_bad_proj!("invalid project.json - missing input TOUCHINGOBJECTMENU")
+    };
+    blocks
+        .get(menu_id)
+        .ok_or_else(|| make_hq_bad_proj!("block for input TOUCHINGOBJECTMENU doesn't exist"))?
+        .block_info()
+        .ok_or_else(|| make_hq_bad_proj!("invalid block for input TOUCHINGOBJECTMENU"))
+}
+
+/// Gets the value of a field which should contain a string, e.g. a dropdown menu.
+fn string_field(block_info: &BlockInfo, name: &str) -> HQResult<Box<str>> {
+    let Some(VarVal::String(value)) = block_info
+        .fields
+        .get(name)
+        .ok_or_else(|| make_hq_bad_proj!("invalid project.json - missing field {name}"))?
+        .get_0()
+    else {
+        hq_bad_proj!("invalid project.json - missing or non-string value for {name} field")
+    };
+    Ok(value.clone())
+}
+
 impl fmt::Display for Thread {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let event = self.event();
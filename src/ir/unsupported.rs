@@ -0,0 +1,283 @@
+//! Finds blocks which can't be compiled, so that they can all be reported at once rather than
+//! compilation failing at the first one.
+
+use super::Thread;
+use super::blocks::is_supported_opcode;
+use super::thread::greater_than_threshold;
+use crate::prelude::*;
+use crate::sb3::{Block, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, Input, Sb3Project};
+use crate::{BlockLocation, HQError};
+
+/// Returns an error for each unsupported block in each target, located at that block.
+///
+/// Only blocks in scripts which could actually be run are checked, i.e. blocks under a supported
+/// hat block or in a custom block definition.
+///
+/// Opcodes which are unknown and don't belong to an extension used by the project are reported
+/// as [`HQErrorType::MalformedProject`](crate::HQErrorType::MalformedProject); all others are
+/// reported as [`HQErrorType::Unimplemented`](crate::HQErrorType::Unimplemented).
+#[must_use]
+pub fn unsupported_blocks(sb3: &Sb3Project) -> Vec<HQError> {
+    sb3.targets
+        .iter()
+        .flat_map(|target| {
+            unsupported_blocks_in(&target.blocks)
+                .into_iter()
+                .map(|block| {
+                    unsupported_block_error(block.block_info, &target.name, &sb3.extensions)
+                        .with_location(BlockLocation {
+                            block_id: block.id.into(),
+                            target: target.name.clone(),
+                            proccode: block.proccode.map(Into::into),
+                        })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the errors from [`unsupported_blocks`], if there are any.
+pub fn check_unsupported_blocks(sb3: &Sb3Project) -> Result<(), Vec<HQError>> {
+    let errors = unsupported_blocks(sb3);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The error to report for an unsupported block in the target with the given name.
+fn unsupported_block_error(
+    block_info: &BlockInfo,
+    target_name: &str,
+    extensions: &[Box<str>],
+) -> HQError {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "too many opcodes to match individually"
+    )]
+    match &block_info.opcode {
+        BlockOpcode::Unknown(name)
+            if extensions
+                .iter()
+                .any(|extension| name.split('_').next() == Some(&**extension)) =>
+        {
+            make_hq_todo!("unsupported extension block `{name}` in target '{target_name}'")
+        }
+        BlockOpcode::Unknown(name) => {
+            make_hq_bad_proj!("unknown block `{name}` in target '{target_name}'")
+        }
+        known => make_hq_todo!("unsupported block `{known:?}` in target '{target_name}'"),
+    }
+}
+
+/// A block which can't be compiled.
+struct UnsupportedBlock<'a> {
+    id: &'a str,
+    block_info: &'a BlockInfo,
+    /// the proccode of the custom block whose definition the block is in, if any
+    proccode: Option<&'a str>,
+}
+
+/// Finds the unsupported blocks used by runnable scripts in a target.
+fn unsupported_blocks_in(blocks: &BlockMap) -> Vec<UnsupportedBlock<'_>> {
+    let mut to_visit: Vec<(&str, Option<&str>)> = vec![];
+    let mut unsupported = vec![];
+    for (id, block) in blocks {
+        let Block::Normal { block_info, .. } = block else {
+            continue;
+        };
+        if !block_info.top_level {
+            continue;
+        }
+        if Thread::is_hat(&block_info.opcode) {
+            // thresholds which aren't constant can't be checked, so scripts under those hats are
+            // skipped (with a warning) and will never be run
+            if block_info.opcode != BlockOpcode::event_whengreaterthan
+                || greater_than_threshold(block_info).is_some()
+            {
+                to_visit.extend(block_info.next.as_deref().map(|next| (next, None)));
+            }
+        } else if block_info.opcode == BlockOpcode::procedures_definition {
+            let proccode = definition_proccode(block_info, blocks);
+            to_visit.extend(block_info.next.as_deref().map(|next| (next, proccode)));
+        } else if matches!(block_info.opcode, BlockOpcode::Unknown(_)) {
+            // probably a hat block from an extension, so the script will never be run
+            unsupported.push(UnsupportedBlock {
+                id,
+                block_info,
+                proccode: None,
+            });
+        }
+    }
+    let mut visited = BTreeSet::new();
+    while let Some((id, proccode)) = to_visit.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Some(Block::Normal { block_info, .. }) = blocks.get(id) else {
+            continue;
+        };
+        // menus are compiled as part of their parent block
+        if block_info.shadow {
+            continue;
+        }
+        if !is_supported_opcode(&block_info.opcode) {
+            unsupported.push(UnsupportedBlock {
+                id,
+                block_info,
+                proccode,
+            });
+        }
+        for input in block_info.inputs.values() {
+            if let Input::Shadow(_, Some(BlockArrayOrId::Id(input_id)), _)
+            | Input::NoShadow(_, Some(BlockArrayOrId::Id(input_id))) = input
+            {
+                to_visit.push((input_id, proccode));
+            }
+        }
+        to_visit.extend(block_info.next.as_deref().map(|next| (next, proccode)));
+    }
+    unsupported
+}
+
+/// The proccode of the custom block defined by a `procedures_definition` block, if it can be
+/// found.
+fn definition_proccode<'a>(block_info: &'a BlockInfo, blocks: &'a BlockMap) -> Option<&'a str> {
+    let (Input::Shadow(_, Some(BlockArrayOrId::Id(prototype_id)), _)
+    | Input::NoShadow(_, Some(BlockArrayOrId::Id(prototype_id)))) =
+        block_info.inputs.get("custom_block")?
+    else {
+        return None;
+    };
+    blocks
+        .get(prototype_id)?
+        .block_info()?
+        .mutation
+        .mutations
+        .get("proccode")?
+        .as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HQErrorType;
+    use crate::ir::test_utils::{block, stage_with_blocks};
+
+    #[test]
+    fn unknown_opcodes_are_deserialized() {
+        let project = stage_with_blocks(
+            &format!(
+                r#"{{ "a": {} }}"#,
+                block("music_playDrumForBeats", None, true)
+            ),
+            &["music"],
+        );
+        let Some(Block::Normal { block_info, .. }) = project.targets[0].blocks.get("a") else {
+            panic!("block should exist");
+        };
+        assert_eq!(
+            block_info.opcode,
+            BlockOpcode::Unknown("music_playDrumForBeats".into())
+        );
+    }
+
+    fn located_at(err: &HQError) -> &str {
+        &err.location.as_ref().unwrap().block_id
+    }
+
+    #[test]
+    fn unsupported_blocks_are_reported() {
+        let project = stage_with_blocks(
+            &format!(
+                r#"{{
+                    "hat": {},
+                    "a": {},
+                    "b": {},
+                    "c": {},
+                    "d": {},
+                    "orphan": {}
+                }}"#,
+                block("event_whenflagclicked", Some("a"), true),
+                block("music_playDrumForBeats", Some("b"), false),
+                block("music_playDrumForBeats", Some("c"), false),
+                block("foo_bar", Some("d"), false),
+                block("sensing_username", None, false),
+                block("sound_play", None, true),
+            ),
+            &["music"],
+        );
+        let errors = unsupported_blocks(&project);
+        assert_eq!(errors.len(), 4);
+        let mut music_locations = errors
+            .iter()
+            .filter(|err| {
+                err.err_type == HQErrorType::Unimplemented
+                    && err.msg.contains("music_playDrumForBeats")
+            })
+            .map(located_at)
+            .collect::<Vec<_>>();
+        music_locations.sort_unstable();
+        assert_eq!(music_locations, ["a", "b"]);
+        assert!(
+            errors
+                .iter()
+                .any(|err| err.err_type == HQErrorType::MalformedProject
+                    && err.msg.contains("foo_bar")
+                    && located_at(err) == "c")
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|err| err.err_type == HQErrorType::Unimplemented
+                    && err.msg.contains("sensing_username")
+                    && located_at(err) == "d")
+        );
+        assert!(
+            errors
+                .iter()
+                .all(|err| &*err.location.as_ref().unwrap().target == "Stage")
+        );
+        assert!(!errors.iter().any(|err| err.msg.contains("sound_play")));
+    }
+
+    fn greater_than_hat(value: &str, next: &str) -> String {
+        format!(
+            r#"{{
+                "opcode": "event_whengreaterthan",
+                "next": "{next}",
+                "parent": null,
+                "inputs": {{ "VALUE": {value} }},
+                "fields": {{ "WHENGREATERTHANMENU": ["TIMER", null] }},
+                "shadow": false,
+                "topLevel": true,
+                "x": 0,
+                "y": 0
+            }}"#
+        )
+    }
+
+    #[test]
+    fn scripts_under_greater_than_hats_with_non_constant_values_are_skipped() {
+        let project = stage_with_blocks(
+            &format!(
+                r#"{{
+                    "constant": {},
+                    "a": {},
+                    "dynamic": {},
+                    "timer": {},
+                    "b": {}
+                }}"#,
+                greater_than_hat(r#"[1, [4, "10"]]"#, "a"),
+                block("looks_show", None, false),
+                greater_than_hat(r#"[3, "timer", [4, "10"]]"#, "b"),
+                block("sensing_timer", None, false),
+                block("sensing_username", None, false),
+            ),
+            &["music"],
+        );
+        // the script under the non-constant hat is never run, so its blocks aren't checked
+        assert!(unsupported_blocks(&project).is_empty());
+    }
+}
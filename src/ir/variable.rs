@@ -107,6 +107,8 @@ impl Hash for RcVar {
 
 #[derive(Debug)]
 pub struct TargetVar {
+    /// the name of the variable as shown in the editor
+    pub name: Box<str>,
     pub var: RcVar,
     /// this MUST not be modified once the `IrProject` is emitted, i.e. once optimisation has begun
     pub is_used: RefCell<bool>,
@@ -166,6 +168,8 @@ pub fn variables_from_target(
             Ok((
                 id.clone(),
                 Rc::new(TargetVar {
+                    #[expect(clippy::unwrap_used, reason = "this field exists on all variants")]
+                    name: var_info.get_0().unwrap().clone(),
                     var: RcVar::new(
                         #[expect(clippy::unwrap_used, reason = "field present in all variants")]
                         var_val_type(var_info.get_1().unwrap())?,
@@ -214,6 +218,21 @@ pub fn used_vars(vars: &TargetVars) -> Box<[RcVar]> {
         .collect()
 }
 
+/// A list of lists in a target that are used somewhere (whether read or written to)
+#[must_use]
+pub fn used_lists(lists: &TargetLists) -> Box<[RcList]> {
+    lists
+        .values()
+        .filter_map(|list| {
+            if *list.is_used.borrow() {
+                Some(list.list.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 impl fmt::Display for RcVar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let possible_types = self.0.possible_types.borrow();
@@ -59,13 +59,14 @@ use wasm_bindgen::prelude::*;
 pub mod error;
 pub mod ir;
 pub mod optimisation;
+pub mod sb2;
 pub mod sb3;
 pub mod wasm;
 #[macro_use]
 pub mod instructions;
 
 #[doc(inline)]
-pub use error::{HQError, HQErrorType, HQResult};
+pub use error::{BlockLocation, HQError, HQErrorType, HQResult};
 
 pub mod registry;
 
@@ -134,11 +135,44 @@ macro_rules! warn {
 #[cfg(feature = "compiler")]
 #[wasm_bindgen]
 pub fn sb3_to_wasm(proj: &str, flags: wasm::WasmFlags) -> HQResult<wasm::FinishedWasm> {
+    sb3_project_to_wasm(&sb3::Sb3Project::try_from(proj)?, flags)
+}
+
+/// Compiles a project from the raw bytes of a `.sb3` file.
+#[cfg(feature = "compiler")]
+#[wasm_bindgen]
+pub fn sb3_archive_to_wasm(archive: &[u8], flags: wasm::WasmFlags) -> HQResult<wasm::FinishedWasm> {
+    sb3_project_to_wasm(&sb3::Sb3Archive::try_from(archive)?.project, flags)
+}
+
+/// Analyses how well a project is supported, without compiling it.
+#[cfg(feature = "compiler")]
+#[wasm_bindgen]
+pub fn analyze_project(proj: &str, flags: wasm::WasmFlags) -> HQResult<ir::CompatibilityReport> {
+    ir::CompatibilityReport::analyze(&sb3::Sb3Project::try_from(proj)?, &flags)
+}
+
+/// Checks a project for errors without compiling it, returning as many of the errors that
+/// compiling it would produce as possible, rather than just the first one. See
+/// [`ir::IrProject::check_sb3`].
+#[cfg(feature = "compiler")]
+#[wasm_bindgen]
+pub fn check_sb3(proj: &str, flags: wasm::WasmFlags) -> HQResult<Vec<JsValue>> {
+    Ok(ir::IrProject::check_sb3(&sb3::Sb3Project::try_from(proj)?, &flags)?
+        .into_iter()
+        .map(JsValue::from)
+        .collect())
+}
+
+#[cfg(feature = "compiler")]
+fn sb3_project_to_wasm(
+    sb3_proj: &sb3::Sb3Project,
+    flags: wasm::WasmFlags,
+) -> HQResult<wasm::FinishedWasm> {
     use ir::IrProject;
     use wasm::flags::Switch;
 
-    let sb3_proj = sb3::Sb3Project::try_from(proj)?;
-    let ir_proj = IrProject::try_from_sb3(&sb3_proj, &flags)?;
+    let ir_proj = IrProject::try_from_sb3(sb3_proj, &flags)?;
     if flags.print_ir == Switch::On {
         crate::log("ir (before optimisation):");
         crate::log(format!("{ir_proj}").as_str());
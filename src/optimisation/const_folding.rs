@@ -29,6 +29,17 @@ impl ConstFoldItem {
         })
     }
 
+    /// The item that is left on the stack once the opcodes of this item have been emitted.
+    fn emitted(&self) -> HQResult<Self> {
+        Ok(match self {
+            Self::Stack(_) => Self::Stack(Rc::from([])),
+            Self::Basic(_) | Self::Boxed(..) | Self::Unknown { .. } => Self::Unknown {
+                possible_types: self.possible_types()?,
+                opcodes: Rc::from([]),
+            },
+        })
+    }
+
     #[must_use]
     pub fn to_opcodes(&self) -> Rc<[IrOpcode]> {
         match self {
@@ -181,6 +192,22 @@ where
                     }
                     new_opcodes.push(opcode.clone());
                 }
+                // reporters which depend on the frame state must be evaluated in order, so
+                // they (and everything beneath them on the stack) can't be deferred
+                ReturnType::Singleton(out_ty) if opcode.depends_on_frame_state() => {
+                    for const_item in &mut const_stack {
+                        new_opcodes.extend(const_item.to_opcodes().iter().cloned());
+                        *const_item = const_item.emitted()?;
+                    }
+                    for const_item in const_inputs {
+                        new_opcodes.extend(const_item.to_opcodes().iter().cloned());
+                    }
+                    new_opcodes.push(opcode.clone());
+                    const_stack.push(ConstFoldItem::Unknown {
+                        possible_types: out_ty,
+                        opcodes: Rc::from([]),
+                    });
+                }
                 ReturnType::Singleton(out_ty) => const_stack.push(ConstFoldItem::Unknown {
                     possible_types: out_ty,
                     opcodes: const_inputs
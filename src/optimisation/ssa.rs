@@ -180,10 +180,12 @@ fn visit_procedure(
             do_ssa,
         )?;
     }
-    if let Some(nonwarped_specific_proc) = &*proc.nonwarped_specific_proc()
-        && let PartialStep::Finished(step_index) = nonwarped_specific_proc.first_step()?.clone()
-    {
-        visit_step_recursively(step_index, project, graphs, &BTreeMap::new(), do_ssa)?;
+    for warp_timer in [false, true] {
+        if let Some(nonwarped_specific_proc) = &*proc.nonwarped_specific_proc_for(warp_timer)
+            && let PartialStep::Finished(step_index) = nonwarped_specific_proc.first_step()?.clone()
+        {
+            visit_step_recursively(step_index, project, graphs, &BTreeMap::new(), do_ssa)?;
+        }
     }
     Ok(())
 }
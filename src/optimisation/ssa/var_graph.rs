@@ -442,8 +442,11 @@ impl VarGraph {
                 IrOpcode::procedures_call_nonwarp(ProceduresCallNonwarpFields {
                     proc,
                     next_step,
+                    warp_timer,
                 }) => {
-                    let Some(nonwarped_specific_proc) = &*proc.nonwarped_specific_proc() else {
+                    let Some(nonwarped_specific_proc) =
+                        &*proc.nonwarped_specific_proc_for(*warp_timer)
+                    else {
                         hq_bug!("tried to call_nonwarp with no non-warped proc")
                     };
                     for arg_var in nonwarped_specific_proc
@@ -481,8 +484,17 @@ impl VarGraph {
                         step_ended_on_stop = true;
                         break 'opcode_loop;
                     }
-                    YieldMode::Schedule(_) => (), // handled at bottom of loop
+                    // handled at bottom of loop
+                    YieldMode::Schedule(_)
+                    | YieldMode::MaybeSchedule(_)
+                    | YieldMode::WarpTimer(_) => (),
                 },
+                IrOpcode::control_create_clone_of(_) => {
+                    // the new clone copies its variables from their globals, so these need to be
+                    // up to date
+                    additional_opcodes.push((i, self.write_back_ssa(variable_maps)));
+                    self.add_node_at_end(Some(StackOperation::Opcode(opcode.clone())));
+                }
                 _ => {
                     let opcode_node = self.add_node(Some(StackOperation::Opcode(opcode.clone())));
                     let last_node = *self.exit_node().borrow();
@@ -847,6 +859,18 @@ impl VarGraph {
         } else {
             None
         };
+        opcodes.extend(self.write_back_ssa(variable_maps));
+        if post_yield {
+            #[expect(clippy::unwrap_used, reason = "guaranteed to be Some")]
+            opcodes.push(yield_op.unwrap());
+        }
+        Ok(())
+    }
+
+    /// Writes the current SSA value of each variable back to its global, returning the opcodes
+    /// to do so
+    fn write_back_ssa(&self, variable_maps: &VariableMaps) -> Vec<IrOpcode> {
+        let mut opcodes = vec![];
         for (global_var, ssa_var) in &variable_maps.ssa {
             let push_node =
                 self.add_node(Some(StackOperation::Push(VarTarget::Var(ssa_var.clone()))));
@@ -869,11 +893,7 @@ impl VarGraph {
                 }),
             ]);
         }
-        if post_yield {
-            #[expect(clippy::unwrap_used, reason = "guaranteed to be Some")]
-            opcodes.push(yield_op.unwrap());
-        }
-        Ok(())
+        opcodes
     }
 
     /// Insert an SSA "phi function"; that is, propagate any new SSAs to the outer scope,
@@ -115,9 +115,39 @@ pub trait Registry: Sized + RegistryType {
         .map_err(|_| make_hq_bug!("registry item index out of bounds"))
     }
 
-    // TODO: register_override_ifexists or similar - for things like mark_waiting_flag,
-    // which need to be overriden if they are registered, but don't actually need to be
-    // registered always.
+    /// get the index of the specified item, without registering it if it doesn't exist yet.
+    fn index_of<N>(&self, key: &Self::Key) -> HQResult<Option<N>>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.registry()
+            .try_borrow()?
+            .get_index_of(key)
+            .map(|index| {
+                N::try_from(index).map_err(|_| make_hq_bug!("registry item index out of bounds"))
+            })
+            .transpose()
+    }
+
+    /// Overrides the value of the specified item, but only if it has already been registered.
+    /// This is for things which need to be overriden if they are used, but which shouldn't
+    /// be included otherwise.
+    fn register_override_if_exists<N>(
+        &self,
+        key: Self::Key,
+        value: Self::Value,
+    ) -> HQResult<Option<N>>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        if self.registry().try_borrow()?.contains_key(&key) {
+            self.register_override(key, value).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 pub trait RegistryDefault: Registry<Value: Default> {
@@ -241,6 +271,17 @@ where
         self.0.register(R::name::<T>(), T::VALUE)
     }
 
+    /// Gets the index of a `NamedRegistryItem` if it has already been registered, without
+    /// registering it otherwise
+    pub fn index_of<T, N>(&self) -> HQResult<Option<N>>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+        T: NamedRegistryItem<R::Value>,
+    {
+        self.0.index_of(&R::name::<T>())
+    }
+
     /// Registers a runtime key-value pair; just calls `register` on the underlying
     /// `Registry`
     pub fn register_dyn<N>(&self, key: R::Key, value: R::Value) -> HQResult<N>
@@ -273,6 +314,19 @@ where
             .register_override(R::name::<T>(), T::r#override(override_arg))
     }
 
+    /// Overrides a `NamedRegistryItem` entry using the override argument types
+    /// associated with the corresponding `NamedRegistryItemOverride`, but only if it has
+    /// already been registered
+    pub fn register_override_if_exists<T, N, A>(&self, override_arg: A) -> HQResult<Option<N>>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+        T: NamedRegistryItem<R::Value> + NamedRegistryItemOverride<R::Value, A>,
+    {
+        self.0
+            .register_override_if_exists(R::name::<T>(), T::r#override(override_arg))
+    }
+
     /// Tries to override a `NamedRegistryItem` entry using the override argument
     /// types associated with the corresponding `TryNamedRegistryItemOverride`
     pub fn try_register_override<T, N, A>(&self, override_arg: A) -> HQResult<N>
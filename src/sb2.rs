@@ -0,0 +1,1498 @@
+//! Representation of Scratch 2.0 (`sb2`) `project.json` files, and their conversion into the
+//! [`sb3`](crate::sb3) representation so that they can be compiled by the normal pipeline.
+//!
+//! The conversion follows that of scratch-vm's `sb2` deserializer: opcodes are renamed,
+//! arguments are turned into inputs and fields, and custom block definitions and calls have their
+//! mutations synthesised. Blocks without an sb3 equivalent are converted into
+//! [`BlockOpcode::Unknown`] blocks, so that they can be reported as unsupported.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::prelude::*;
+use crate::sb3::{
+    Block, BlockArray, BlockArrayOrId, BlockInfo, BlockMap, BlockOpcode, Costume,
+    CostumeDataFormat, Field, Input, Meta, Mutation, Sb3Project, Sound, Target, VarVal,
+    VariableInfo,
+};
+
+/// A sprite or the stage in a Scratch 2.0 project
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sb2Object {
+    pub obj_name: Box<str>,
+    /// each script is `[x, y, [block, ...]]`, where each block is `[opcode, arg, ...]`
+    #[serde(default)]
+    pub scripts: Vec<(f64, f64, Vec<Value>)>,
+    #[serde(default)]
+    pub variables: Vec<Sb2Variable>,
+    #[serde(default)]
+    pub lists: Vec<Sb2List>,
+    #[serde(default)]
+    pub sounds: Vec<Sb2Sound>,
+    #[serde(default)]
+    pub costumes: Vec<Sb2Costume>,
+    #[serde(default)]
+    pub current_costume_index: f64,
+    /// sprites, as well as variable and list watchers; only present on the stage
+    #[serde(default)]
+    pub children: Vec<Value>,
+    #[serde(default)]
+    pub scratch_x: f64,
+    #[serde(default)]
+    pub scratch_y: f64,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default = "default_direction")]
+    pub direction: f64,
+    #[serde(default)]
+    pub rotation_style: Option<Box<str>>,
+    #[serde(default)]
+    pub is_draggable: bool,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    #[serde(default, rename = "tempoBPM")]
+    pub tempo_bpm: Option<f64>,
+}
+
+const fn default_scale() -> f64 {
+    1.0
+}
+
+const fn default_direction() -> f64 {
+    90.0
+}
+
+const fn default_visible() -> bool {
+    true
+}
+
+/// A variable in a Scratch 2.0 project
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sb2Variable {
+    pub name: Box<str>,
+    pub value: Value,
+    #[serde(default)]
+    pub is_persistent: bool,
+}
+
+/// A list in a Scratch 2.0 project
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sb2List {
+    pub list_name: Box<str>,
+    #[serde(default)]
+    pub contents: Vec<Value>,
+}
+
+/// A costume in a Scratch 2.0 project
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sb2Costume {
+    pub costume_name: Box<str>,
+    #[serde(rename = "baseLayerID", default)]
+    pub base_layer_id: i64,
+    #[serde(rename = "baseLayerMD5")]
+    pub base_layer_md5: Box<str>,
+    #[serde(default)]
+    pub bitmap_resolution: Option<f64>,
+    #[serde(default)]
+    pub rotation_center_x: f64,
+    #[serde(default)]
+    pub rotation_center_y: f64,
+}
+
+/// A sound in a Scratch 2.0 project
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sb2Sound {
+    pub sound_name: Box<str>,
+    #[serde(rename = "soundID", default)]
+    pub sound_id: i64,
+    pub md5: Box<str>,
+    #[serde(default)]
+    pub sample_count: f64,
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default)]
+    pub format: Option<Box<str>>,
+}
+
+/// A Scratch 2.0 project, i.e. its stage (which contains its sprites)
+#[derive(Deserialize, Debug, Clone)]
+pub struct Sb2Project(pub Sb2Object);
+
+impl TryFrom<&str> for Sb2Project {
+    type Error = HQError;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(string).map_err(|err| {
+            make_hq_bad_proj!(
+                "Invalid sb2 project.json at project.json:{}:{}. Actual error: {}",
+                err.line(),
+                err.column(),
+                err
+            )
+        })
+    }
+}
+
+impl Sb2Project {
+    /// The sprites in this project, in layer order (backmost first).
+    pub fn sprites(&self) -> HQResult<Vec<Sb2Object>> {
+        self.0
+            .children
+            .iter()
+            .filter(|child| child.get("objName").is_some() && child.get("costumes").is_some())
+            .map(|child| {
+                Sb2Object::deserialize(child)
+                    .map_err(|err| make_hq_bad_proj!("Invalid sprite in sb2 project: {err}"))
+            })
+            .collect()
+    }
+
+    /// Maps the file names that assets are stored under in an `sb2` archive (e.g. `3.png`) to
+    /// the `md5ext` that they are referred to by in the converted project.
+    pub fn asset_files(&self) -> HQResult<BTreeMap<Box<str>, Box<str>>> {
+        let mut files = BTreeMap::new();
+        for object in core::iter::once(self.0.clone()).chain(self.sprites()?) {
+            for costume in &object.costumes {
+                files.insert(
+                    format!(
+                        "{}.{}",
+                        costume.base_layer_id,
+                        extension(&costume.base_layer_md5)
+                    )
+                    .into(),
+                    costume.base_layer_md5.clone(),
+                );
+            }
+            for sound in &object.sounds {
+                files.insert(
+                    format!("{}.{}", sound.sound_id, extension(&sound.md5)).into(),
+                    sound.md5.clone(),
+                );
+            }
+        }
+        Ok(files)
+    }
+}
+
+fn extension(md5ext: &str) -> &str {
+    md5ext.rsplit_once('.').map_or("", |(_, ext)| ext)
+}
+
+fn asset_id(md5ext: &str) -> &str {
+    md5ext.rsplit_once('.').map_or(md5ext, |(id, _)| id)
+}
+
+/// How an argument of an sb2 block is converted
+#[derive(Clone, Copy)]
+enum Arg {
+    /// a number input with the given shadow type
+    Number(&'static str, u32),
+    Text(&'static str),
+    Color(&'static str),
+    Boolean(&'static str),
+    Substack(&'static str),
+    /// an input whose shadow is a menu block (with the given opcode and field name)
+    Menu(&'static str, &'static str, &'static str),
+    /// a broadcast input, whose shadow is an `event_broadcast_menu`
+    BroadcastInput,
+    Field(&'static str),
+    /// a field whose sb3 value is the uppercase version of the sb2 value
+    UppercaseField(&'static str),
+    /// the `CURRENTMENU` field of `sensing_current` (see [`current_menu_value`])
+    CurrentMenu,
+    BroadcastField,
+    Variable,
+    List,
+    /// a field which is always given the specified value; this doesn't consume an argument
+    Fixed(&'static str, &'static str),
+}
+
+use Arg::{
+    Boolean, BroadcastField, BroadcastInput, Color, CurrentMenu, Field as FieldArg, Fixed, List,
+    Menu, Number, Substack, Text, UppercaseField, Variable,
+};
+
+/// Maps an sb2 opcode to its sb3 opcode and how its arguments are converted.
+///
+/// Custom blocks (`procDef`, `call` and `getParam`) aren't included here as they need special
+/// handling.
+fn block_spec(opcode: &str) -> Option<(&'static str, &'static [Arg])> {
+    Some(match opcode {
+        // motion
+        "forward:" => ("motion_movesteps", &[Number("STEPS", 4)]),
+        "turnRight:" => ("motion_turnright", &[Number("DEGREES", 4)]),
+        "turnLeft:" => ("motion_turnleft", &[Number("DEGREES", 4)]),
+        "heading:" => ("motion_pointindirection", &[Number("DIRECTION", 8)]),
+        "pointTowards:" => (
+            "motion_pointtowards",
+            &[Menu("TOWARDS", "motion_pointtowards_menu", "TOWARDS")],
+        ),
+        "gotoX:y:" => ("motion_gotoxy", &[Number("X", 4), Number("Y", 4)]),
+        "gotoSpriteOrMouse:" => ("motion_goto", &[Menu("TO", "motion_goto_menu", "TO")]),
+        "glideSecs:toX:y:elapsed:from:" => (
+            "motion_glidesecstoxy",
+            &[Number("SECS", 4), Number("X", 4), Number("Y", 4)],
+        ),
+        "changeXposBy:" => ("motion_changexby", &[Number("DX", 4)]),
+        "xpos:" => ("motion_setx", &[Number("X", 4)]),
+        "changeYposBy:" => ("motion_changeyby", &[Number("DY", 4)]),
+        "ypos:" => ("motion_sety", &[Number("Y", 4)]),
+        "bounceOffEdge" => ("motion_ifonedgebounce", &[]),
+        "setRotationStyle" => ("motion_setrotationstyle", &[FieldArg("STYLE")]),
+        "xpos" => ("motion_xposition", &[]),
+        "ypos" => ("motion_yposition", &[]),
+        "heading" => ("motion_direction", &[]),
+        // looks
+        "say:duration:elapsed:from:" => ("looks_sayforsecs", &[Text("MESSAGE"), Number("SECS", 4)]),
+        "say:" => ("looks_say", &[Text("MESSAGE")]),
+        "think:duration:elapsed:from:" => {
+            ("looks_thinkforsecs", &[Text("MESSAGE"), Number("SECS", 4)])
+        }
+        "think:" => ("looks_think", &[Text("MESSAGE")]),
+        "show" => ("looks_show", &[]),
+        "hide" => ("looks_hide", &[]),
+        "lookLike:" => (
+            "looks_switchcostumeto",
+            &[Menu("COSTUME", "looks_costume", "COSTUME")],
+        ),
+        "nextCostume" => ("looks_nextcostume", &[]),
+        "startScene" => (
+            "looks_switchbackdropto",
+            &[Menu("BACKDROP", "looks_backdrops", "BACKDROP")],
+        ),
+        "startSceneAndWait" => (
+            "looks_switchbackdroptoandwait",
+            &[Menu("BACKDROP", "looks_backdrops", "BACKDROP")],
+        ),
+        "nextScene" => ("looks_nextbackdrop", &[]),
+        "changeGraphicEffect:by:" => (
+            "looks_changeeffectby",
+            &[FieldArg("EFFECT"), Number("CHANGE", 4)],
+        ),
+        "setGraphicEffect:to:" => (
+            "looks_seteffectto",
+            &[FieldArg("EFFECT"), Number("VALUE", 4)],
+        ),
+        "filterReset" => ("looks_cleargraphiceffects", &[]),
+        "changeSizeBy:" => ("looks_changesizeby", &[Number("CHANGE", 4)]),
+        "setSizeTo:" => ("looks_setsizeto", &[Number("SIZE", 4)]),
+        "comeToFront" => ("looks_gotofrontback", &[Fixed("FRONT_BACK", "front")]),
+        "goBackByLayers:" => (
+            "looks_goforwardbackwardlayers",
+            &[Fixed("FORWARD_BACKWARD", "backward"), Number("NUM", 7)],
+        ),
+        "costumeIndex" => ("looks_costumenumbername", &[Fixed("NUMBER_NAME", "number")]),
+        "costumeName" => ("looks_costumenumbername", &[Fixed("NUMBER_NAME", "name")]),
+        "sceneName" => ("looks_backdropnumbername", &[Fixed("NUMBER_NAME", "name")]),
+        "backgroundIndex" => (
+            "looks_backdropnumbername",
+            &[Fixed("NUMBER_NAME", "number")],
+        ),
+        "scale" => ("looks_size", &[]),
+        // sound
+        "playSound:" => (
+            "sound_play",
+            &[Menu("SOUND_MENU", "sound_sounds_menu", "SOUND_MENU")],
+        ),
+        "doPlaySoundAndWait" => (
+            "sound_playuntildone",
+            &[Menu("SOUND_MENU", "sound_sounds_menu", "SOUND_MENU")],
+        ),
+        "stopAllSounds" => ("sound_stopallsounds", &[]),
+        "changeVolumeBy:" => ("sound_changevolumeby", &[Number("VOLUME", 4)]),
+        "setVolumeTo:" => ("sound_setvolumeto", &[Number("VOLUME", 4)]),
+        "volume" => ("sound_volume", &[]),
+        // pen
+        "clearPenTrails" => ("pen_clear", &[]),
+        "stampCostume" => ("pen_stamp", &[]),
+        "putPenDown" => ("pen_penDown", &[]),
+        "putPenUp" => ("pen_penUp", &[]),
+        "penColor:" => ("pen_setPenColorToColor", &[Color("COLOR")]),
+        "changePenHueBy:" => ("pen_changePenHueBy", &[Number("HUE", 4)]),
+        "setPenHueTo:" => ("pen_setPenHueToNumber", &[Number("HUE", 4)]),
+        "changePenShadeBy:" => ("pen_changePenShadeBy", &[Number("SHADE", 4)]),
+        "setPenShadeTo:" => ("pen_setPenShadeToNumber", &[Number("SHADE", 4)]),
+        "changePenSizeBy:" => ("pen_changePenSizeBy", &[Number("SIZE", 4)]),
+        "penSize:" => ("pen_setPenSizeTo", &[Number("SIZE", 4)]),
+        // events
+        "whenGreenFlag" => ("event_whenflagclicked", &[]),
+        "whenKeyPressed" => ("event_whenkeypressed", &[FieldArg("KEY_OPTION")]),
+        "whenClicked" => ("event_whenthisspriteclicked", &[]),
+        "whenSceneStarts" => ("event_whenbackdropswitchesto", &[FieldArg("BACKDROP")]),
+        "whenSensorGreaterThan" => (
+            "event_whengreaterthan",
+            &[UppercaseField("WHENGREATERTHANMENU"), Number("VALUE", 4)],
+        ),
+        "whenIReceive" => ("event_whenbroadcastreceived", &[BroadcastField]),
+        "broadcast:" => ("event_broadcast", &[BroadcastInput]),
+        "doBroadcastAndWait" => ("event_broadcastandwait", &[BroadcastInput]),
+        // control
+        "wait:elapsed:from:" => ("control_wait", &[Number("DURATION", 5)]),
+        "doRepeat" => (
+            "control_repeat",
+            &[Number("TIMES", 6), Substack("SUBSTACK")],
+        ),
+        "doForever" => ("control_forever", &[Substack("SUBSTACK")]),
+        "doIf" => ("control_if", &[Boolean("CONDITION"), Substack("SUBSTACK")]),
+        "doIfElse" => (
+            "control_if_else",
+            &[
+                Boolean("CONDITION"),
+                Substack("SUBSTACK"),
+                Substack("SUBSTACK2"),
+            ],
+        ),
+        "doWaitUntil" => ("control_wait_until", &[Boolean("CONDITION")]),
+        "doUntil" => (
+            "control_repeat_until",
+            &[Boolean("CONDITION"), Substack("SUBSTACK")],
+        ),
+        "doWhile" => (
+            "control_while",
+            &[Boolean("CONDITION"), Substack("SUBSTACK")],
+        ),
+        "doForLoop" => (
+            "control_for_each",
+            &[Variable, Number("VALUE", 6), Substack("SUBSTACK")],
+        ),
+        "stopScripts" => ("control_stop", &[FieldArg("STOP_OPTION")]),
+        "whenCloned" => ("control_start_as_clone", &[]),
+        "createCloneOf" => (
+            "control_create_clone_of",
+            &[Menu(
+                "CLONE_OPTION",
+                "control_create_clone_of_menu",
+                "CLONE_OPTION",
+            )],
+        ),
+        "deleteClone" => ("control_delete_this_clone", &[]),
+        // sensing
+        "touching:" => (
+            "sensing_touchingobject",
+            &[Menu(
+                "TOUCHINGOBJECTMENU",
+                "sensing_touchingobjectmenu",
+                "TOUCHINGOBJECTMENU",
+            )],
+        ),
+        "touchingColor:" => ("sensing_touchingcolor", &[Color("COLOR")]),
+        "color:sees:" => (
+            "sensing_coloristouchingcolor",
+            &[Color("COLOR"), Color("COLOR2")],
+        ),
+        "distanceTo:" => (
+            "sensing_distanceto",
+            &[Menu(
+                "DISTANCETOMENU",
+                "sensing_distancetomenu",
+                "DISTANCETOMENU",
+            )],
+        ),
+        "doAsk" => ("sensing_askandwait", &[Text("QUESTION")]),
+        "answer" => ("sensing_answer", &[]),
+        "keyPressed:" => (
+            "sensing_keypressed",
+            &[Menu("KEY_OPTION", "sensing_keyoptions", "KEY_OPTION")],
+        ),
+        "mousePressed" => ("sensing_mousedown", &[]),
+        "mouseX" => ("sensing_mousex", &[]),
+        "mouseY" => ("sensing_mousey", &[]),
+        "soundLevel" => ("sensing_loudness", &[]),
+        "timer" => ("sensing_timer", &[]),
+        "timerReset" => ("sensing_resettimer", &[]),
+        "getAttribute:of:" => (
+            "sensing_of",
+            &[
+                FieldArg("PROPERTY"),
+                Menu("OBJECT", "sensing_of_object_menu", "OBJECT"),
+            ],
+        ),
+        "timeAndDate" => ("sensing_current", &[CurrentMenu]),
+        "timestamp" => ("sensing_dayssince2000", &[]),
+        "getUserName" => ("sensing_username", &[]),
+        // operators
+        "+" => ("operator_add", &[Number("NUM1", 4), Number("NUM2", 4)]),
+        "-" => ("operator_subtract", &[Number("NUM1", 4), Number("NUM2", 4)]),
+        "*" => ("operator_multiply", &[Number("NUM1", 4), Number("NUM2", 4)]),
+        "/" => ("operator_divide", &[Number("NUM1", 4), Number("NUM2", 4)]),
+        "randomFrom:to:" => ("operator_random", &[Number("FROM", 4), Number("TO", 4)]),
+        "<" => ("operator_lt", &[Text("OPERAND1"), Text("OPERAND2")]),
+        "=" => ("operator_equals", &[Text("OPERAND1"), Text("OPERAND2")]),
+        ">" => ("operator_gt", &[Text("OPERAND1"), Text("OPERAND2")]),
+        "&" => ("operator_and", &[Boolean("OPERAND1"), Boolean("OPERAND2")]),
+        "|" => ("operator_or", &[Boolean("OPERAND1"), Boolean("OPERAND2")]),
+        "not" => ("operator_not", &[Boolean("OPERAND")]),
+        "concatenate:with:" => ("operator_join", &[Text("STRING1"), Text("STRING2")]),
+        "letter:of:" => ("operator_letter_of", &[Number("LETTER", 6), Text("STRING")]),
+        "stringLength:" => ("operator_length", &[Text("STRING")]),
+        "%" => ("operator_mod", &[Number("NUM1", 4), Number("NUM2", 4)]),
+        "rounded" => ("operator_round", &[Number("NUM", 4)]),
+        "computeFunction:of:" => ("operator_mathop", &[FieldArg("OPERATOR"), Number("NUM", 4)]),
+        // data
+        "readVariable" => ("data_variable", &[Variable]),
+        "setVar:to:" => ("data_setvariableto", &[Variable, Text("VALUE")]),
+        "changeVar:by:" => ("data_changevariableby", &[Variable, Number("VALUE", 4)]),
+        "showVariable:" => ("data_showvariable", &[Variable]),
+        "hideVariable:" => ("data_hidevariable", &[Variable]),
+        "contentsOfList:" => ("data_listcontents", &[List]),
+        "append:toList:" => ("data_addtolist", &[Text("ITEM"), List]),
+        "deleteLine:ofList:" => ("data_deleteoflist", &[Number("INDEX", 7), List]),
+        "insert:at:ofList:" => (
+            "data_insertatlist",
+            &[Text("ITEM"), Number("INDEX", 7), List],
+        ),
+        "setLine:ofList:to:" => (
+            "data_replaceitemoflist",
+            &[Number("INDEX", 7), List, Text("ITEM")],
+        ),
+        "getLine:ofList:" => ("data_itemoflist", &[Number("INDEX", 7), List]),
+        "lineCountOfList:" => ("data_lengthoflist", &[List]),
+        "list:contains:" => ("data_listcontainsitem", &[List, Text("ITEM")]),
+        "showList:" => ("data_showlist", &[List]),
+        "hideList:" => ("data_hidelist", &[List]),
+        _ => return None,
+    })
+}
+
+/// Converts a JSON literal in an sb2 block into the string that it would be represented as in
+/// an sb3 project.
+fn literal_string(value: &Value) -> Box<str> {
+    match value {
+        Value::String(string) => string.as_str().into(),
+        Value::Number(number) => number.to_string().into(),
+        Value::Bool(boolean) => boolean.to_string().into(),
+        Value::Null | Value::Array(_) | Value::Object(_) => "".into(),
+    }
+}
+
+fn var_val(value: &Value) -> VarVal {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|int| i32::try_from(int).ok())
+            .map_or_else(
+                || VarVal::Float(number.as_f64().unwrap_or(0.0)),
+                VarVal::Int,
+            ),
+        Value::Bool(boolean) => VarVal::Bool(*boolean),
+        Value::String(_) | Value::Null | Value::Array(_) | Value::Object(_) => {
+            VarVal::String(literal_string(value))
+        }
+    }
+}
+
+/// sb2 colours are stored as (A)RGB integers
+fn colour_string(value: &Value) -> Box<str> {
+    match value {
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "colours are integers, and only the bottom 24 bits are needed"
+        )]
+        Value::Number(number) => format!(
+            "#{:06x}",
+            (number.as_f64().unwrap_or(0.0) as i64 as u64) & 0x00ff_ffff
+        )
+        .into(),
+        Value::String(_) | Value::Bool(_) | Value::Null | Value::Array(_) | Value::Object(_) => {
+            literal_string(value)
+        }
+    }
+}
+
+fn opcode_name(opcode: &BlockOpcode) -> String {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "too many opcodes to match individually"
+    )]
+    match opcode {
+        BlockOpcode::Unknown(name) => name.to_string(),
+        known => format!("{known:?}"),
+    }
+}
+
+const fn field(value: Box<str>, id: Option<Box<str>>) -> Field {
+    Field::ValueId(Some(VarVal::String(value)), id)
+}
+
+/// Maps the sb2 value of the menu of a `timeAndDate` block to the sb3 value of the `CURRENTMENU`
+/// field of `sensing_current`. Unknown values are uppercased, as they can't be used anyway.
+fn current_menu_value(value: &str) -> Box<str> {
+    match value {
+        "year" => "YEAR".into(),
+        "month" => "MONTH".into(),
+        "date" => "DATE".into(),
+        "day of week" => "DAYOFWEEK".into(),
+        "hour" => "HOUR".into(),
+        "minute" => "MINUTE".into(),
+        "second" => "SECOND".into(),
+        _ => value.to_uppercase().into(),
+    }
+}
+
+fn opcode(name: &str) -> HQResult<BlockOpcode> {
+    BlockOpcode::deserialize(Value::String(name.into()))
+        .map_err(|_| make_hq_bug!("couldn't deserialize opcode {name}"))
+}
+
+/// A custom block which is defined in the target being converted
+struct ProcInfo {
+    arg_ids: Vec<Box<str>>,
+    warp: bool,
+}
+
+/// Converts the scripts of a single target.
+struct ScriptConverter<'a> {
+    blocks: BlockMap,
+    /// variable name -> id, for variables that can be accessed from this target
+    variables: &'a mut BTreeMap<Box<str>, Box<str>>,
+    /// list name -> id, for lists that can be accessed from this target
+    lists: &'a mut BTreeMap<Box<str>, Box<str>>,
+    /// variables or lists which are used but not declared, and so must be created in this target
+    new_variables: Vec<(Box<str>, Box<str>)>,
+    new_lists: Vec<(Box<str>, Box<str>)>,
+    /// broadcast name -> id, for all broadcasts in the project
+    broadcasts: &'a mut BTreeMap<Box<str>, Box<str>>,
+    procs: BTreeMap<Box<str>, ProcInfo>,
+    next_id: &'a mut usize,
+}
+
+impl ScriptConverter<'_> {
+    fn new_id(&mut self) -> Box<str> {
+        *self.next_id += 1;
+        format!("sb2_{}", self.next_id).into()
+    }
+
+    fn variable_id(&mut self, name: &str) -> Box<str> {
+        if let Some(id) = self.variables.get(name) {
+            return id.clone();
+        }
+        let id = self.new_id();
+        self.variables.insert(name.into(), id.clone());
+        self.new_variables.push((id.clone(), name.into()));
+        id
+    }
+
+    fn list_id(&mut self, name: &str) -> Box<str> {
+        if let Some(id) = self.lists.get(name) {
+            return id.clone();
+        }
+        let id = self.new_id();
+        self.lists.insert(name.into(), id.clone());
+        self.new_lists.push((id.clone(), name.into()));
+        id
+    }
+
+    fn broadcast_id(&mut self, name: &str) -> Box<str> {
+        if let Some(id) = self.broadcasts.get(name) {
+            return id.clone();
+        }
+        let id = self.new_id();
+        self.broadcasts.insert(name.into(), id.clone());
+        id
+    }
+
+    fn insert_block(&mut self, id: Box<str>, block_info: BlockInfo) {
+        self.blocks.insert(
+            id,
+            Block::Normal {
+                x: 0,
+                y: 0,
+                block_info,
+            },
+        );
+    }
+
+    /// Finds the custom block definitions in the given scripts, so that calls can refer to
+    /// their argument ids.
+    fn register_procs(&mut self, scripts: &[(f64, f64, Vec<Value>)]) {
+        for (_, _, script) in scripts {
+            let Some(Value::Array(hat)) = script.first() else {
+                continue;
+            };
+            if let [
+                Value::String(op),
+                Value::String(proccode),
+                Value::Array(arg_names),
+                ..,
+            ] = &hat[..]
+                && op == "procDef"
+                && !self.procs.contains_key(proccode.as_str())
+            {
+                let warp = hat.get(4).is_some_and(|warp| warp == &Value::Bool(true));
+                let arg_ids = arg_names.iter().map(|_| self.new_id()).collect();
+                self.procs
+                    .insert(proccode.as_str().into(), ProcInfo { arg_ids, warp });
+            }
+        }
+    }
+
+    /// Converts a list of blocks into a stack, returning the id of the first block.
+    fn stack(&mut self, blocks: &[Value], parent: Option<&str>) -> HQResult<Option<Box<str>>> {
+        let mut first = None;
+        let mut previous: Option<Box<str>> = parent.map(Box::from);
+        for block in blocks {
+            let Value::Array(block) = block else {
+                hq_bad_proj!("non-array block in sb2 script")
+            };
+            let id = self.block(block, previous.as_deref(), false)?;
+            if let Some(previous_id) = &previous
+                && first.is_some()
+                && let Some(Block::Normal { block_info, .. }) = self.blocks.get_mut(previous_id)
+            {
+                block_info.next = Some(id.clone());
+            }
+            first.get_or_insert_with(|| id.clone());
+            previous = Some(id);
+        }
+        Ok(first)
+    }
+
+    /// Converts a script, returning the id of its top block.
+    fn script(&mut self, x: f64, y: f64, blocks: &[Value]) -> HQResult<Option<Box<str>>> {
+        let Some(first) = self.stack(blocks, None)? else {
+            return Ok(None);
+        };
+        if let Some(Block::Normal {
+            x: block_x,
+            y: block_y,
+            block_info,
+        }) = self.blocks.get_mut(&first)
+        {
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "positions are only used for display purposes"
+            )]
+            {
+                *block_x = x as i32;
+                *block_y = y as i32;
+            }
+            block_info.top_level = true;
+        }
+        Ok(Some(first))
+    }
+
+    /// Converts an argument which should become an input, returning the input.
+    fn input(&mut self, value: &Value, parent: &str, arg: Arg) -> HQResult<Option<Input>> {
+        let reporter = if let Value::Array(block) = value {
+            Some(self.block(block, Some(parent), false)?)
+        } else {
+            None
+        };
+        let shadow = match arg {
+            Number(_, ty) => BlockArrayOrId::Array(BlockArray::ColorOrString(
+                ty,
+                if reporter.is_some() {
+                    "".into()
+                } else {
+                    literal_string(value)
+                },
+            )),
+            Text(_) => BlockArrayOrId::Array(BlockArray::ColorOrString(
+                10,
+                if reporter.is_some() {
+                    "".into()
+                } else {
+                    literal_string(value)
+                },
+            )),
+            Color(_) => BlockArrayOrId::Array(BlockArray::ColorOrString(
+                9,
+                if reporter.is_some() {
+                    "#000000".into()
+                } else {
+                    colour_string(value)
+                },
+            )),
+            Menu(_, menu_opcode, menu_field) => {
+                let menu_id = self.new_id();
+                let menu_value = if reporter.is_some() {
+                    "".into()
+                } else {
+                    literal_string(value)
+                };
+                self.insert_block(
+                    menu_id.clone(),
+                    BlockInfo {
+                        opcode: opcode(menu_opcode)?,
+                        next: None,
+                        parent: Some(parent.into()),
+                        inputs: BTreeMap::new(),
+                        fields: BTreeMap::from([(menu_field.into(), field(menu_value, None))]),
+                        shadow: true,
+                        top_level: false,
+                        mutation: Mutation::default(),
+                    },
+                );
+                BlockArrayOrId::Id(menu_id)
+            }
+            BroadcastInput => {
+                let menu_id = self.new_id();
+                let name = if reporter.is_some() {
+                    "message1".into()
+                } else {
+                    literal_string(value)
+                };
+                let broadcast_id = self.broadcast_id(&name);
+                self.insert_block(
+                    menu_id.clone(),
+                    BlockInfo {
+                        opcode: BlockOpcode::event_broadcast_menu,
+                        next: None,
+                        parent: Some(parent.into()),
+                        inputs: BTreeMap::new(),
+                        fields: BTreeMap::from([(
+                            "BROADCAST_OPTION".into(),
+                            field(name, Some(broadcast_id)),
+                        )]),
+                        shadow: true,
+                        top_level: false,
+                        mutation: Mutation::default(),
+                    },
+                );
+                BlockArrayOrId::Id(menu_id)
+            }
+            Boolean(_) | Substack(_) => {
+                return Ok(reporter.map(|id| Input::NoShadow(2, Some(BlockArrayOrId::Id(id)))));
+            }
+            FieldArg(_) | UppercaseField(_) | CurrentMenu | BroadcastField | Variable | List
+            | Fixed(..) => {
+                hq_bug!("tried to convert a field argument into an input")
+            }
+        };
+        Ok(Some(match reporter {
+            Some(id) => Input::Shadow(3, Some(BlockArrayOrId::Id(id)), Some(shadow)),
+            None => Input::NoShadow(1, Some(shadow)),
+        }))
+    }
+
+    /// Converts a single block, returning its id.
+    fn block(&mut self, block: &[Value], parent: Option<&str>, shadow: bool) -> HQResult<Box<str>> {
+        let Some(Value::String(sb2_opcode)) = block.first() else {
+            hq_bad_proj!("sb2 block without an opcode")
+        };
+        let args = &block[1..];
+        let id = self.new_id();
+        let mut block_info = BlockInfo {
+            opcode: BlockOpcode::Unknown(sb2_opcode.as_str().into()),
+            next: None,
+            parent: parent.map(Box::from),
+            inputs: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            shadow,
+            top_level: false,
+            mutation: Mutation::default(),
+        };
+        match sb2_opcode.as_str() {
+            "procDef" => self.proc_def(&id, args, &mut block_info),
+            "call" => self.call(&id, args, &mut block_info)?,
+            "getParam" => {
+                let name = args.first().map(literal_string).unwrap_or_default();
+                block_info.opcode = if args.get(1) == Some(&Value::String("b".into())) {
+                    BlockOpcode::argument_reporter_boolean
+                } else {
+                    BlockOpcode::argument_reporter_string_number
+                };
+                block_info.fields.insert("VALUE".into(), field(name, None));
+            }
+            _ => {
+                if let Some((sb3_opcode, spec)) = block_spec(sb2_opcode) {
+                    block_info.opcode = opcode(sb3_opcode)?;
+                    self.args(&id, args, spec, &mut block_info)?;
+                }
+            }
+        }
+        if block_info.opcode == BlockOpcode::control_stop {
+            let option = block_info
+                .fields
+                .get("STOP_OPTION")
+                .and_then(Field::get_0)
+                .cloned();
+            let has_next = matches!(option, Some(VarVal::String(option)) if option.starts_with("other scripts"));
+            block_info
+                .mutation
+                .mutations
+                .insert("hasnext".into(), Value::String(has_next.to_string()));
+        }
+        self.insert_block(id.clone(), block_info);
+        Ok(id)
+    }
+
+    fn args(
+        &mut self,
+        id: &str,
+        args: &[Value],
+        spec: &[Arg],
+        block_info: &mut BlockInfo,
+    ) -> HQResult<()> {
+        let mut args = args.iter();
+        for &arg in spec {
+            if let Fixed(name, value) = arg {
+                block_info
+                    .fields
+                    .insert(name.into(), field(value.into(), None));
+                continue;
+            }
+            let value = args.next().unwrap_or(&Value::Null);
+            match arg {
+                Number(name, _) | Text(name) | Color(name) | Boolean(name) | Menu(name, _, _) => {
+                    if let Some(input) = self.input(value, id, arg)? {
+                        block_info.inputs.insert(name.into(), input);
+                    }
+                }
+                BroadcastInput => {
+                    if let Some(input) = self.input(value, id, arg)? {
+                        block_info.inputs.insert("BROADCAST_INPUT".into(), input);
+                    }
+                }
+                Substack(name) => {
+                    let substack = if let Value::Array(blocks) = value {
+                        self.stack(blocks, Some(id))?
+                    } else {
+                        None
+                    };
+                    if let Some(first) = substack {
+                        block_info.inputs.insert(
+                            name.into(),
+                            Input::NoShadow(2, Some(BlockArrayOrId::Id(first))),
+                        );
+                    }
+                }
+                FieldArg(name) => {
+                    block_info
+                        .fields
+                        .insert(name.into(), field(literal_string(value), None));
+                }
+                UppercaseField(name) => {
+                    block_info.fields.insert(
+                        name.into(),
+                        field(literal_string(value).to_uppercase().into(), None),
+                    );
+                }
+                CurrentMenu => {
+                    block_info.fields.insert(
+                        "CURRENTMENU".into(),
+                        field(current_menu_value(&literal_string(value)), None),
+                    );
+                }
+                BroadcastField => {
+                    let name = literal_string(value);
+                    let broadcast_id = self.broadcast_id(&name);
+                    block_info
+                        .fields
+                        .insert("BROADCAST_OPTION".into(), field(name, Some(broadcast_id)));
+                }
+                Variable => {
+                    let name = literal_string(value);
+                    let variable_id = self.variable_id(&name);
+                    block_info
+                        .fields
+                        .insert("VARIABLE".into(), field(name, Some(variable_id)));
+                }
+                List => {
+                    let name = literal_string(value);
+                    let list_id = self.list_id(&name);
+                    block_info
+                        .fields
+                        .insert("LIST".into(), field(name, Some(list_id)));
+                }
+                Fixed(..) => hq_bug!("fixed fields should have been handled already"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts a `procDef` block into a `procedures_definition` with a `procedures_prototype`
+    /// (and its argument reporters).
+    fn proc_def(&mut self, id: &str, args: &[Value], block_info: &mut BlockInfo) {
+        let proccode = args.first().map(literal_string).unwrap_or_default();
+        let arg_names: Vec<Box<str>> = match args.get(1) {
+            Some(Value::Array(names)) => names.iter().map(literal_string).collect(),
+            _ => vec![],
+        };
+        let arg_defaults: Vec<Value> = match args.get(2) {
+            Some(Value::Array(defaults)) => defaults.clone(),
+            _ => vec![],
+        };
+        let (arg_ids, warp) = match self.procs.get(&proccode) {
+            Some(ProcInfo { arg_ids, warp }) => (arg_ids.clone(), *warp),
+            None => (arg_names.iter().map(|_| self.new_id()).collect(), false),
+        };
+        let arg_types = proccode
+            .split('%')
+            .skip(1)
+            .filter_map(|part| part.chars().next())
+            .filter(|ty| matches!(ty, 's' | 'n' | 'b'));
+
+        let prototype_id = self.new_id();
+        let mut prototype_inputs = BTreeMap::new();
+        for ((arg_id, arg_name), ty) in arg_ids.iter().zip(&arg_names).zip(arg_types) {
+            let reporter_id = self.new_id();
+            self.insert_block(
+                reporter_id.clone(),
+                BlockInfo {
+                    opcode: if ty == 'b' {
+                        BlockOpcode::argument_reporter_boolean
+                    } else {
+                        BlockOpcode::argument_reporter_string_number
+                    },
+                    next: None,
+                    parent: Some(prototype_id.clone()),
+                    inputs: BTreeMap::new(),
+                    fields: BTreeMap::from([("VALUE".into(), field(arg_name.clone(), None))]),
+                    shadow: true,
+                    top_level: false,
+                    mutation: Mutation::default(),
+                },
+            );
+            prototype_inputs.insert(
+                arg_id.clone(),
+                Input::NoShadow(1, Some(BlockArrayOrId::Id(reporter_id))),
+            );
+        }
+        let string_array = |strings: &[Box<str>]| {
+            Value::Array(
+                strings
+                    .iter()
+                    .map(|string| Value::String(string.to_string()))
+                    .collect(),
+            )
+        };
+        self.insert_block(
+            prototype_id.clone(),
+            BlockInfo {
+                opcode: BlockOpcode::procedures_prototype,
+                next: None,
+                parent: Some(id.into()),
+                inputs: prototype_inputs,
+                fields: BTreeMap::new(),
+                shadow: true,
+                top_level: false,
+                mutation: Mutation {
+                    mutations: BTreeMap::from([
+                        ("proccode".into(), Value::String(proccode.to_string())),
+                        ("argumentids".into(), string_array(&arg_ids)),
+                        ("argumentnames".into(), string_array(&arg_names)),
+                        ("argumentdefaults".into(), Value::Array(arg_defaults)),
+                        ("warp".into(), Value::String(warp.to_string())),
+                    ]),
+                    ..Mutation::default()
+                },
+            },
+        );
+        block_info.opcode = BlockOpcode::procedures_definition;
+        block_info.inputs.insert(
+            "custom_block".into(),
+            Input::NoShadow(1, Some(BlockArrayOrId::Id(prototype_id))),
+        );
+    }
+
+    /// Converts a `call` block into a `procedures_call`.
+    fn call(&mut self, id: &str, args: &[Value], block_info: &mut BlockInfo) -> HQResult<()> {
+        let proccode = args.first().map(literal_string).unwrap_or_default();
+        let arg_types: Vec<char> = proccode
+            .split('%')
+            .skip(1)
+            .filter_map(|part| part.chars().next())
+            .filter(|ty| matches!(ty, 's' | 'n' | 'b'))
+            .collect();
+        let (arg_ids, warp) = match self.procs.get(&proccode) {
+            Some(ProcInfo { arg_ids, warp }) => (arg_ids.clone(), *warp),
+            None => (arg_types.iter().map(|_| self.new_id()).collect(), false),
+        };
+        // a `call` block with no arguments at all doesn't even have a proccode
+        let values = args.get(1..).unwrap_or_default();
+        for ((arg_id, ty), value) in arg_ids.iter().zip(&arg_types).zip(values) {
+            let arg = match ty {
+                'b' => Boolean(""),
+                'n' => Number("", 4),
+                _ => Text(""),
+            };
+            if let Some(input) = self.input(value, id, arg)? {
+                block_info.inputs.insert(arg_id.clone(), input);
+            }
+        }
+        block_info.opcode = BlockOpcode::procedures_call;
+        block_info.mutation.mutations = BTreeMap::from([
+            ("proccode".into(), Value::String(proccode.to_string())),
+            (
+                "argumentids".into(),
+                Value::Array(
+                    arg_ids
+                        .iter()
+                        .map(|arg_id| Value::String(arg_id.to_string()))
+                        .collect(),
+                ),
+            ),
+            ("warp".into(), Value::String(warp.to_string())),
+        ]);
+        Ok(())
+    }
+}
+
+fn costume(costume: &Sb2Costume) -> HQResult<Costume> {
+    let data_format = match extension(&costume.base_layer_md5) {
+        "png" => CostumeDataFormat::png,
+        "svg" => CostumeDataFormat::svg,
+        "jpeg" => CostumeDataFormat::jpeg,
+        "jpg" => CostumeDataFormat::jpg,
+        "bmp" => CostumeDataFormat::bmp,
+        "gif" => CostumeDataFormat::gif,
+        ext => hq_bad_proj!("unknown costume format {ext} in sb2 project"),
+    };
+    Ok(Costume {
+        asset_id: asset_id(&costume.base_layer_md5).into(),
+        name: costume.costume_name.clone(),
+        md5ext: costume.base_layer_md5.clone(),
+        data_format,
+        bitmap_resolution: costume.bitmap_resolution.unwrap_or(1.0),
+        rotation_center_x: costume.rotation_center_x,
+        rotation_center_y: costume.rotation_center_y,
+        size: None,
+    })
+}
+
+fn sound(sound: &Sb2Sound) -> Sound {
+    Sound {
+        asset_id: asset_id(&sound.md5).into(),
+        name: sound.sound_name.clone(),
+        md5ext: sound.md5.clone(),
+        data_format: extension(&sound.md5).into(),
+        rate: sound.rate,
+        sample_count: sound.sample_count,
+        format: sound.format.clone(),
+    }
+}
+
+fn rotation_style(style: Option<&str>) -> Box<str> {
+    match style {
+        Some("leftRight") => "left-right",
+        Some("none") => "don't rotate",
+        _ => "all around",
+    }
+    .into()
+}
+
+/// Global names and ids which are shared between all targets
+#[derive(Default)]
+struct GlobalIds {
+    variables: BTreeMap<Box<str>, Box<str>>,
+    lists: BTreeMap<Box<str>, Box<str>>,
+    broadcasts: BTreeMap<Box<str>, Box<str>>,
+    next_id: usize,
+}
+
+fn convert_target(
+    object: &Sb2Object,
+    is_stage: bool,
+    layer_order: i32,
+    globals: &mut GlobalIds,
+) -> HQResult<Target> {
+    let mut variables = BTreeMap::new();
+    let mut lists = BTreeMap::new();
+    let mut variable_ids = if is_stage {
+        BTreeMap::new()
+    } else {
+        globals.variables.clone()
+    };
+    let mut list_ids = if is_stage {
+        BTreeMap::new()
+    } else {
+        globals.lists.clone()
+    };
+    for variable in &object.variables {
+        globals.next_id += 1;
+        let id: Box<str> = format!("sb2_{}", globals.next_id).into();
+        variable_ids.insert(variable.name.clone(), id.clone());
+        variables.insert(
+            id,
+            if variable.is_persistent {
+                VariableInfo::CloudVar(variable.name.clone(), var_val(&variable.value), true)
+            } else {
+                VariableInfo::LocalVar(variable.name.clone(), var_val(&variable.value))
+            },
+        );
+    }
+    for list in &object.lists {
+        globals.next_id += 1;
+        let id: Box<str> = format!("sb2_{}", globals.next_id).into();
+        list_ids.insert(list.list_name.clone(), id.clone());
+        lists.insert(
+            id,
+            (
+                list.list_name.clone(),
+                list.contents.iter().map(var_val).collect(),
+            ),
+        );
+    }
+
+    let mut converter = ScriptConverter {
+        blocks: BTreeMap::new(),
+        variables: &mut variable_ids,
+        lists: &mut list_ids,
+        new_variables: vec![],
+        new_lists: vec![],
+        broadcasts: &mut globals.broadcasts,
+        procs: BTreeMap::new(),
+        next_id: &mut globals.next_id,
+    };
+    converter.register_procs(&object.scripts);
+    for (x, y, script) in &object.scripts {
+        converter.script(*x, *y, script)?;
+    }
+    let ScriptConverter {
+        blocks,
+        new_variables,
+        new_lists,
+        ..
+    } = converter;
+    for (id, name) in new_variables {
+        variables.insert(id, VariableInfo::LocalVar(name, VarVal::Int(0)));
+    }
+    for (id, name) in new_lists {
+        lists.insert(id, (name, vec![]));
+    }
+    if is_stage {
+        globals.variables = variable_ids;
+        globals.lists = list_ids;
+    }
+
+    Ok(Target {
+        is_stage,
+        name: object.obj_name.clone(),
+        variables,
+        lists,
+        broadcasts: BTreeMap::new(),
+        blocks,
+        comments: BTreeMap::new(),
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "costume indices are small non-negative integers"
+        )]
+        current_costume: object.current_costume_index.max(0.0) as u32,
+        costumes: object
+            .costumes
+            .iter()
+            .map(costume)
+            .collect::<HQResult<_>>()?,
+        sounds: object.sounds.iter().map(sound).collect(),
+        layer_order,
+        volume: 100.0,
+        tempo: object.tempo_bpm.unwrap_or(60.0),
+        video_state: None,
+        video_transparency: 50.0,
+        text_to_speech_language: None,
+        visible: object.visible,
+        x: object.scratch_x,
+        y: object.scratch_y,
+        size: object.scale * 100.0,
+        direction: object.direction,
+        draggable: object.is_draggable,
+        rotation_style: rotation_style(object.rotation_style.as_deref()),
+        unknown: BTreeMap::new(),
+    })
+}
+
+impl TryFrom<&Sb2Project> for Sb3Project {
+    type Error = HQError;
+
+    fn try_from(sb2: &Sb2Project) -> Result<Self, Self::Error> {
+        let mut globals = GlobalIds::default();
+        let mut stage = convert_target(&sb2.0, true, 0, &mut globals)?;
+        let mut targets = vec![];
+        for (layer, sprite) in sb2.sprites()?.iter().enumerate() {
+            let layer_order = i32::try_from(layer + 1)
+                .map_err(|_| make_hq_bad_proj!("too many sprites in sb2 project"))?;
+            targets.push(convert_target(sprite, false, layer_order, &mut globals)?);
+        }
+        stage.broadcasts = globals
+            .broadcasts
+            .into_iter()
+            .map(|(name, id)| (id, name))
+            .collect();
+        targets.insert(0, stage);
+
+        let extensions = ["pen", "music", "videoSensing", "text2speech"]
+            .into_iter()
+            .filter(|extension| {
+                let prefix = format!("{extension}_");
+                targets
+                    .iter()
+                    .flat_map(|target| target.blocks.values())
+                    .filter_map(Block::block_info)
+                    .any(|block_info| opcode_name(&block_info.opcode).starts_with(&prefix))
+            })
+            .map(Box::from)
+            .collect();
+
+        Ok(Self {
+            targets,
+            monitors: vec![],
+            extensions,
+            meta: Meta {
+                semver: "3.0.0".into(),
+                vm: "0.0.0".into(),
+                agent: "sb2 converter".into(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sb3::Sb3Archive;
+    use crate::wasm::WasmFlags;
+    use crate::wasm::flags::unit_test_wasm_features;
+
+    fn project(sprite_scripts: &str) -> Sb3Project {
+        let sb2 = Sb2Project::try_from(
+            format!(
+                r#"{{
+                    "objName": "Stage",
+                    "variables": [{{ "name": "global", "value": 5, "isPersistent": false }}],
+                    "costumes": [{{
+                        "costumeName": "backdrop1",
+                        "baseLayerID": 3,
+                        "baseLayerMD5": "739b5e2a2435f6e1ec2993791b423146.png",
+                        "bitmapResolution": 1,
+                        "rotationCenterX": 240,
+                        "rotationCenterY": 180
+                    }}],
+                    "children": [
+                        {{
+                            "objName": "Sprite1",
+                            "scripts": {sprite_scripts},
+                            "lists": [{{ "listName": "things", "contents": ["a", 1] }}],
+                            "costumes": [{{
+                                "costumeName": "costume1",
+                                "baseLayerID": 1,
+                                "baseLayerMD5": "f9a1c175dbe2e5dee472858dd30d16bb.svg",
+                                "rotationCenterX": 47,
+                                "rotationCenterY": 55
+                            }}],
+                            "scratchX": 10,
+                            "scratchY": -20,
+                            "scale": 0.5,
+                            "direction": 90,
+                            "rotationStyle": "leftRight",
+                            "visible": true
+                        }},
+                        {{ "target": "Stage", "cmd": "getVar:", "param": "global" }}
+                    ]
+                }}"#
+            )
+            .as_str(),
+        )
+        .unwrap();
+        Sb3Project::try_from(&sb2).unwrap()
+    }
+
+    fn blocks_with_opcode<'a>(target: &'a Target, opcode: &BlockOpcode) -> Vec<&'a BlockInfo> {
+        target
+            .blocks
+            .values()
+            .filter_map(Block::block_info)
+            .filter(|block_info| block_info.opcode == *opcode)
+            .collect()
+    }
+
+    #[test]
+    fn converts_targets() {
+        let project = project("[]");
+        assert_eq!(project.targets.len(), 2);
+        let [stage, sprite] = &project.targets[..] else {
+            panic!("expected 2 targets")
+        };
+        assert!(stage.is_stage);
+        assert_eq!(stage.variables.len(), 1);
+        assert_eq!(&*sprite.name, "Sprite1");
+        assert!((sprite.size - 50.0).abs() < f64::EPSILON);
+        assert!((sprite.y + 20.0).abs() < f64::EPSILON);
+        assert_eq!(&*sprite.rotation_style, "left-right");
+        assert_eq!(sprite.layer_order, 1);
+        assert_eq!(sprite.lists.len(), 1);
+        assert_eq!(
+            &*sprite.costumes[0].asset_id,
+            "f9a1c175dbe2e5dee472858dd30d16bb"
+        );
+        assert_eq!(sprite.costumes[0].data_format, CostumeDataFormat::svg);
+    }
+
+    #[test]
+    fn converts_scripts() {
+        let project = project(
+            r#"[[24, 24, [
+                ["whenGreenFlag"],
+                ["doRepeat", 10, [
+                    ["changeVar:by:", "global", ["+", 1, ["readVariable", "global"]]]
+                ]],
+                ["doIfElse", ["not", false], [["say:", "yes"]], [["append:toList:", "x", "things"]]],
+                ["broadcast:", "go"]
+            ]]]"#,
+        );
+        let sprite = &project.targets[1];
+        let hats = blocks_with_opcode(sprite, &BlockOpcode::event_whenflagclicked);
+        assert_eq!(hats.len(), 1);
+        assert!(hats[0].top_level);
+        let repeat_id = hats[0].next.as_ref().unwrap();
+        let Some(repeat) = sprite.blocks[repeat_id].block_info() else {
+            panic!("repeat should be a normal block")
+        };
+        assert_eq!(repeat.opcode, BlockOpcode::control_repeat);
+        assert_eq!(
+            repeat.inputs["TIMES"],
+            Input::NoShadow(
+                1,
+                Some(BlockArrayOrId::Array(BlockArray::ColorOrString(
+                    6,
+                    "10".into()
+                )))
+            )
+        );
+        assert!(matches!(
+            repeat.inputs["SUBSTACK"],
+            Input::NoShadow(2, Some(BlockArrayOrId::Id(_)))
+        ));
+        let Some(if_else) = sprite.blocks[repeat.next.as_ref().unwrap()].block_info() else {
+            panic!("if/else should be a normal block")
+        };
+        assert_eq!(if_else.opcode, BlockOpcode::control_if_else);
+        assert!(if_else.inputs.contains_key("SUBSTACK2"));
+
+        // the global variable should be referred to by the stage's id for it
+        let stage_var_id = project.targets[0].variables.keys().next().unwrap();
+        let change = blocks_with_opcode(sprite, &BlockOpcode::data_changevariableby)[0];
+        assert_eq!(
+            change.fields["VARIABLE"],
+            field("global".into(), Some(stage_var_id.clone()))
+        );
+        assert!(matches!(
+            change.inputs["VALUE"],
+            Input::Shadow(3, Some(BlockArrayOrId::Id(_)), Some(_))
+        ));
+        // list references resolve to the sprite's list
+        let add = blocks_with_opcode(sprite, &BlockOpcode::data_addtolist)[0];
+        let list_id = sprite.lists.keys().next().unwrap();
+        assert_eq!(
+            add.fields["LIST"],
+            field("things".into(), Some(list_id.clone()))
+        );
+        // broadcasts are collected on the stage
+        assert_eq!(
+            project.targets[0].broadcasts.values().collect::<Vec<_>>(),
+            vec![&Box::<str>::from("go")]
+        );
+    }
+
+    #[test]
+    fn converts_custom_blocks() {
+        let project = project(
+            r#"[
+                [0, 0, [["procDef", "foo %n %b", ["a", "b"], [1, false], true], ["say:", ["getParam", "a", "r"]]]],
+                [0, 100, [["whenGreenFlag"], ["call", "foo %n %b", 5, ["not", false]]]]
+            ]"#,
+        );
+        let sprite = &project.targets[1];
+        let prototype = blocks_with_opcode(sprite, &BlockOpcode::procedures_prototype)[0];
+        let call = blocks_with_opcode(sprite, &BlockOpcode::procedures_call)[0];
+        assert_eq!(
+            prototype.mutation.mutations["argumentids"],
+            call.mutation.mutations["argumentids"]
+        );
+        assert_eq!(
+            prototype.mutation.mutations["warp"],
+            Value::String("true".into())
+        );
+        let Value::Array(arg_ids) = &call.mutation.mutations["argumentids"] else {
+            panic!("argumentids should be an array")
+        };
+        let Value::String(bool_arg_id) = &arg_ids[1] else {
+            panic!("argument ids should be strings")
+        };
+        assert!(matches!(
+            call.inputs[bool_arg_id.as_str()],
+            Input::NoShadow(2, Some(BlockArrayOrId::Id(_)))
+        ));
+        assert_eq!(
+            blocks_with_opcode(sprite, &BlockOpcode::argument_reporter_boolean).len(),
+            1
+        );
+        assert_eq!(
+            blocks_with_opcode(sprite, &BlockOpcode::argument_reporter_string_number).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn converts_time_and_date_menus() {
+        let project = project(
+            r#"[[0, 0, [
+                ["whenGreenFlag"],
+                ["say:", ["timeAndDate", "day of week"]],
+                ["say:", ["timeAndDate", "minute"]]
+            ]]]"#,
+        );
+        let menus = blocks_with_opcode(&project.targets[1], &BlockOpcode::sensing_current)
+            .iter()
+            .map(|block_info| block_info.fields["CURRENTMENU"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(menus.len(), 2);
+        assert!(menus.contains(&field("DAYOFWEEK".into(), None)));
+        assert!(menus.contains(&field("MINUTE".into(), None)));
+    }
+
+    #[test]
+    fn calls_without_a_proccode_are_converted() {
+        let project = project(r#"[[0, 0, [["whenGreenFlag"], ["call"]]]]"#);
+        let call = blocks_with_opcode(&project.targets[1], &BlockOpcode::procedures_call)[0];
+        assert_eq!(
+            call.mutation.mutations["proccode"],
+            Value::String(String::new())
+        );
+    }
+
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn compiles_sb2_fixture() {
+        let archive = Sb3Archive::try_from(
+            &include_bytes!("../test/fixtures/execute/procedures-number-number-boolean.sb2")[..],
+        )
+        .unwrap();
+        let sprite = &archive.project.targets[1];
+        assert_eq!(&*sprite.name, "Sprite1");
+        assert_eq!(
+            blocks_with_opcode(sprite, &BlockOpcode::procedures_definition).len(),
+            3
+        );
+        assert_eq!(
+            blocks_with_opcode(sprite, &BlockOpcode::procedures_call).len(),
+            3
+        );
+        assert!(archive.asset(&sprite.costumes[0].md5ext).is_some());
+        let wasm_bytes =
+            crate::sb3_project_to_wasm(&archive.project, WasmFlags::new(unit_test_wasm_features()))
+                .unwrap()
+                .wasm_bytes;
+        wasmparser::validate(&wasm_bytes).unwrap();
+    }
+
+    #[test]
+    fn unknown_blocks_are_kept() {
+        let project = project(r#"[[0, 0, [["whenGreenFlag"], ["someUnknownBlock:", 1]]]]"#);
+        assert_eq!(
+            blocks_with_opcode(
+                &project.targets[1],
+                &BlockOpcode::Unknown("someUnknownBlock:".into())
+            )
+            .len(),
+            1
+        );
+    }
+}
@@ -1,10 +1,14 @@
 //! 1-1 representation of `project.json` or `sprite.json` files
 //! in the `sb3` format.
 //!
-//! `sb` or `sb2` files must be converted first;
-//! `sb3` files must be unzipped first. See <https://en.scratch-wiki.info/wiki/Scratch_File_Format>
-//! for a loose informal specification.
+//! `sb` or `sb2` files must be converted first (see [`crate::sb2`] for the latter);
+//! `sb3` files must be unzipped first, e.g. using [`Sb3Archive`].
+//! See <https://en.scratch-wiki.info/wiki/Scratch_File_Format> for a loose informal specification.
 
+mod archive;
+mod inflate;
+
+pub use archive::Sb3Archive;
 use enum_field_getter::EnumFieldGetter;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -40,7 +44,7 @@ pub struct Comment {
 /// and a few hidden but non-obsolete blocks. A block being listed here does not imply that
 /// it is supported by `HyperQuark`.
 #[expect(non_camel_case_types, reason = "opcodes are snake_case")]
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum BlockOpcode {
     control_repeat,
     control_repeat_until,
@@ -84,6 +88,7 @@ pub enum BlockOpcode {
     event_whenkeypressed,
     event_whenthisspriteclicked,
     event_whentouchingobject,
+    event_touchingobjectmenu,
     event_whenstageclicked,
     event_whenbackdropswitchesto,
     event_whengreaterthan,
@@ -124,6 +129,7 @@ pub enum BlockOpcode {
     motion_pointtowards,
     motion_glidesecstoxy,
     motion_glideto,
+    motion_glideto_menu,
     motion_ifonedgebounce,
     motion_setrotationstyle,
     motion_changexby,
@@ -212,8 +218,9 @@ pub enum BlockOpcode {
     sound_setvolumeto,
     sound_changevolumeby,
     sound_volume,
-    // #[serde(other)]
-    // other,
+    /// Any opcode which isn't listed above, e.g. one from an extension.
+    #[serde(untagged)]
+    Unknown(Box<str>),
 }
 
 /// A scratch block - either special or not
@@ -340,6 +347,10 @@ pub struct Costume {
     pub bitmap_resolution: f64,
     pub rotation_center_x: f64,
     pub rotation_center_y: f64,
+    /// the (width, height) of the costume's image in pixels, if known. This isn't stored in
+    /// project.json, so is only filled in when the project is loaded from an archive.
+    #[serde(skip)]
+    pub size: Option<(f64, f64)>,
 }
 
 /// A sound
@@ -0,0 +1,455 @@
+//! Reading of `.sb3` files, which are zip archives containing a `project.json` alongside the
+//! project's assets, each named by its `md5ext`.
+//!
+//! `.sb2` files are zip archives too, so they can be read in the same way; their projects are
+//! converted into the sb3 format.
+//!
+//! Only the subset of the zip format which is needed for `.sb3` files is supported: archives
+//! must not be split or encrypted, and entries must be stored or deflated.
+
+use super::inflate::inflate;
+use super::{CostumeDataFormat, Sb3Project};
+use crate::prelude::*;
+use crate::sb2::Sb2Project;
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+
+/// A scratch project loaded from a `.sb3` file, along with its assets.
+#[derive(Debug, Clone)]
+pub struct Sb3Archive {
+    pub project: Sb3Project,
+    /// the contents of each asset, keyed by its `md5ext`
+    pub assets: BTreeMap<Box<str>, Box<[u8]>>,
+}
+
+impl Sb3Archive {
+    /// Returns the contents of the asset with the given `md5ext`, if it exists.
+    #[must_use]
+    pub fn asset(&self, md5ext: &str) -> Option<&[u8]> {
+        self.assets.get(md5ext).map(|bytes| &**bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for Sb3Archive {
+    type Error = HQError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let mut project_json = None;
+        let mut files = BTreeMap::new();
+        for (path, contents) in zip_entries(bytes)? {
+            // some programs put the project in a subdirectory, so only look at file names
+            let Some(name) = path.rsplit('/').next() else {
+                continue;
+            };
+            if path.ends_with('/') || name.is_empty() {
+                continue;
+            }
+            if name == "project.json" {
+                project_json = Some(contents);
+            } else {
+                files.insert(Box::<str>::from(name), contents);
+            }
+        }
+        let Some(project_json) = project_json else {
+            hq_bad_proj!("project.json not found in sb3 archive")
+        };
+        let project_json = str::from_utf8(&project_json)
+            .map_err(|_| make_hq_bad_proj!("project.json is not valid UTF-8"))?;
+        let mut project = if serde_json::from_str::<serde_json::Value>(project_json)
+            .is_ok_and(|json| json.get("objName").is_some())
+        {
+            // sb2 archives store assets by their id rather than by their md5ext
+            let sb2 = Sb2Project::try_from(project_json)?;
+            for (file_name, md5ext) in sb2.asset_files()? {
+                if let Some(contents) = files.remove(&file_name) {
+                    files.insert(md5ext, contents);
+                }
+            }
+            Sb3Project::try_from(&sb2)?
+        } else {
+            Sb3Project::try_from(project_json)?
+        };
+        let mut assets = BTreeMap::new();
+        for target in &project.targets {
+            let md5exts = target
+                .costumes
+                .iter()
+                .map(|costume| &costume.md5ext)
+                .chain(target.sounds.iter().map(|sound| &sound.md5ext));
+            for md5ext in md5exts {
+                if assets.contains_key(md5ext) {
+                    continue;
+                }
+                let Some(contents) = files.remove(md5ext) else {
+                    hq_bad_proj!(
+                        "asset {md5ext} (used in target '{}') not found in sb3 archive",
+                        target.name
+                    )
+                };
+                assets.insert(md5ext.clone(), contents.into_boxed_slice());
+            }
+        }
+        for costume in project
+            .targets
+            .iter_mut()
+            .flat_map(|target| target.costumes.iter_mut())
+        {
+            costume.size = assets
+                .get(&costume.md5ext)
+                .and_then(|contents| image_size(costume.data_format, contents));
+        }
+        Ok(Self { project, assets })
+    }
+}
+
+/// Reads the (width, height) of an image from its header, if the format is understood.
+fn image_size(data_format: CostumeDataFormat, contents: &[u8]) -> Option<(f64, f64)> {
+    match data_format {
+        CostumeDataFormat::png => {
+            let (Some(&[w0, w1, w2, w3]), Some(&[h0, h1, h2, h3])) =
+                (contents.get(16..20), contents.get(20..24))
+            else {
+                return None;
+            };
+            Some((
+                f64::from(u32::from_be_bytes([w0, w1, w2, w3])),
+                f64::from(u32::from_be_bytes([h0, h1, h2, h3])),
+            ))
+        }
+        CostumeDataFormat::gif => {
+            let (Some(&[w0, w1]), Some(&[h0, h1])) = (contents.get(6..8), contents.get(8..10))
+            else {
+                return None;
+            };
+            Some((
+                f64::from(u16::from_le_bytes([w0, w1])),
+                f64::from(u16::from_le_bytes([h0, h1])),
+            ))
+        }
+        CostumeDataFormat::bmp => {
+            let (Some(&[w0, w1, w2, w3]), Some(&[h0, h1, h2, h3])) =
+                (contents.get(18..22), contents.get(22..26))
+            else {
+                return None;
+            };
+            // bitmaps are stored upside down if the height is negative
+            Some((
+                f64::from(i32::from_le_bytes([w0, w1, w2, w3])),
+                f64::from(i32::from_le_bytes([h0, h1, h2, h3])).abs(),
+            ))
+        }
+        CostumeDataFormat::svg => svg_size(str::from_utf8(contents).ok()?),
+        // jpeg dimensions are stored in a frame header somewhere in the file, which isn't worth
+        // hunting for just to get a slightly more accurate bounding box
+        CostumeDataFormat::jpeg | CostumeDataFormat::jpg => None,
+    }
+}
+
+/// Reads the size of an svg from the `width` and `height` attributes of its root element,
+/// falling back to its `viewBox`.
+fn svg_size(svg: &str) -> Option<(f64, f64)> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+    let length = |name: &str| -> Option<f64> {
+        svg_attribute(tag, name)?
+            .trim()
+            .trim_end_matches("px")
+            .parse()
+            .ok()
+    };
+    if let (Some(width), Some(height)) = (length("width"), length("height")) {
+        return Some((width, height));
+    }
+    let view_box = svg_attribute(tag, "viewBox")?
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<f64>, _>>()
+        .ok()?;
+    let &[_, _, width, height] = view_box.as_slice() else {
+        return None;
+    };
+    Some((width, height))
+}
+
+/// Returns the value of an attribute in an xml tag.
+fn svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(index, _)| {
+        if !tag[..index].ends_with(|c: char| c.is_ascii_whitespace()) {
+            return None;
+        }
+        let rest = tag[index + name.len()..].trim_start().strip_prefix('=')?.trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &rest[1..];
+        Some(&value[..value.find(quote)?])
+    })
+}
+
+/// Adds to an offset within a zip archive. Offsets are read from the archive itself, so this
+/// may overflow if the archive is corrupt.
+fn add_offset(offset: usize, length: usize) -> HQResult<usize> {
+    offset
+        .checked_add(length)
+        .ok_or_else(|| make_hq_bad_proj!("offset out of bounds in zip archive"))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> HQResult<u16> {
+    let Some(&[a, b]) = bytes.get(offset..add_offset(offset, 2)?) else {
+        hq_bad_proj!("unexpected end of zip archive")
+    };
+    Ok(u16::from_le_bytes([a, b]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> HQResult<u32> {
+    let Some(&[a, b, c, d]) = bytes.get(offset..add_offset(offset, 4)?) else {
+        hq_bad_proj!("unexpected end of zip archive")
+    };
+    Ok(u32::from_le_bytes([a, b, c, d]))
+}
+
+fn read_usize(bytes: &[u8], offset: usize) -> HQResult<usize> {
+    usize::try_from(read_u32(bytes, offset)?)
+        .map_err(|_| make_hq_bug!("u32 out of bounds for usize"))
+}
+
+/// Returns the (path, decompressed contents) of each file in a zip archive.
+fn zip_entries(bytes: &[u8]) -> HQResult<Vec<(Box<str>, Vec<u8>)>> {
+    // the end of central directory record is at least 22 bytes long, but may be followed by a
+    // comment of up to 65535 bytes
+    let Some(end_offset) = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(22 + 0xffff)
+        .find(|&offset| read_u32(bytes, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+    else {
+        hq_bad_proj!("sb3 file is not a valid zip archive")
+    };
+    let entry_count = read_u16(bytes, add_offset(end_offset, 10)?)?;
+    let mut offset = read_usize(bytes, add_offset(end_offset, 16)?)?;
+    let mut entries = vec![];
+    for _ in 0..entry_count {
+        if read_u32(bytes, offset)? != CENTRAL_DIRECTORY_HEADER_SIGNATURE {
+            hq_bad_proj!("corrupt central directory in zip archive")
+        }
+        let flags = read_u16(bytes, add_offset(offset, 8)?)?;
+        let method = read_u16(bytes, add_offset(offset, 10)?)?;
+        let crc = read_u32(bytes, add_offset(offset, 16)?)?;
+        let compressed_size = read_usize(bytes, add_offset(offset, 20)?)?;
+        let uncompressed_size = read_usize(bytes, add_offset(offset, 24)?)?;
+        let name_length = usize::from(read_u16(bytes, add_offset(offset, 28)?)?);
+        let extra_length = usize::from(read_u16(bytes, add_offset(offset, 30)?)?);
+        let comment_length = usize::from(read_u16(bytes, add_offset(offset, 32)?)?);
+        let local_offset = read_usize(bytes, add_offset(offset, 42)?)?;
+        let name_offset = add_offset(offset, 46)?;
+        let Some(name) = bytes.get(name_offset..add_offset(name_offset, name_length)?) else {
+            hq_bad_proj!("unexpected end of zip archive")
+        };
+        let name: Box<str> = String::from_utf8_lossy(name).into();
+        offset = add_offset(
+            add_offset(name_offset, name_length)?,
+            extra_length + comment_length,
+        )?;
+
+        if flags & 1 != 0 {
+            hq_bad_proj!("zip entry {name} is encrypted")
+        }
+        if read_u32(bytes, local_offset)? != LOCAL_FILE_HEADER_SIGNATURE {
+            hq_bad_proj!("corrupt local file header for {name} in zip archive")
+        }
+        let local_name_length = usize::from(read_u16(bytes, add_offset(local_offset, 26)?)?);
+        let local_extra_length = usize::from(read_u16(bytes, add_offset(local_offset, 28)?)?);
+        let data_offset = add_offset(
+            add_offset(local_offset, 30)?,
+            local_name_length + local_extra_length,
+        )?;
+        let Some(data) = bytes.get(data_offset..add_offset(data_offset, compressed_size)?) else {
+            hq_bad_proj!("unexpected end of zip archive")
+        };
+        let contents = match method {
+            0 => data.to_vec(),
+            8 => inflate(data, uncompressed_size)?,
+            _ => hq_bad_proj!("zip entry {name} uses unsupported compression method {method}"),
+        };
+        if contents.len() != uncompressed_size {
+            hq_bad_proj!("zip entry {name} is corrupt (size mismatch)")
+        }
+        if crc32(&contents) != crc {
+            hq_bad_proj!("zip entry {name} is corrupt (CRC mismatch)")
+        }
+        entries.push((name, contents));
+    }
+    Ok(entries)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a zip archive with the given (name, contents) entries, all stored uncompressed.
+    fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = vec![];
+        let mut central_directory = vec![];
+        for (name, contents) in files {
+            let local_offset = u32::try_from(zip.len()).unwrap();
+            let size = u32::try_from(contents.len()).unwrap();
+            let name_length = u16::try_from(name.len()).unwrap();
+            zip.extend(LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+            zip.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            zip.extend(crc32(contents).to_le_bytes());
+            zip.extend(size.to_le_bytes());
+            zip.extend(size.to_le_bytes());
+            zip.extend(name_length.to_le_bytes());
+            zip.extend([0, 0]);
+            zip.extend(name.as_bytes());
+            zip.extend(*contents);
+
+            central_directory.extend(CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+            central_directory.extend([20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            central_directory.extend(crc32(contents).to_le_bytes());
+            central_directory.extend(size.to_le_bytes());
+            central_directory.extend(size.to_le_bytes());
+            central_directory.extend(name_length.to_le_bytes());
+            central_directory.extend([0; 12]);
+            central_directory.extend(local_offset.to_le_bytes());
+            central_directory.extend(name.as_bytes());
+        }
+        let central_directory_offset = u32::try_from(zip.len()).unwrap();
+        let central_directory_size = u32::try_from(central_directory.len()).unwrap();
+        let entry_count = u16::try_from(files.len()).unwrap();
+        zip.extend(central_directory);
+        zip.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        zip.extend([0, 0, 0, 0]);
+        zip.extend(entry_count.to_le_bytes());
+        zip.extend(entry_count.to_le_bytes());
+        zip.extend(central_directory_size.to_le_bytes());
+        zip.extend(central_directory_offset.to_le_bytes());
+        zip.extend([0, 0]);
+        zip
+    }
+
+    const PROJECT_JSON: &str = r#"{
+        "targets": [{
+            "isStage": true,
+            "name": "Stage",
+            "variables": {},
+            "lists": {},
+            "broadcasts": {},
+            "blocks": {},
+            "comments": {},
+            "currentCostume": 0,
+            "costumes": [{
+                "assetId": "abc",
+                "name": "backdrop1",
+                "md5ext": "abc.svg",
+                "dataFormat": "svg",
+                "rotationCenterX": 0,
+                "rotationCenterY": 0
+            }],
+            "sounds": [],
+            "volume": 100,
+            "layerOrder": 0
+        }],
+        "monitors": [],
+        "extensions": [],
+        "meta": { "semver": "3.0.0", "vm": "0.2.0", "agent": "" }
+    }"#;
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn loads_project_and_assets() {
+        let zip = stored_zip(&[
+            ("project.json", PROJECT_JSON.as_bytes()),
+            ("abc.svg", b"<svg></svg>"),
+        ]);
+        let archive = Sb3Archive::try_from(&zip[..]).unwrap();
+        assert_eq!(archive.project.targets[0].name.as_ref(), "Stage");
+        assert_eq!(archive.asset("abc.svg"), Some(&b"<svg></svg>"[..]));
+    }
+
+    #[test]
+    fn missing_asset_is_an_error() {
+        let zip = stored_zip(&[("project.json", PROJECT_JSON.as_bytes())]);
+        let err = Sb3Archive::try_from(&zip[..]).unwrap_err();
+        assert!(err.msg.contains("abc.svg"));
+    }
+
+    #[test]
+    fn costume_sizes_are_read_from_assets() {
+        let zip = stored_zip(&[
+            ("project.json", PROJECT_JSON.as_bytes()),
+            (
+                "abc.svg",
+                br#"<svg xmlns="http://www.w3.org/2000/svg" stroke-width="2" width="48px"
+                    height = '64' viewBox="0 0 1 1"></svg>"#,
+            ),
+        ]);
+        let archive = Sb3Archive::try_from(&zip[..]).unwrap();
+        assert_eq!(archive.project.targets[0].costumes[0].size, Some((48.0, 64.0)));
+    }
+
+    #[test]
+    fn image_sizes() {
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 13];
+        png.extend(b"IHDR");
+        png.extend(300u32.to_be_bytes());
+        png.extend(200u32.to_be_bytes());
+        assert_eq!(image_size(CostumeDataFormat::png, &png), Some((300.0, 200.0)));
+        assert_eq!(
+            image_size(CostumeDataFormat::svg, br#"<svg viewBox="0,0 12 34"/>"#),
+            Some((12.0, 34.0))
+        );
+        assert_eq!(image_size(CostumeDataFormat::png, b"not a png"), None);
+    }
+
+    /// The offset of the first central directory header in an archive built by [`stored_zip`].
+    fn central_directory_offset(zip: &[u8]) -> usize {
+        read_usize(zip, zip.len() - 22 + 16).unwrap()
+    }
+
+    #[test]
+    fn corrupt_entries_are_errors() {
+        let zip = stored_zip(&[
+            ("project.json", PROJECT_JSON.as_bytes()),
+            ("abc.svg", b"<svg></svg>"),
+        ]);
+
+        let mut wrong_crc = zip.clone();
+        wrong_crc[central_directory_offset(&zip) + 16] ^= 1;
+        let err = Sb3Archive::try_from(&wrong_crc[..]).unwrap_err();
+        assert!(err.msg.contains("CRC mismatch"));
+
+        let mut wrong_size = zip.clone();
+        wrong_size[central_directory_offset(&zip) + 24] += 1;
+        let err = Sb3Archive::try_from(&wrong_size[..]).unwrap_err();
+        assert!(err.msg.contains("size mismatch"));
+
+        // a local header offset near the end of the address space mustn't overflow
+        let mut huge_offset = zip;
+        let local_offset = central_directory_offset(&huge_offset) + 42;
+        huge_offset[local_offset..local_offset + 4].fill(0xff);
+        assert!(Sb3Archive::try_from(&huge_offset[..]).is_err());
+    }
+
+    #[test]
+    fn missing_project_json_is_an_error() {
+        let zip = stored_zip(&[("abc.svg", b"<svg></svg>")]);
+        assert!(Sb3Archive::try_from(&zip[..]).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! A decoder for raw DEFLATE streams ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951)), which
+//! is the compression method used by almost all zip archives (including `.sb3` files).
+
+use crate::prelude::*;
+
+/// (base length, extra bits) for length codes 257..=285
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// (base distance, extra bits) for distance codes 0..=29
+const DISTANCES: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+/// the order in which code length code lengths are given in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// the position of the next unread bit
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn bit(&mut self) -> HQResult<u16> {
+        let Some(byte) = self.bytes.get(self.position / 8) else {
+            hq_bad_proj!("unexpected end of deflate stream")
+        };
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit.into())
+    }
+
+    /// Reads `count` bits, least significant bit first.
+    fn bits(&mut self, count: u8) -> HQResult<u16> {
+        let mut value = 0;
+        for i in 0..count {
+            value |= self.bit()? << i;
+        }
+        Ok(value)
+    }
+
+    const fn align_to_byte(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+
+    fn bytes(&mut self, count: usize) -> HQResult<&'a [u8]> {
+        let start = self.position / 8;
+        let Some(bytes) = start
+            .checked_add(count)
+            .and_then(|end| self.bytes.get(start..end))
+        else {
+            hq_bad_proj!("unexpected end of deflate stream")
+        };
+        self.position += count * 8;
+        Ok(bytes)
+    }
+}
+
+/// A canonical Huffman code, as described in section 3.2.2 of RFC 1951.
+struct Huffman {
+    /// the number of codes of each length
+    counts: [u16; 16],
+    /// the symbols, ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> HQResult<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                let offset = &mut offsets[usize::from(length)];
+                symbols[usize::from(*offset)] = u16::try_from(symbol)
+                    .map_err(|_| make_hq_bug!("huffman symbol out of bounds"))?;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> HQResult<u16> {
+        // the first code of the current length
+        let mut first = 0;
+        // the index in `symbols` of the first code of the current length
+        let mut index = 0;
+        let mut code = 0;
+        for &count in &self.counts[1..] {
+            code |= reader.bit()?;
+            if code < first + count {
+                return Ok(self.symbols[usize::from(index + code - first)]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        hq_bad_proj!("invalid huffman code in deflate stream")
+    }
+}
+
+fn fixed_codes() -> HQResult<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> HQResult<(Huffman, Huffman)> {
+    let literal_count = usize::from(reader.bits(5)?) + 257;
+    let distance_count = usize::from(reader.bits(5)?) + 1;
+    let code_length_count = usize::from(reader.bits(4)?) + 4;
+    let mut code_length_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[index] = u8::try_from(reader.bits(3)?)
+            .map_err(|_| make_hq_bug!("3-bit value out of bounds for u8"))?;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths)?;
+    let mut lengths: Vec<u8> = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (
+                u8::try_from(symbol).map_err(|_| make_hq_bug!("code length out of bounds"))?,
+                1,
+            ),
+            16 => {
+                let Some(&previous) = lengths.last() else {
+                    hq_bad_proj!("deflate code length repeat with no previous length")
+                };
+                (previous, reader.bits(2)? + 3)
+            }
+            17 => (0, reader.bits(3)? + 3),
+            18 => (0, reader.bits(7)? + 11),
+            _ => hq_bad_proj!("invalid code length symbol in deflate stream"),
+        };
+        lengths.extend(core::iter::repeat_n(length, usize::from(repeat)));
+    }
+    if lengths.len() > literal_count + distance_count {
+        hq_bad_proj!("too many code lengths in deflate stream")
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+/// Checks that `length` more bytes can be written to `output` without it growing past `size`.
+fn check_output_size(output: &[u8], length: usize, size: usize) -> HQResult<()> {
+    if output
+        .len()
+        .checked_add(length)
+        .is_none_or(|new_length| new_length > size)
+    {
+        hq_bad_proj!("deflate stream is longer than its declared size")
+    }
+    Ok(())
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> HQResult<()> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        match symbol {
+            0..=255 => {
+                check_output_size(output, 1, size)?;
+                output.push(
+                    u8::try_from(symbol)
+                        .map_err(|_| make_hq_bug!("literal out of bounds for u8"))?,
+                );
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let (length_base, length_extra) = LENGTHS[usize::from(symbol - 257)];
+                let length = usize::from(length_base + reader.bits(length_extra)?);
+                let Some(&(distance_base, distance_extra)) =
+                    DISTANCES.get(usize::from(distances.decode(reader)?))
+                else {
+                    hq_bad_proj!("invalid distance code in deflate stream")
+                };
+                let distance =
+                    usize::from(distance_base) + usize::from(reader.bits(distance_extra)?);
+                let Some(start) = output.len().checked_sub(distance) else {
+                    hq_bad_proj!("deflate back-reference is too far back")
+                };
+                check_output_size(output, length, size)?;
+                // the referenced range may overlap with the bytes being written, so this must
+                // be copied byte-by-byte
+                for i in start..start + length {
+                    output.push(output[i]);
+                }
+            }
+            _ => hq_bad_proj!("invalid literal/length code in deflate stream"),
+        }
+    }
+}
+
+/// Decompresses a raw DEFLATE stream, which must decompress to at most `size` bytes (the
+/// uncompressed size declared by the archive), so that a corrupt or malicious stream can't
+/// expand to fill all available memory.
+pub fn inflate(bytes: &[u8], size: usize) -> HQResult<Vec<u8>> {
+    let mut reader = BitReader::new(bytes);
+    let mut output = vec![];
+    loop {
+        let last = reader.bit()? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let &[a, b, c, d] = reader.bytes(4)? else {
+                    hq_bug!("BitReader::bytes returned the wrong number of bytes")
+                };
+                let length = u16::from_le_bytes([a, b]);
+                let inverse_length = u16::from_le_bytes([c, d]);
+                if length != !inverse_length {
+                    hq_bad_proj!("corrupt stored block in deflate stream")
+                }
+                check_output_size(&output, length.into(), size)?;
+                output.extend_from_slice(reader.bytes(length.into())?);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &mut output, size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, size, &literals, &distances)?;
+            }
+            _ => hq_bad_proj!("invalid block type in deflate stream"),
+        }
+        if last {
+            break Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inflate;
+
+    #[test]
+    fn inflates_stored_block() {
+        assert_eq!(
+            inflate(
+                &[0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'],
+                5
+            )
+            .unwrap(),
+            b"hello"
+        );
+    }
+
+    #[test]
+    fn inflates_fixed_huffman_block() {
+        // zlib.compress(b"hello hello hello hello")[2:-4]
+        assert_eq!(
+            inflate(
+                &[0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01],
+                23
+            )
+            .unwrap(),
+            b"hello hello hello hello"
+        );
+    }
+
+    #[test]
+    fn inflates_dynamic_huffman_block() {
+        // zlib.compress(b"the quick brown fox jumps over the lazy dog. " * 3
+        //     + b"pack my box with five dozen liquor jugs", 9)[2:-4]
+        let compressed = [
+            0xb5, 0xcb, 0xc9, 0x11, 0x80, 0x20, 0x10, 0x44, 0xd1, 0x54, 0x3a, 0x02, 0x73, 0x02,
+            0x65, 0x53, 0x60, 0xd8, 0x11, 0xa3, 0x77, 0xca, 0x1c, 0x3c, 0x76, 0xfd, 0xd7, 0xcd,
+            0x2a, 0xe4, 0xee, 0xf6, 0x0b, 0xb2, 0xd0, 0x8c, 0xd0, 0x74, 0xe3, 0xec, 0x21, 0x55,
+            0xd0, 0x50, 0x05, 0x8d, 0xb3, 0x17, 0xcf, 0xc2, 0x41, 0x66, 0xfb, 0xd6, 0x3f, 0x38,
+            0x09, 0x76, 0x61, 0x41, 0x32, 0x9a, 0xae, 0x59, 0x68, 0x37, 0x14, 0xa7, 0x47, 0x45,
+            0x78, 0x97, 0x3b, 0x15, 0xfe, 0x9a, 0xfa, 0x02,
+        ];
+        assert_eq!(
+            inflate(&compressed, 174).unwrap(),
+            [
+                &b"the quick brown fox jumps over the lazy dog. ".repeat(3)[..],
+                b"pack my box with five dozen liquor jugs"
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        assert!(inflate(&[0xcb, 0x48], 23).is_err());
+    }
+
+    #[test]
+    fn rejects_output_longer_than_declared_size() {
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01];
+        assert!(inflate(&fixed, 22).is_err());
+        let stored = [0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'];
+        assert!(inflate(&stored, 4).is_err());
+    }
+}
@@ -9,7 +9,7 @@ pub mod registries;
 pub use external::ExternalEnvironment;
 pub use flags::WasmFlags;
 pub use func::{Instruction as InternalInstruction, StepFunc, StepTarget};
-pub use project::{FinishedWasm, WasmProject};
+pub use project::{EventHandler, FinishedWasm, WasmProject};
 pub use registries::{
     GlobalExportable, GlobalMutable, Registries, StepsTable, StringsTable, ThreadsTable,
 };
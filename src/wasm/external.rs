@@ -1,5 +1,80 @@
-#[derive(Debug, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::prelude::*;
+
+/// The environment that a compiled project will be run in, which determines where its imports
+/// come from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
+#[wasm_bindgen]
 pub enum ExternalEnvironment {
+    /// Imports are provided by the JavaScript glue in `js/`, with each function imported from
+    /// the module corresponding to its directory (e.g. `looks.say_string`), and string
+    /// functions coming from the JS String Builtins (`wasm:js-string`).
     WebBrowser,
+    /// Imports are provided by a non-browser host (e.g. wasmtime), using the following ABI:
+    ///
+    /// - strings are stored in the linear memory exported as `strings`, as little-endian UTF-16
+    ///   code units. A string is passed around as an i64 whose upper 32 bits are the byte offset
+    ///   of its first code unit in that memory, and whose lower 32 bits are its length in code
+    ///   units, so `0` is always the empty string. Strings are immutable.
+    /// - hosts which return a new string should allocate it by calling the exported
+    ///   `string_alloc` function with the string's length in code units, which returns a string
+    ///   of that length, and then write the code units into the memory that it points to.
+    /// - strings which aren't constants are allocated in a scratch region which is reused at the
+    ///   start of every call to `tick`, so hosts must copy any string that they are passed if
+    ///   they need it after the current tick. Strings which the project stores (e.g. in a
+    ///   variable or a list) are copied out of the scratch region into memory which is never
+    ///   freed; hosts which set an exported string global (e.g. `sensing_answer`) should do the
+    ///   same by passing the string to the exported `string_persist` function, which returns
+    ///   the handle of the copy.
+    /// - string constants are defined in the module, so no globals are imported.
+    /// - say/think output goes to the logging hooks in the `hq_log` module, which are named
+    ///   `say_{type}`, `say_debug_{type}`, `think_{type}` and `think_debug_{type}` (where
+    ///   `type` is `int`, `float` or `string`), and take the value followed by the index of the
+    ///   target which is speaking.
+    /// - every other function is imported from the `hq_host` module. String functions are named
+    ///   `string_{name}`, e.g. `string_concat`, and behave like the
+    ///   [JS String Builtins](https://github.com/WebAssembly/js-string-builtins) of the same
+    ///   name; all other functions are named `{module}_{name}`, e.g. `pen_line`, and have the
+    ///   same signatures as their counterparts in `js/`. Strings are represented as above in
+    ///   both cases.
+    ///
+    /// Functions which only affect rendering (e.g. `pen_line`) may be implemented as no-ops.
+    Headless,
+}
+
+impl ExternalEnvironment {
+    /// Are strings stored in linear memory (see [`ExternalEnvironment::Headless`]), rather than
+    /// being represented by `externref`s?
+    #[must_use]
+    pub const fn linear_memory_strings(self) -> bool {
+        matches!(self, Self::Headless)
+    }
+
+    /// Maps an import as it is registered by an instruction to the module and name that it
+    /// should be imported as in this environment.
+    #[must_use]
+    pub fn import_name<'a>(self, module: &'a str, name: &'a str) -> (&'a str, Cow<'a, str>) {
+        match self {
+            Self::WebBrowser => (module, Cow::Borrowed(name)),
+            Self::Headless if module == "looks" && is_bubble_import(name) => {
+                ("hq_log", Cow::Borrowed(name))
+            }
+            Self::Headless => (
+                "hq_host",
+                Cow::Owned(if module == "wasm:js-string" {
+                    format!("string_{name}")
+                } else {
+                    format!("{module}_{name}")
+                }),
+            ),
+        }
+    }
+}
+
+/// Is this the name of one of the `looks` imports which update a speech or thought bubble?
+fn is_bubble_import(name: &str) -> bool {
+    name.starts_with("say_") || name.starts_with("think_")
 }
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use super::ExternalEnvironment;
 use crate::prelude::*;
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -35,6 +36,20 @@ pub enum VarTypeConvergence {
     Tight,
 }
 
+#[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum RandomSource {
+    Host,
+    Seeded,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum ClockSource {
+    Host,
+    Virtual,
+}
+
 // #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 // #[wasm_bindgen]
 // pub enum MemoryLayout {
@@ -161,6 +176,47 @@ macro_rules! stringmap {
     }}
 }
 
+/// The value of a flag, as named in the keys of [`FlagInfo::wasm_features`].
+trait FlagValue {
+    fn flag_value(&self) -> String;
+}
+
+/// implements [`FlagValue`] for enums, naming each value after its variant
+macro_rules! enum_flag_value {
+    ($($ty:ty { $($variant:ident),+ $(,)? })+) => {$(
+        impl FlagValue for $ty {
+            fn flag_value(&self) -> String {
+                match self {
+                    $(Self::$variant => stringify!($variant),)+
+                }
+                .into()
+            }
+        }
+    )+};
+}
+
+enum_flag_value! {
+    WasmStringType { ExternRef, JsStringBuiltins }
+    Switch { On, Off }
+    ListType { GCArray, LinearMemory }
+    VarTypeConvergence { Any, Base, Tight }
+    RandomSource { Host, Seeded }
+    ClockSource { Host, Virtual }
+    ExternalEnvironment { WebBrowser, Headless }
+}
+
+impl FlagValue for u32 {
+    fn flag_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl FlagValue for f64 {
+    fn flag_value(&self) -> String {
+        self.to_string()
+    }
+}
+
 /// stringifies the name of a type whilst ensuring that the type is valid
 macro_rules! ty_str {
     ($ty:ty) => {{
@@ -187,6 +243,13 @@ pub struct WasmFlags {
     pub do_ssa: Switch,
     pub eager_number_parsing: Switch,
     pub variable_merging: Switch,
+    pub skip_unsupported_blocks: Switch,
+    pub rng: RandomSource,
+    pub clock: ClockSource,
+    pub virtual_epoch: f64,
+    pub virtual_framerate: u32,
+    pub warp_timer: u32,
+    pub environment: ExternalEnvironment,
     // pub memory_layout: MemoryLayout
 }
 
@@ -222,6 +285,8 @@ impl WasmFlags {
             },
             print_ir: Switch::Off,
             integers: Switch::Off,
+            // GC is still needed for threads and clones (see `required_wasm_features`), so this
+            // fallback doesn't let projects run without it
             list_type: if wasm_features.contains(&WasmFeature::GC) {
                 ListType::GCArray
             } else {
@@ -232,9 +297,84 @@ impl WasmFlags {
             do_ssa: Switch::On,
             eager_number_parsing: Switch::On,
             variable_merging: Switch::On,
+            skip_unsupported_blocks: Switch::Off,
+            rng: RandomSource::Host,
+            clock: ClockSource::Host,
+            virtual_epoch: 0.0,
+            virtual_framerate: 30,
+            warp_timer: 0,
+            environment: ExternalEnvironment::WebBrowser,
         }
     }
 
+    /// The WASM features which modules compiled with these flags rely on, as given by the
+    /// [`FlagInfo`] of each flag for its current value.
+    ///
+    /// This always includes the features that compiled modules use regardless of the flags:
+    /// [`WasmFeature::ReferenceTypes`] and [`WasmFeature::GC`], as threads are represented as GC
+    /// structs stored in tables; [`WasmFeature::TypedFunctionReferences`], for calling steps
+    /// with `return_call_ref`; and [`WasmFeature::BulkMemory`], for `memory.copy` and
+    /// `memory.fill`.
+    #[wasm_bindgen]
+    #[must_use]
+    pub fn required_wasm_features(&self) -> Vec<WasmFeature> {
+        // destructured so that new flags can't be left out
+        let Self {
+            string_type,
+            wasm_opt,
+            print_ir,
+            integers,
+            list_type,
+            unroll_loops,
+            var_type_convergence,
+            do_ssa,
+            eager_number_parsing,
+            variable_merging,
+            skip_unsupported_blocks,
+            rng,
+            clock,
+            virtual_epoch,
+            virtual_framerate,
+            warp_timer,
+            environment,
+        } = self;
+        let flags: [(&str, &dyn FlagValue); 17] = [
+            ("string_type", string_type),
+            ("wasm_opt", wasm_opt),
+            ("print_ir", print_ir),
+            ("integers", integers),
+            ("list_type", list_type),
+            ("unroll_loops", unroll_loops),
+            ("var_type_convergence", var_type_convergence),
+            ("do_ssa", do_ssa),
+            ("eager_number_parsing", eager_number_parsing),
+            ("variable_merging", variable_merging),
+            ("skip_unsupported_blocks", skip_unsupported_blocks),
+            ("rng", rng),
+            ("clock", clock),
+            ("virtual_epoch", virtual_epoch),
+            ("virtual_framerate", virtual_framerate),
+            ("warp_timer", warp_timer),
+            ("environment", environment),
+        ];
+        let mut features = vec![
+            WasmFeature::ReferenceTypes,
+            WasmFeature::GC,
+            WasmFeature::TypedFunctionReferences,
+            WasmFeature::BulkMemory,
+        ];
+        for feature in flags.into_iter().flat_map(|(flag, value)| {
+            Self::flag_info(flag)
+                .wasm_features(&value.flag_value())
+                .unwrap_or_default()
+        }) {
+            if !features.contains(&feature) {
+                features.push(feature);
+            }
+        }
+        features
+    }
+
     #[wasm_bindgen]
     #[must_use]
     pub fn flag_info(flag: &str) -> FlagInfo {
@@ -256,12 +396,14 @@ impl WasmFlags {
                 .with_description(
                     "GCArray (recommended) - uses GC arrays.\
                     <br>\
-                    LinearMemory (unimplemented) - stores lists in linear memory."
+                    LinearMemory - stores lists in growable buffers in linear memory. This still \
+                    needs GC, as the lists of each clone are kept in GC arrays (and threads are \
+                    GC structs regardless)."
                 )
                 .with_ty(ty_str!(ListType))
                 .with_wasm_features(stringmap! {
                     GCArray : vec![WasmFeature::GC],
-                    LinearMemory : vec![]
+                    LinearMemory : vec![WasmFeature::BulkMemory, WasmFeature::GC]
                 }),
             // "memory_layout" => FlagInfo::new()
             //     .with_name("Memory layout")
@@ -325,6 +467,70 @@ impl WasmFlags {
                 .with_name("Merge variables")
                 .with_description("Merges variables of the same type. Can improve wasm-opt performance.")
                 .with_ty(ty_str!(Switch)),
+            "skip_unsupported_blocks" => FlagInfo::new()
+                .with_name("Skip unsupported blocks")
+                .with_description("Compile projects which use unsupported blocks anyway, replacing \
+                unsupported stack blocks with blocks that do nothing and unsupported reporters with \
+                empty strings.\
+                <br>\
+                Projects compiled like this may not work as expected.")
+                .with_ty(ty_str!(Switch)),
+            "rng" => FlagInfo::new()
+                .with_name("Random number generator")
+                .with_description("Host (recommended) - uses the host's random number generator \
+                (i.e. <code>Math.random</code>).\
+                <br>\
+                Seeded - uses a pseudo-random number generator within the module, which can be \
+                seeded with the exported <code>set_random_seed</code> function so that runs can be \
+                reproduced (e.g. for testing or debugging).")
+                .with_ty(ty_str!(RandomSource)),
+            "clock" => FlagInfo::new()
+                .with_name("Clock")
+                .with_description("Host (recommended) - the timer follows the host's clock, and \
+                <code>days since 2000</code> uses the current date.\
+                <br>\
+                Virtual - the timer advances by exactly one frame of the virtual framerate on \
+                each tick, and <code>days since 2000</code> starts at the virtual epoch and \
+                advances along with it, so that runs can be reproduced (e.g. for testing or \
+                debugging).")
+                .with_ty(ty_str!(ClockSource)),
+            "virtual_epoch" => FlagInfo::new()
+                .with_name("Virtual epoch")
+                .with_description("The value of <code>days since 2000</code> when a project \
+                using a virtual clock starts; this may be fractional. Negative values are treated \
+                as 0.")
+                .with_ty(ty_str!(f64)),
+            "virtual_framerate" => FlagInfo::new()
+                .with_name("Virtual framerate")
+                .with_description("The number of ticks per second when using a virtual clock; \
+                the timer advances by 1/n of a second on each tick. Values below 1 are treated as 1.\
+                <br>\
+                Recommended: 30, which matches Scratch.")
+                .with_ty(ty_str!(u32)),
+            "warp_timer" => FlagInfo::new()
+                .with_name("Warp timer")
+                .with_description("Stops infinite loops in 'run without screen refresh' custom \
+                blocks from freezing the page, like TurboWarp's warp timer. Loops in these blocks \
+                check the time every n iterations, and yield once the current frame has been \
+                running for more than half a second; with the virtual clock, they yield every n \
+                iterations instead. Warped blocks run more slowly with this enabled, as they have \
+                to be compiled in such a way that they can yield.\
+                <br>\
+                Recommended: 0 (disabled) for finished projects.")
+                .with_ty(ty_str!(u32)),
+            "environment" => FlagInfo::new()
+                .with_name("Runtime environment")
+                .with_description("WebBrowser (recommended) - runs in the browser, using the JavaScript glue.\
+                <br>\
+                Headless - runs on a non-browser host (e.g. wasmtime). Strings are stored in the \
+                exported <code>strings</code> memory, say/think blocks are logged through the \
+                <code>hq_log</code> module, and all other imports come from the \
+                <code>hq_host</code> module.")
+                .with_ty(ty_str!(ExternalEnvironment))
+                .with_wasm_features(stringmap! {
+                    WebBrowser : vec![],
+                    Headless : vec![WasmFeature::MultiMemory]
+                }),
             _ => FlagInfo::new().with_name(format!("unknown setting '{flag}'").as_str()),
         }
     }
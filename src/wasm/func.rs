@@ -7,29 +7,39 @@ use wasm_encoder::{
 };
 use wasm_gen::wasm;
 
-use super::{Registries, WasmFlags, WasmProject};
+use super::{EventHandler, Registries, WasmFlags, WasmProject};
 use crate::instructions::{IrOpcode, wrap_instructions};
-use crate::ir::{Event, PartialStep, Proc, RcVar, Step, StepIndex};
+use crate::ir::{Event, IrType, PartialStep, Proc, RcVar, Step, StepIndex};
 use crate::prelude::*;
-use crate::wasm::registries::TypeRegistry;
+use crate::wasm::registries::functions::static_functions::PersistString;
+use crate::wasm::registries::{LinearStringLayout, TypeRegistry, linear_string_val_type};
 
 #[derive(Clone, Debug)]
 pub enum Instruction {
     Immediate(wasm_encoder::Instruction<'static>),
     LazyStepRef(StepIndex),
     LazyWarpedProcCall(Rc<Proc>),
-    LazyNonWarpedProcRef(Rc<Proc>),
+    /// (procedure, whether to use its warp-timed variant)
+    LazyNonWarpedProcRef((Rc<Proc>, bool)),
     LazyGlobalGet(u32),
     LazyGlobalSet(u32),
     LazyBroadcastSpawn(Box<str>),
-    LazyBroadcastSpawnAndWait((Box<str>, StepIndex, StepIndex, u32)),
+    /// (broadcast name, poll step, next step, thread indices array local, i32 local, i32 local)
+    LazyBroadcastSpawnAndWait((Box<str>, StepIndex, StepIndex, u32, u32, u32)),
+    /// (IR target index, local holding the new clone's instance index)
+    LazyCloneStartSpawn((u32, u32)),
+    /// (local holding the index of the backdrop that has just been switched to)
+    LazyBackdropSwitchSpawn(u32),
+    /// (backdrop index local, poll step, next step, thread indices array local, i32 local, i32
+    /// local)
+    LazyBackdropSwitchSpawnAndWait((u32, StepIndex, StepIndex, u32, u32, u32)),
     StaticFunctionCall(u32),
 }
 
 impl Instruction {
     pub fn eval(
         &self,
-        events: &BTreeMap<Event, Vec<u32>>,
+        events: &BTreeMap<Event, Vec<EventHandler>>,
         types: &Rc<TypeRegistry>,
         threads_count_global: u32,
         spawn_new_thread_func: u32,
@@ -53,113 +63,114 @@ impl Instruction {
                         .map_err(|_| make_hq_bug!("step index out of bounds"))?,
             )]),
             Self::LazyBroadcastSpawn(broadcast) => {
-                let broadcast_indices = events
+                let broadcast_handlers = events
                     .get(&Event::Broadcast(broadcast.clone()))
                     .cloned()
                     .unwrap_or_default();
 
                 // todo: these should begin execution in the same step, I think, possibly immediately?
 
-                broadcast_indices
-                    .iter()
-                    .flat_map(|&i| {
-                        [
-                            WInstruction::RefFunc(i + imported_func_count + static_func_count),
-                            WInstruction::RefNull(HeapType::Abstract {
-                                shared: false,
-                                ty: AbstractHeapType::Struct,
-                            }),
-                            WInstruction::Call(spawn_new_thread_func + imported_func_count),
-                        ]
-                    })
-                    .chain([
-                        WInstruction::GlobalGet(threads_count_global + imported_global_count),
-                        WInstruction::I32Const(
-                            i32::try_from(broadcast_indices.len())
-                                .map_err(|_| make_hq_bug!("indices len out of bounds"))?,
-                        ),
-                        WInstruction::I32Add,
-                        WInstruction::GlobalSet(threads_count_global + imported_global_count),
-                    ])
-                    .collect()
+                Self::spawn_handlers(
+                    &broadcast_handlers,
+                    None,
+                    threads_count_global,
+                    spawn_new_thread_func,
+                    imported_func_count,
+                    static_func_count,
+                    imported_global_count,
+                )?
+                .into()
             }
-            Self::LazyBroadcastSpawnAndWait((broadcast, poll_step, next_step, arr_local)) => {
-                let broadcast_indices = events
+            Self::LazyBroadcastSpawnAndWait((
+                broadcast,
+                poll_step,
+                next_step,
+                arr_local,
+                start_local,
+                index_local,
+            )) => {
+                let broadcast_handlers = events
                     .get(&Event::Broadcast(broadcast.clone()))
                     .cloned()
                     .unwrap_or_default();
 
-                let i32_array_type = types.array(StorageType::Val(ValType::I32), true)?;
-                let thread_poll_struct = types.struct_(vec![FieldType {
-                    element_type: StorageType::Val(ValType::Ref(RefType {
-                        nullable: false,
-                        heap_type: HeapType::Concrete(i32_array_type),
-                    })),
-                    mutable: false,
-                }])?;
-
-                let poll_step_index: u32 = poll_step
-                    .0
-                    .try_into()
-                    .map_err(|_| make_hq_bug!("poll_step index out of bounds"))?;
-
-                let next_step_index: u32 = next_step
-                    .0
-                    .try_into()
-                    .map_err(|_| make_hq_bug!("next_step index out of bounds"))?;
-
-                let broadcast_num = i32::try_from(broadcast_indices.len())
-                    .map_err(|_| make_hq_bug!("indices len out of bounds"))?;
-
-                [
-                    WInstruction::I32Const(broadcast_num),
-                    WInstruction::ArrayNewDefault(i32_array_type),
-                    WInstruction::LocalSet(*arr_local),
-                ]
-                .into_iter()
-                .chain(
-                    broadcast_indices
-                        .iter()
-                        .enumerate()
-                        .map(|(j, &i)| {
-                            // todo: should these should begin execution in the same step?
-                            Ok([
-                                WInstruction::LocalGet(*arr_local),
-                                WInstruction::I32Const(
-                                    j.try_into()
-                                        .map_err(|_| make_hq_bug!("index out of bounds"))?,
-                                ),
-                                WInstruction::TableSize(threads_table),
-                                WInstruction::ArraySet(i32_array_type),
-                                WInstruction::RefFunc(i + imported_func_count + static_func_count),
-                                WInstruction::RefNull(HeapType::Abstract {
-                                    shared: false,
-                                    ty: AbstractHeapType::Struct,
-                                }),
-                                WInstruction::Call(spawn_new_thread_func + imported_func_count),
-                            ])
-                        })
-                        .collect::<HQResult<Box<[_]>>>()?
-                        .into_iter()
-                        .flatten(),
-                )
-                .chain([
-                    WInstruction::GlobalGet(threads_count_global + imported_global_count),
-                    WInstruction::I32Const(broadcast_num),
-                    WInstruction::I32Add,
-                    WInstruction::GlobalSet(threads_count_global + imported_global_count),
-                    WInstruction::RefFunc(
-                        poll_step_index + imported_func_count + static_func_count,
-                    ),
-                    WInstruction::LocalGet(*arr_local),
-                    WInstruction::StructNew(thread_poll_struct),
-                    WInstruction::RefFunc(
-                        next_step_index + imported_func_count + static_func_count,
-                    ),
-                    WInstruction::Call(spawn_thread_in_stack_func + imported_func_count),
-                ])
-                .collect()
+                Self::spawn_and_wait(
+                    Self::spawn_handlers(
+                        &broadcast_handlers,
+                        None,
+                        threads_count_global,
+                        spawn_new_thread_func,
+                        imported_func_count,
+                        static_func_count,
+                        imported_global_count,
+                    )?,
+                    *poll_step,
+                    *next_step,
+                    *arr_local,
+                    *start_local,
+                    *index_local,
+                    types,
+                    spawn_thread_in_stack_func,
+                    threads_table,
+                    imported_func_count,
+                    static_func_count,
+                )?
             }
+            Self::LazyCloneStartSpawn((target_index, instance_local)) => {
+                let clone_handlers = events
+                    .get(&Event::CloneStart(*target_index))
+                    .cloned()
+                    .unwrap_or_default();
+
+                Self::spawn_handlers(
+                    &clone_handlers,
+                    Some(*instance_local),
+                    threads_count_global,
+                    spawn_new_thread_func,
+                    imported_func_count,
+                    static_func_count,
+                    imported_global_count,
+                )?
+                .into()
+            }
+            Self::LazyBackdropSwitchSpawn(backdrop_local) => Self::backdrop_switch_spawn(
+                events,
+                *backdrop_local,
+                threads_count_global,
+                spawn_new_thread_func,
+                imported_func_count,
+                static_func_count,
+                imported_global_count,
+            )?
+            .into(),
+            Self::LazyBackdropSwitchSpawnAndWait((
+                backdrop_local,
+                poll_step,
+                next_step,
+                arr_local,
+                start_local,
+                index_local,
+            )) => Self::spawn_and_wait(
+                Self::backdrop_switch_spawn(
+                    events,
+                    *backdrop_local,
+                    threads_count_global,
+                    spawn_new_thread_func,
+                    imported_func_count,
+                    static_func_count,
+                    imported_global_count,
+                )?,
+                *poll_step,
+                *next_step,
+                *arr_local,
+                *start_local,
+                *index_local,
+                types,
+                spawn_thread_in_stack_func,
+                threads_table,
+                imported_func_count,
+                static_func_count,
+            )?,
             Self::LazyWarpedProcCall(proc) => {
                 let Some(ref warped_specific_proc) = *proc.warped_specific_proc() else {
                     hq_bug!("tried to use LazyWarpedProcCall on a non-warped step")
@@ -174,8 +185,10 @@ impl Instruction {
                             .map_err(|_| make_hq_bug!("step index out of bounds"))?,
                 )])
             }
-            Self::LazyNonWarpedProcRef(proc) => {
-                let Some(ref nonwarped_specific_proc) = *proc.nonwarped_specific_proc() else {
+            Self::LazyNonWarpedProcRef((proc, warp_timer)) => {
+                let Some(ref nonwarped_specific_proc) =
+                    *proc.nonwarped_specific_proc_for(*warp_timer)
+                else {
                     hq_bug!("tried to use LazyNonWarpedProcRef on a non-non-warped step")
                 };
                 let PartialStep::Finished(step_index) = *nonwarped_specific_proc.first_step()?
@@ -200,6 +213,179 @@ impl Instruction {
             }
         })
     }
+
+    /// Spawns the handlers of every backdrop switch hat whose backdrop is the one whose index is
+    /// in `backdrop_local`.
+    fn backdrop_switch_spawn(
+        events: &BTreeMap<Event, Vec<EventHandler>>,
+        backdrop_local: u32,
+        threads_count_global: u32,
+        spawn_new_thread_func: u32,
+        imported_func_count: u32,
+        static_func_count: u32,
+        imported_global_count: u32,
+    ) -> HQResult<Vec<WInstruction<'static>>> {
+        let mut instructions = vec![];
+        for (event, handlers) in events {
+            let Event::BackdropSwitchesTo(backdrop_index) = event else {
+                continue;
+            };
+            instructions.extend([
+                WInstruction::LocalGet(backdrop_local),
+                WInstruction::I32Const(
+                    (*backdrop_index)
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("backdrop index out of bounds"))?,
+                ),
+                WInstruction::I32Eq,
+                WInstruction::If(wasm_encoder::BlockType::Empty),
+            ]);
+            instructions.extend(Self::spawn_handlers(
+                handlers,
+                None,
+                threads_count_global,
+                spawn_new_thread_func,
+                imported_func_count,
+                static_func_count,
+                imported_global_count,
+            )?);
+            instructions.push(WInstruction::End);
+        }
+        Ok(instructions)
+    }
+
+    /// Runs `spawn`, and then moves the current thread onto `poll_step`, which waits for all of
+    /// the threads spawned by `spawn` to finish before continuing with `next_step`.
+    fn spawn_and_wait(
+        spawn: Vec<WInstruction<'static>>,
+        poll_step: StepIndex,
+        next_step: StepIndex,
+        arr_local: u32,
+        start_local: u32,
+        index_local: u32,
+        types: &Rc<TypeRegistry>,
+        spawn_thread_in_stack_func: u32,
+        threads_table: u32,
+        imported_func_count: u32,
+        static_func_count: u32,
+    ) -> HQResult<Box<[WInstruction<'static>]>> {
+        let i32_array_type = types.array(StorageType::Val(ValType::I32), true)?;
+        let thread_poll_struct = types.struct_(vec![FieldType {
+            element_type: StorageType::Val(ValType::Ref(RefType {
+                nullable: false,
+                heap_type: HeapType::Concrete(i32_array_type),
+            })),
+            mutable: false,
+        }])?;
+
+        let poll_step_index: u32 = poll_step
+            .0
+            .try_into()
+            .map_err(|_| make_hq_bug!("poll_step index out of bounds"))?;
+
+        let next_step_index: u32 = next_step
+            .0
+            .try_into()
+            .map_err(|_| make_hq_bug!("next_step index out of bounds"))?;
+
+        // new threads are always added to the end of the threads table, so the spawned
+        // threads are exactly those between the old and new ends of the table; we can't
+        // know how many there are at compile time, as clones may be involved.
+        // todo: should these should begin execution in the same step?
+        Ok([
+            WInstruction::TableSize(threads_table),
+            WInstruction::LocalSet(start_local),
+        ]
+        .into_iter()
+        .chain(spawn)
+        .chain([
+            WInstruction::TableSize(threads_table),
+            WInstruction::LocalGet(start_local),
+            WInstruction::I32Sub,
+            WInstruction::ArrayNewDefault(i32_array_type),
+            WInstruction::LocalSet(arr_local),
+            WInstruction::I32Const(0),
+            WInstruction::LocalSet(index_local),
+            WInstruction::Block(wasm_encoder::BlockType::Empty),
+            WInstruction::Loop(wasm_encoder::BlockType::Empty),
+            WInstruction::LocalGet(index_local),
+            WInstruction::LocalGet(arr_local),
+            WInstruction::ArrayLen,
+            WInstruction::I32GeU,
+            WInstruction::BrIf(1),
+            WInstruction::LocalGet(arr_local),
+            WInstruction::LocalGet(index_local),
+            WInstruction::LocalGet(start_local),
+            WInstruction::LocalGet(index_local),
+            WInstruction::I32Add,
+            WInstruction::ArraySet(i32_array_type),
+            WInstruction::LocalGet(index_local),
+            WInstruction::I32Const(1),
+            WInstruction::I32Add,
+            WInstruction::LocalSet(index_local),
+            WInstruction::Br(0),
+            WInstruction::End,
+            WInstruction::End,
+            WInstruction::RefFunc(poll_step_index + imported_func_count + static_func_count),
+            WInstruction::LocalGet(arr_local),
+            WInstruction::StructNew(thread_poll_struct),
+            WInstruction::RefFunc(next_step_index + imported_func_count + static_func_count),
+            WInstruction::Call(spawn_thread_in_stack_func + imported_func_count),
+        ])
+        .collect())
+    }
+
+    /// Spawns a new thread for each of the given event handlers, and increments the thread count
+    /// accordingly. If `instance_local` is `None`, threads are also spawned for any clones of the
+    /// handlers' targets; otherwise, threads are only spawned for the instance in that local.
+    pub fn spawn_handlers(
+        handlers: &[EventHandler],
+        instance_local: Option<u32>,
+        threads_count_global: u32,
+        spawn_new_thread_func: u32,
+        imported_func_count: u32,
+        static_func_count: u32,
+        imported_global_count: u32,
+    ) -> HQResult<Vec<WInstruction<'static>>> {
+        let mut instructions = vec![];
+        for handler in handlers {
+            let target = i32::try_from(handler.target)
+                .map_err(|_| make_hq_bug!("target index out of bounds"))?;
+            instructions.extend([
+                WInstruction::RefFunc(handler.step + imported_func_count + static_func_count),
+                WInstruction::RefNull(HeapType::Abstract {
+                    shared: false,
+                    ty: AbstractHeapType::Struct,
+                }),
+                instance_local.map_or(WInstruction::I32Const(target), WInstruction::LocalGet),
+                WInstruction::Call(spawn_new_thread_func + imported_func_count),
+            ]);
+            if instance_local.is_none()
+                && let Some((spawn_clone_threads_func, sprite_index)) = handler.clones
+            {
+                instructions.extend([
+                    WInstruction::RefFunc(handler.step + imported_func_count + static_func_count),
+                    WInstruction::I32Const(target),
+                    WInstruction::I32Const(
+                        sprite_index
+                            .try_into()
+                            .map_err(|_| make_hq_bug!("sprite index out of bounds"))?,
+                    ),
+                    WInstruction::Call(spawn_clone_threads_func + imported_func_count),
+                ]);
+            }
+        }
+        instructions.extend([
+            WInstruction::GlobalGet(threads_count_global + imported_global_count),
+            WInstruction::I32Const(
+                i32::try_from(handlers.len())
+                    .map_err(|_| make_hq_bug!("handlers len out of bounds"))?,
+            ),
+            WInstruction::I32Add,
+            WInstruction::GlobalSet(threads_count_global + imported_global_count),
+        ]);
+        Ok(instructions)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -227,6 +413,8 @@ pub struct StepFunc {
     target: StepTarget,
     // the actual target index, for interfacing with js
     target_index: u32,
+    /// the number of targets in the project
+    target_count: u32,
     costume_names: Rc<Vec<Vec<Box<str>>>>,
 }
 
@@ -263,6 +451,12 @@ impl StepFunc {
         &self.costume_names
     }
 
+    /// The number of targets in the project, which is needed to convert between instance
+    /// indices and target indices. This is always at least 1.
+    pub fn target_count(&self) -> HQResult<u32> {
+        Ok(self.target_count.max(1))
+    }
+
     /// creates a new step function, with one paramter
     #[must_use]
     pub fn new(
@@ -270,6 +464,7 @@ impl StepFunc {
         flags: WasmFlags,
         target: StepTarget,
         target_index: u32,
+        target_count: u32,
         costume_names: Rc<Vec<Vec<Box<str>>>>,
     ) -> Self {
         Self {
@@ -283,6 +478,7 @@ impl StepFunc {
             local_variables: RefCell::new(BTreeMap::default()),
             target,
             target_index,
+            target_count,
             costume_names,
         }
     }
@@ -290,6 +486,7 @@ impl StepFunc {
     /// creates a new step function with the specified amount of paramters.
     /// currently only used in testing to validate types
     #[must_use]
+    #[expect(clippy::too_many_arguments, reason = "too many arguments!")]
     pub fn new_with_types(
         params: Box<[ValType]>,
         output: Box<[ValType]>,
@@ -297,6 +494,7 @@ impl StepFunc {
         flags: WasmFlags,
         target: StepTarget,
         target_index: u32,
+        target_count: u32,
         costume_names: Rc<Vec<Vec<Box<str>>>>,
     ) -> Self {
         Self {
@@ -310,6 +508,7 @@ impl StepFunc {
             local_variables: RefCell::new(BTreeMap::default()),
             target,
             target_index,
+            target_count,
             costume_names,
         }
     }
@@ -374,6 +573,24 @@ impl StepFunc {
         Ok(())
     }
 
+    /// Instructions which copy the value at the top of the stack out of the scratch region of
+    /// the strings memory if it is a string (i.e. if `ty` is a string type), for values which are
+    /// about to be stored somewhere that outlives the current tick. This is empty unless strings
+    /// are stored in linear memory (see [`LinearStringLayout`]); boxed strings don't need this,
+    /// as they are copied when their strings table slot is set.
+    pub fn persist_string(&self, ty: IrType) -> HQResult<Vec<Instruction>> {
+        if !self.flags.environment.linear_memory_strings()
+            || WasmProject::ir_type_to_wasm(ty) != ValType::EXTERNREF
+        {
+            return Ok(vec![]);
+        }
+        let persist_string = self
+            .registries()
+            .static_functions()
+            .register::<PersistString, _>()?;
+        Ok(wasm![#StaticFunctionCall(persist_string)])
+    }
+
     pub fn add_instructions(
         &self,
         instructions: impl IntoIterator<Item = Instruction>,
@@ -385,12 +602,15 @@ impl StepFunc {
         Ok(())
     }
 
-    /// Takes ownership of the function and returns the backing `wasm_encoder` `Function`
+    /// Takes ownership of the function and returns the backing `wasm_encoder` `Function`.
+    ///
+    /// If `linear_strings` is `Some`, strings are stored in linear memory, so instructions and
+    /// locals which use `externref` strings are rewritten to use that layout.
     pub fn finish(
         self,
         funcs: &mut FunctionSection,
         code: &mut CodeSection,
-        events: &BTreeMap<Event, Vec<u32>>,
+        events: &BTreeMap<Event, Vec<EventHandler>>,
         types: &Rc<TypeRegistry>,
         threads_count_global: u32,
         spawn_new_thread_func: u32,
@@ -399,8 +619,16 @@ impl StepFunc {
         imported_func_count: u32,
         static_func_count: u32,
         imported_global_count: u32,
+        linear_strings: Option<&LinearStringLayout>,
     ) -> HQResult<()> {
-        let mut func = Function::new_with_locals_types(self.locals.take());
+        let locals = self.locals.take().into_iter().map(|local| {
+            if linear_strings.is_some() {
+                linear_string_val_type(local)
+            } else {
+                local
+            }
+        });
+        let mut func = Function::new_with_locals_types(locals);
         for instruction in self.instructions().take() {
             for real_instruction in instruction.eval(
                 events,
@@ -413,7 +641,11 @@ impl StepFunc {
                 static_func_count,
                 imported_global_count,
             )? {
-                func.instruction(&real_instruction);
+                if let Some(layout) = linear_strings {
+                    layout.instruction(&mut func, &real_instruction)?;
+                } else {
+                    func.instruction(&real_instruction);
+                }
             }
         }
         func.instruction(&wasm_encoder::Instruction::End);
@@ -437,6 +669,7 @@ impl StepFunc {
         steps: &Rc<RefCell<Vec<Self>>>,
         registries: Rc<Registries>,
         flags: WasmFlags,
+        target_count: u32,
         costume_names: Rc<Vec<Vec<Box<str>>>>,
     ) -> HQResult<Self> {
         hq_assert!(
@@ -488,10 +721,18 @@ impl StepFunc {
                 flags,
                 target,
                 target_index,
+                target_count,
                 costume_names,
             )
         } else {
-            Self::new(registries, flags, target, target_index, costume_names)
+            Self::new(
+                registries,
+                flags,
+                target,
+                target_index,
+                target_count,
+                costume_names,
+            )
         };
         if let Some(ref proc_context) = step.try_borrow()?.context().proc_context
             && !step.try_borrow()?.context().warp
@@ -70,6 +70,26 @@ memory_layout! {
     COSTUME: i32
     /// 4-byte adding (so that sprite chunks are aligned to 8 bits)
     _PADDING: i32
+    /// volume of sounds played by the stage (0-100) (f64)
+    VOLUME: f64
+    /// pitch effect of sounds played by the stage (-360-360) (f64)
+    PITCH: f64
+    /// pan effect of sounds played by the stage (-100-100) (f64)
+    PAN: f64
+    /// color graphic effect of stage (f64)
+    COLOR_EFFECT: f64
+    /// fisheye graphic effect of stage (f64)
+    FISHEYE_EFFECT: f64
+    /// whirl graphic effect of stage (f64)
+    WHIRL_EFFECT: f64
+    /// pixelate graphic effect of stage (f64)
+    PIXELATE_EFFECT: f64
+    /// mosaic graphic effect of stage (f64)
+    MOSAIC_EFFECT: f64
+    /// brightness graphic effect of stage (-100-100) (f64)
+    BRIGHTNESS_EFFECT: f64
+    /// ghost graphic effect of stage (0-100) (f64)
+    GHOST_EFFECT: f64
 }
 
 memory_layout! {
@@ -100,12 +120,94 @@ memory_layout! {
     PEN_DOWN: i8
     /// non-zero if sprite is visible, 0 otherwise (i8)
     VISIBLE: i8
-    /// bytes 58-59 padding
-    _PADDING: i16
+    /// non-zero if this block belongs to a clone which currently exists, 0 otherwise (i8)
+    CLONE_ACTIVE: i8
+    /// rotation style of sprite; see `RotationStyle::id` (i8)
+    ROTATION_STYLE: i8
     /// current costume number, 0-indexed (i32)
     COSTUME: i32
     /// sprite size, where default is 100(%) (f64)
     SIZE: f64
     /// sprite rotation, in scratch angles (0 = up, 90 = right) (f64)
     ROTATION: f64
+    /// volume of sounds played by sprite (0-100) (f64)
+    VOLUME: f64
+    /// pitch effect of sounds played by sprite (-360-360) (f64)
+    PITCH: f64
+    /// pan effect of sounds played by sprite (-100-100) (f64)
+    PAN: f64
+    /// color graphic effect of sprite (f64)
+    COLOR_EFFECT: f64
+    /// fisheye graphic effect of sprite (f64)
+    FISHEYE_EFFECT: f64
+    /// whirl graphic effect of sprite (f64)
+    WHIRL_EFFECT: f64
+    /// pixelate graphic effect of sprite (f64)
+    PIXELATE_EFFECT: f64
+    /// mosaic graphic effect of sprite (f64)
+    MOSAIC_EFFECT: f64
+    /// brightness graphic effect of sprite (-100-100) (f64)
+    BRIGHTNESS_EFFECT: f64
+    /// ghost graphic effect of sprite (0-100) (f64)
+    GHOST_EFFECT: f64
+    /// non-zero if sprite can be dragged in the player, 0 otherwise (i8)
+    DRAGGABLE: i8
+    /// 1-byte padding (so that `CLONE_OWNER` is aligned)
+    _PADDING: i8
+    /// wasm index of the sprite which this clone belongs to; only meaningful if `CLONE_ACTIVE` is
+    /// non-zero (i16)
+    CLONE_OWNER: i16
+    /// legacy (scratch 2) shade of pen colour (0-200) (f32)
+    PEN_SHADE: f32
+    /// one bit per `when touching` hat of the sprite, set if that hat's predicate was true for
+    /// this instance on the last tick (i64)
+    TOUCHING_HATS: i64
+}
+
+memory_layout! {
+    list_header
+    /// number of items currently in the list; whilst the buffer is free, a pointer to the next
+    /// free buffer instead (0 if there are no more) (i32)
+    LENGTH: i32
+    /// number of items that the list's buffer has room for (i32)
+    CAPACITY: i32
+    /// size of each item in the buffer, in bytes; free buffers are only reused for lists with the
+    /// same item size (i32)
+    ITEM_SIZE: i32
+    /// for lists of strings, whose items are kept in the strings table rather than in the buffer,
+    /// the index of the first of `CAPACITY` consecutive slots belonging to the buffer; -1 if
+    /// they haven't been allocated yet (i32)
+    STRING_SLOTS: i32
+}
+
+/// The maximum number of items that a list can hold, as in Scratch
+pub const MAX_LIST_LENGTH: u32 = 200_000;
+
+/// The maximum number of clones that can exist at once, across all sprites
+pub const MAX_CLONES: u32 = 300;
+
+/// The memory offset of the original sprite with the given (wasm) sprite index.
+#[must_use]
+pub const fn sprite_offset(wasm_sprite_index: u32) -> u32 {
+    stage::BLOCK_SIZE + wasm_sprite_index * sprite::BLOCK_SIZE
+}
+
+/// The memory offset of the pool of clone blocks, which is shared between all sprites and placed
+/// after every original sprite. Clone slot `n` (1-indexed) lives at
+/// `clone_pool_offset(target_count) + (n - 1) * sprite::BLOCK_SIZE`.
+///
+/// The number of targets is used rather than the number of sprites as it is known before any
+/// sprites are registered, and is always greater than the number of sprites.
+#[must_use]
+pub const fn clone_pool_offset(target_count: u32) -> u32 {
+    sprite_offset(target_count)
+}
+
+/// The instance memory offset of a clone in slot 0, i.e. the value to which
+/// `slot * sprite::BLOCK_SIZE` should be added to get the instance memory offset of the clone of
+/// the given sprite in slot `slot`. Accesses to a sprite's fields add `sprite_offset` to the
+/// instance memory offset, so this is never negative.
+#[must_use]
+pub const fn clone_mem_offset_base(target_count: u32, wasm_sprite_index: u32) -> u32 {
+    clone_pool_offset(target_count) - sprite_offset(wasm_sprite_index) - sprite::BLOCK_SIZE
 }
@@ -1,34 +1,54 @@
-use itertools::Itertools;
 use wasm_bindgen::prelude::*;
 use wasm_encoder::{
-    AbstractHeapType, BlockType as WasmBlockType, CodeSection, ConstExpr, DataCountSection,
-    DataSection, ElementSection, Elements, ExportKind, ExportSection, FieldType, Function,
-    FunctionSection, GlobalSection, HeapType, ImportSection, Instruction, MemorySection,
-    MemoryType, Module, RefType, StartSection, StorageType, TableSection, TypeSection, ValType,
+    BlockType as WasmBlockType, CodeSection, ConstExpr, DataCountSection, DataSection,
+    ElementSection, Elements, ExportKind, ExportSection, FieldType, Function, FunctionSection,
+    GlobalSection, HeapType, ImportSection, Instruction, MemArg, MemorySection, MemoryType, Module,
+    RefType, StartSection, StorageType, TableSection, TypeSection, ValType,
 };
 use wasm_gen::wasm;
 
-use super::{ExternalEnvironment, GlobalExportable, GlobalMutable, Registries};
-use crate::ir::{Event, IrProject, IrType, StepIndex};
+use super::{ExternalEnvironment, GlobalExportable, GlobalMutable, Registries, mem_layout};
+use crate::ir::{Event, EventThreshold, GreaterThanMenu, IrProject, IrType, StepIndex};
 use crate::prelude::*;
+use crate::wasm::flags::{ClockSource, ListType, RandomSource};
+use crate::wasm::registries::LinearStringLayout;
 use crate::wasm::registries::functions::static_functions::{
-    MarkWaitingFlag, SpawnNewThread, SpawnThreadInStack,
+    AllocListBuffer, AllocScratchString, AllocString, CopyListBuffer, CopyStringSlots,
+    DeleteInstanceThreads, FreeListBuffer, MarkWaitingFlag, NewStringSlots, PersistString,
+    RandomFloat, SetRandomSeed, SetStringSlot, SpawnCloneThreads, SpawnNewThread,
+    SpawnThreadInStack,
 };
-use crate::wasm::{StepFunc, StringsTable, ThreadsTable, WasmFlags};
+use crate::wasm::{InternalInstruction, StepFunc, StringsTable, ThreadsTable, WasmFlags};
+
+/// A thread which is started when an event is triggered.
+#[derive(Clone, Copy, Debug)]
+pub struct EventHandler {
+    /// the index of the *`step_func`* (NOT function index) that the thread starts at
+    pub step: u32,
+    /// the IR index of the target that the thread belongs to
+    pub target: u32,
+    /// if clones of the target may exist, the index of the `SpawnCloneThreads` static function
+    /// and the wasm index of the sprite
+    pub clones: Option<(u32, u32)>,
+    /// the wasm index of the sprite that the thread belongs to, or `None` for the stage
+    pub sprite: Option<u32>,
+}
 
 /// A respresentation of a WASM representation of a project. Cannot be created directly;
 /// use `TryFrom<IrProject>`.
 pub struct WasmProject {
-    #[expect(dead_code, reason = "doesn't need to be used... yet")]
     flags: WasmFlags,
     /// step funcs corresponding to the non-inlined steps, in the same order (hopefully)
     steps: Rc<RefCell<Vec<StepFunc>>>,
-    /// maps an event to a list of *`step_func`* indices (NOT function indices) which are
-    /// triggered by that event.
-    events: BTreeMap<Event, Vec<u32>>,
+    /// maps an event to a list of handlers which are triggered by that event.
+    events: BTreeMap<Event, Vec<EventHandler>>,
     registries: Rc<Registries>,
     target_names: Vec<Box<str>>,
+    /// the initial layer order of each target, in the same order as `target_names`
+    layer_orders: Vec<i32>,
     costume_names: Rc<Vec<Vec<Box<str>>>>,
+    /// the volume that the stage starts with; sprites' volumes are found via the sprite registry
+    stage_volume: f64,
     environment: ExternalEnvironment,
 }
 
@@ -51,7 +71,9 @@ impl WasmProject {
             environment,
             registries: Rc::new(Registries::default()),
             target_names: vec![],
+            layer_orders: vec![],
             costume_names: Rc::new(costume_names),
+            stage_volume: 100.0,
         }
     }
 
@@ -104,38 +126,122 @@ impl WasmProject {
         let mut data = DataSection::new();
         let mut globals = GlobalSection::new();
 
-        memories.memory(MemoryType {
-            minimum: 1,
-            maximum: None,
-            memory64: false,
-            shared: false,
-            page_size_log2: None,
-        });
-
         let mut start_func = Function::new([]);
 
+        let linear_strings = self.environment.linear_memory_strings();
+        // strings in linear memory don't need a strings table unless something has already used it
+        let strings_table = if linear_strings {
+            self.registries().tables().index_of::<StringsTable, _>()?
+        } else {
+            Some(self.registries().tables().register::<StringsTable, _>()?)
+        };
+        let tabled_strings = self
+            .registries()
+            .tabled_strings()
+            .registry()
+            .try_borrow()?
+            .keys()
+            .cloned()
+            .collect::<Box<[_]>>();
+
         Rc::unwrap_or_clone(self.registries().tabled_strings().clone()).finish(
             self.registries().strings(),
-            self.registries().tables().register::<StringsTable, _>()?,
-            &mut start_func,
+            strings_table
+                .filter(|_| !linear_strings)
+                .map(|strings_table| (strings_table, &mut start_func)),
         )?;
 
-        Rc::unwrap_or_clone(self.registries().strings().clone()).finish(&mut imports);
+        let mut string_layout = if linear_strings {
+            Some(LinearStringLayout::new(
+                self.registries().strings(),
+                &tabled_strings,
+                strings_table,
+                &self.costume_name_tables()?,
+            )?)
+        } else {
+            None
+        };
+
+        Rc::unwrap_or_clone(self.registries().strings().clone()).finish(
+            &mut imports,
+            &mut globals,
+            string_layout.as_ref(),
+        )?;
+
+        self.registries().external_functions().clone().finish(
+            &mut imports,
+            self.registries().types(),
+            self.environment,
+        )?;
+
+        // this has to come before the lists are finished, as lists of strings in linear memory
+        // are initialised using these functions
+        if let Some(layout) = string_layout.take() {
+            let scratch_end = self.imported_global_count()?
+                + self
+                    .registries()
+                    .globals()
+                    .string_scratch_end::<u32>(layout.heap_start()?)?;
+            let heap_end = self.imported_global_count()?
+                + self
+                    .registries()
+                    .globals()
+                    .string_heap_end::<u32>(layout.persistent_heap_start()?)?;
+            let alloc_string = self.imported_func_count()?
+                + self
+                    .registries()
+                    .static_functions()
+                    .register_override::<AllocString, u32, _>(heap_end)?;
+            self.registries()
+                .static_functions()
+                .register_override::<AllocScratchString, usize, _>((
+                    scratch_end,
+                    layout.persistent_heap_start()?,
+                    alloc_string,
+                ))?;
+            let persist_string = self.imported_func_count()?
+                + self
+                    .registries()
+                    .static_functions()
+                    .register_override::<PersistString, u32, _>((
+                        alloc_string,
+                        layout.heap_start()?,
+                        layout.persistent_heap_start()?,
+                    ))?;
+            let new_string_slots = self.imported_func_count()?
+                + self
+                    .registries()
+                    .static_functions()
+                    .register_override::<NewStringSlots, u32, _>((alloc_string, persist_string))?;
+            let set_string_slot = self.imported_func_count()?
+                + self
+                    .registries()
+                    .static_functions()
+                    .register_override::<SetStringSlot, u32, _>(persist_string)?;
+            let copy_string_slots = self.imported_func_count()?
+                + self
+                    .registries()
+                    .static_functions()
+                    .register::<CopyStringSlots, u32>()?;
+            string_layout = Some(layout.with_slot_functions((
+                new_string_slots,
+                set_string_slot,
+                copy_string_slots,
+            )));
+        }
 
         self.registries().lists().clone().finish(
             &mut data,
             &mut elements,
             &mut start_func,
             self.imported_global_count()?,
+            self.flags.list_type,
+            strings_table,
+            string_layout.as_ref(),
         )?;
 
         start_func.instruction(&Instruction::End);
 
-        self.registries()
-            .external_functions()
-            .clone()
-            .finish(&mut imports, self.registries().types())?;
-
         self.registries()
             .static_functions()
             .register_override::<SpawnNewThread, usize, _>((
@@ -156,6 +262,63 @@ impl WasmProject {
                 self.threads_table_index()?,
             ))?;
 
+        self.registries()
+            .static_functions()
+            .register_override_if_exists::<SpawnCloneThreads, usize, _>((
+                self.registries().types().step_func_type()?,
+                self.imported_func_count()? + self.spawn_new_thread_func::<u32>()?,
+                self.imported_global_count()? + self.threads_count_global::<u32>()?,
+                self.target_count()?,
+            ))?;
+
+        self.registries()
+            .static_functions()
+            .register_override_if_exists::<DeleteInstanceThreads, usize, _>((
+                self.registries().types().thread_struct_type()?,
+                self.threads_table_index()?,
+                self.imported_global_count()? + self.threads_count_global::<u32>()?,
+            ))?;
+
+        if self.flags.list_type == ListType::LinearMemory {
+            let free_list = self.imported_global_count()?
+                + self.registries().globals().list_free_list::<u32>()?;
+            if self
+                .registries()
+                .static_functions()
+                .index_of::<CopyListBuffer, usize>()?
+                .is_some()
+            {
+                let alloc_list_buffer = self.imported_func_count()?
+                    + self
+                        .registries()
+                        .static_functions()
+                        .register_override::<AllocListBuffer, u32, _>((
+                            self.imported_global_count()?
+                                + self.registries().globals().list_heap_end::<u32>()?,
+                            free_list,
+                        ))?;
+                self.registries()
+                    .static_functions()
+                    .register_override::<CopyListBuffer, usize, _>(alloc_list_buffer)?;
+            }
+            self.registries()
+                .static_functions()
+                .register_override_if_exists::<FreeListBuffer, usize, _>(free_list)?;
+        }
+
+        if self.flags.rng == RandomSource::Seeded {
+            let rng_state =
+                self.imported_global_count()? + self.registries().globals().rng_state::<u32>()?;
+            // always export the seed setter, even if nothing is random, so that hosts can seed
+            // every project in the same way
+            self.registries()
+                .static_functions()
+                .register_override::<SetRandomSeed, usize, _>(rng_state)?;
+            self.registries()
+                .static_functions()
+                .register_override_if_exists::<RandomFloat, usize, _>(rng_state)?;
+        }
+
         self.registries()
             .static_functions()
             .register_override::<MarkWaitingFlag, usize, _>(self.registries().types().struct_(
@@ -186,13 +349,21 @@ impl WasmProject {
                 self.imported_func_count()?,
                 self.static_func_count()?,
                 self.imported_global_count()?,
+                string_layout.as_ref(),
             )?;
         }
 
-        self.tick_func(&mut functions, &mut codes, &mut exports)?;
+        self.tick_func(
+            &mut functions,
+            &mut codes,
+            &mut exports,
+            string_layout.as_ref(),
+        )?;
 
         self.finish_events(&mut functions, &mut codes, &mut exports)?;
 
+        self.delete_all_clones_func(&mut functions, &mut codes, &mut exports)?;
+
         self.unreachable_dbg_func(&mut functions, &mut codes, &mut exports)?;
 
         codes.function(&start_func);
@@ -217,31 +388,12 @@ impl WasmProject {
                 .collect(),
         ));
 
-        Rc::unwrap_or_clone(self.registries().types().clone()).finish(&mut types);
+        Rc::unwrap_or_clone(self.registries().types().clone()).finish(&mut types, linear_strings);
 
         // TODO: make an elements registry to deal with this at the site of the block
-        for (table_index, table_name) in self
-            .registries()
-            .tables()
-            .registry()
-            .try_borrow()?
-            .keys()
-            .enumerate()
-        {
-            if table_name.starts_with("costume_names_") {
-                #[expect(
-                    clippy::unwrap_used,
-                    reason = "checked immediately before that this is a prefix of the table name"
-                )]
-                let target_index: u32 = table_name
-                    .strip_prefix("costume_names_")
-                    .unwrap()
-                    .parse()
-                    .map_err(|_| make_hq_bug!("couldn't parse target index from table name"))?;
-                let costume_names = self
-                    .costume_names
-                    .get(target_index as usize)
-                    .ok_or_else(|| make_hq_bug!("target index out of bounds for costume names"))?;
+        // (costume names are laid out in memory instead if strings are stored in linear memory)
+        if !linear_strings {
+            for (table_index, costume_names) in self.costume_name_tables()? {
                 let name_globals = costume_names
                     .iter()
                     .map(|costume_name| {
@@ -263,11 +415,7 @@ impl WasmProject {
                     })
                     .collect::<HQResult<Box<[_]>>>()?;
                 elements.active(
-                    Some(
-                        table_index
-                            .try_into()
-                            .map_err(|_| make_hq_bug!("table index out of bounds"))?,
-                    ),
+                    Some(table_index),
                     &ConstExpr::i32_const(0),
                     Elements::Expressions(RefType::EXTERNREF, Cow::Borrowed(&*name_globals)),
                 );
@@ -279,14 +427,48 @@ impl WasmProject {
             .clone()
             .finish(&mut tables, &mut exports);
 
+        self.initial_state(&mut data)?;
+
+        memories.memory(MemoryType {
+            minimum: self.memory_pages()?,
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+
         exports.export("memory", ExportKind::Memory, 0);
 
+        if let Some(layout) = &string_layout {
+            const PAGE_SIZE: u64 = 1 << 16;
+            memories.memory(MemoryType {
+                minimum: u64::from(layout.persistent_heap_start()?).div_ceil(PAGE_SIZE),
+                maximum: None,
+                memory64: false,
+                shared: false,
+                page_size_log2: None,
+            });
+            exports.export("strings", ExportKind::Memory, LinearStringLayout::MEMORY);
+            data.active(
+                LinearStringLayout::MEMORY,
+                &ConstExpr::i32_const(0),
+                layout.data().iter().copied(),
+            );
+        }
+
         self.registries().globals().clone().finish(
             &mut globals,
             &mut exports,
-            self.imported_global_count()?,
+            // string constants are defined globals, which have already been added, if strings
+            // are stored in linear memory
+            if linear_strings {
+                0
+            } else {
+                self.imported_global_count()?
+            },
             self.imported_func_count()?,
             self.static_func_count()?,
+            linear_strings,
         );
 
         module
@@ -321,6 +503,7 @@ impl WasmProject {
                 .into_iter()
                 .map(core::convert::Into::into)
                 .collect(),
+            layer_orders: self.layer_orders,
         })
     }
 
@@ -344,6 +527,37 @@ impl WasmProject {
             .map_err(|_| make_hq_bug!("static function map len out of bounds"))
     }
 
+    /// The costume names table of each target which has one, along with its costume names.
+    fn costume_name_tables(&self) -> HQResult<Vec<(u32, &[Box<str>])>> {
+        self.registries()
+            .tables()
+            .registry()
+            .try_borrow()?
+            .keys()
+            .enumerate()
+            .filter_map(|(table_index, table_name)| {
+                Some((table_index, table_name.strip_prefix("costume_names_")?))
+            })
+            .map(|(table_index, target_index)| {
+                let target_index: usize = target_index
+                    .parse()
+                    .map_err(|_| make_hq_bug!("couldn't parse target index from table name"))?;
+                let costume_names = self
+                    .costume_names
+                    .get(target_index)
+                    .ok_or_else(|| make_hq_bug!("target index out of bounds for costume names"))?;
+                Ok((
+                    table_index
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("table index out of bounds"))?,
+                    costume_names.as_slice(),
+                ))
+            })
+            .collect()
+    }
+
+    /// The number of globals which come before those in the global registry, which are the
+    /// string constants. These are imported unless strings are stored in linear memory.
     fn imported_global_count(&self) -> HQResult<u32> {
         self.registries()
             .strings()
@@ -418,77 +632,107 @@ impl WasmProject {
         )
     }
 
+    /// The number of targets in the project. This is used to convert between instance indices
+    /// and target indices, so is always at least 1 to avoid division by zero.
+    fn target_count(&self) -> HQResult<u32> {
+        Ok(u32::try_from(self.target_names.len())
+            .map_err(|_| make_hq_bug!("target count out of bounds"))?
+            .max(1))
+    }
+
+    /// Adds data segments which initialise the parts of the stage's and sprites' state which
+    /// don't start at 0: volumes, rotation styles and whether sprites are draggable.
+    fn initial_state(&self, data: &mut DataSection) -> HQResult<()> {
+        data.active(
+            0,
+            &ConstExpr::i32_const(
+                mem_layout::stage::VOLUME
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("stage volume offset out of bounds"))?,
+            ),
+            self.stage_volume.to_le_bytes(),
+        );
+        for (sprite_index, target) in self
+            .registries()
+            .sprites()
+            .registry()
+            .try_borrow()?
+            .keys()
+            .enumerate()
+        {
+            let sprite_offset = mem_layout::sprite_offset(
+                u32::try_from(sprite_index)
+                    .map_err(|_| make_hq_bug!("sprite index out of bounds"))?,
+            );
+            let segments: [(u32, &[u8]); 4] = [
+                (mem_layout::sprite::VOLUME, &target.volume().to_le_bytes()),
+                (mem_layout::sprite::ROTATION_STYLE, &[target.rotation_style().id()]),
+                (mem_layout::sprite::DRAGGABLE, &[u8::from(target.draggable())]),
+                (mem_layout::sprite::PEN_SHADE, &50.0_f32.to_le_bytes()),
+            ];
+            for (field_offset, bytes) in segments {
+                data.active(
+                    0,
+                    &ConstExpr::i32_const(
+                        (sprite_offset + field_offset)
+                            .try_into()
+                            .map_err(|_| make_hq_bug!("sprite field offset out of bounds"))?,
+                    ),
+                    bytes.iter().copied(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of memory pages needed to hold the stage and all sprites (including clones)
+    fn memory_pages(&self) -> HQResult<u64> {
+        const PAGE_SIZE: u64 = 1 << 16;
+        let end = if self.registries().clones().targets()?.is_empty() {
+            let sprite_count =
+                u32::try_from(self.registries().sprites().registry().try_borrow()?.len())
+                    .map_err(|_| make_hq_bug!("sprite count out of bounds"))?;
+            mem_layout::sprite_offset(sprite_count)
+        } else {
+            mem_layout::clone_pool_offset(self.target_count()?)
+                + mem_layout::MAX_CLONES * mem_layout::sprite::BLOCK_SIZE
+        };
+        Ok(u64::from(end).div_ceil(PAGE_SIZE).max(1))
+    }
+
     #[expect(clippy::needless_pass_by_value, reason = "annoying to borrow a box")]
     fn finish_event(
         &self,
         export_name: Box<str>,
-        indices: &[u32],
+        handlers: &[EventHandler],
+        instance_param: bool,
         funcs: &mut FunctionSection,
         codes: &mut CodeSection,
         exports: &mut ExportSection,
     ) -> HQResult<u32> {
         let mut func = Function::new(vec![]);
 
-        let threads_count = self.threads_count_global()?;
-
-        let spawn_new_thread = self.spawn_new_thread_func()?;
-
-        let instrs = indices
-            .iter()
-            .map(|&i| {
-                Ok(wasm![
-                    RefFunc(i + self.imported_func_count()? + self.static_func_count()?),
-                    RefNull(HeapType::Abstract {
-                        shared: false,
-                        ty: AbstractHeapType::Struct
-                    }),
-                    #StaticFunctionCall(spawn_new_thread),
-                ])
-            })
-            .flatten_ok()
-            .collect::<HQResult<Vec<_>>>()?;
-
-        for instruction in instrs {
-            for real_instruction in instruction.eval(
-                &self.events,
-                self.registries().types(),
-                self.threads_count_global()?,
-                self.spawn_new_thread_func()?,
-                self.spawn_thread_in_stack_func()?,
-                self.threads_table_index()?,
-                self.imported_func_count()?,
-                self.static_func_count()?,
-                self.imported_global_count()?,
-            )? {
-                func.instruction(&real_instruction);
-            }
-        }
-        for instruction in wasm![
-            #LazyGlobalGet(threads_count),
-            I32Const(
-                i32::try_from(indices.len())
-                    .map_err(|_| make_hq_bug!("indices len out of bounds"))?
-            ),
-            I32Add,
-            #LazyGlobalSet(threads_count)
-        ] {
-            for real_instruction in instruction.eval(
-                &self.events,
-                self.registries().types(),
-                self.threads_count_global()?,
-                self.spawn_new_thread_func()?,
-                self.spawn_thread_in_stack_func()?,
-                self.threads_table_index()?,
-                self.imported_func_count()?,
-                self.static_func_count()?,
-                self.imported_global_count()?,
-            )? {
-                func.instruction(&real_instruction);
-            }
+        for instruction in InternalInstruction::spawn_handlers(
+            handlers,
+            instance_param.then_some(0),
+            self.threads_count_global()?,
+            self.spawn_new_thread_func()?,
+            self.imported_func_count()?,
+            self.static_func_count()?,
+            self.imported_global_count()?,
+        )? {
+            func.instruction(&instruction);
         }
         func.instruction(&Instruction::End);
 
-        funcs.function(self.registries().types().function(vec![], vec![])?);
+        funcs.function(self.registries().types().function(
+            if instance_param {
+                vec![ValType::I32]
+            } else {
+                vec![]
+            },
+            vec![],
+        )?);
         codes.function(&func);
         exports.export(
             &export_name,
@@ -508,18 +752,28 @@ impl WasmProject {
         let event_funcs = self
             .events
             .iter()
-            .map(|(event, indices)| {
+            .map(|(event, handlers)| {
                 Ok(Some((
                     event,
                     self.finish_event(
                         match event {
                             Event::FlagClicked => "flag_clicked".into(),
-                            Event::Broadcast(_) => return Ok(None), // broadcasts handled in the sender blocks
+                            Event::KeyPressed(key) => format!("key_pressed_{key}").into(),
+                            Event::BackdropSwitchesTo(index) => {
+                                format!("backdrop_switched_to_{index}").into()
+                            }
+                            // broadcasts and clone starts are handled in the sender blocks, and
+                            // edge-activated hats are checked in the tick function
+                            Event::Broadcast(_)
+                            | Event::CloneStart(_)
+                            | Event::GreaterThan(..)
+                            | Event::TouchingObject(..) => return Ok(None),
                             Event::SpriteClicked(index) => {
                                 format!("spriteClicked{index}").into_boxed_str()
                             }
                         },
-                        indices,
+                        handlers,
+                        matches!(event, Event::SpriteClicked(_)),
                         funcs,
                         codes,
                         exports,
@@ -545,15 +799,24 @@ impl WasmProject {
             })
             .collect::<HQResult<_>>()?;
         if !sprite_clicked_indices.is_empty() {
+            let target_count = self
+                .target_count()?
+                .try_into()
+                .map_err(|_| make_hq_bug!("target count out of bounds"))?;
             let mut sprite_clicked_func = Function::new([]);
             let sprite_clicked_instrs: Vec<_> = sprite_clicked_indices
                 .iter()
                 .flat_map(|index| {
+                    // the parameter is the instance index of the sprite that was clicked, which
+                    // might be a clone
                     wasm![
                         LocalGet(0),
+                        I32Const(target_count),
+                        I32RemU,
                         I32Const(*index),
                         I32Eq,
                         If(WasmBlockType::Empty),
+                        LocalGet(0),
                         Call(
                             event_funcs[&Event::SpriteClicked(
                                 #[expect(
@@ -601,11 +864,316 @@ impl WasmProject {
         Ok(())
     }
 
+    /// Generates instructions which evaluate the predicate of an edge-activated hat (see
+    /// [`Event::is_edge_activated`]), leaving an i32 on the stack. Returns `None` for events
+    /// which aren't edge-activated. Touching hats are checked for the sprite instance held in
+    /// local 5.
+    ///
+    /// Any imports needed by the predicate are registered here, so this must also be called
+    /// before the imports are finalised.
+    fn edge_activated_predicate(
+        registries: &Registries,
+        event: &Event,
+    ) -> HQResult<Option<Vec<InternalInstruction>>> {
+        Ok(Some(match event {
+            Event::GreaterThan(menu, EventThreshold(value)) => {
+                let global_index = match menu {
+                    GreaterThanMenu::Timer => registries.globals().sensing_timer()?,
+                    // -1 is the loudness reported by scratch if there is no microphone
+                    GreaterThanMenu::Loudness => registries.globals().register(
+                        "sensing_loudness".into(),
+                        (
+                            ValType::F64,
+                            ConstExpr::f64_const((-1.0).into()),
+                            GlobalMutable(true),
+                            GlobalExportable(true),
+                        ),
+                    )?,
+                };
+                wasm![
+                    #LazyGlobalGet(global_index),
+                    F64Const((*value).into()),
+                    F64Gt,
+                ]
+            }
+            Event::TouchingObject(_, object) => {
+                let func_index = registries.external_functions().register(
+                    ("sensing", "touchingobject".into()),
+                    (
+                        vec![ValType::I32, ValType::EXTERNREF, ValType::F64, ValType::F64],
+                        vec![ValType::I32],
+                    ),
+                )?;
+                let string_index = registries.strings().register_default(object.clone())?;
+                let mouse_global = |name: &str| {
+                    registries.globals().register::<u32>(
+                        name.into(),
+                        (
+                            ValType::F64,
+                            ConstExpr::f64_const(0.0.into()),
+                            GlobalMutable(true),
+                            GlobalExportable(true),
+                        ),
+                    )
+                };
+                let mouse_x = mouse_global("mouseX")?;
+                let mouse_y = mouse_global("mouseY")?;
+                wasm![
+                    LocalGet(5),
+                    // string imports always come before any other global
+                    GlobalGet(string_index),
+                    #LazyGlobalGet(mouse_x),
+                    #LazyGlobalGet(mouse_y),
+                    Call(func_index),
+                ]
+            }
+            Event::FlagClicked
+            | Event::Broadcast(_)
+            | Event::SpriteClicked(_)
+            | Event::CloneStart(_)
+            | Event::KeyPressed(_)
+            | Event::BackdropSwitchesTo(_) => return Ok(None),
+        }))
+    }
+
+    /// Generates instructions which start the threads of any edge-activated hats whose predicate
+    /// has become true since the last tick. Uses locals 4 to 7 as scratch i32 locals.
+    ///
+    /// Touching hats are checked separately for the original sprite and for each of its clones;
+    /// see [`Self::touching_hat`].
+    fn edge_activated_hats(&self) -> HQResult<Vec<InternalInstruction>> {
+        let mut instructions = vec![];
+        // the number of touching hats seen so far for each sprite
+        let mut touching_hats: BTreeMap<u32, u32> = BTreeMap::new();
+        for (i, (event, handlers)) in self.events.iter().enumerate() {
+            let Some(predicate) = Self::edge_activated_predicate(&self.registries(), event)? else {
+                continue;
+            };
+            if let Event::TouchingObject(target_index, _) = event {
+                let Some(sprite_index) = handlers.first().and_then(|handler| handler.sprite) else {
+                    continue;
+                };
+                let hat_count = touching_hats.entry(*target_index).or_default();
+                let hat_bit = *hat_count;
+                *hat_count += 1;
+                instructions.extend(self.touching_hat(
+                    predicate,
+                    handlers,
+                    *target_index,
+                    sprite_index,
+                    hat_bit,
+                )?);
+                continue;
+            }
+            let last_value = self.registries().globals().register(
+                format!("__edge_activated_hat_{i}").into(),
+                (
+                    ValType::I32,
+                    ConstExpr::i32_const(0),
+                    GlobalMutable(true),
+                    GlobalExportable(false),
+                ),
+            )?;
+            instructions.extend(predicate);
+            instructions.extend(wasm![
+                LocalTee(5),
+                #LazyGlobalGet(last_value),
+                I32Eqz,
+                I32And,
+                LocalGet(5),
+                #LazyGlobalSet(last_value),
+                If(WasmBlockType::Empty),
+            ]);
+            instructions.extend(
+                InternalInstruction::spawn_handlers(
+                    handlers,
+                    None,
+                    self.threads_count_global()?,
+                    self.spawn_new_thread_func()?,
+                    self.imported_func_count()?,
+                    self.static_func_count()?,
+                    self.imported_global_count()?,
+                )?
+                .into_iter()
+                .map(InternalInstruction::Immediate),
+            );
+            instructions.extend(wasm![End]);
+        }
+        Ok(instructions)
+    }
+
+    /// Generates instructions which check a touching hat of the given sprite for the original
+    /// sprite and each of its clones, starting the hat's threads for any instance which has
+    /// started touching the object since the last tick.
+    ///
+    /// Whether the predicate was true on the last tick is kept per instance, in bit `hat_bit` of
+    /// the instance's `TOUCHING_HATS` field. Uses local 6 for the clone slot, local 7 for the
+    /// address of the instance's memory, local 5 for the instance, and local 4 for the result of
+    /// the predicate.
+    fn touching_hat(
+        &self,
+        predicate: Vec<InternalInstruction>,
+        handlers: &[EventHandler],
+        target_index: u32,
+        sprite_index: u32,
+        hat_bit: u32,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        use mem_layout::sprite as sprite_layout;
+
+        if hat_bit >= i64::BITS {
+            crate::warn!(
+                "only {} `when touching` hats are supported per sprite; skipping the rest",
+                i64::BITS
+            );
+            return Ok(vec![]);
+        }
+        let hat_mask = 1i64 << hat_bit;
+        let block_size: i32 = sprite_layout::BLOCK_SIZE
+            .try_into()
+            .map_err(|_| make_hq_bug!("sprite block size out of bounds"))?;
+        let target_count: i32 = self
+            .target_count()?
+            .try_into()
+            .map_err(|_| make_hq_bug!("target count out of bounds"))?;
+        // clone slots are 1-indexed, so slot `n` lives at `slot_base + n * BLOCK_SIZE`
+        let slot_base =
+            mem_layout::clone_pool_offset(self.target_count()?) - sprite_layout::BLOCK_SIZE;
+        let slot_base_i32: i32 = slot_base
+            .try_into()
+            .map_err(|_| make_hq_bug!("clone pool offset out of bounds"))?;
+        // if the sprite can't be cloned, only the original sprite (slot 0) needs checking
+        let last_slot: i32 = if handlers.iter().any(|handler| handler.clones.is_some()) {
+            mem_layout::MAX_CLONES
+                .try_into()
+                .map_err(|_| make_hq_bug!("MAX_CLONES out of bounds"))?
+        } else {
+            0
+        };
+        let touching_hats_mem_arg = MemArg {
+            offset: sprite_layout::TOUCHING_HATS.into(),
+            align: 3,
+            memory_index: 0,
+        };
+        Ok(wasm![
+            I32Const(0),
+            LocalSet(6),
+            Loop(WasmBlockType::Empty),
+            // slots in the clone pool are shared between sprites, so check that this slot holds
+            // an active clone which belongs to this sprite
+            LocalGet(6),
+            I32Eqz,
+            If(WasmBlockType::Result(ValType::I32)),
+            I32Const(1),
+            Else,
+            LocalGet(6),
+            I32Const(block_size),
+            I32Mul,
+            I32Load8U(MemArg {
+                offset: (slot_base + sprite_layout::CLONE_ACTIVE).into(),
+                align: 0,
+                memory_index: 0,
+            }),
+            I32Const(0),
+            I32Ne,
+            LocalGet(6),
+            I32Const(block_size),
+            I32Mul,
+            I32Load16U(MemArg {
+                offset: (slot_base + sprite_layout::CLONE_OWNER).into(),
+                align: 1,
+                memory_index: 0,
+            }),
+            I32Const(
+                sprite_index
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("sprite index out of bounds"))?
+            ),
+            I32Eq,
+            I32And,
+            End,
+            If(WasmBlockType::Empty),
+            LocalGet(6),
+            I32Const(block_size),
+            I32Mul,
+            I32Const(slot_base_i32),
+            I32Add,
+            I32Const(
+                mem_layout::sprite_offset(sprite_index)
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("sprite offset out of bounds"))?
+            ),
+            LocalGet(6),
+            Select,
+            LocalSet(7),
+            LocalGet(6),
+            I32Const(target_count),
+            I32Mul,
+            I32Const(
+                target_index
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("target index out of bounds"))?
+            ),
+            I32Add,
+            LocalSet(5),
+        ]
+        .into_iter()
+        .chain(predicate)
+        .chain(wasm![
+            LocalTee(4),
+            LocalGet(7),
+            I64Load(touching_hats_mem_arg),
+            I64Const(hat_mask),
+            I64And,
+            I64Eqz,
+            I32And,
+            // remember the value of the predicate for the next tick
+            LocalGet(7),
+            LocalGet(7),
+            I64Load(touching_hats_mem_arg),
+            I64Const(!hat_mask),
+            I64And,
+            I64Const(hat_mask),
+            I64Const(0),
+            LocalGet(4),
+            Select,
+            I64Or,
+            I64Store(touching_hats_mem_arg),
+            If(WasmBlockType::Empty),
+        ])
+        .chain(
+            InternalInstruction::spawn_handlers(
+                handlers,
+                Some(5),
+                self.threads_count_global()?,
+                self.spawn_new_thread_func()?,
+                self.imported_func_count()?,
+                self.static_func_count()?,
+                self.imported_global_count()?,
+            )?
+            .into_iter()
+            .map(InternalInstruction::Immediate),
+        )
+        .chain(wasm![
+            End,
+            End,
+            LocalGet(6),
+            I32Const(1),
+            I32Add,
+            LocalTee(6),
+            I32Const(last_slot),
+            I32LeS,
+            BrIf(0),
+            End,
+        ])
+        .collect())
+    }
+
     fn tick_func(
         &self,
         funcs: &mut FunctionSection,
         codes: &mut CodeSection,
         exports: &mut ExportSection,
+        string_layout: Option<&LinearStringLayout>,
     ) -> HQResult<()> {
         let thread_struct_type = self.registries().types().thread_struct_type()?;
         let stack_struct_ty = self.registries().types().stack_struct_type()?;
@@ -626,71 +1194,377 @@ impl WasmProject {
                     heap_type: HeapType::Concrete(stack_struct_ty),
                 }),
             ),
+            (4, ValType::I32),
         ]);
 
         let step_func_ty = self.registries().types().step_func_type()?;
         let stack_array_ty = self.registries().types().stack_array_type()?;
 
-        let instructions = wasm![
-            TableSize(self.threads_table_index()?),
-            LocalTee(1),
-            I32Eqz,
-            BrIf(0),
+        let current_instance = self.registries().globals().current_instance()?;
+        let instance_mem_offset = self.registries().globals().instance_mem_offset()?;
+        let target_count: i32 = self
+            .target_count()?
+            .try_into()
+            .map_err(|_| make_hq_bug!("target count out of bounds"))?;
+
+        // point the instance memory offset at the thread's clone block in the clone pool, and
+        // swap in the variables and lists of the thread's sprite instance, if it is a clone
+        // (or if it's an original sprite and a clone was previously active)
+        let mut swap_instructions = vec![];
+        for target in self.registries().clones().targets()? {
+            let active_slot = self.registries().clones().active_slot_global(&target)?;
+            let sprite_index = self
+                .registries()
+                .sprites()
+                .register_default(Rc::clone(&target))?;
+            swap_instructions.extend(wasm![
+                #LazyGlobalGet(current_instance),
+                I32Const(target_count),
+                I32RemU,
+                I32Const(
+                    target
+                        .index()
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("target index out of bounds"))?
+                ),
+                I32Eq,
+                If(WasmBlockType::Empty),
+                LocalGet(4),
+                I32Const(
+                    mem_layout::sprite::BLOCK_SIZE
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("sprite block size out of bounds"))?
+                ),
+                I32Mul,
+                I32Const(
+                    mem_layout::clone_mem_offset_base(self.target_count()?, sprite_index)
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("clone memory offset out of bounds"))?
+                ),
+                I32Add,
+                I32Const(0),
+                LocalGet(4),
+                Select,
+                #LazyGlobalSet(instance_mem_offset),
+                LocalGet(4),
+                #LazyGlobalGet(active_slot),
+                I32Ne,
+                If(WasmBlockType::Empty),
+            ]);
+            swap_instructions.extend(self.registries().clones().save_instructions(
+                &target,
+                self.registries().variables(),
+                self.registries().lists(),
+                self.flags.list_type,
+            )?);
+            swap_instructions.extend(self.registries().clones().load_instructions(
+                &target,
+                4,
+                self.registries().variables(),
+                self.registries().lists(),
+                self.flags.list_type,
+            )?);
+            swap_instructions.extend(wasm![End, End]);
+        }
+
+        let edge_activated_hats = self.edge_activated_hats()?;
+        if !edge_activated_hats.is_empty() {
+            // lets the host know that it needs to keep ticking even if there are no threads
+            self.registries().globals().register::<u32>(
+                "edge_activated_hats".into(),
+                (
+                    ValType::I32,
+                    ConstExpr::i32_const(1),
+                    GlobalMutable(false),
+                    GlobalExportable(true),
+                ),
+            )?;
+        }
+
+        let advance_clock = if self.flags.clock == ClockSource::Virtual {
+            // lets the host know that it shouldn't advance the timer itself
+            self.registries().globals().register::<u32>(
+                "virtual_clock".into(),
+                (
+                    ValType::I32,
+                    ConstExpr::i32_const(1),
+                    GlobalMutable(false),
+                    GlobalExportable(true),
+                ),
+            )?;
+            let timer = self.registries().globals().sensing_timer()?;
+            let virtual_time = self.registries().globals().virtual_time()?;
+            let tick_seconds = 1.0 / f64::from(self.flags.virtual_framerate.max(1));
+            wasm![
+                #LazyGlobalGet(timer),
+                F64Const(tick_seconds.into()),
+                F64Add,
+                #LazyGlobalSet(timer),
+                #LazyGlobalGet(virtual_time),
+                F64Const(tick_seconds.into()),
+                F64Add,
+                #LazyGlobalSet(virtual_time),
+            ]
+        } else {
+            vec![]
+        };
+
+        // strings allocated in the scratch region during the last tick are no longer needed, as
+        // any which were stored have been copied out of it
+        let reset_string_scratch = if let Some(layout) = string_layout {
+            let scratch_end = self
+                .registries()
+                .globals()
+                .string_scratch_end::<u32>(layout.heap_start()?)?;
+            wasm![
+                I32Const(
+                    layout
+                        .heap_start()?
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("string scratch start out of bounds"))?
+                ),
+                #LazyGlobalSet(scratch_end),
+            ]
+        } else {
+            vec![]
+        };
+
+        // the clock is advanced first so that edge-activated hats see the new time
+        let instructions = reset_string_scratch
+            .into_iter()
+            .chain(advance_clock)
+            .chain(edge_activated_hats)
+            .chain(wasm![
+                TableSize(self.threads_table_index()?),
+                LocalTee(1),
+                I32Eqz,
+                BrIf(0),
+                Loop(WasmBlockType::Empty),
+                LocalGet(0),
+                LocalGet(0),
+                TableGet(self.threads_table_index()?),
+                LocalTee(2),
+                RefIsNull,
+                If(WasmBlockType::Empty),
+                LocalGet(0),
+                I32Const(1),
+                I32Add,
+                LocalTee(0),
+                LocalGet(1),
+                I32LtS,
+                If(WasmBlockType::Empty),
+                Br(2),
+                Else,
+                Return,
+                End,
+                End,
+                LocalGet(2),
+                RefAsNonNull,
+                StructGet {
+                    struct_type_index: thread_struct_type,
+                    field_index: 2
+                },
+                #LazyGlobalSet(current_instance),
+                #LazyGlobalGet(current_instance),
+                I32Const(target_count),
+                I32DivU,
+                LocalSet(4),
+                // original sprites and the stage don't need an offset; clones have their offset
+                // set when their variables are swapped in
+                I32Const(0),
+                #LazyGlobalSet(instance_mem_offset),
+            ])
+            .chain(swap_instructions)
+            .chain(wasm![
+                LocalGet(2),
+                RefAsNonNull,
+                StructGet {
+                    struct_type_index: thread_struct_type,
+                    field_index: 1
+                },
+                LocalGet(2),
+                RefAsNonNull,
+                StructGet {
+                    struct_type_index: thread_struct_type,
+                    field_index: 0
+                },
+                I32Const(1),
+                I32Sub,
+                ArrayGet(stack_array_ty),
+                RefAsNonNull,
+                LocalTee(3),
+                StructGet {
+                    struct_type_index: stack_struct_ty,
+                    field_index: 1
+                },
+                LocalGet(3),
+                StructGet {
+                    struct_type_index: stack_struct_ty,
+                    field_index: 0
+                },
+                CallRef(step_func_ty),
+                LocalGet(0),
+                I32Const(1),
+                I32Add,
+                LocalTee(0),
+                LocalGet(1),
+                I32LtS,
+                BrIf(0),
+                End,
+            ]);
+        for instr in instructions {
+            for real_instruction in instr.eval(
+                &self.events,
+                self.registries().types(),
+                self.threads_count_global()?,
+                self.spawn_new_thread_func()?,
+                self.spawn_thread_in_stack_func()?,
+                self.threads_table_index()?,
+                self.imported_func_count()?,
+                self.static_func_count()?,
+                self.imported_global_count()?,
+            )? {
+                tick_func.instruction(&real_instruction);
+            }
+        }
+        tick_func.instruction(&Instruction::End);
+        funcs.function(self.registries().types().function(vec![], vec![])?);
+        codes.function(&tick_func);
+        exports.export(
+            "tick",
+            ExportKind::Func,
+            funcs.len() + self.imported_func_count()? - 1,
+        );
+        Ok(())
+    }
+
+    /// Generates the exported `delete_all_clones` function, which deletes all clones and stops
+    /// their threads (e.g. when the green flag is clicked or the project is stopped). This is
+    /// only generated if there are targets which can be cloned.
+    fn delete_all_clones_func(
+        &self,
+        funcs: &mut FunctionSection,
+        codes: &mut CodeSection,
+        exports: &mut ExportSection,
+    ) -> HQResult<()> {
+        let clonable_targets = self.registries().clones().targets()?;
+        if clonable_targets.is_empty() {
+            return Ok(());
+        }
+
+        let thread_struct_type = self.registries().types().thread_struct_type()?;
+        let threads_table = self.threads_table_index()?;
+        let threads_count = self.threads_count_global()?;
+        let clones_count = self.registries().globals().clones_count()?;
+        let target_count: i32 = self
+            .target_count()?
+            .try_into()
+            .map_err(|_| make_hq_bug!("target count out of bounds"))?;
+
+        let mut func = Function::new(vec![
+            (1, ValType::I32),
+            (
+                1,
+                ValType::Ref(RefType {
+                    nullable: true,
+                    heap_type: HeapType::Concrete(thread_struct_type),
+                }),
+            ),
+        ]);
+
+        let mut instructions = wasm![
+            I32Const(
+                mem_layout::clone_pool_offset(self.target_count()?)
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("memory position out of bounds"))?
+            ),
+            I32Const(0),
+            I32Const(
+                (mem_layout::MAX_CLONES * mem_layout::sprite::BLOCK_SIZE)
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("clones memory size out of bounds"))?
+            ),
+            MemoryFill(0),
+        ];
+        for target in &*clonable_targets {
+            let active_slot = self.registries().clones().active_slot_global(target)?;
+            if self.flags.list_type == ListType::LinearMemory {
+                // the active clone's list buffers are only held in the list globals until they
+                // are saved, so save them so that they can be freed along with the others
+                instructions.extend(self.registries().clones().save_instructions(
+                    target,
+                    self.registries().variables(),
+                    self.registries().lists(),
+                    self.flags.list_type,
+                )?);
+                instructions.extend(self.registries().clones().free_clone_slots_instructions(
+                    target,
+                    0,
+                    self.registries().lists(),
+                    self.flags.list_type,
+                    self.registries().static_functions(),
+                )?);
+            }
+            instructions.extend(wasm![
+                #LazyGlobalGet(active_slot),
+                If(WasmBlockType::Empty),
+                I32Const(0),
+                LocalSet(0),
+            ]);
+            instructions.extend(self.registries().clones().load_instructions(
+                target,
+                0,
+                self.registries().variables(),
+                self.registries().lists(),
+                self.flags.list_type,
+            )?);
+            instructions.extend(wasm![End]);
+        }
+        instructions.extend(wasm![
+            I32Const(0),
+            #LazyGlobalSet(clones_count),
+            // stop all threads belonging to clones
+            I32Const(0),
+            LocalSet(0),
+            Block(WasmBlockType::Empty),
             Loop(WasmBlockType::Empty),
             LocalGet(0),
+            TableSize(threads_table),
+            I32GeU,
+            BrIf(1),
             LocalGet(0),
-            TableGet(self.threads_table_index()?),
-            LocalTee(2),
+            TableGet(threads_table),
+            LocalTee(1),
             RefIsNull,
+            I32Eqz,
             If(WasmBlockType::Empty),
-            LocalGet(0),
-            I32Const(1),
-            I32Add,
-            LocalTee(0),
             LocalGet(1),
-            I32LtS,
-            If(WasmBlockType::Empty),
-            Br(2),
-            Else,
-            Return,
-            End,
-            End,
-            LocalGet(2),
             RefAsNonNull,
             StructGet {
                 struct_type_index: thread_struct_type,
-                field_index: 1
-            },
-            LocalGet(2),
-            RefAsNonNull,
-            StructGet {
-                struct_type_index: thread_struct_type,
-                field_index: 0
+                field_index: 2
             },
+            I32Const(target_count),
+            I32GeU,
+            If(WasmBlockType::Empty),
+            LocalGet(0),
+            RefNull(HeapType::Concrete(thread_struct_type)),
+            TableSet(threads_table),
+            #LazyGlobalGet(threads_count),
             I32Const(1),
             I32Sub,
-            ArrayGet(stack_array_ty),
-            RefAsNonNull,
-            LocalTee(3),
-            StructGet {
-                struct_type_index: stack_struct_ty,
-                field_index: 1
-            },
-            LocalGet(3),
-            StructGet {
-                struct_type_index: stack_struct_ty,
-                field_index: 0
-            },
-            CallRef(step_func_ty),
+            #LazyGlobalSet(threads_count),
+            End,
+            End,
             LocalGet(0),
             I32Const(1),
             I32Add,
-            LocalTee(0),
-            LocalGet(1),
-            I32LtS,
-            BrIf(0),
+            LocalSet(0),
+            Br(0),
             End,
-        ];
+            End,
+        ]);
+
         for instr in instructions {
             for real_instruction in instr.eval(
                 &self.events,
@@ -703,14 +1577,14 @@ impl WasmProject {
                 self.static_func_count()?,
                 self.imported_global_count()?,
             )? {
-                tick_func.instruction(&real_instruction);
+                func.instruction(&real_instruction);
             }
         }
-        tick_func.instruction(&Instruction::End);
+        func.instruction(&Instruction::End);
         funcs.function(self.registries().types().function(vec![], vec![])?);
-        codes.function(&tick_func);
+        codes.function(&func);
         exports.export(
-            "tick",
+            "delete_all_clones",
             ExportKind::Func,
             funcs.len() + self.imported_func_count()? - 1,
         );
@@ -724,8 +1598,10 @@ impl WasmProject {
     ) -> HQResult<Self> {
         let steps = Rc::new(RefCell::new(Vec::new()));
         let registries = Rc::new(Registries::default());
-        let mut events: BTreeMap<Event, Vec<u32>> = BTreeMap::default();
+        let mut events: BTreeMap<Event, Vec<EventHandler>> = BTreeMap::default();
         events.insert(Event::FlagClicked, vec![]); // make sure that `flag_clicked` will be defined
+        let target_count = u32::try_from(ir_project.targets().try_borrow()?.len())
+            .map_err(|_| make_hq_bug!("target count out of bounds"))?;
         let costume_names = Rc::new(
             ir_project
                 .targets()
@@ -748,23 +1624,78 @@ impl WasmProject {
                 &steps,
                 Rc::clone(&registries),
                 flags,
+                target_count,
                 Rc::clone(&costume_names),
             )?;
         }
+        for target in ir_project.targets().try_borrow()?.values() {
+            if target.is_clonable()? {
+                registries.clones().register(target)?;
+            }
+        }
         // add thread event handlers for them
         for thread in ir_project.threads().try_borrow()?.iter() {
-            events.entry(thread.event().clone()).or_default().push(
-                u32::try_from(thread.first_step().0)
-                    .map_err(|_| make_hq_bug!("step func index out of bounds"))?,
+            let target = Rc::clone(
+                ir_project
+                    .steps()
+                    .try_borrow()?
+                    .get(thread.first_step().0)
+                    .ok_or_else(|| make_hq_bug!("thread's first step index out of bounds"))?
+                    .try_borrow()?
+                    .context()
+                    .target(),
             );
+            let sprite = if target.is_stage() {
+                None
+            } else {
+                Some(registries.sprites().register_default(Rc::clone(&target))?)
+            };
+            let clones = if target.is_clonable()? {
+                let sprite_index = registries.sprites().register_default(Rc::clone(&target))?;
+                Some((
+                    registries
+                        .static_functions()
+                        .register::<SpawnCloneThreads, _>()?,
+                    sprite_index,
+                ))
+            } else {
+                None
+            };
+            events
+                .entry(thread.event().clone())
+                .or_default()
+                .push(EventHandler {
+                    step: u32::try_from(thread.first_step().0)
+                        .map_err(|_| make_hq_bug!("step func index out of bounds"))?,
+                    target: target.index(),
+                    clones,
+                    sprite,
+                });
+        }
+        // the predicates of edge-activated hats are only generated when the tick function is,
+        // which is after imports have been finalised, so make sure their imports exist now
+        for event in events.keys() {
+            Self::edge_activated_predicate(&registries, event)?;
         }
         Ok(Self {
             flags,
             steps,
             events,
             registries,
-            environment: ExternalEnvironment::WebBrowser,
+            environment: flags.environment,
+            stage_volume: ir_project
+                .targets()
+                .try_borrow()?
+                .values()
+                .find(|target| target.is_stage())
+                .map_or(100.0, |stage| stage.volume()),
             target_names: ir_project.targets().try_borrow()?.keys().cloned().collect(),
+            layer_orders: ir_project
+                .targets()
+                .try_borrow()?
+                .values()
+                .map(|target| target.layer_order())
+                .collect(),
             costume_names,
         })
     }
@@ -777,16 +1708,25 @@ pub struct FinishedWasm {
     pub wasm_bytes: Box<[u8]>,
     #[wasm_bindgen(getter_with_clone)]
     pub target_names: Vec<String>,
+    /// the initial layer order of each target, in the same order as `target_names`
+    #[wasm_bindgen(getter_with_clone)]
+    pub layer_orders: Vec<i32>,
     #[wasm_bindgen(getter_with_clone)]
     pub strings: Vec<String>,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Registries, WasmProject};
+    use wasm_encoder::{
+        AbstractHeapType, BlockType, Function, HeapType, Instruction, MemArg, ValType,
+    };
+
+    use super::{EventHandler, Registries, WasmProject};
+    use crate::ir::{Event, EventThreshold, GreaterThanMenu};
     use crate::prelude::*;
-    use crate::wasm::flags::all_wasm_features;
-    use crate::wasm::{ExternalEnvironment, WasmFlags};
+    use crate::wasm::flags::{ClockSource, all_wasm_features};
+    use crate::wasm::registries::{LinearStringLayout, StringRegistry};
+    use crate::wasm::{ExternalEnvironment, StepFunc, StepTarget, WasmFlags};
 
     #[test]
     fn empty_project_is_valid_wasm() {
@@ -799,7 +1739,152 @@ mod tests {
             environment: ExternalEnvironment::WebBrowser,
             registries,
             target_names: vec![],
+            layer_orders: vec![],
+            costume_names: Rc::new(vec![]),
+            stage_volume: 100.0,
+        };
+        let wasm_bytes = project.finish().unwrap().wasm_bytes;
+        if let Err(err) = wasmparser::validate(&wasm_bytes) {
+            panic!(
+                "wasmparser error: {:?}\nwasm:\n{}",
+                err,
+                wasmprinter::print_bytes(wasm_bytes).unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn edge_activated_hats_are_valid_wasm() {
+        let registries = Rc::new(Registries::default());
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let events = BTreeMap::from([
+            (
+                Event::GreaterThan(GreaterThanMenu::Timer, EventThreshold(10.0)),
+                vec![],
+            ),
+            (
+                Event::GreaterThan(GreaterThanMenu::Loudness, EventThreshold(10.0)),
+                vec![],
+            ),
+            (Event::TouchingObject(0, "_mouse_".into()), vec![]),
+        ]);
+        for event in events.keys() {
+            WasmProject::edge_activated_predicate(&registries, event).unwrap();
+        }
+        let project = WasmProject {
+            flags: WasmFlags::new(all_wasm_features()),
+            steps,
+            events,
+            environment: ExternalEnvironment::WebBrowser,
+            registries,
+            target_names: vec![],
+            layer_orders: vec![],
+            costume_names: Rc::new(vec![]),
+            stage_volume: 100.0,
+        };
+        let wasm_bytes = project.finish().unwrap().wasm_bytes;
+        if let Err(err) = wasmparser::validate(&wasm_bytes) {
+            panic!(
+                "wasmparser error: {:?}\nwasm:\n{}",
+                err,
+                wasmprinter::print_bytes(wasm_bytes).unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn touching_hats_of_clones_are_valid_wasm() {
+        let registries = Rc::new(Registries::default());
+        let flags = WasmFlags::new(all_wasm_features());
+        let steps = Rc::new(RefCell::new(vec![StepFunc::new(
+            Rc::clone(&registries),
+            flags,
+            StepTarget::Sprite(0),
+            1,
+            2,
+            Rc::new(vec![]),
+        )]));
+        let events = BTreeMap::from([(
+            Event::TouchingObject(1, "_edge_".into()),
+            vec![EventHandler {
+                step: 0,
+                target: 1,
+                clones: Some((0, 0)),
+                sprite: Some(0),
+            }],
+        )]);
+        for event in events.keys() {
+            WasmProject::edge_activated_predicate(&registries, event).unwrap();
+        }
+        let project = WasmProject {
+            flags,
+            steps,
+            events,
+            environment: ExternalEnvironment::WebBrowser,
+            registries,
+            target_names: vec!["Stage".into(), "Sprite1".into()],
+            layer_orders: vec![],
+            costume_names: Rc::new(vec![]),
+            stage_volume: 100.0,
+        };
+        let wasm_bytes = project.finish().unwrap().wasm_bytes;
+        if let Err(err) = wasmparser::validate(&wasm_bytes) {
+            panic!(
+                "wasmparser error: {:?}\nwasm:\n{}",
+                err,
+                wasmprinter::print_bytes(wasm_bytes).unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn virtual_clock_advances_by_one_frame() {
+        let mut flags = WasmFlags::new(all_wasm_features());
+        flags.clock = ClockSource::Virtual;
+        flags.virtual_framerate = 4;
+        let project = WasmProject {
+            flags,
+            steps: Rc::new(RefCell::new(Vec::new())),
+            events: BTreeMap::new(),
+            environment: ExternalEnvironment::WebBrowser,
+            registries: Rc::new(Registries::default()),
+            target_names: vec![],
+            layer_orders: vec![],
+            costume_names: Rc::new(vec![]),
+            stage_volume: 100.0,
+        };
+        let wat = wasmprinter::print_bytes(project.finish().unwrap().wasm_bytes).unwrap();
+        assert!(wat.contains("f64.const 0x1p-2 (;=0.25;)"));
+    }
+
+    #[test]
+    fn headless_project_imports_from_host() {
+        let registries = Rc::new(Registries::default());
+        registries
+            .external_functions()
+            .register::<usize>(
+                ("looks", "say_string".into()),
+                (vec![ValType::EXTERNREF, ValType::I32], vec![]),
+            )
+            .unwrap();
+        registries
+            .external_functions()
+            .register::<usize>(
+                ("wasm:js-string", "length".into()),
+                (vec![ValType::EXTERNREF], vec![ValType::I32]),
+            )
+            .unwrap();
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let project = WasmProject {
+            flags: WasmFlags::new(all_wasm_features()),
+            steps,
+            events: BTreeMap::new(),
+            environment: ExternalEnvironment::Headless,
+            registries,
+            target_names: vec![],
+            layer_orders: vec![],
             costume_names: Rc::new(vec![]),
+            stage_volume: 100.0,
         };
         let wasm_bytes = project.finish().unwrap().wasm_bytes;
         if let Err(err) = wasmparser::validate(&wasm_bytes) {
@@ -809,5 +1894,237 @@ mod tests {
                 wasmprinter::print_bytes(wasm_bytes).unwrap()
             )
         }
+        let wat = wasmprinter::print_bytes(wasm_bytes).unwrap();
+        assert!(wat.contains(r#"(import "hq_log" "say_string""#));
+        assert!(wat.contains(r#"(import "hq_host" "string_length""#));
+        assert!(!wat.contains("wasm:js-string"));
+        // strings live in linear memory, so nothing should refer to externrefs
+        assert!(!wat.contains("externref"));
+        assert!(!wat.contains(r#"(import """#));
+        assert!(wat.contains(r#"(export "strings" (memory 1))"#));
+        assert!(wat.contains(r#"(export "string_alloc" (func"#));
+        assert!(wat.contains(r#"(export "string_persist" (func"#));
+    }
+
+    #[test]
+    fn linear_string_layout_packs_handles() {
+        let strings = StringRegistry::default();
+        strings.register_default::<usize>("".into()).unwrap();
+        strings.register_default::<usize>("hi".into()).unwrap();
+        strings.register_default::<usize>("costume".into()).unwrap();
+        let costume_names: Box<[Box<str>]> = Box::from(["costume".into()]);
+        let layout =
+            LinearStringLayout::new(&strings, &["hi".into()], Some(0), &[(1, &costume_names)])
+                .unwrap();
+        // one tabled string slot and one costume name slot come before the string contents
+        let hi_address = 2 * LinearStringLayout::SLOT_SIZE;
+        assert_eq!(layout.handle("").unwrap(), i64::from(hi_address) << 32);
+        assert_eq!(
+            layout.handle("hi").unwrap(),
+            (i64::from(hi_address) << 32) | 2
+        );
+        assert_eq!(
+            layout.handle("costume").unwrap(),
+            (i64::from(hi_address + 4) << 32) | 7
+        );
+        assert_eq!(
+            layout.data()[..8],
+            layout.handle("hi").unwrap().to_le_bytes()
+        );
+        assert_eq!(
+            layout.data()[8..16],
+            layout.handle("costume").unwrap().to_le_bytes()
+        );
+        assert_eq!(layout.data()[16..20], [b'h', 0, b'i', 0]);
+        assert_eq!(
+            layout.heap_start().unwrap() % LinearStringLayout::SLOT_SIZE,
+            0
+        );
+    }
+
+    /// A layout with the strings table at index 0 and a costume names table at index 1, whose
+    /// `NewStringSlots`, `SetStringSlot` and `CopyStringSlots` functions are 10, 11 and 12.
+    fn string_table_layout() -> LinearStringLayout {
+        let strings = StringRegistry::default();
+        strings.register_default::<usize>("hi".into()).unwrap();
+        strings.register_default::<usize>("costume".into()).unwrap();
+        let costume_names: Box<[Box<str>]> = Box::from(["costume".into()]);
+        LinearStringLayout::new(&strings, &["hi".into()], Some(0), &[(1, &costume_names)])
+            .unwrap()
+            .with_slot_functions((10, 11, 12))
+    }
+
+    fn rewritten(layout: &LinearStringLayout, instruction: &Instruction<'_>) -> HQResult<Vec<u8>> {
+        let mut func = Function::new([]);
+        layout.instruction(&mut func, instruction)?;
+        Ok(func.into_raw_body())
+    }
+
+    fn encoded(instructions: &[Instruction<'_>]) -> Vec<u8> {
+        let mut func = Function::new([]);
+        for instruction in instructions {
+            func.instruction(instruction);
+        }
+        func.into_raw_body()
+    }
+
+    #[test]
+    fn linear_strings_rewrite_string_types() {
+        let layout = string_table_layout();
+        let string_block = BlockType::Result(ValType::EXTERNREF);
+        let handle_block = BlockType::Result(ValType::I64);
+        for (instruction, expected) in [
+            (
+                Instruction::TypedSelect(ValType::EXTERNREF),
+                Instruction::TypedSelect(ValType::I64),
+            ),
+            (
+                Instruction::Block(string_block),
+                Instruction::Block(handle_block),
+            ),
+            (
+                Instruction::Loop(string_block),
+                Instruction::Loop(handle_block),
+            ),
+            (Instruction::If(string_block), Instruction::If(handle_block)),
+            // other types are left alone
+            (
+                Instruction::TypedSelect(ValType::F64),
+                Instruction::TypedSelect(ValType::F64),
+            ),
+            (
+                Instruction::Block(BlockType::Empty),
+                Instruction::Block(BlockType::Empty),
+            ),
+            (
+                Instruction::If(BlockType::FunctionType(3)),
+                Instruction::If(BlockType::FunctionType(3)),
+            ),
+        ] {
+            assert_eq!(
+                rewritten(&layout, &instruction).unwrap(),
+                encoded(&[expected])
+            );
+        }
+    }
+
+    #[test]
+    fn linear_strings_rewrite_null_strings() {
+        let layout = string_table_layout();
+        // null strings can only be used as the empty string, which is the zero handle
+        for heap_type in [
+            HeapType::EXTERN,
+            HeapType::Abstract {
+                shared: false,
+                ty: AbstractHeapType::NoExtern,
+            },
+            HeapType::Abstract {
+                shared: true,
+                ty: AbstractHeapType::Extern,
+            },
+        ] {
+            assert_eq!(
+                rewritten(&layout, &Instruction::RefNull(heap_type)).unwrap(),
+                encoded(&[Instruction::I64Const(0)])
+            );
+        }
+        // other references are left alone; strings are never null-checked, so null checks
+        // can only be on other references
+        for instruction in [
+            Instruction::RefNull(HeapType::Abstract {
+                shared: false,
+                ty: AbstractHeapType::Struct,
+            }),
+            Instruction::RefNull(HeapType::Concrete(2)),
+            Instruction::RefIsNull,
+            Instruction::RefAsNonNull,
+        ] {
+            assert_eq!(
+                rewritten(&layout, &instruction).unwrap(),
+                encoded(&[instruction.clone()])
+            );
+        }
+    }
+
+    #[test]
+    fn linear_strings_rewrite_string_tables() {
+        let layout = string_table_layout();
+        let slot = |offset| {
+            Instruction::I64Load(MemArg {
+                offset,
+                align: 3,
+                memory_index: LinearStringLayout::MEMORY,
+            })
+        };
+        for (instruction, expected) in [
+            (
+                Instruction::TableGet(0),
+                vec![Instruction::I32Const(3), Instruction::I32Shl, slot(0)],
+            ),
+            // the costume name handles come after the one tabled string's slot
+            (
+                Instruction::TableGet(1),
+                vec![
+                    Instruction::I32Const(3),
+                    Instruction::I32Shl,
+                    slot(LinearStringLayout::SLOT_SIZE.into()),
+                ],
+            ),
+            (Instruction::TableGrow(0), vec![Instruction::Call(10)]),
+            (Instruction::TableSet(0), vec![Instruction::Call(11)]),
+            (
+                Instruction::TableCopy {
+                    src_table: 0,
+                    dst_table: 0,
+                },
+                vec![Instruction::Call(12)],
+            ),
+            // other tables are left alone
+            (Instruction::TableGet(2), vec![Instruction::TableGet(2)]),
+            (Instruction::TableSize(2), vec![Instruction::TableSize(2)]),
+        ] {
+            assert_eq!(
+                rewritten(&layout, &instruction).unwrap(),
+                encoded(&expected)
+            );
+        }
+    }
+
+    #[test]
+    fn linear_strings_reject_unsupported_table_operations() {
+        let layout = string_table_layout();
+        for table in [0, 1] {
+            for instruction in [
+                Instruction::TableSize(table),
+                Instruction::TableFill(table),
+                Instruction::TableInit {
+                    elem_index: 0,
+                    table,
+                },
+                Instruction::TableCopy {
+                    src_table: table,
+                    dst_table: 2,
+                },
+                Instruction::TableCopy {
+                    src_table: 2,
+                    dst_table: table,
+                },
+            ] {
+                assert!(rewritten(&layout, &instruction).is_err());
+            }
+        }
+        // costume names can't be changed
+        assert!(rewritten(&layout, &Instruction::TableSet(1)).is_err());
+        assert!(rewritten(&layout, &Instruction::TableGrow(1)).is_err());
+        assert!(
+            rewritten(
+                &layout,
+                &Instruction::TableCopy {
+                    src_table: 1,
+                    dst_table: 1,
+                }
+            )
+            .is_err()
+        );
     }
 }
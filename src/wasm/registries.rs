@@ -1,3 +1,4 @@
+pub mod clones;
 pub mod functions;
 pub mod globals;
 pub mod lists;
@@ -7,10 +8,14 @@ pub mod targets;
 pub mod types;
 pub mod variables;
 
+pub use clones::CloneRegistry;
 pub use functions::{ExternalFunctionRegistry, StaticFunctionRegistry};
 pub use globals::{GlobalExportable, GlobalMutable, GlobalRegistry};
 pub use lists::ListRegistry;
-pub use strings::{StringRegistry, TabledStringRegistry};
+pub use strings::{
+    LinearStringLayout, StringRegistry, TabledStringRegistry, linear_string_field_type,
+    linear_string_val_type,
+};
 pub use tables::{StepsTable, StringsTable, TableRegistry, ThreadsTable};
 pub use targets::SpriteRegistry;
 pub use types::TypeRegistry;
@@ -29,6 +34,7 @@ pub struct Registries {
     variables: VariableRegistry,
     sprites: SpriteRegistry,
     lists: ListRegistry,
+    clones: CloneRegistry,
 }
 
 impl Default for Registries {
@@ -39,6 +45,7 @@ impl Default for Registries {
         let types = Rc::new(TypeRegistry::default());
         let variables = VariableRegistry::new(&globals, &strings, &tabled_strings);
         let lists = ListRegistry::new(&globals, &types, &strings, &tabled_strings);
+        let clones = CloneRegistry::new(&globals, &types);
         Self {
             globals,
             variables,
@@ -50,6 +57,7 @@ impl Default for Registries {
             sprites: SpriteRegistry::default(),
             static_functions: StaticFunctionRegistry::default(),
             lists,
+            clones,
         }
     }
 }
@@ -94,4 +102,8 @@ impl Registries {
     pub const fn lists(&self) -> &ListRegistry {
         &self.lists
     }
+
+    pub const fn clones(&self) -> &CloneRegistry {
+        &self.clones
+    }
 }
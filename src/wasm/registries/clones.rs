@@ -0,0 +1,450 @@
+use wasm_encoder::{BlockType, ConstExpr, HeapType, RefType, StorageType, ValType};
+use wasm_gen::wasm;
+
+use super::functions::static_functions::{CopyListBuffer, FreeListBuffer};
+use super::{
+    GlobalExportable, GlobalMutable, GlobalRegistry, ListRegistry, StaticFunctionRegistry,
+    TableRegistry, TypeRegistry, VariableRegistry,
+};
+use crate::ir::{RcList, RcVar, Target as IrTarget, used_lists, used_vars};
+use crate::prelude::*;
+use crate::registry::SetRegistry;
+use crate::wasm::flags::ListType;
+use crate::wasm::{InternalInstruction, WasmProject, mem_layout};
+
+/// Keeps track of targets which can be cloned, and of the globals used to store the variables
+/// and lists of each clone of those targets.
+///
+/// Each variable and list belonging to a clonable target still has a single global, which holds
+/// the value for whichever clone (or the original sprite) is 'active' for that target. The
+/// values for the other instances are kept in arrays indexed by clone slot (0 being the original
+/// sprite), and are swapped in by the scheduler before running a thread belonging to a
+/// different instance.
+///
+/// These arrays are GC arrays whichever [`ListType`](crate::wasm::flags::ListType) is used, so
+/// clones always need [`WasmFeature::GC`](crate::wasm::flags::WasmFeature::GC); for lists in
+/// linear memory, only the buffer pointers are stored in them.
+pub struct CloneRegistry(
+    SetRegistry<Rc<IrTarget>>,
+    Rc<GlobalRegistry>,
+    Rc<TypeRegistry>,
+);
+
+impl CloneRegistry {
+    const fn registry(&self) -> &SetRegistry<Rc<IrTarget>> {
+        &self.0
+    }
+
+    const fn globals(&self) -> &Rc<GlobalRegistry> {
+        &self.1
+    }
+
+    const fn types(&self) -> &Rc<TypeRegistry> {
+        &self.2
+    }
+
+    #[must_use]
+    pub fn new(globals: &Rc<GlobalRegistry>, types: &Rc<TypeRegistry>) -> Self {
+        Self(SetRegistry::default(), Rc::clone(globals), Rc::clone(types))
+    }
+
+    pub fn register(&self, target: &Rc<IrTarget>) -> HQResult<()> {
+        self.registry()
+            .register_default::<usize>(Rc::clone(target))?;
+        Ok(())
+    }
+
+    /// The targets which have been registered as clonable, in the order they were registered.
+    pub fn targets(&self) -> HQResult<Box<[Rc<IrTarget>]>> {
+        Ok(self
+            .registry()
+            .registry()
+            .try_borrow()?
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    /// The global holding the clone slot whose variables and lists are currently held in the
+    /// target's variable and list globals.
+    pub fn active_slot_global<N>(&self, target: &IrTarget) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.globals().register(
+            format!("__clone_active_slot_{}", target.index()).into(),
+            (
+                ValType::I32,
+                ConstExpr::i32_const(0),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
+
+    /// Registers an array global with one element per clone slot; returns the index of the
+    /// global and of the array type.
+    fn store_global(&self, name: String, elem_type: ValType) -> HQResult<(u32, u32)> {
+        let array_type = self.types().array(StorageType::Val(elem_type), true)?;
+        let global = self.globals().register(
+            name.into(),
+            (
+                ValType::Ref(RefType {
+                    nullable: false,
+                    heap_type: HeapType::Concrete(array_type),
+                }),
+                ConstExpr::extended([
+                    wasm_encoder::Instruction::I32Const(
+                        (mem_layout::MAX_CLONES + 1)
+                            .try_into()
+                            .map_err(|_| make_hq_bug!("MAX_CLONES out of bounds"))?,
+                    ),
+                    wasm_encoder::Instruction::ArrayNewDefault(array_type),
+                ]),
+                GlobalMutable(false),
+                GlobalExportable(false),
+            ),
+        )?;
+        Ok((global, array_type))
+    }
+
+    /// Returns the global & type of the array holding the value of a variable for each clone
+    /// slot. The value for the active slot is only up to date once it has been saved; see
+    /// [`Self::save_instructions`].
+    pub fn variable_store_global(&self, var: &RcVar) -> HQResult<(u32, u32)> {
+        self.store_global(
+            format!("__clone_store_var_{}", var.id()),
+            WasmProject::ir_type_to_wasm(*var.possible_types()),
+        )
+    }
+
+    /// Instructions to store the values of the target's variables and lists into the clone slot
+    /// that is currently active.
+    pub fn save_instructions(
+        &self,
+        target: &IrTarget,
+        variables: &VariableRegistry,
+        lists: &ListRegistry,
+        list_type: ListType,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        let active_slot = self.active_slot_global(target)?;
+        let mut instructions = vec![];
+        for var in used_vars(target.variables()) {
+            let var_global = variables.register(&var)?;
+            let (store, store_ty) = self.variable_store_global(&var)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(store),
+                #LazyGlobalGet(active_slot),
+                #LazyGlobalGet(var_global),
+                ArraySet(store_ty),
+            ]);
+        }
+        for list in used_lists(target.lists()) {
+            if list_type == ListType::LinearMemory {
+                let (pointer_global, store, store_ty) = self.list_pointer_globals(&list, lists)?;
+                instructions.extend(wasm![
+                    #LazyGlobalGet(store),
+                    #LazyGlobalGet(active_slot),
+                    #LazyGlobalGet(pointer_global),
+                    ArraySet(store_ty),
+                ]);
+                continue;
+            }
+            let (list_global, list_store, list_store_ty, len_global, len_store, len_store_ty) =
+                self.list_globals(&list, lists)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(list_store),
+                #LazyGlobalGet(active_slot),
+                #LazyGlobalGet(list_global),
+                ArraySet(list_store_ty),
+                #LazyGlobalGet(len_store),
+                #LazyGlobalGet(active_slot),
+                #LazyGlobalGet(len_global),
+                ArraySet(len_store_ty),
+            ]);
+        }
+        Ok(instructions)
+    }
+
+    /// Instructions to load the target's variables and lists from the clone slot held in
+    /// `slot_local`, and to mark that slot as active. This does not save the values of the
+    /// currently active slot; see [`Self::save_instructions`].
+    pub fn load_instructions(
+        &self,
+        target: &IrTarget,
+        slot_local: u32,
+        variables: &VariableRegistry,
+        lists: &ListRegistry,
+        list_type: ListType,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        let active_slot = self.active_slot_global(target)?;
+        let mut instructions = vec![];
+        for var in used_vars(target.variables()) {
+            let var_global = variables.register(&var)?;
+            let (store, store_ty) = self.variable_store_global(&var)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(store),
+                LocalGet(slot_local),
+                ArrayGet(store_ty),
+                #LazyGlobalSet(var_global),
+            ]);
+        }
+        for list in used_lists(target.lists()) {
+            if list_type == ListType::LinearMemory {
+                let (pointer_global, store, store_ty) = self.list_pointer_globals(&list, lists)?;
+                instructions.extend(wasm![
+                    #LazyGlobalGet(store),
+                    LocalGet(slot_local),
+                    ArrayGet(store_ty),
+                    #LazyGlobalSet(pointer_global),
+                ]);
+                continue;
+            }
+            let (list_global, list_store, list_store_ty, len_global, len_store, len_store_ty) =
+                self.list_globals(&list, lists)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(list_store),
+                LocalGet(slot_local),
+                ArrayGet(list_store_ty),
+                RefAsNonNull,
+                #LazyGlobalSet(list_global),
+                #LazyGlobalGet(len_store),
+                LocalGet(slot_local),
+                ArrayGet(len_store_ty),
+                #LazyGlobalSet(len_global),
+            ]);
+        }
+        instructions.extend(wasm![LocalGet(slot_local), #LazyGlobalSet(active_slot)]);
+        Ok(instructions)
+    }
+
+    /// Instructions to copy the stored variables and lists of the clone slot in `src_local` into
+    /// the slot in `dst_local`. Lists are copied, rather than shared between the two slots.
+    ///
+    /// The values of the active slot should be saved beforehand if it might be the source slot.
+    #[expect(clippy::too_many_arguments, reason = "too many arguments!")]
+    pub fn copy_instructions(
+        &self,
+        target: &IrTarget,
+        src_local: u32,
+        dst_local: u32,
+        variables: &VariableRegistry,
+        lists: &ListRegistry,
+        list_type: ListType,
+        static_functions: &StaticFunctionRegistry,
+        tables: &TableRegistry,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        let mut instructions = vec![];
+        for var in used_vars(target.variables()) {
+            // make sure that the variable's global exists, for consistency with `save_instructions`
+            variables.register::<u32>(&var)?;
+            let (store, store_ty) = self.variable_store_global(&var)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(store),
+                LocalGet(dst_local),
+                #LazyGlobalGet(store),
+                LocalGet(src_local),
+                ArrayGet(store_ty),
+                ArraySet(store_ty),
+            ]);
+        }
+        for list in used_lists(target.lists()) {
+            if list_type == ListType::LinearMemory {
+                let (_, store, store_ty) = self.list_pointer_globals(&list, lists)?;
+                let copy_func = static_functions.register::<CopyListBuffer, _>()?;
+                // the new buffers are freed when the clone is deleted; see `free_instructions`
+                // and `free_clone_slots_instructions`
+                static_functions.register::<FreeListBuffer, u32>()?;
+                let item_size = ListRegistry::item_size(&list)
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("list item size out of bounds"))?;
+                instructions.extend(wasm![
+                    #LazyGlobalGet(store),
+                    LocalGet(dst_local),
+                    #LazyGlobalGet(store),
+                    LocalGet(src_local),
+                    ArrayGet(store_ty),
+                    I32Const(item_size),
+                    I32Const(0),
+                    #StaticFunctionCall(copy_func),
+                    ArraySet(store_ty),
+                ]);
+                instructions.extend(ListRegistry::copy_string_items(
+                    &list,
+                    tables,
+                    &wasm![#LazyGlobalGet(store), LocalGet(src_local), ArrayGet(store_ty)],
+                    &wasm![#LazyGlobalGet(store), LocalGet(dst_local), ArrayGet(store_ty)],
+                )?);
+                continue;
+            }
+            let (_, list_store, list_store_ty, _, len_store, len_store_ty) =
+                self.list_globals(&list, lists)?;
+            let list_ty = lists.array_type(&list)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(list_store),
+                LocalGet(dst_local),
+                #LazyGlobalGet(list_store),
+                LocalGet(src_local),
+                ArrayGet(list_store_ty),
+                RefAsNonNull,
+                ArrayLen,
+                ArrayNewDefault(list_ty),
+                ArraySet(list_store_ty),
+                #LazyGlobalGet(list_store),
+                LocalGet(dst_local),
+                ArrayGet(list_store_ty),
+                RefAsNonNull,
+                I32Const(0),
+                #LazyGlobalGet(list_store),
+                LocalGet(src_local),
+                ArrayGet(list_store_ty),
+                RefAsNonNull,
+                I32Const(0),
+                #LazyGlobalGet(len_store),
+                LocalGet(src_local),
+                ArrayGet(len_store_ty),
+                ArrayCopy {
+                    array_type_index_dst: list_ty,
+                    array_type_index_src: list_ty,
+                },
+                #LazyGlobalGet(len_store),
+                LocalGet(dst_local),
+                #LazyGlobalGet(len_store),
+                LocalGet(src_local),
+                ArrayGet(len_store_ty),
+                ArraySet(len_store_ty),
+            ]);
+        }
+        Ok(instructions)
+    }
+
+    /// Instructions to free the buffers of the target's lists which are currently loaded, when
+    /// lists are stored in linear memory, so that they can be reused; this is for when the active
+    /// clone is deleted. The list pointer globals are set to 0 afterwards, so that the freed
+    /// buffers aren't saved into the clone stores (and freed again later).
+    pub fn free_instructions(
+        &self,
+        target: &IrTarget,
+        lists: &ListRegistry,
+        list_type: ListType,
+        static_functions: &StaticFunctionRegistry,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        if list_type != ListType::LinearMemory {
+            return Ok(vec![]);
+        }
+        let free_func = static_functions.register::<FreeListBuffer, _>()?;
+        let mut instructions = vec![];
+        for list in used_lists(target.lists()) {
+            let pointer_global = lists.register_linear_memory(&list)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(pointer_global),
+                #StaticFunctionCall(free_func),
+                I32Const(0),
+                #LazyGlobalSet(pointer_global),
+            ]);
+        }
+        Ok(instructions)
+    }
+
+    /// Instructions to free the stored buffers of the target's lists for every clone slot (i.e.
+    /// every slot apart from the original sprite's), when lists are stored in linear memory,
+    /// using `slot_local` as the loop counter. Freed slots are set to 0.
+    ///
+    /// The values of the active slot should be saved beforehand, and the original sprite's slot
+    /// should be loaded afterwards if a clone is active.
+    pub fn free_clone_slots_instructions(
+        &self,
+        target: &IrTarget,
+        slot_local: u32,
+        lists: &ListRegistry,
+        list_type: ListType,
+        static_functions: &StaticFunctionRegistry,
+    ) -> HQResult<Vec<InternalInstruction>> {
+        let target_lists = used_lists(target.lists());
+        if list_type != ListType::LinearMemory || target_lists.is_empty() {
+            return Ok(vec![]);
+        }
+        let free_func = static_functions.register::<FreeListBuffer, _>()?;
+        let mut instructions = wasm![
+            I32Const(1),
+            LocalSet(slot_local),
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            LocalGet(slot_local),
+            I32Const(
+                mem_layout::MAX_CLONES
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("MAX_CLONES out of bounds"))?
+            ),
+            I32GtU,
+            BrIf(1),
+        ];
+        for list in target_lists {
+            let (_, store, store_ty) = self.list_pointer_globals(&list, lists)?;
+            instructions.extend(wasm![
+                #LazyGlobalGet(store),
+                LocalGet(slot_local),
+                ArrayGet(store_ty),
+                #StaticFunctionCall(free_func),
+                #LazyGlobalGet(store),
+                LocalGet(slot_local),
+                I32Const(0),
+                ArraySet(store_ty),
+            ]);
+        }
+        instructions.extend(wasm![
+            LocalGet(slot_local),
+            I32Const(1),
+            I32Add,
+            LocalSet(slot_local),
+            Br(0),
+            End,
+            End,
+        ]);
+        Ok(instructions)
+    }
+
+    /// Returns the buffer pointer global, and the pointer store global & type, for a list stored
+    /// in linear memory. Its length is kept in the buffer, so doesn't need storing separately.
+    fn list_pointer_globals(
+        &self,
+        list: &RcList,
+        lists: &ListRegistry,
+    ) -> HQResult<(u32, u32, u32)> {
+        let pointer_global = lists.register_linear_memory(list)?;
+        let (store, store_ty) =
+            self.store_global(format!("__clone_store_ptr_{}", list.id()), ValType::I32)?;
+        Ok((pointer_global, store, store_ty))
+    }
+
+    /// Returns the list global, list store global & type, length global, and length store
+    /// global & type for a list.
+    fn list_globals(
+        &self,
+        list: &RcList,
+        lists: &ListRegistry,
+    ) -> HQResult<(u32, u32, u32, u32, u32, u32)> {
+        let (list_global, Some(len_global)) = lists.register(list)? else {
+            hq_bug!("lists of clonable targets should have a mutable length")
+        };
+        let list_ty = lists.array_type(list)?;
+        let (list_store, list_store_ty) = self.store_global(
+            format!("__clone_store_list_{}", list.id()),
+            ValType::Ref(RefType {
+                nullable: true,
+                heap_type: HeapType::Concrete(list_ty),
+            }),
+        )?;
+        let (len_store, len_store_ty) =
+            self.store_global(format!("__clone_store_len_{}", list.id()), ValType::I32)?;
+        Ok((
+            list_global,
+            list_store,
+            list_store_ty,
+            len_global,
+            len_store,
+            len_store_ty,
+        ))
+    }
+}
@@ -1,8 +1,12 @@
 #![allow(clippy::cast_possible_wrap, reason = "can't use try_into in const")]
 
+mod clones;
+mod lists;
 mod mark_waiting_flag;
 mod pen_colour;
+mod random;
 mod spawn_threads;
+mod strings;
 
 use wasm_encoder::{
     CodeSection, EntityType, ExportKind, ExportSection, Function, FunctionSection, ImportSection,
@@ -12,14 +16,21 @@ use wasm_encoder::{
 use super::TypeRegistry;
 use crate::prelude::*;
 use crate::registry::{MapRegistry, Registry};
+use crate::wasm::ExternalEnvironment;
 
 pub type ExternalFunctionRegistry =
     MapRegistry<(&'static str, Box<str>), (Vec<ValType>, Vec<ValType>)>;
 
 impl ExternalFunctionRegistry {
-    pub fn finish(self, imports: &mut ImportSection, type_registry: &TypeRegistry) -> HQResult<()> {
+    pub fn finish(
+        self,
+        imports: &mut ImportSection,
+        type_registry: &TypeRegistry,
+        environment: ExternalEnvironment,
+    ) -> HQResult<()> {
         for ((module, name), (params, results)) in self.registry().take() {
             let type_index = type_registry.function(params, results)?;
+            let (module, name) = environment.import_name(module, &name);
             imports.import(module, &name, EntityType::Function(type_index));
         }
         Ok(())
@@ -105,10 +116,28 @@ impl StaticFunctionRegistry {
     }
 }
 
+pub use random::SEED_MIX;
+
 pub mod static_functions {
+    pub use super::clones::{
+        DeleteInstanceThreads, DeleteInstanceThreadsOverride, SpawnCloneThreads,
+        SpawnCloneThreadsOverride,
+    };
+    pub use super::lists::{
+        AllocListBuffer, AllocListBufferOverride, CopyListBuffer, CopyListBufferOverride,
+        FreeListBuffer, FreeListBufferOverride,
+    };
     pub use super::mark_waiting_flag::MarkWaitingFlag;
-    pub use super::pen_colour::{UpdatePenColorFromHSV, UpdatePenColorFromRGB};
+    pub use super::pen_colour::{
+        UpdatePenColorFromHSV, UpdatePenColorFromRGB, UpdatePenColorFromShade,
+    };
+    pub use super::random::{RandomFloat, SetRandomSeed};
     pub use super::spawn_threads::{
         SpawnNewThread, SpawnNewThreadOverride, SpawnThreadInStack, SpawnThreadInStackOverride,
     };
+    pub use super::strings::{
+        AllocScratchString, AllocScratchStringOverride, AllocString, AllocStringOverride,
+        CopyStringSlots, NewStringSlots, NewStringSlotsOverride, PersistString,
+        PersistStringOverride, SetStringSlot, SetStringSlotOverride,
+    };
 }
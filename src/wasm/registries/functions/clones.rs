@@ -0,0 +1,201 @@
+use mem_layout::sprite as sprite_layout;
+use wasm_encoder::{AbstractHeapType, BlockType, HeapType, MemArg, RefType, ValType};
+use wasm_gen::wasm_const;
+
+use super::{MaybeStaticFunction, StaticFunction};
+use crate::prelude::*;
+use crate::wasm::mem_layout;
+
+/// Spawns a new thread with the provided step function for each clone of a sprite which
+/// currently exists, and increments the threads count accordingly.
+///
+/// Takes 3 parameters:
+/// - step funcref - the step to spawn
+/// - i32 - the IR index of the target
+/// - i32 - the wasm index of the sprite
+///
+/// Override with:
+/// - u32 - the index of the step func type
+/// - u32 - the (absolute) function index of `SpawnNewThread`
+/// - u32 - the (absolute) global index of the threads count global
+/// - u32 - the number of targets in the project
+pub struct SpawnCloneThreads;
+impl NamedRegistryItem<MaybeStaticFunction> for SpawnCloneThreads {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type SpawnCloneThreadsOverride = (u32, u32, u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, SpawnCloneThreadsOverride>
+    for SpawnCloneThreads
+{
+    fn r#override(
+        (func_ty, spawn_new_thread, threads_count, target_count): SpawnCloneThreadsOverride,
+    ) -> MaybeStaticFunction {
+        // clone slots are 1-indexed, so slot `n` lives at `slot_base + n * BLOCK_SIZE`
+        let slot_base = mem_layout::clone_pool_offset(target_count) - sprite_layout::BLOCK_SIZE;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([
+                    ValType::Ref(RefType {
+                        nullable: false,
+                        heap_type: HeapType::Concrete(func_ty),
+                    }),
+                    ValType::I32,
+                    ValType::I32,
+                ]),
+                returns: Box::from([]),
+                locals: Box::from([ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    I32Const(1),
+                    LocalSet(3),
+                    Block(BlockType::Empty),
+                    Loop(BlockType::Empty),
+                    LocalGet(3),
+                    I32Const(mem_layout::MAX_CLONES as i32),
+                    I32GtU,
+                    BrIf(1),
+                    // slots in the clone pool are shared between sprites, so check that this
+                    // slot holds an active clone which belongs to the given sprite
+                    LocalGet(3),
+                    I32Const(sprite_layout::BLOCK_SIZE as i32),
+                    I32Mul,
+                    I32Load8U(MemArg {
+                        offset: (slot_base + sprite_layout::CLONE_ACTIVE).into(),
+                        align: 0,
+                        memory_index: 0,
+                    }),
+                    I32Const(0),
+                    I32Ne,
+                    LocalGet(3),
+                    I32Const(sprite_layout::BLOCK_SIZE as i32),
+                    I32Mul,
+                    I32Load16U(MemArg {
+                        offset: (slot_base + sprite_layout::CLONE_OWNER).into(),
+                        align: 1,
+                        memory_index: 0,
+                    }),
+                    LocalGet(2),
+                    I32Eq,
+                    I32And,
+                    If(BlockType::Empty),
+                    LocalGet(0),
+                    RefNull(HeapType::Abstract {
+                        shared: false,
+                        ty: AbstractHeapType::Struct,
+                    }),
+                    LocalGet(1),
+                    LocalGet(3),
+                    I32Const(target_count as i32),
+                    I32Mul,
+                    I32Add,
+                    Call(spawn_new_thread),
+                    GlobalGet(threads_count),
+                    I32Const(1),
+                    I32Add,
+                    GlobalSet(threads_count),
+                    End,
+                    LocalGet(3),
+                    I32Const(1),
+                    I32Add,
+                    LocalSet(3),
+                    Br(0),
+                    End,
+                    End,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Stops all threads belonging to a sprite instance (i.e. an original sprite, a clone, or the
+/// stage), except for one, and decrements the threads count accordingly. This is used both for
+/// deleting clones and for stopping other scripts in a sprite.
+///
+/// Takes 2 parameters:
+/// - i32 - the instance index whose threads should be stopped
+/// - i32 - the index of a thread which should not be stopped
+///
+/// Override with:
+/// - u32 - the index of the thread struct type
+/// - u32 - the index of the threads table
+/// - u32 - the (absolute) global index of the threads count global
+pub struct DeleteInstanceThreads;
+impl NamedRegistryItem<MaybeStaticFunction> for DeleteInstanceThreads {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type DeleteInstanceThreadsOverride = (u32, u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, DeleteInstanceThreadsOverride>
+    for DeleteInstanceThreads
+{
+    fn r#override(
+        (thread_struct_ty, threads_table, threads_count): DeleteInstanceThreadsOverride,
+    ) -> MaybeStaticFunction {
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32, ValType::I32]),
+                returns: Box::from([]),
+                locals: Box::from([
+                    ValType::I32,
+                    ValType::Ref(RefType {
+                        nullable: true,
+                        heap_type: HeapType::Concrete(thread_struct_ty),
+                    }),
+                ]),
+                instructions: Box::from(wasm_const![
+                    Block(BlockType::Empty),
+                    Loop(BlockType::Empty),
+                    LocalGet(2),
+                    TableSize(threads_table),
+                    I32GeU,
+                    BrIf(1),
+                    LocalGet(2),
+                    TableGet(threads_table),
+                    LocalTee(3),
+                    RefIsNull,
+                    I32Eqz,
+                    If(BlockType::Empty),
+                    LocalGet(3),
+                    RefAsNonNull,
+                    StructGet {
+                        struct_type_index: thread_struct_ty,
+                        field_index: 2,
+                    },
+                    LocalGet(0),
+                    I32Eq,
+                    LocalGet(2),
+                    LocalGet(1),
+                    I32Ne,
+                    I32And,
+                    If(BlockType::Empty),
+                    LocalGet(2),
+                    RefNull(HeapType::Concrete(thread_struct_ty)),
+                    TableSet(threads_table),
+                    GlobalGet(threads_count),
+                    I32Const(1),
+                    I32Sub,
+                    GlobalSet(threads_count),
+                    End,
+                    End,
+                    LocalGet(2),
+                    I32Const(1),
+                    I32Add,
+                    LocalSet(2),
+                    Br(0),
+                    End,
+                    End,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use mem_layout::list_header;
+use wasm_encoder::{BlockType as WasmBlockType, MemArg, ValType};
+use wasm_gen::wasm_const;
+
+use super::{MaybeStaticFunction, StaticFunction};
+use crate::prelude::*;
+use crate::wasm::mem_layout;
+
+fn header_mem_arg(field: u32) -> MemArg {
+    MemArg {
+        offset: field.into(),
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+/// Allocates a list buffer in linear memory with a length of 0, returning a pointer to it.
+///
+/// The smallest free buffer with the same item size and at least the requested capacity is
+/// reused if there is one (keeping its capacity, and its string slots if it has any); otherwise
+/// a new buffer is allocated by bumping the list heap end global, growing memory as needed.
+///
+/// Takes 2 parameters:
+/// - i32 size of each item, in bytes
+/// - i32 minimum capacity of the buffer
+///
+/// Override with:
+/// - u32 - the (absolute) global index of the list heap end global
+/// - u32 - the (absolute) global index of the list free list global
+pub struct AllocListBuffer;
+impl NamedRegistryItem<MaybeStaticFunction> for AllocListBuffer {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type AllocListBufferOverride = (u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, AllocListBufferOverride> for AllocListBuffer {
+    fn r#override((heap_end, free_list): AllocListBufferOverride) -> MaybeStaticFunction {
+        let length = header_mem_arg(list_header::LENGTH);
+        let capacity = header_mem_arg(list_header::CAPACITY);
+        let item_size = header_mem_arg(list_header::ITEM_SIZE);
+        let string_slots = header_mem_arg(list_header::STRING_SLOTS);
+        let header_size = list_header::BLOCK_SIZE as i32;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32, ValType::I32]),
+                returns: Box::from([ValType::I32]),
+                // current buffer, previous buffer, best buffer, buffer before the best buffer
+                locals: Box::from([ValType::I32, ValType::I32, ValType::I32, ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    GlobalGet(free_list),
+                    LocalSet(2),
+                    Block(WasmBlockType::Empty),
+                    Loop(WasmBlockType::Empty),
+                    LocalGet(2),
+                    I32Eqz,
+                    BrIf(1),
+                    LocalGet(2),
+                    I32Load(item_size),
+                    LocalGet(0),
+                    I32Eq,
+                    LocalGet(2),
+                    I32Load(capacity),
+                    LocalGet(1),
+                    I32GeU,
+                    I32And,
+                    LocalGet(4),
+                    I32Eqz,
+                    LocalGet(2),
+                    I32Load(capacity),
+                    LocalGet(4),
+                    I32Load(capacity),
+                    I32LtU,
+                    I32Or,
+                    I32And,
+                    If(WasmBlockType::Empty),
+                    LocalGet(2),
+                    LocalSet(4),
+                    LocalGet(3),
+                    LocalSet(5),
+                    End,
+                    LocalGet(2),
+                    LocalSet(3),
+                    LocalGet(2),
+                    I32Load(length),
+                    LocalSet(2),
+                    Br(0),
+                    End,
+                    End,
+                    LocalGet(4),
+                    If(WasmBlockType::Empty),
+                    // unlink the buffer from the free list
+                    LocalGet(5),
+                    I32Eqz,
+                    If(WasmBlockType::Empty),
+                    LocalGet(4),
+                    I32Load(length),
+                    GlobalSet(free_list),
+                    Else,
+                    LocalGet(5),
+                    LocalGet(4),
+                    I32Load(length),
+                    I32Store(length),
+                    End,
+                    Else,
+                    GlobalGet(heap_end),
+                    LocalTee(4),
+                    // keep buffers 8-byte aligned
+                    LocalGet(1),
+                    LocalGet(0),
+                    I32Mul,
+                    I32Const(header_size + 7),
+                    I32Add,
+                    I32Const(-8),
+                    I32And,
+                    I32Add,
+                    GlobalSet(heap_end),
+                    GlobalGet(heap_end),
+                    MemorySize(0),
+                    I32Const(16),
+                    I32Shl,
+                    I32GtU,
+                    If(WasmBlockType::Empty),
+                    // grow by enough pages to fit the new buffer
+                    GlobalGet(heap_end),
+                    MemorySize(0),
+                    I32Const(16),
+                    I32Shl,
+                    I32Sub,
+                    I32Const(0xFFFF),
+                    I32Add,
+                    I32Const(16),
+                    I32ShrU,
+                    MemoryGrow(0),
+                    I32Const(-1),
+                    I32Eq,
+                    If(WasmBlockType::Empty),
+                    Unreachable,
+                    End,
+                    End,
+                    LocalGet(4),
+                    LocalGet(1),
+                    I32Store(capacity),
+                    LocalGet(4),
+                    LocalGet(0),
+                    I32Store(item_size),
+                    LocalGet(4),
+                    I32Const(-1),
+                    I32Store(string_slots),
+                    End,
+                    LocalGet(4),
+                    I32Const(0),
+                    I32Store(length),
+                    LocalGet(4),
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Adds a list buffer in linear memory to the free list, so that it can be reused by
+/// `AllocListBuffer`. Does nothing if the pointer is 0.
+///
+/// Takes 1 parameter, the i32 pointer to the buffer.
+///
+/// Override with one u32, the (absolute) global index of the list free list global
+pub struct FreeListBuffer;
+impl NamedRegistryItem<MaybeStaticFunction> for FreeListBuffer {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type FreeListBufferOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, FreeListBufferOverride> for FreeListBuffer {
+    fn r#override(free_list: FreeListBufferOverride) -> MaybeStaticFunction {
+        let length = header_mem_arg(list_header::LENGTH);
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32]),
+                returns: Box::from([]),
+                locals: Box::from([]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    If(WasmBlockType::Empty),
+                    LocalGet(0),
+                    GlobalGet(free_list),
+                    I32Store(length),
+                    LocalGet(0),
+                    GlobalSet(free_list),
+                    End,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Copies a list buffer in linear memory into a buffer allocated by `AllocListBuffer`,
+/// returning a pointer to the new buffer. The new buffer has the same length as the old one, and
+/// a capacity of at least that of the old buffer and the requested minimum capacity. The old
+/// buffer is left untouched.
+///
+/// The items of lists of strings aren't stored in the buffer, so aren't copied; see
+/// [`ListRegistry::copy_string_items`](crate::wasm::registries::ListRegistry::copy_string_items).
+///
+/// Takes 3 parameters:
+/// - i32 pointer to the old buffer
+/// - i32 size of each item, in bytes
+/// - i32 minimum capacity of the new buffer
+///
+/// Override with one u32, the (absolute) function index of `AllocListBuffer`
+pub struct CopyListBuffer;
+impl NamedRegistryItem<MaybeStaticFunction> for CopyListBuffer {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type CopyListBufferOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, CopyListBufferOverride> for CopyListBuffer {
+    fn r#override(alloc_list_buffer: CopyListBufferOverride) -> MaybeStaticFunction {
+        let length = header_mem_arg(list_header::LENGTH);
+        let capacity = header_mem_arg(list_header::CAPACITY);
+        let header_size = list_header::BLOCK_SIZE as i32;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32, ValType::I32, ValType::I32]),
+                returns: Box::from([ValType::I32]),
+                locals: Box::from([ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(1),
+                    // capacity = max(old capacity, minimum capacity)
+                    LocalGet(0),
+                    I32Load(capacity),
+                    LocalTee(3),
+                    LocalGet(2),
+                    LocalGet(3),
+                    LocalGet(2),
+                    I32GtU,
+                    Select,
+                    Call(alloc_list_buffer),
+                    LocalTee(3),
+                    LocalGet(0),
+                    I32Load(length),
+                    I32Store(length),
+                    LocalGet(3),
+                    I32Const(header_size),
+                    I32Add,
+                    LocalGet(0),
+                    I32Const(header_size),
+                    I32Add,
+                    LocalGet(0),
+                    I32Load(length),
+                    LocalGet(1),
+                    I32Mul,
+                    MemoryCopy {
+                        src_mem: 0,
+                        dst_mem: 0,
+                    },
+                    LocalGet(3),
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
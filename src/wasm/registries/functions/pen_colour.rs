@@ -1,4 +1,4 @@
-use mem_layout::{sprite as sprite_layout, stage as stage_layout};
+use mem_layout::sprite as sprite_layout;
 use wasm_encoder::{BlockType as WasmBlockType, MemArg, ValType};
 use wasm_gen::wasm_const;
 
@@ -8,7 +8,6 @@ use crate::wasm::mem_layout;
 
 index_counter! {
     hsv2rgb_locals
-    SPRITE_INDEX
     MEM_POS
     HUE SAT VAL
     REGION
@@ -20,7 +19,7 @@ index_counter! {
 
 /// Updates the stored RGBA pen colour from the HSV colour.
 ///
-/// Takes 1 paramter, an i32 corresponding to the target index
+/// Takes 1 paramter, an i32 corresponding to the position in memory of the sprite's block
 ///
 /// Not overridable.
 pub struct UpdatePenColorFromHSV;
@@ -41,12 +40,7 @@ impl NamedRegistryItem<MaybeStaticFunction> for UpdatePenColorFromHSV {
                 }),
                 instructions: (wasm_const![
                     // hsv->rgb based off of https://stackoverflow.com/a/14733008
-                    LocalGet(hsv2rgb_locals::SPRITE_INDEX),
-                    I32Const(sprite_layout::BLOCK_SIZE as i32),
-                    I32Mul,
-                    I32Const(stage_layout::BLOCK_SIZE as i32),
-                    I32Add,
-                    LocalTee(hsv2rgb_locals::MEM_POS), // position in memory of sprite info
+                    LocalGet(hsv2rgb_locals::MEM_POS), // position in memory of sprite info
                     F32Load(MemArg {
                         offset: sprite_layout::PEN_COLOR.into(),
                         align: 2,
@@ -290,7 +284,6 @@ impl NamedRegistryItem<MaybeStaticFunction> for UpdatePenColorFromHSV {
 
 index_counter! {
     rgb2hsv_locals
-    SPRITE_INDEX
     MEM_POS
     R G B A
     RGB_MIN RGB_MAX
@@ -299,7 +292,7 @@ index_counter! {
 
 /// Updates the stored HSV pen colour from the RGBA colour.
 ///
-/// Takes one parameter, an i32 corresponding to the target index.
+/// Takes one parameter, an i32 corresponding to the position in memory of the sprite's block.
 ///
 /// Not overridable.
 pub struct UpdatePenColorFromRGB;
@@ -320,12 +313,7 @@ impl NamedRegistryItem<MaybeStaticFunction> for UpdatePenColorFromRGB {
                 }),
                 instructions: (wasm_const![
                     // rgb->hsv based off of https://stackoverflow.com/a/14733008
-                    LocalGet(rgb2hsv_locals::SPRITE_INDEX),
-                    I32Const(sprite_layout::BLOCK_SIZE as i32),
-                    I32Mul,
-                    I32Const(stage_layout::BLOCK_SIZE as i32),
-                    I32Add,
-                    LocalTee(rgb2hsv_locals::MEM_POS), // position in memory of sprite info
+                    LocalGet(rgb2hsv_locals::MEM_POS), // position in memory of sprite info
                     F32Load(MemArg {
                         offset: sprite_layout::PEN_COLOR_R.into(),
                         align: 2,
@@ -524,3 +512,105 @@ impl NamedRegistryItem<MaybeStaticFunction> for UpdatePenColorFromRGB {
         },
     };
 }
+
+index_counter! {
+    shade_locals
+    MEM_POS
+    SHADE
+}
+
+/// Updates the stored saturation and brightness of the pen colour from the legacy (Scratch 2)
+/// pen shade, keeping the hue. `UpdatePenColorFromHSV` should be called afterwards.
+///
+/// Scratch mixes the fully saturated hue with black (for shades below 50) or white (for shades
+/// above 50) and converts the result back to HSV, which only ever changes the brightness or the
+/// saturation respectively, so we set them directly.
+/// See <https://github.com/scratchfoundation/scratch-vm/blob/8dbcc1f/src/extensions/scratch3_pen/index.js>
+///
+/// Takes one parameter, an i32 corresponding to the position in memory of the sprite's block.
+///
+/// Not overridable.
+pub struct UpdatePenColorFromShade;
+impl NamedRegistryItem<MaybeStaticFunction> for UpdatePenColorFromShade {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || {
+            Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32]),
+                returns: Box::from([]),
+                locals: Box::from([ValType::F32]),
+                instructions: (wasm_const![
+                    LocalGet(shade_locals::MEM_POS), // position in memory of sprite info
+                    F32Load(MemArg {
+                        offset: sprite_layout::PEN_SHADE.into(),
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    LocalSet(shade_locals::SHADE), // shade ∈ [0, 200)
+                    F32Const(200.0.into()),
+                    LocalGet(shade_locals::SHADE),
+                    F32Sub,
+                    LocalGet(shade_locals::SHADE),
+                    LocalGet(shade_locals::SHADE),
+                    F32Const(100.0.into()),
+                    F32Gt,
+                    Select,
+                    LocalSet(shade_locals::SHADE), // shade ∈ [0, 100]
+                    LocalGet(shade_locals::SHADE),
+                    F32Const(50.0.into()),
+                    F32Lt,
+                    If(WasmBlockType::Empty),
+                    // mix black with the hue
+                    LocalGet(shade_locals::MEM_POS),
+                    F32Const(100.0.into()),
+                    F32Store(MemArg {
+                        offset: sprite_layout::PEN_SATURATION.into(),
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    LocalGet(shade_locals::MEM_POS),
+                    LocalGet(shade_locals::SHADE),
+                    F32Const(10.0.into()),
+                    F32Add,
+                    F32Const(60.0.into()),
+                    F32Div,
+                    F32Const(100.0.into()),
+                    F32Mul,
+                    F32Store(MemArg {
+                        offset: sprite_layout::PEN_BRIGHTNESS.into(),
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    Else,
+                    // mix the hue with white
+                    LocalGet(shade_locals::MEM_POS),
+                    F32Const(1.0.into()),
+                    LocalGet(shade_locals::SHADE),
+                    F32Const(50.0.into()),
+                    F32Sub,
+                    F32Const(60.0.into()),
+                    F32Div,
+                    F32Sub,
+                    F32Const(100.0.into()),
+                    F32Mul,
+                    F32Store(MemArg {
+                        offset: sprite_layout::PEN_SATURATION.into(),
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    LocalGet(shade_locals::MEM_POS),
+                    F32Const(100.0.into()),
+                    F32Store(MemArg {
+                        offset: sprite_layout::PEN_BRIGHTNESS.into(),
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    End,
+                    End,
+                ] as &[_])
+                    .into(),
+            })
+        },
+    };
+}
@@ -0,0 +1,105 @@
+use wasm_encoder::ValType;
+use wasm_gen::wasm_const;
+
+use super::{MaybeStaticFunction, StaticFunction};
+use crate::prelude::*;
+
+/// Seeds are xored with this before being used as the random state, which guarantees that the
+/// state is never 0 (from which xorshift would never escape). This is `0x9E3779B97F4A7C15`.
+pub const SEED_MIX: i64 = -0x61C8_8646_80B5_83EB;
+
+/// Generates a pseudo-random f64 in the range [0, 1) using xorshift64*, updating the random
+/// state global.
+///
+/// Takes no parameters.
+///
+/// Override with one u32, the (absolute) global index of the random state global
+pub struct RandomFloat;
+impl NamedRegistryItem<MaybeStaticFunction> for RandomFloat {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type RandomFloatOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, RandomFloatOverride> for RandomFloat {
+    fn r#override(rng_state: u32) -> MaybeStaticFunction {
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([]),
+                returns: Box::from([ValType::F64]),
+                locals: Box::from([ValType::I64]),
+                instructions: Box::from(wasm_const![
+                    GlobalGet(rng_state),
+                    LocalTee(0),
+                    LocalGet(0),
+                    I64Const(12),
+                    I64ShrU,
+                    I64Xor,
+                    LocalTee(0),
+                    LocalGet(0),
+                    I64Const(25),
+                    I64Shl,
+                    I64Xor,
+                    LocalTee(0),
+                    LocalGet(0),
+                    I64Const(27),
+                    I64ShrU,
+                    I64Xor,
+                    LocalTee(0),
+                    GlobalSet(rng_state),
+                    LocalGet(0),
+                    I64Const(0x2545_F491_4F6C_DD1D),
+                    I64Mul,
+                    // keep the top 53 bits, so that the result is exactly representable
+                    I64Const(11),
+                    I64ShrU,
+                    F64ConvertI64U,
+                    F64Const((f64::EPSILON / 2.0).into()), // 2^-53
+                    F64Mul,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Sets the seed of the pseudo-random number generator.
+///
+/// This is designed to be exported (as `"set_random_seed"`) and called by the host, so that
+/// runs can be reproduced.
+///
+/// Takes 1 parameter, an i32 seed.
+///
+/// Override with one u32, the (absolute) global index of the random state global
+pub struct SetRandomSeed;
+impl NamedRegistryItem<MaybeStaticFunction> for SetRandomSeed {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type SetRandomSeedOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, SetRandomSeedOverride> for SetRandomSeed {
+    fn r#override(rng_state: u32) -> MaybeStaticFunction {
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: Some("set_random_seed".into()),
+                params: Box::from([ValType::I32]),
+                returns: Box::from([]),
+                locals: Box::from([]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    I64ExtendI32U,
+                    I64Const(SEED_MIX),
+                    I64Xor,
+                    GlobalSet(rng_state),
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
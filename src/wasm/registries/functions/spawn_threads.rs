@@ -127,9 +127,10 @@ impl NamedRegistryItemOverride<MaybeStaticFunction, SpawnThreadInStackOverride>
 /// Spawn a new thread with the provided step function. This does not call it
 /// immediately, instead leaving that for the scheduler or calling function to do so.
 ///
-/// Takes 2 parameters:
+/// Takes 3 parameters:
 /// - step funcref - the step to spawn
 /// - ref null struct - the stack struct to spawn it with
+/// - i32 - the instance index of the sprite (or clone) that the thread belongs to
 ///
 /// Override with:
 /// - u32 - the index of the step func type
@@ -164,6 +165,7 @@ impl NamedRegistryItemOverride<MaybeStaticFunction, SpawnNewThreadOverride> for
                             ty: AbstractHeapType::Struct,
                         },
                     }),
+                    ValType::I32,
                 ]),
                 returns: Box::from([]),
                 locals: Box::from([]),
@@ -184,6 +186,7 @@ impl NamedRegistryItemOverride<MaybeStaticFunction, SpawnNewThreadOverride> for
                         array_size: 8,
                         array_type_index: stack_array_ty,
                     },
+                    LocalGet(2),
                     StructNew(thread_struct_ty),
                     I32Const(1),
                     TableGrow(threads_table_index),
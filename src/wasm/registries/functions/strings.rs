@@ -0,0 +1,398 @@
+use wasm_encoder::{BlockType as WasmBlockType, MemArg, ValType};
+use wasm_gen::wasm_const;
+
+use super::{MaybeStaticFunction, StaticFunction};
+use crate::prelude::*;
+use crate::wasm::registries::LinearStringLayout;
+
+/// Allocates a string in the persistent part of the strings memory, when strings are stored in
+/// linear memory, returning its handle. The contents of the string are left uninitialised.
+///
+/// Strings are allocated by bumping the string heap end global, which is kept aligned to
+/// [`LinearStringLayout::SLOT_SIZE`]; memory is grown as needed. Strings allocated here are never
+/// freed, so this is only used for strings which are stored (see [`PersistString`]) and for
+/// strings table slots.
+///
+/// Takes 1 parameter, the i32 length of the string in UTF-16 code units.
+///
+/// Override with one u32, the (absolute) global index of the string heap end global
+pub struct AllocString;
+impl NamedRegistryItem<MaybeStaticFunction> for AllocString {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type AllocStringOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, AllocStringOverride> for AllocString {
+    fn r#override(heap_end: u32) -> MaybeStaticFunction {
+        const MEMORY: u32 = LinearStringLayout::MEMORY;
+        const SLOT_MASK: i32 = LinearStringLayout::SLOT_SIZE as i32 - 1;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32]),
+                returns: Box::from([ValType::I64]),
+                locals: Box::from([ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    GlobalGet(heap_end),
+                    LocalTee(1),
+                    // 2 bytes per code unit, rounded up to the slot size
+                    LocalGet(0),
+                    I32Const(1),
+                    I32Shl,
+                    I32Const(SLOT_MASK),
+                    I32Add,
+                    I32Const(!SLOT_MASK),
+                    I32And,
+                    I32Add,
+                    GlobalSet(heap_end),
+                    GlobalGet(heap_end),
+                    MemorySize(MEMORY),
+                    I32Const(16),
+                    I32Shl,
+                    I32GtU,
+                    If(WasmBlockType::Empty),
+                    // grow by enough pages to fit the new string
+                    GlobalGet(heap_end),
+                    MemorySize(MEMORY),
+                    I32Const(16),
+                    I32Shl,
+                    I32Sub,
+                    I32Const(0xFFFF),
+                    I32Add,
+                    I32Const(16),
+                    I32ShrU,
+                    MemoryGrow(MEMORY),
+                    I32Const(-1),
+                    I32Eq,
+                    If(WasmBlockType::Empty),
+                    Unreachable,
+                    End,
+                    End,
+                    LocalGet(1),
+                    I64ExtendI32U,
+                    I64Const(32),
+                    I64Shl,
+                    LocalGet(0),
+                    I64ExtendI32U,
+                    I64Or,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Allocates a string in the scratch region of the strings memory, when strings are stored in
+/// linear memory (see [`LinearStringLayout`]), returning its handle. The contents of the string
+/// are left uninitialised.
+///
+/// The scratch region is reset at the start of every tick, so strings allocated here are only
+/// valid until then, unless they are copied out by [`PersistString`]. If the string doesn't fit
+/// in what is left of the scratch region, it is allocated with [`AllocString`] instead. This is
+/// exported (as `"string_alloc"`) so that hosts can return new strings.
+///
+/// Takes 1 parameter, the i32 length of the string in UTF-16 code units.
+///
+/// Override with:
+/// - u32, the (absolute) global index of the string scratch end global
+/// - u32, the address of the end of the scratch region
+/// - u32, the (absolute) function index of `AllocString`
+pub struct AllocScratchString;
+impl NamedRegistryItem<MaybeStaticFunction> for AllocScratchString {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type AllocScratchStringOverride = (u32, u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, AllocScratchStringOverride>
+    for AllocScratchString
+{
+    fn r#override(
+        (scratch_end, scratch_limit, alloc_string): AllocScratchStringOverride,
+    ) -> MaybeStaticFunction {
+        const SLOT_MASK: i32 = LinearStringLayout::SLOT_SIZE as i32 - 1;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: Some("string_alloc".into()),
+                params: Box::from([ValType::I32]),
+                returns: Box::from([ValType::I64]),
+                locals: Box::from([ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    GlobalGet(scratch_end),
+                    // 2 bytes per code unit, rounded up to the slot size
+                    LocalGet(0),
+                    I32Const(1),
+                    I32Shl,
+                    I32Const(SLOT_MASK),
+                    I32Add,
+                    I32Const(!SLOT_MASK),
+                    I32And,
+                    I32Add,
+                    LocalTee(1),
+                    I32Const(scratch_limit.cast_signed()),
+                    I32GtU,
+                    If(WasmBlockType::Empty),
+                    LocalGet(0),
+                    Call(alloc_string),
+                    Return,
+                    End,
+                    GlobalGet(scratch_end),
+                    I64ExtendI32U,
+                    I64Const(32),
+                    I64Shl,
+                    LocalGet(0),
+                    I64ExtendI32U,
+                    I64Or,
+                    LocalGet(1),
+                    GlobalSet(scratch_end),
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Copies a string out of the scratch region of the strings memory (see [`AllocScratchString`])
+/// into the persistent part, when strings are stored in linear memory, returning the handle of
+/// the copy. Strings which aren't in the scratch region are returned as they are.
+///
+/// This is used on strings which are about to be stored somewhere that outlives the current
+/// tick, e.g. in a variable, a list or a strings table slot. This is exported (as
+/// `"string_persist"`) so that hosts can set exported string globals, e.g. `sensing_answer`.
+///
+/// Takes 1 parameter, the i64 handle of the string.
+///
+/// Override with:
+/// - u32, the (absolute) function index of `AllocString`
+/// - u32, the address of the start of the scratch region
+/// - u32, the address of the end of the scratch region
+pub struct PersistString;
+impl NamedRegistryItem<MaybeStaticFunction> for PersistString {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type PersistStringOverride = (u32, u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, PersistStringOverride> for PersistString {
+    fn r#override(
+        (alloc_string, scratch_start, scratch_limit): PersistStringOverride,
+    ) -> MaybeStaticFunction {
+        const MEMORY: u32 = LinearStringLayout::MEMORY;
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: Some("string_persist".into()),
+                params: Box::from([ValType::I64]),
+                returns: Box::from([ValType::I64]),
+                locals: Box::from([ValType::I32, ValType::I64]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    I64Const(32),
+                    I64ShrU,
+                    I32WrapI64,
+                    LocalTee(1),
+                    I32Const(scratch_start.cast_signed()),
+                    I32GeU,
+                    LocalGet(1),
+                    I32Const(scratch_limit.cast_signed()),
+                    I32LtU,
+                    I32And,
+                    If(WasmBlockType::Result(ValType::I64)),
+                    // the lower 32 bits of the handle are the length
+                    LocalGet(0),
+                    I32WrapI64,
+                    Call(alloc_string),
+                    LocalTee(2),
+                    I64Const(32),
+                    I64ShrU,
+                    I32WrapI64,
+                    LocalGet(1),
+                    LocalGet(0),
+                    I32WrapI64,
+                    I32Const(1),
+                    I32Shl,
+                    MemoryCopy {
+                        src_mem: MEMORY,
+                        dst_mem: MEMORY,
+                    },
+                    LocalGet(2),
+                    Else,
+                    LocalGet(0),
+                    End,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Allocates new slots in the strings table, when strings are stored in linear memory (see
+/// [`LinearStringLayout`]), and stores a string handle in each of them. Returns the index of the
+/// first new slot; the new slots are consecutive. The string is copied out of the scratch region
+/// first, as slots outlive the current tick.
+///
+/// Takes 2 parameters:
+/// - i64 handle of the string
+/// - i32 number of slots to allocate
+///
+/// Override with:
+/// - u32, the (absolute) function index of `AllocString`
+/// - u32, the (absolute) function index of `PersistString`
+pub struct NewStringSlots;
+impl NamedRegistryItem<MaybeStaticFunction> for NewStringSlots {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type NewStringSlotsOverride = (u32, u32);
+impl NamedRegistryItemOverride<MaybeStaticFunction, NewStringSlotsOverride> for NewStringSlots {
+    fn r#override((alloc_string, persist_string): NewStringSlotsOverride) -> MaybeStaticFunction {
+        const SLOT_SIZE: i32 = LinearStringLayout::SLOT_SIZE as i32;
+        const SLOT_UNITS: i32 = SLOT_SIZE / 2;
+        let slot = MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: LinearStringLayout::MEMORY,
+        };
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I64, ValType::I32]),
+                returns: Box::from([ValType::I32]),
+                locals: Box::from([ValType::I32, ValType::I32]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    Call(persist_string),
+                    LocalSet(0),
+                    // slots are allocated in the same way as a string of the same size
+                    LocalGet(1),
+                    I32Const(SLOT_UNITS),
+                    I32Mul,
+                    Call(alloc_string),
+                    I64Const(32),
+                    I64ShrU,
+                    I32WrapI64,
+                    LocalTee(2),
+                    LocalGet(1),
+                    I32Const(SLOT_SIZE),
+                    I32Mul,
+                    I32Add,
+                    LocalSet(3),
+                    // the index of the first slot is returned
+                    LocalGet(2),
+                    I32Const(3),
+                    I32ShrU,
+                    Block(WasmBlockType::Empty),
+                    Loop(WasmBlockType::Empty),
+                    LocalGet(2),
+                    LocalGet(3),
+                    I32GeU,
+                    BrIf(1),
+                    LocalGet(2),
+                    LocalGet(0),
+                    I64Store(slot),
+                    LocalGet(2),
+                    I32Const(SLOT_SIZE),
+                    I32Add,
+                    LocalSet(2),
+                    Br(0),
+                    End,
+                    End,
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Stores a string handle in a slot of the strings table, when strings are stored in linear
+/// memory (see [`LinearStringLayout`]). The string is copied out of the scratch region first, as
+/// slots outlive the current tick.
+///
+/// Takes 2 parameters:
+/// - i32 index of the slot
+/// - i64 handle of the string
+///
+/// Override with one u32, the (absolute) function index of `PersistString`
+pub struct SetStringSlot;
+impl NamedRegistryItem<MaybeStaticFunction> for SetStringSlot {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || None,
+    };
+}
+pub type SetStringSlotOverride = u32;
+impl NamedRegistryItemOverride<MaybeStaticFunction, SetStringSlotOverride> for SetStringSlot {
+    fn r#override(persist_string: u32) -> MaybeStaticFunction {
+        MaybeStaticFunction {
+            static_function: Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32, ValType::I64]),
+                returns: Box::from([]),
+                locals: Box::from([]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    I32Const(3),
+                    I32Shl,
+                    LocalGet(1),
+                    Call(persist_string),
+                    I64Store(MemArg {
+                        offset: 0,
+                        align: 3,
+                        memory_index: LinearStringLayout::MEMORY,
+                    }),
+                    End,
+                ] as &[_]),
+            }),
+            maybe_populate: || None,
+        }
+    }
+}
+
+/// Copies a range of slots of the strings table into another range (which may overlap), when
+/// strings are stored in linear memory (see [`LinearStringLayout`]).
+///
+/// Takes 3 parameters:
+/// - i32 index of the first destination slot
+/// - i32 index of the first source slot
+/// - i32 number of slots to copy
+///
+/// Not overridable.
+pub struct CopyStringSlots;
+impl NamedRegistryItem<MaybeStaticFunction> for CopyStringSlots {
+    const VALUE: MaybeStaticFunction = MaybeStaticFunction {
+        static_function: None,
+        maybe_populate: || {
+            Some(StaticFunction {
+                export: None,
+                params: Box::from([ValType::I32, ValType::I32, ValType::I32]),
+                returns: Box::from([]),
+                locals: Box::from([]),
+                instructions: Box::from(wasm_const![
+                    LocalGet(0),
+                    I32Const(3),
+                    I32Shl,
+                    LocalGet(1),
+                    I32Const(3),
+                    I32Shl,
+                    LocalGet(2),
+                    I32Const(3),
+                    I32Shl,
+                    MemoryCopy {
+                        src_mem: LinearStringLayout::MEMORY,
+                        dst_mem: LinearStringLayout::MEMORY,
+                    },
+                    End,
+                ] as &[_]),
+            })
+        },
+    };
+}
@@ -2,6 +2,8 @@ use core::ops::Deref;
 
 use wasm_encoder::{ConstExpr, ExportKind, ExportSection, GlobalSection, GlobalType, ValType};
 
+use super::functions::SEED_MIX;
+use super::linear_string_val_type;
 use crate::prelude::*;
 use crate::registry::MapRegistry;
 
@@ -36,6 +38,7 @@ impl GlobalRegistry {
         imported_global_count: u32,
         _imported_function_count: u32,
         _static_function_count: u32,
+        linear_strings: bool,
     ) {
         for (key, (ty, suggested_initial, mutable, export)) in self.registry().take() {
             if *export {
@@ -47,7 +50,11 @@ impl GlobalRegistry {
             }
             globals.global(
                 GlobalType {
-                    val_type: ty,
+                    val_type: if linear_strings {
+                        linear_string_val_type(ty)
+                    } else {
+                        ty
+                    },
                     mutable: *mutable,
                     shared: false,
                 },
@@ -55,4 +62,184 @@ impl GlobalRegistry {
             );
         }
     }
+
+    fn register_internal_i32<N>(&self, name: &str) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            name.into(),
+            (
+                ValType::I32,
+                ConstExpr::i32_const(0),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
+
+    /// The number of seconds that the virtual clock has advanced by since the project started,
+    /// when the `clock` flag is `Virtual`. Unlike the project timer, this is never reset.
+    pub fn virtual_time<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            "virtual_time".into(),
+            (
+                ValType::F64,
+                ConstExpr::f64_const(0.0.into()),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
+
+    /// The project timer, in seconds. This is advanced by the host, or by the `tick` function
+    /// if the `clock` flag is `Virtual`.
+    pub fn sensing_timer<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            "sensing_timer".into(),
+            (
+                ValType::F64,
+                ConstExpr::f64_const(0.0.into()),
+                GlobalMutable(true),
+                GlobalExportable(true),
+            ),
+        )
+    }
+
+    /// The state of the in-module pseudo-random number generator, which is used instead of the
+    /// host's when the `rng` flag is `Seeded`. This starts off as if it had been seeded with 0.
+    pub fn rng_state<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            "rng_state".into(),
+            (
+                ValType::I64,
+                ConstExpr::i64_const(SEED_MIX),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
+
+    /// The instance index of the sprite (or clone) that the currently running thread belongs to.
+    ///
+    /// For original targets this is the IR target index; clone `n` of a target has instance
+    /// index `target_index + target_count * n`.
+    pub fn current_instance<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32("current_instance")
+    }
+
+    /// The offset in memory of the current instance's sprite block, relative to the block
+    /// of the original sprite. This is 0 for original sprites.
+    pub fn instance_mem_offset<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32("instance_mem_offset")
+    }
+
+    /// A counter which changes whenever the speech bubble of the target with the given IR target
+    /// index is updated, so that timed bubbles can tell whether they have been replaced.
+    pub fn bubble_id<N>(&self, target_index: u32) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32(&format!("bubble_id_{target_index}"))
+    }
+
+    /// The end of the region of linear memory used for list buffers when the `list_type` flag
+    /// is `LinearMemory`. New buffers are allocated here, growing the memory if necessary.
+    pub fn list_heap_end<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32("list_heap_end")
+    }
+
+    /// The first list buffer in the list of buffers which have been freed, or 0 if there are
+    /// none; each free buffer holds a pointer to the next one (see
+    /// [`list_header::LENGTH`](crate::wasm::mem_layout::list_header::LENGTH)).
+    pub fn list_free_list<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32("list_free_list")
+    }
+
+    /// The number of clones that currently exist, across all sprites.
+    pub fn clones_count<N>(&self) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register_internal_i32("clones_count")
+    }
+
+    /// The end of the part of the strings memory used by strings and strings table slots which
+    /// are allocated at runtime and never freed, when strings are stored in linear memory. This
+    /// starts off just after the scratch region (see [`LinearStringLayout`]).
+    ///
+    /// [`LinearStringLayout`]: super::LinearStringLayout
+    pub fn string_heap_end<N>(&self, heap_start: u32) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            "string_heap_end".into(),
+            (
+                ValType::I32,
+                ConstExpr::i32_const(
+                    heap_start
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("string heap start out of bounds"))?,
+                ),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
+
+    /// The end of the part of the scratch region of the strings memory which has been allocated
+    /// during the current tick, when strings are stored in linear memory. This is reset to the
+    /// start of the scratch region at the start of every tick.
+    pub fn string_scratch_end<N>(&self, scratch_start: u32) -> HQResult<N>
+    where
+        N: TryFrom<usize>,
+        <N as TryFrom<usize>>::Error: fmt::Debug,
+    {
+        self.register(
+            "string_scratch_end".into(),
+            (
+                ValType::I32,
+                ConstExpr::i32_const(
+                    scratch_start
+                        .try_into()
+                        .map_err(|_| make_hq_bug!("string scratch start out of bounds"))?,
+                ),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )
+    }
 }
@@ -1,16 +1,30 @@
+use mem_layout::list_header;
 use wasm_encoder::{
-    ConstExpr, DataSection, ElementSection, Elements, Function, HeapType, Instruction, RefType,
-    StorageType, ValType,
+    BlockType, ConstExpr, DataSection, ElementSection, Elements, Function, HeapType, Instruction,
+    MemArg, RefType, StorageType, ValType,
 };
+use wasm_gen::wasm;
 
 use super::super::WasmProject;
-use super::{GlobalExportable, GlobalMutable, GlobalRegistry, TypeRegistry};
+use super::{GlobalExportable, GlobalMutable, GlobalRegistry, TableRegistry, TypeRegistry};
 use crate::instructions::{BOXED_BOOL_PATTERN, BOXED_INT_PATTERN, BOXED_STRING_PATTERN};
 use crate::ir::{IrType, RcList};
 use crate::prelude::*;
 use crate::registry::MapRegistry;
 use crate::sb3::VarVal;
-use crate::wasm::registries::{StringRegistry, TabledStringRegistry};
+use crate::wasm::flags::ListType;
+use crate::wasm::registries::{
+    LinearStringLayout, StringRegistry, StringsTable, TabledStringRegistry,
+};
+use crate::wasm::{InternalInstruction, mem_layout};
+
+fn header_mem_arg(field: u32) -> MemArg {
+    MemArg {
+        offset: field.into(),
+        align: 2,
+        memory_index: 0,
+    }
+}
 
 #[derive(Clone)]
 pub struct ListRegistry(
@@ -158,12 +172,252 @@ impl ListRegistry {
         Ok((array_global, length_global))
     }
 
+    /// Whether the items of a list stored in linear memory are kept in the strings table rather
+    /// than in its buffer, which is the case for lists of strings (see
+    /// [`list_header::STRING_SLOTS`]).
+    #[must_use]
+    pub fn has_string_slots(list: &RcList) -> bool {
+        matches!(
+            WasmProject::ir_type_to_wasm(*list.possible_types()),
+            ValType::Ref(_)
+        )
+    }
+
+    /// The number of bytes used by each item of a list stored in linear memory. This is 0 for
+    /// lists of strings, whose items are kept in the strings table instead.
+    #[must_use]
+    pub fn item_size(list: &RcList) -> u32 {
+        match WasmProject::ir_type_to_wasm(*list.possible_types()) {
+            ValType::F64 | ValType::I64 => 8,
+            ValType::Ref(_) => 0,
+            _ => 4,
+        }
+    }
+
+    /// Registers a list whose items are stored in linear memory, returning the index of the
+    /// global holding a pointer to the list's buffer. The buffer starts with a header (see
+    /// [`list_header`]), followed by the items themselves.
+    ///
+    /// Buffers are allocated in the start function, and may be moved when they need to grow.
+    /// Buffers which are no longer used are added to a free list, from which new buffers are
+    /// taken where possible (see
+    /// [`AllocListBuffer`](super::functions::static_functions::AllocListBuffer)).
+    pub fn register_linear_memory(&self, list: &RcList) -> HQResult<u32> {
+        let pointer_global = self.globals().register(
+            format!("__rclist_ptr_{}", list.id()).into(),
+            (
+                ValType::I32,
+                ConstExpr::i32_const(0),
+                GlobalMutable(true),
+                GlobalExportable(false),
+            ),
+        )?;
+
+        self.registry()
+            .register::<usize>(list.clone(), pointer_global)?;
+
+        // strings in boxed lists are stored as indices into the strings table, so they need to
+        // be registered before the tabled strings registry is finished; the initial items of
+        // lists of strings are copied into their slots from the strings themselves.
+        list.initial_value()
+            .iter()
+            .try_for_each::<_, HQResult<()>>(|val| {
+                if let VarVal::String(s) = val {
+                    if Self::has_string_slots(list) {
+                        self.strings().register_default::<usize>(s.clone())?;
+                    } else {
+                        self.tabled_strings().register_default::<usize>(s.clone())?;
+                    }
+                }
+                Ok(())
+            })?;
+
+        Ok(pointer_global)
+    }
+
+    /// Instructions to copy the items of a list of strings stored in linear memory from the
+    /// string slots of one buffer into those of another, allocating the new buffer's slots first
+    /// if it doesn't have any yet. This is needed after copying the buffer with
+    /// [`CopyListBuffer`](super::functions::static_functions::CopyListBuffer), which only
+    /// copies items stored in the buffer itself; nothing is needed for other lists.
+    ///
+    /// `old` and `new` should each push a pointer to the old or new buffer respectively.
+    pub fn copy_string_items(
+        list: &RcList,
+        tables: &TableRegistry,
+        old: &[InternalInstruction],
+        new: &[InternalInstruction],
+    ) -> HQResult<Vec<InternalInstruction>> {
+        if !Self::has_string_slots(list) {
+            return Ok(vec![]);
+        }
+        let strings_table = tables.register::<StringsTable, _>()?;
+        let string_slots = header_mem_arg(list_header::STRING_SLOTS);
+        Ok(new
+            .iter()
+            .cloned()
+            .chain(wasm![
+                I32Load(string_slots),
+                I32Const(-1),
+                I32Eq,
+                If(BlockType::Empty),
+            ])
+            .chain(new.iter().cloned())
+            .chain(wasm![RefNull(HeapType::EXTERN)])
+            .chain(new.iter().cloned())
+            .chain(wasm![
+                I32Load(header_mem_arg(list_header::CAPACITY)),
+                TableGrow(strings_table),
+                I32Store(string_slots),
+                End,
+            ])
+            .chain(new.iter().cloned())
+            .chain(wasm![I32Load(string_slots)])
+            .chain(old.iter().cloned())
+            .chain(wasm![I32Load(string_slots)])
+            .chain(old.iter().cloned())
+            .chain(wasm![
+                I32Load(header_mem_arg(list_header::LENGTH)),
+                TableCopy {
+                    src_table: strings_table,
+                    dst_table: strings_table,
+                },
+            ])
+            .collect())
+    }
+
+    /// The initial items of a list, as they are laid out in memory. Strings in boxed lists are
+    /// represented by their index in the strings table. This shouldn't be used for lists of
+    /// strings, whose items aren't laid out in memory.
+    fn initial_bytes(&self, list: &RcList) -> HQResult<Box<[u8]>> {
+        Ok(match list.possible_types().base_type() {
+            Some(IrType::Float) => list
+                .initial_value()
+                .iter()
+                .map(|val| {
+                    let VarVal::Float(f) = val else {
+                        hq_bug!("VarVal type should be included in var's possible types");
+                    };
+                    Ok((*f).to_le_bytes())
+                })
+                .collect::<HQResult<Box<[_]>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+            Some(IrType::String) => {
+                hq_bug!("the items of lists of strings aren't laid out in memory")
+            }
+            Some(IrType::Int | IrType::Boolean) => list
+                .initial_value()
+                .iter()
+                .map(|val| {
+                    Ok(match val {
+                        VarVal::Int(i) => *i,
+                        VarVal::Bool(b) => (*b).into(),
+                        VarVal::String(_) | VarVal::Float(_) => {
+                            hq_bug!("VarVal type should be included in var's possible types")
+                        }
+                    }
+                    .to_le_bytes())
+                })
+                .collect::<HQResult<Box<[_]>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+            _ => list
+                .initial_value()
+                .iter()
+                .map(|val| {
+                    Ok(match val {
+                        VarVal::Int(i) => i64::from(*i) | BOXED_INT_PATTERN,
+                        VarVal::Bool(b) => i64::from(*b) | BOXED_BOOL_PATTERN,
+                        VarVal::Float(f) => i64::from_le_bytes(f.to_le_bytes()),
+                        VarVal::String(s) => {
+                            self.tabled_strings().register_default::<i64>(s.clone())?
+                                | BOXED_STRING_PATTERN
+                        }
+                    }
+                    .to_le_bytes())
+                })
+                .collect::<HQResult<Box<[_]>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+        })
+    }
+
+    /// The handles of the initial items of a list of strings, as they are laid out in memory,
+    /// when strings are stored in linear memory.
+    fn initial_string_handles(list: &RcList, layout: &LinearStringLayout) -> HQResult<Box<[u8]>> {
+        Ok(list
+            .initial_value()
+            .iter()
+            .map(|val| {
+                let VarVal::String(s) = val else {
+                    hq_bug!("VarVal type should be included in var's possible types")
+                };
+                Ok(layout.handle(s)?.to_le_bytes())
+            })
+            .collect::<HQResult<Box<[_]>>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// The initial items of a list of strings, as the globals of those strings.
+    fn initial_string_globals(&self, list: &RcList) -> HQResult<Box<[ConstExpr]>> {
+        list.initial_value()
+            .iter()
+            .map(|val| {
+                let VarVal::String(s) = val else {
+                    hq_bug!("VarVal type should be included in var's possible types")
+                };
+                let string_idx = self.strings().register_default(s.clone())?;
+                Ok(ConstExpr::global_get(string_idx))
+            })
+            .collect()
+    }
+
+    #[expect(clippy::too_many_arguments, reason = "too many arguments!")]
     pub fn finish(
         self,
         data_section: &mut DataSection,
         elem_section: &mut ElementSection,
         start_func: &mut Function,
         imported_global_count: u32,
+        list_type: ListType,
+        strings_table: Option<u32>,
+        linear_strings: Option<&LinearStringLayout>,
+    ) -> HQResult<()> {
+        match list_type {
+            ListType::GCArray => self.finish_gc_arrays(
+                data_section,
+                elem_section,
+                start_func,
+                imported_global_count,
+                linear_strings,
+            ),
+            ListType::LinearMemory => self.finish_linear_memory(
+                data_section,
+                elem_section,
+                start_func,
+                imported_global_count,
+                strings_table,
+                linear_strings,
+            ),
+        }
+    }
+
+    /// Initialises GC array lists from passive segments. Lists of strings are initialised from
+    /// element segments of string globals or, if strings are stored in linear memory, from data
+    /// segments of string handles.
+    fn finish_gc_arrays(
+        &self,
+        data_section: &mut DataSection,
+        elem_section: &mut ElementSection,
+        start_func: &mut Function,
+        imported_global_count: u32,
+        linear_strings: Option<&LinearStringLayout>,
     ) -> HQResult<()> {
         for (list, &array_global) in self.registry().registry().borrow().iter() {
             start_func
@@ -181,135 +435,216 @@ impl ListRegistry {
 
             let array_type_index = self.array_type(list)?;
 
-            match list.possible_types().base_type() {
-                Some(IrType::Float) => {
-                    let floats_bytes = list
-                        .initial_value()
-                        .iter()
-                        .map(|val| {
-                            let VarVal::Float(f) = val else {
-                                hq_bug!("VarVal type should be included in var's possible types");
-                            };
-                            Ok((*f).to_le_bytes())
-                        })
-                        .collect::<HQResult<Box<[_]>>>()?
-                        .into_iter()
-                        .flatten()
-                        .collect::<Box<[_]>>();
+            if list.possible_types().base_type() == Some(IrType::String)
+                && let Some(layout) = linear_strings
+            {
+                data_section.passive(Self::initial_string_handles(list, layout)?);
+
+                start_func.instruction(&Instruction::ArrayInitData {
+                    array_type_index,
+                    array_data_index: data_section.len() - 1,
+                });
+            } else if list.possible_types().base_type() == Some(IrType::String) {
+                let strings = self.initial_string_globals(list)?;
+
+                elem_section.passive(Elements::Expressions(
+                    RefType::EXTERNREF,
+                    Cow::Borrowed(&strings),
+                ));
 
-                    data_section.passive(floats_bytes);
+                start_func.instruction(&Instruction::ArrayInitElem {
+                    array_type_index,
+                    array_elem_index: elem_section.len() - 1,
+                });
+            } else {
+                // todo: use packed i8 type for booleans?
+                data_section.passive(self.initial_bytes(list)?);
+
+                start_func.instruction(&Instruction::ArrayInitData {
+                    array_type_index,
+                    array_data_index: data_section.len() - 1,
+                });
+            }
+        }
 
-                    start_func.instruction(&Instruction::ArrayInitData {
-                        array_type_index,
-                        array_data_index: data_section.len() - 1,
-                    });
-                }
-                Some(IrType::Int) => {
-                    let ints_bytes = list
-                        .initial_value()
-                        .iter()
-                        .map(|val| {
-                            Ok(match val {
-                                VarVal::Int(i) => *i,
-                                VarVal::Bool(b) => (*b).into(),
-                                VarVal::String(_) | VarVal::Float(_) => {
-                                    hq_bug!(
-                                        "VarVal type should be included in var's possible types"
-                                    )
-                                }
-                            }
-                            .to_le_bytes())
-                        })
-                        .collect::<HQResult<Box<[_]>>>()?
-                        .into_iter()
-                        .flatten()
-                        .collect::<Box<[_]>>();
+        Ok(())
+    }
 
-                    data_section.passive(ints_bytes);
+    /// Allocates the buffers of lists stored in linear memory, one after another at the end of
+    /// the initial memory, and copies their initial values into them. The initial items of lists
+    /// of strings are copied into newly allocated string slots instead, from element segments of
+    /// string globals or, if strings are stored in linear memory, from data segments of string
+    /// handles.
+    fn finish_linear_memory(
+        &self,
+        data_section: &mut DataSection,
+        elem_section: &mut ElementSection,
+        start_func: &mut Function,
+        imported_global_count: u32,
+        strings_table: Option<u32>,
+        linear_strings: Option<&LinearStringLayout>,
+    ) -> HQResult<()> {
+        const PAGE_SIZE: u32 = 1 << 16;
+        const MIN_CAPACITY: u32 = 8;
+
+        /// Where the initial items of a list are copied from.
+        enum InitialItems {
+            /// a data segment of the items themselves
+            Bytes(u32),
+            /// a data segment of string handles, copied into the list's string slots
+            Handles(u32),
+            /// an element segment of strings, copied into the list's string slots
+            Strings(u32),
+        }
 
-                    start_func.instruction(&Instruction::ArrayInitData {
-                        array_type_index,
-                        array_data_index: data_section.len() - 1,
-                    });
-                }
-                Some(IrType::Boolean) => {
-                    // todo: use packed i8 type?
-
-                    let bools_bytes = list
-                        .initial_value()
-                        .iter()
-                        .map(|val| {
-                            let VarVal::Bool(b) = val else {
-                                hq_bug!("VarVal type should be included in var's possible types")
-                            };
-                            Ok(i32::from(*b).to_le_bytes())
-                        })
-                        .collect::<HQResult<Box<[_]>>>()?
-                        .into_iter()
-                        .flatten()
-                        .collect::<Box<[_]>>();
+        if self.registry().registry().try_borrow()?.is_empty() {
+            return Ok(());
+        }
 
-                    data_section.passive(bools_bytes);
+        let heap_end = self.globals().list_heap_end::<u32>()? + imported_global_count;
+
+        let mut buffers = vec![];
+        let mut total_size = 0u32;
+        for (list, &pointer_global) in self.registry().registry().try_borrow()?.iter() {
+            let length: u32 = list
+                .initial_value()
+                .len()
+                .try_into()
+                .map_err(|_| make_hq_bug!("list initial value length out of bounds"))?;
+            let capacity = if *list.length_mutable().borrow() {
+                length.max(MIN_CAPACITY)
+            } else {
+                length
+            };
+            let initial_items = if !Self::has_string_slots(list) {
+                data_section.passive(self.initial_bytes(list)?);
+                InitialItems::Bytes(data_section.len() - 1)
+            } else if let Some(layout) = linear_strings {
+                data_section.passive(Self::initial_string_handles(list, layout)?);
+                InitialItems::Handles(data_section.len() - 1)
+            } else {
+                let strings = self.initial_string_globals(list)?;
+                elem_section.passive(Elements::Expressions(
+                    RefType::EXTERNREF,
+                    Cow::Borrowed(&strings),
+                ));
+                InitialItems::Strings(elem_section.len() - 1)
+            };
+            buffers.push((
+                pointer_global + imported_global_count,
+                total_size,
+                length,
+                capacity,
+                Self::item_size(list),
+                initial_items,
+            ));
+            // keep buffers 8-byte aligned, as they are when allocated at runtime
+            total_size +=
+                (list_header::BLOCK_SIZE + capacity * Self::item_size(list)).next_multiple_of(8);
+        }
 
-                    start_func.instruction(&Instruction::ArrayInitData {
-                        array_type_index,
-                        array_data_index: data_section.len() - 1,
-                    });
-                }
-                Some(IrType::String) => {
-                    let strings = list
-                        .initial_value()
-                        .iter()
-                        .map(|val| {
-                            let VarVal::String(s) = val else {
-                                hq_bug!("VarVal type should be included in var's possible types")
-                            };
-                            let string_idx = self.strings().register_default(s.clone())?;
-                            Ok(ConstExpr::global_get(string_idx))
-                        })
-                        .collect::<HQResult<Box<[_]>>>()?;
+        let as_i32 = |n: u32| -> HQResult<i32> {
+            n.try_into()
+                .map_err(|_| make_hq_bug!("list buffer size out of bounds"))
+        };
 
-                    elem_section.passive(Elements::Expressions(
-                        RefType::EXTERNREF,
-                        Cow::Borrowed(&strings),
-                    ));
+        start_func
+            .instruction(&Instruction::MemorySize(0))
+            .instruction(&Instruction::I32Const(16))
+            .instruction(&Instruction::I32Shl)
+            .instruction(&Instruction::GlobalSet(heap_end))
+            .instruction(&Instruction::I32Const(as_i32(total_size.div_ceil(PAGE_SIZE))?))
+            .instruction(&Instruction::MemoryGrow(0))
+            .instruction(&Instruction::Drop);
 
-                    start_func.instruction(&Instruction::ArrayInitElem {
-                        array_type_index,
-                        array_elem_index: elem_section.len() - 1,
-                    });
+        for (pointer_global, offset, length, capacity, item_size, initial_items) in buffers {
+            start_func
+                .instruction(&Instruction::GlobalGet(heap_end))
+                .instruction(&Instruction::I32Const(as_i32(offset)?))
+                .instruction(&Instruction::I32Add)
+                .instruction(&Instruction::GlobalSet(pointer_global));
+            for (field, value) in [
+                (list_header::LENGTH, as_i32(length)?),
+                (list_header::CAPACITY, as_i32(capacity)?),
+                (list_header::ITEM_SIZE, as_i32(item_size)?),
+                (list_header::STRING_SLOTS, -1),
+            ] {
+                start_func
+                    .instruction(&Instruction::GlobalGet(pointer_global))
+                    .instruction(&Instruction::I32Const(value))
+                    .instruction(&Instruction::I32Store(header_mem_arg(field)));
+            }
+            match initial_items {
+                InitialItems::Bytes(data_index) => {
+                    start_func
+                        .instruction(&Instruction::GlobalGet(pointer_global))
+                        .instruction(&Instruction::I32Const(as_i32(list_header::BLOCK_SIZE)?))
+                        .instruction(&Instruction::I32Add)
+                        .instruction(&Instruction::I32Const(0))
+                        .instruction(&Instruction::I32Const(as_i32(length * item_size)?))
+                        .instruction(&Instruction::MemoryInit { mem: 0, data_index })
+                        .instruction(&Instruction::DataDrop(data_index));
                 }
-                _ => {
-                    let boxed_bytes = list
-                        .initial_value()
-                        .iter()
-                        .map(|val| {
-                            Ok(match val {
-                                VarVal::Int(i) => i64::from(*i) | BOXED_INT_PATTERN,
-                                VarVal::Bool(b) => i64::from(*b) | BOXED_BOOL_PATTERN,
-                                VarVal::Float(f) => i64::from_le_bytes(f.to_le_bytes()),
-                                VarVal::String(s) => {
-                                    self.tabled_strings().register_default::<i64>(s.clone())?
-                                        | BOXED_STRING_PATTERN
-                                }
-                            }
-                            .to_le_bytes())
+                InitialItems::Handles(data_index) => {
+                    let Some(layout) = linear_strings else {
+                        hq_bug!("string handles without strings in linear memory")
+                    };
+                    start_func
+                        .instruction(&Instruction::GlobalGet(pointer_global))
+                        .instruction(&Instruction::I64Const(0))
+                        .instruction(&Instruction::I32Const(as_i32(capacity)?))
+                        .instruction(&Instruction::Call(layout.new_string_slots()))
+                        .instruction(&Instruction::I32Store(header_mem_arg(
+                            list_header::STRING_SLOTS,
+                        )))
+                        .instruction(&Instruction::GlobalGet(pointer_global))
+                        .instruction(&Instruction::I32Load(header_mem_arg(
+                            list_header::STRING_SLOTS,
+                        )))
+                        .instruction(&Instruction::I32Const(as_i32(
+                            LinearStringLayout::SLOT_SIZE,
+                        )?))
+                        .instruction(&Instruction::I32Mul)
+                        .instruction(&Instruction::I32Const(0))
+                        .instruction(&Instruction::I32Const(as_i32(
+                            length * LinearStringLayout::SLOT_SIZE,
+                        )?))
+                        .instruction(&Instruction::MemoryInit {
+                            mem: LinearStringLayout::MEMORY,
+                            data_index,
                         })
-                        .collect::<HQResult<Box<[_]>>>()?
-                        .into_iter()
-                        .flatten()
-                        .collect::<Box<[_]>>();
-
-                    data_section.passive(boxed_bytes);
-
-                    start_func.instruction(&Instruction::ArrayInitData {
-                        array_type_index,
-                        array_data_index: data_section.len() - 1,
-                    });
+                        .instruction(&Instruction::DataDrop(data_index));
+                }
+                InitialItems::Strings(elem_index) => {
+                    let Some(table) = strings_table else {
+                        hq_bug!("lists of strings need the strings table")
+                    };
+                    start_func
+                        .instruction(&Instruction::GlobalGet(pointer_global))
+                        .instruction(&Instruction::RefNull(HeapType::EXTERN))
+                        .instruction(&Instruction::I32Const(as_i32(capacity)?))
+                        .instruction(&Instruction::TableGrow(table))
+                        .instruction(&Instruction::I32Store(header_mem_arg(
+                            list_header::STRING_SLOTS,
+                        )))
+                        .instruction(&Instruction::GlobalGet(pointer_global))
+                        .instruction(&Instruction::I32Load(header_mem_arg(
+                            list_header::STRING_SLOTS,
+                        )))
+                        .instruction(&Instruction::I32Const(0))
+                        .instruction(&Instruction::I32Const(as_i32(length)?))
+                        .instruction(&Instruction::TableInit { elem_index, table })
+                        .instruction(&Instruction::ElemDrop(elem_index));
                 }
             }
         }
 
+        start_func
+            .instruction(&Instruction::GlobalGet(heap_end))
+            .instruction(&Instruction::I32Const(as_i32(total_size)?))
+            .instruction(&Instruction::I32Add)
+            .instruction(&Instruction::GlobalSet(heap_end));
+
         Ok(())
     }
 }
@@ -1,4 +1,7 @@
-use wasm_encoder::{EntityType, Function, GlobalType, ImportSection, Instruction, ValType};
+use wasm_encoder::{
+    AbstractHeapType, BlockType, ConstExpr, EntityType, FieldType, Function, GlobalSection,
+    GlobalType, HeapType, ImportSection, Instruction, MemArg, RefType, StorageType, ValType,
+};
 
 use crate::prelude::*;
 use crate::registry::SetRegistry;
@@ -19,7 +22,28 @@ impl StringRegistry {
         self.0.registry()
     }
 
-    pub fn finish(self, imports: &mut ImportSection) {
+    /// Imports each string as an immutable `externref` global from the `""` module or, if
+    /// strings are stored in linear memory, defines each one as an immutable i64 global
+    /// containing its handle. Either way, the `n`th string is global `n`.
+    pub fn finish(
+        self,
+        imports: &mut ImportSection,
+        globals: &mut GlobalSection,
+        linear_strings: Option<&LinearStringLayout>,
+    ) -> HQResult<()> {
+        if let Some(layout) = linear_strings {
+            for string in self.0.registry().take().keys() {
+                globals.global(
+                    GlobalType {
+                        val_type: ValType::I64,
+                        mutable: false,
+                        shared: false,
+                    },
+                    &ConstExpr::i64_const(layout.handle(string)?),
+                );
+            }
+            return Ok(());
+        }
         for string in self.0.registry().take().keys() {
             imports.import(
                 "",
@@ -31,6 +55,7 @@ impl StringRegistry {
                 }),
             );
         }
+        Ok(())
     }
 }
 
@@ -50,20 +75,310 @@ impl TabledStringRegistry {
         self.0.registry()
     }
 
+    /// Registers each tabled string in the strings registry, and adds it to the strings table
+    /// in the start function. If `start` is `None` (because strings are stored in linear
+    /// memory), the strings are only registered, as their slots are laid out in memory by the
+    /// [`LinearStringLayout`] instead.
     pub fn finish(
         self,
         strings: &StringRegistry,
-        strings_table: u32,
-        start_func: &mut Function,
+        mut start: Option<(u32, &mut Function)>,
     ) -> HQResult<()> {
         for string in self.registry().take().keys() {
             let string_idx = strings.register_default(string.clone())?;
 
-            start_func
-                .instruction(&Instruction::GlobalGet(string_idx))
-                .instruction(&Instruction::I32Const(1))
-                .instruction(&Instruction::TableGrow(strings_table))
-                .instruction(&Instruction::Drop);
+            if let Some((strings_table, start_func)) = start.as_mut() {
+                start_func
+                    .instruction(&Instruction::GlobalGet(string_idx))
+                    .instruction(&Instruction::I32Const(1))
+                    .instruction(&Instruction::TableGrow(*strings_table))
+                    .instruction(&Instruction::Drop);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maps a type as it is used during code generation, where strings are `externref`s, to the
+/// type that is used when strings are stored in linear memory, where they are i64 handles (see
+/// [`ExternalEnvironment::Headless`](crate::wasm::ExternalEnvironment::Headless)).
+#[must_use]
+pub const fn linear_string_val_type(val_type: ValType) -> ValType {
+    match val_type {
+        ValType::Ref(RefType {
+            heap_type:
+                HeapType::Abstract {
+                    ty: AbstractHeapType::Extern | AbstractHeapType::NoExtern,
+                    ..
+                },
+            ..
+        }) => ValType::I64,
+        ValType::I32
+        | ValType::I64
+        | ValType::F32
+        | ValType::F64
+        | ValType::V128
+        | ValType::Ref(_) => val_type,
+    }
+}
+
+/// [`linear_string_val_type`], for the fields of GC types.
+#[must_use]
+pub const fn linear_string_field_type(field_type: FieldType) -> FieldType {
+    FieldType {
+        element_type: match field_type.element_type {
+            StorageType::Val(val_type) => StorageType::Val(linear_string_val_type(val_type)),
+            StorageType::I8 | StorageType::I16 => field_type.element_type,
+        },
+        mutable: field_type.mutable,
+    }
+}
+
+/// The layout of the memory that strings are stored in when they are stored in linear memory
+/// (see [`ExternalEnvironment::Headless`](crate::wasm::ExternalEnvironment::Headless)). This
+/// also rewrites instructions which treat strings as `externref`s so that they use this
+/// memory instead.
+///
+/// The memory starts with a slot for each tabled string, which holds the string's handle; the
+/// index of a slot in the strings table is its address divided by [`Self::SLOT_SIZE`]. These
+/// are followed by the handles of the costume names in each costume names table, then the
+/// contents of each string constant. Strings and slots which are created at runtime are
+/// allocated after that, starting at [`Self::heap_start`]:
+///
+/// - strings returned by the host are allocated in a scratch region of [`Self::SCRATCH_SIZE`]
+///   bytes, which is reused every tick.
+/// - strings which are stored somewhere that outlives the tick (a variable, a list, a
+///   procedure argument or a strings table slot) are copied out of the scratch region into the
+///   persistent heap after it, which starts at [`Self::persistent_heap_start`]. Slots are also
+///   allocated there. Nothing in the persistent heap is ever freed.
+pub struct LinearStringLayout {
+    /// the handle of each string in the strings registry, in the same order
+    handles: IndexMap<Box<str>, i64>,
+    /// the initial contents of the memory, starting at address 0
+    data: Box<[u8]>,
+    /// maps the index of each costume names table to the address of its first handle
+    costume_names: BTreeMap<u32, u32>,
+    /// the index of the strings table, if anything uses it
+    strings_table: Option<u32>,
+    /// the absolute indices of the `NewStringSlots`, `SetStringSlot` and `CopyStringSlots` static
+    /// functions
+    slot_functions: (u32, u32, u32),
+}
+
+impl LinearStringLayout {
+    /// The index of the memory that strings are stored in, which is exported as `strings`.
+    pub const MEMORY: u32 = 1;
+
+    /// The size of each slot in the strings table, in bytes.
+    pub const SLOT_SIZE: u32 = 8;
+
+    /// The size of the scratch region which temporary strings are allocated in, in bytes.
+    pub const SCRATCH_SIZE: u32 = 1 << 20;
+
+    pub fn new(
+        strings: &StringRegistry,
+        tabled_strings: &[Box<str>],
+        strings_table: Option<u32>,
+        costume_name_tables: &[(u32, &[Box<str>])],
+    ) -> HQResult<Self> {
+        let slot_count = tabled_strings.len()
+            + costume_name_tables
+                .iter()
+                .map(|(_, names)| names.len())
+                .sum::<usize>();
+        let contents_start = slot_count * Self::SLOT_SIZE as usize;
+
+        let mut handles = IndexMap::default();
+        let mut contents = vec![];
+        for string in strings.registry().try_borrow()?.keys() {
+            let code_units = string.encode_utf16().collect::<Box<[_]>>();
+            handles.insert(
+                string.clone(),
+                Self::make_handle(contents_start + contents.len(), code_units.len())?,
+            );
+            contents.extend(code_units.iter().flat_map(|unit| unit.to_le_bytes()));
+        }
+
+        let mut layout = Self {
+            handles,
+            data: Box::from([]),
+            costume_names: BTreeMap::new(),
+            strings_table,
+            slot_functions: (0, 0, 0),
+        };
+
+        let mut data = Vec::with_capacity(contents_start + contents.len());
+        for string in tabled_strings {
+            data.extend(layout.handle(string)?.to_le_bytes());
+        }
+        for (table_index, names) in costume_name_tables {
+            layout.costume_names.insert(
+                *table_index,
+                data.len()
+                    .try_into()
+                    .map_err(|_| make_hq_bug!("costume names address out of bounds"))?,
+            );
+            for name in *names {
+                data.extend(layout.handle(name)?.to_le_bytes());
+            }
+        }
+        data.extend(contents);
+        // keep slots which are allocated at runtime aligned
+        data.resize(data.len().next_multiple_of(Self::SLOT_SIZE as usize), 0);
+        layout.data = data.into_boxed_slice();
+
+        Ok(layout)
+    }
+
+    /// Sets the absolute indices of the `NewStringSlots`, `SetStringSlot` and `CopyStringSlots`
+    /// static functions, which are used in place of the strings table instructions that can't be
+    /// rewritten in place.
+    #[must_use]
+    pub const fn with_slot_functions(mut self, slot_functions: (u32, u32, u32)) -> Self {
+        self.slot_functions = slot_functions;
+        self
+    }
+
+    /// The absolute index of the `NewStringSlots` static function.
+    #[must_use]
+    pub const fn new_string_slots(&self) -> u32 {
+        self.slot_functions.0
+    }
+
+    /// Packs an address and a length (in UTF-16 code units) into a string handle.
+    fn make_handle(address: usize, length: usize) -> HQResult<i64> {
+        let address =
+            u32::try_from(address).map_err(|_| make_hq_bug!("string address out of bounds"))?;
+        let length =
+            u32::try_from(length).map_err(|_| make_hq_bug!("string length out of bounds"))?;
+        Ok(((u64::from(address) << 32) | u64::from(length)).cast_signed())
+    }
+
+    /// The handle of a string in the strings registry.
+    pub fn handle(&self, string: &str) -> HQResult<i64> {
+        self.handles
+            .get(string)
+            .copied()
+            .ok_or_else(|| make_hq_bug!("couldn't find string in linear string layout"))
+    }
+
+    /// The initial contents of the memory, starting at address 0.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The address at which strings allocated at runtime start, which is the start of the
+    /// scratch region.
+    pub fn heap_start(&self) -> HQResult<u32> {
+        self.data
+            .len()
+            .try_into()
+            .map_err(|_| make_hq_bug!("string memory size out of bounds"))
+    }
+
+    /// The address at which the persistent heap starts, just after the scratch region.
+    pub fn persistent_heap_start(&self) -> HQResult<u32> {
+        self.heap_start()?
+            .checked_add(Self::SCRATCH_SIZE)
+            .ok_or_else(|| make_hq_bug!("string memory size out of bounds"))
+    }
+
+    /// Adds `instruction` to `func`, rewriting it if it treats strings as `externref`s:
+    ///
+    /// - types are mapped with [`linear_string_val_type`]
+    /// - null strings become the empty string
+    /// - the strings table and costume names tables are read from memory
+    /// - the strings table is grown, set and copied within using the `NewStringSlots`,
+    ///   `SetStringSlot` and `CopyStringSlots` static functions
+    ///
+    /// Any other operation on the strings table or a costume names table is a bug. Null checks
+    /// are left alone, as strings are never null-checked.
+    pub fn instruction(&self, func: &mut Function, instruction: &Instruction<'_>) -> HQResult<()> {
+        let slot = |offset: u32| {
+            Instruction::I64Load(MemArg {
+                offset: offset.into(),
+                align: 3,
+                memory_index: Self::MEMORY,
+            })
+        };
+        let block_type = |block_type: BlockType| match block_type {
+            BlockType::Result(val_type) => BlockType::Result(linear_string_val_type(val_type)),
+            BlockType::Empty | BlockType::FunctionType(_) => block_type,
+        };
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "too many variants to match explicitly"
+        )]
+        match instruction {
+            Instruction::TypedSelect(val_type) => {
+                func.instruction(&Instruction::TypedSelect(linear_string_val_type(*val_type)));
+            }
+            Instruction::Block(ty) => {
+                func.instruction(&Instruction::Block(block_type(*ty)));
+            }
+            Instruction::Loop(ty) => {
+                func.instruction(&Instruction::Loop(block_type(*ty)));
+            }
+            Instruction::If(ty) => {
+                func.instruction(&Instruction::If(block_type(*ty)));
+            }
+            Instruction::RefNull(HeapType::Abstract {
+                ty: AbstractHeapType::Extern | AbstractHeapType::NoExtern,
+                ..
+            }) => {
+                // the empty string
+                func.instruction(&Instruction::I64Const(0));
+            }
+            Instruction::TableGet(table) if Some(*table) == self.strings_table => {
+                func.instruction(&Instruction::I32Const(3))
+                    .instruction(&Instruction::I32Shl)
+                    .instruction(&slot(0));
+            }
+            Instruction::TableGet(table) if self.costume_names.contains_key(table) => {
+                let address = self
+                    .costume_names
+                    .get(table)
+                    .copied()
+                    .ok_or_else(|| make_hq_bug!("costume names table disappeared"))?;
+                func.instruction(&Instruction::I32Const(3))
+                    .instruction(&Instruction::I32Shl)
+                    .instruction(&slot(address));
+            }
+            Instruction::TableGrow(table) if Some(*table) == self.strings_table => {
+                func.instruction(&Instruction::Call(self.slot_functions.0));
+            }
+            Instruction::TableSet(table) if Some(*table) == self.strings_table => {
+                func.instruction(&Instruction::Call(self.slot_functions.1));
+            }
+            Instruction::TableCopy {
+                src_table,
+                dst_table,
+            } if Some(*src_table) == self.strings_table && src_table == dst_table => {
+                func.instruction(&Instruction::Call(self.slot_functions.2));
+            }
+            Instruction::TableCopy {
+                src_table: table, ..
+            }
+            | Instruction::TableCopy {
+                dst_table: table, ..
+            }
+            | Instruction::TableInit { table, .. }
+                if Some(*table) == self.strings_table || self.costume_names.contains_key(table) =>
+            {
+                hq_bug!("unsupported string table operation for strings in linear memory")
+            }
+            Instruction::TableSet(table)
+            | Instruction::TableGrow(table)
+            | Instruction::TableSize(table)
+            | Instruction::TableFill(table)
+                if Some(*table) == self.strings_table || self.costume_names.contains_key(table) =>
+            {
+                hq_bug!("unsupported string table operation for strings in linear memory")
+            }
+            _ => {
+                func.instruction(instruction);
+            }
         }
         Ok(())
     }
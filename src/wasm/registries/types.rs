@@ -2,6 +2,7 @@ use wasm_encoder::{
     AbstractHeapType, FieldType, HeapType, RefType, StorageType, TypeSection, ValType,
 };
 
+use super::{linear_string_field_type, linear_string_val_type};
 use crate::ir::RcVar;
 use crate::prelude::*;
 use crate::registry::SetRegistry;
@@ -92,6 +93,11 @@ impl TypeRegistry {
                 })),
                 mutable: true,
             },
+            // the instance index of the sprite (or clone) that this thread belongs to
+            FieldType {
+                element_type: StorageType::Val(ValType::I32),
+                mutable: false,
+            },
         ])
     }
 
@@ -114,12 +120,38 @@ impl TypeRegistry {
         )
     }
 
-    pub fn finish(self, types: &mut TypeSection) {
+    /// Adds each type to the type section. If `linear_strings` is true, `externref` strings are
+    /// replaced by i64 handles (see [`linear_string_val_type`]).
+    pub fn finish(self, types: &mut TypeSection, linear_strings: bool) {
+        let val_type = |val_type: ValType| {
+            if linear_strings {
+                linear_string_val_type(val_type)
+            } else {
+                val_type
+            }
+        };
+        let field_type = |field_type: FieldType| {
+            if linear_strings {
+                linear_string_field_type(field_type)
+            } else {
+                field_type
+            }
+        };
         for ty in self.registry().take().keys().cloned() {
             match ty {
-                WasmType::Function(params, results) => types.ty().function(params, results),
-                WasmType::Array(elem_type, mutable) => types.ty().array(&elem_type, mutable),
-                WasmType::Struct(fields) => types.ty().struct_(fields),
+                WasmType::Function(params, results) => types.ty().function(
+                    params.into_iter().map(val_type),
+                    results.into_iter().map(val_type),
+                ),
+                WasmType::Array(element_type, mutable) => types.ty().array(
+                    &field_type(FieldType {
+                        element_type,
+                        mutable,
+                    })
+                    .element_type,
+                    mutable,
+                ),
+                WasmType::Struct(fields) => types.ty().struct_(fields.into_iter().map(field_type)),
             }
         }
     }
@@ -76,6 +76,133 @@ const makeTestRenderer = () =>
     },
   );
 
+// fixtures which are also run with lists stored in linear memory
+const linearMemoryListFiles = ["hq-list-growth.sb3"];
+
+const runTest = async (uri, flags, skip) => {
+  let plannedCount = 0;
+  let testCount = 0;
+  let didEnd = false;
+  const testResults = { passes: [], failures: [] };
+  const reporters = {
+    comment(message) {
+      console.log(`[${uri}]`, message);
+    },
+    pass(reason) {
+      testCount++;
+      testResults.passes.push(reason);
+      console.log(`[${uri}] pass:`, reason);
+    },
+    fail(reason) {
+      testCount++;
+      testResults.failures.push(reason);
+      console.log(`[${uri}] fail:`, reason);
+    },
+    plan(count) {
+      plannedCount = Number(count);
+      console.log(`[${uri}] planned ${plannedCount} tests`);
+    },
+    end() {
+      didEnd = true;
+      console.log(`[${uri}] test ended`);
+    },
+  };
+
+  const reportVmResult = (text) => {
+    const command = text.split(/\s+/, 1)[0].toLowerCase();
+    if (reporters[command]) {
+      return reporters[command](text.substring(command.length).trim());
+    }
+
+    // Default to a comment with the full text if we didn't match
+    // any command prefix
+    return reporters.comment(text);
+  };
+
+  const projectBuffer = Buffer.from(
+    fs.readFileSync(path.join(executeDir, uri)),
+  );
+
+  const [project_json, _] = await unpackProject(projectBuffer);
+  // console.log(JSON.stringify(project_json, null, 2));
+  console.log("loaded project");
+  await new Promise((resolve) => setTimeout(resolve, 10));
+
+  let project_wasm;
+
+  try {
+    project_wasm = sb3_to_wasm(
+      JSON.stringify(project_json, null, 2),
+      WasmFlags.from_js(flags),
+    );
+  } catch (e) {
+    // compiler errors are plain objects (see `HQError`), not `Error`s
+    if (e?.err_type === "Unimplemented") {
+      skip(e.msg);
+    } else {
+      throw e;
+    }
+  }
+  await new Promise((resolve) => setTimeout(resolve, 10));
+
+  console.log("compiled project");
+  await new Promise((resolve) => setTimeout(resolve, 10));
+
+  // todo: run wasm-opt if specified in flags?
+
+  // Run the project and once all threads are complete check the results.
+  const runner = new ProjectRunner();
+  await runner.init({
+    wasm_bytes: project_wasm.wasm_bytes,
+    target_names: project_wasm.target_names,
+    layer_orders: project_wasm.layer_orders,
+    project_json,
+    strings: project_wasm.strings,
+    settings: defaultSettings,
+    timeout: 5000,
+    assets: new Proxy(
+      {},
+      {
+        get() {
+          return {
+            dataFormat: "svg",
+            data: "",
+          };
+        },
+      },
+    ),
+    importOverrides: {
+      looks: {
+        say_string: (string) => reportVmResult(string),
+      },
+    },
+    makeRenderer: makeTestRenderer,
+  });
+
+  runner.addEventListener("timeout", () => {
+    throw new Error(`Timeout waiting for threads to complete: ${uri}`);
+  });
+
+  runner.flag_clicked();
+
+  await runner.run();
+
+  // Verify test end was called
+  if (!didEnd) {
+    throw new Error(`Test did not call "end"`);
+  }
+
+  // If a plan was specified, verify we ran the planned number of tests
+  if (plannedCount > 0 && testCount !== plannedCount) {
+    throw new Error(`Expected ${plannedCount} tests, but ran ${testCount}`);
+  }
+
+  // All failures should be reported
+  if (testResults.failures.length > 0) {
+    throw new Error(`Test failures: ${testResults.failures.join(", ")}`);
+  }
+};
+
 describe("Integration tests", () => {
   const files = fs
     .readdirSync(executeDir)
@@ -96,126 +223,17 @@ describe("Integration tests", () => {
         ].includes(uri),
     );
   for (const uri of files) {
-    test.sequential(`${uri} (default flags)`, async ({ skip }) => {
-      let plannedCount = 0;
-      let testCount = 0;
-      let didEnd = false;
-      const testResults = { passes: [], failures: [] };
-      const reporters = {
-        comment(message) {
-          console.log(`[${uri}]`, message);
-        },
-        pass(reason) {
-          testCount++;
-          testResults.passes.push(reason);
-          console.log(`[${uri}] pass:`, reason);
-        },
-        fail(reason) {
-          testCount++;
-          testResults.failures.push(reason);
-          console.log(`[${uri}] fail:`, reason);
-        },
-        plan(count) {
-          plannedCount = Number(count);
-          console.log(`[${uri}] planned ${plannedCount} tests`);
-        },
-        end() {
-          didEnd = true;
-          console.log(`[${uri}] test ended`);
-        },
-      };
-
-      const reportVmResult = (text) => {
-        const command = text.split(/\s+/, 1)[0].toLowerCase();
-        if (reporters[command]) {
-          return reporters[command](text.substring(command.length).trim());
-        }
-
-        // Default to a comment with the full text if we didn't match
-        // any command prefix
-        return reporters.comment(text);
-      };
-
-      const projectBuffer = Buffer.from(
-        fs.readFileSync(path.join(executeDir, uri)),
-      );
-
-      const [project_json, _] = await unpackProject(projectBuffer);
-      // console.log(JSON.stringify(project_json, null, 2));
-      console.log("loaded project");
-      await new Promise((resolve) => setTimeout(resolve, 10));
-
-      let project_wasm;
-
-      try {
-        project_wasm = sb3_to_wasm(
-          JSON.stringify(project_json, null, 2),
-          WasmFlags.from_js(defaultSettings.to_js()),
-        );
-      } catch (e) {
-        if (/todo/.test(e.toString())) {
-          skip(e);
-        } else {
-          throw e;
-        }
-      }
-      await new Promise((resolve) => setTimeout(resolve, 10));
-
-      console.log("compiled project");
-      await new Promise((resolve) => setTimeout(resolve, 10));
-
-      // todo: run wasm-opt if specified in flags?
-
-      // Run the project and once all threads are complete check the results.
-      const runner = new ProjectRunner();
-      await runner.init({
-        wasm_bytes: project_wasm.wasm_bytes,
-        target_names: project_wasm.target_names,
-        project_json,
-        strings: project_wasm.strings,
-        settings: defaultSettings,
-        timeout: 5000,
-        assets: new Proxy(
-          {},
-          {
-            get() {
-              return {
-                dataFormat: "svg",
-                data: "",
-              };
-            },
-          },
-        ),
-        importOverrides: {
-          looks: {
-            say_string: (string) => reportVmResult(string),
-          },
-        },
-        makeRenderer: makeTestRenderer,
-      });
-
-      runner.addEventListener("timeout", () => {
-        throw new Error(`Timeout waiting for threads to complete: ${uri}`);
-      });
-
-      runner.flag_clicked();
-
-      await runner.run();
-
-      // Verify test end was called
-      if (!didEnd) {
-        throw new Error(`Test did not call "end"`);
-      }
-
-      // If a plan was specified, verify we ran the planned number of tests
-      if (plannedCount > 0 && testCount !== plannedCount) {
-        throw new Error(`Expected ${plannedCount} tests, but ran ${testCount}`);
-      }
-
-      // All failures should be reported
-      if (testResults.failures.length > 0) {
-        throw new Error(`Test failures: ${testResults.failures.join(", ")}`);
-      }
-    });
+    test.sequential(`${uri} (default flags)`, ({ skip }) =>
+      runTest(uri, defaultSettings.to_js(), skip),
+    );
+  }
+  for (const uri of linearMemoryListFiles) {
+    test.sequential(`${uri} (lists in linear memory)`, ({ skip }) =>
+      runTest(
+        uri,
+        { ...defaultSettings.to_js(), list_type: "LinearMemory" },
+        skip,
+      ),
+    );
   }
 });